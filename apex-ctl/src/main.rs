@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use apex_hardware::{Device, USBDevice};
+use chrono::Local;
 use clap::{ArgAction, Parser, Subcommand};
 use log::{info, LevelFilter};
 use simplelog::{Config as LoggerConfig, SimpleLogger};
+use std::{fs, fs::OpenOptions, io::Write};
 
 #[derive(Parser)]
 #[clap(version = "1.0", author = "not-jan")]
@@ -20,6 +22,42 @@ enum SubCommand {
     Clear,
     /// Fill the OLED screen
     Fill,
+    /// Keep track of the habits shown on the habit screen
+    #[command(subcommand)]
+    Habit(HabitCommand),
+}
+
+#[derive(Subcommand)]
+enum HabitCommand {
+    /// Mark a habit as done for today
+    Done {
+        /// The name of the habit as configured in `habits.names`
+        name: String,
+    },
+}
+
+fn connect() -> Result<USBDevice> {
+    info!("Connecting to the USB device");
+
+    USBDevice::try_connect()
+}
+
+fn mark_habit_done(name: &str) -> Result<()> {
+    // This has to match the state directory of apex-tux
+    let dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .ok_or_else(|| anyhow!("Couldn't find the state directory!"))?
+        .join("apex-tux");
+    fs::create_dir_all(&dir)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("habits"))?;
+    writeln!(file, "{}\t{}", Local::now().format("%Y-%m-%d"), name)?;
+
+    info!("Marked `{}` as done for today", name);
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -33,13 +71,10 @@ fn main() -> Result<()> {
 
     SimpleLogger::init(filter, LoggerConfig::default())?;
 
-    info!("Connecting to the USB device");
-
-    let mut device = USBDevice::try_connect()?;
-
     match opts.subcmd {
-        SubCommand::Clear => device.clear()?,
-        SubCommand::Fill => device.fill()?,
+        SubCommand::Clear => connect()?.clear()?,
+        SubCommand::Fill => connect()?.fill()?,
+        SubCommand::Habit(HabitCommand::Done { name }) => mark_habit_done(&name)?,
     };
 
     Ok(())
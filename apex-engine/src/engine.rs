@@ -70,6 +70,7 @@ impl Engine {
 impl AsyncDevice for Engine {
     type ClearResult<'a> = impl Future<Output = Result<()>> + 'a;
     type DrawResult<'a> = impl Future<Output = Result<()>> + 'a;
+    type ReconnectResult<'a> = impl Future<Output = Result<()>> + 'a;
     type ShutdownResult<'a> = impl Future<Output = Result<()>> + 'a;
 
     #[allow(clippy::needless_lifetimes)]
@@ -111,4 +112,10 @@ impl AsyncDevice for Engine {
             Ok(())
         }
     }
+
+    /// The engine is reached over HTTP, there's no handle that could go stale
+    #[allow(clippy::needless_lifetimes)]
+    fn reconnect<'this>(&'this mut self) -> Self::ReconnectResult<'this> {
+        async { Ok(()) }
+    }
 }
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use bitvec::prelude::*;
-use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
 #[cfg(feature = "async")]
 use std::future::Future;
 
@@ -14,24 +14,167 @@ pub struct FrameBuffer {
     /// sending the image to a display device. The implementations of
     /// `Drawable` and `DrawTarget` take this quirk into account.
     pub framebuffer: BitArray<[u8; FB_SIZE], Msb0>,
+    /// The bounding box of all pixels that changed since the `FrameBuffer`
+    /// was created or `FrameBuffer::clear_dirty` was called.
+    dirty: Option<Rectangle>,
 }
 
 impl Default for FrameBuffer {
     fn default() -> Self {
         let mut framebuffer = BitArray::<[u8; FB_SIZE], Msb0>::ZERO;
         framebuffer.as_raw_mut_slice()[0] = 0x61;
-        FrameBuffer { framebuffer }
+        FrameBuffer {
+            framebuffer,
+            dirty: None,
+        }
     }
 }
 
+/// Decides how the pixels of an overlay are combined with the pixels that are
+/// already in a `FrameBuffer`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Every pixel of the overlay replaces the pixel below it, this is how
+    /// drawing on a `FrameBuffer` normally works.
+    Replace,
+    /// Only the pixels of the overlay that are on get drawn, the others are
+    /// transparent.
+    Mask,
+    /// The pixels of the overlay that are on invert the pixels below them so
+    /// the overlay stays visible on top of any content.
+    Xor,
+}
+
 impl FrameBuffer {
     /// Initializes a new `FrameBuffer` with all pixels set to
     /// `BinaryColor::Off `
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns the area that changed since the `FrameBuffer` was created or
+    /// since the last call to `FrameBuffer::clear_dirty`. Drawing a pixel in
+    /// the color it already has doesn't count as a change.
+    pub fn dirty(&self) -> Option<Rectangle> {
+        self.dirty
+    }
+
+    /// Forgets about all changes, e.g. after they were sent to a device.
+    pub fn clear_dirty(&mut self) {
+        self.dirty = None;
+    }
+
+    /// Grows the dirty area so it contains `point`.
+    fn mark_dirty(&mut self, point: Point) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => {
+                let bottom_right = dirty.bottom_right().unwrap_or(dirty.top_left);
+                Rectangle::with_corners(
+                    dirty.top_left.component_min(point),
+                    bottom_right.component_max(point),
+                )
+            }
+            None => Rectangle::new(point, Size::new(1, 1)),
+        });
+    }
+
+    /// Sets a single pixel, `point` has to be on the display.
+    fn set(&mut self, point: Point, on: bool) {
+        let index = (point.x + point.y * 128 + 8) as usize;
+        if self.framebuffer[index] != on {
+            self.framebuffer.set(index, on);
+            self.mark_dirty(point);
+        }
+    }
+
+    /// Draws `overlay` on top of this `FrameBuffer`, combining the pixels
+    /// according to `mode`.
+    pub fn blit(&mut self, overlay: &FrameBuffer, mode: BlendMode) {
+        // The header and the trailing byte aren't pixels and must be left alone
+        let len = FB_SIZE - 1;
+        let target = &mut self.framebuffer.as_raw_mut_slice()[1..len];
+        let source = &overlay.framebuffer.as_raw_slice()[1..len];
+
+        let mut changes = Vec::new();
+        for (byte, (target, source)) in target.iter_mut().zip(source).enumerate() {
+            let blended = match mode {
+                BlendMode::Replace => *source,
+                BlendMode::Mask => *target | *source,
+                BlendMode::Xor => *target ^ *source,
+            };
+            let changed = *target ^ blended;
+            *target = blended;
+
+            if changed != 0 {
+                // Every byte holds eight pixels of the same row, starting with the most
+                // significant bit
+                let first = byte * 8 + changed.leading_zeros() as usize;
+                let last = byte * 8 + 7 - changed.trailing_zeros() as usize;
+                changes.push(first);
+                changes.push(last);
+            }
+        }
+
+        for pixel in changes {
+            self.mark_dirty(Point::new((pixel % 128) as i32, (pixel / 128) as i32));
+        }
+    }
+
+    /// Returns a `DrawTarget` that combines everything drawn on it with the
+    /// existing pixels according to `mode`. This allows drawing icons or
+    /// text on top of other content without the black box around them.
+    pub fn blend(&mut self, mode: BlendMode) -> Blended<'_> {
+        Blended { target: self, mode }
+    }
+}
+
+/// A `FrameBuffer` that blends everything drawn on it, see
+/// `FrameBuffer::blend`.
+#[derive(Debug)]
+pub struct Blended<'a> {
+    target: &'a mut FrameBuffer,
+    mode: BlendMode,
+}
+
+impl OriginDimensions for Blended<'_> {
+    fn size(&self) -> Size {
+        self.target.size()
+    }
+}
+
+impl DrawTarget for Blended<'_> {
+    type Color = BinaryColor;
+    type Error = anyhow::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if let (x @ 0..=127, y @ 0..=39) = (coord.x, coord.y) {
+                let index = (x + y * 128 + 8) as usize;
+                let below = self.target.framebuffer[index];
+                let value = match self.mode {
+                    BlendMode::Replace => color.is_on(),
+                    BlendMode::Mask => below || color.is_on(),
+                    BlendMode::Xor => below ^ color.is_on(),
+                };
+                self.target.set(coord, value);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for FrameBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.framebuffer.as_raw_slice() == other.framebuffer.as_raw_slice()
+    }
 }
 
+impl Eq for FrameBuffer {}
+
 /// This trait represents a device that can receive new images to be displayed.
 pub trait Device {
     /// Sends a `FrameBuffer` to the device.
@@ -42,6 +185,12 @@ pub trait Device {
     fn clear(&mut self) -> Result<()>;
 
     fn shutdown(&mut self) -> Result<()>;
+
+    /// Gets a fresh handle to the device after sending to it failed several
+    /// times in a row. Devices that can't get lost don't need to do anything.
+    fn reconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl Drawable for FrameBuffer {
@@ -86,10 +235,8 @@ impl DrawTarget for FrameBuffer {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for Pixel(coord, color) in pixels {
-            if let (x @ 0..=127, y @ 0..=39) = (coord.x, coord.y) {
-                // Calculate the index in the framebuffer.
-                let index: i32 = x + y * 128 + 8;
-                self.framebuffer.set(index as u32 as usize, color.is_on());
+            if let (0..=127, 0..=39) = (coord.x, coord.y) {
+                self.set(coord, color.is_on());
             }
         }
 
@@ -110,12 +257,18 @@ pub trait AsyncDevice {
     where
         Self: 'a;
 
+    type ReconnectResult<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
     #[allow(clippy::needless_lifetimes)]
     fn draw<'this>(&'this mut self, display: &'this FrameBuffer) -> Self::DrawResult<'this>;
     #[allow(clippy::needless_lifetimes)]
     fn clear<'this>(&'this mut self) -> Self::ClearResult<'this>;
     #[allow(clippy::needless_lifetimes)]
     fn shutdown<'this>(&'this mut self) -> Self::ShutdownResult<'this>;
+    #[allow(clippy::needless_lifetimes)]
+    fn reconnect<'this>(&'this mut self) -> Self::ReconnectResult<'this>;
 }
 
 #[cfg(feature = "async")]
@@ -130,6 +283,9 @@ where
     where
         Self: 'a;
     type ShutdownResult<'a> = impl Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+    type ReconnectResult<'a> = impl Future<Output = Result<()>> + 'a
     where
         Self: 'a;
 
@@ -150,4 +306,10 @@ where
         let x = <Self as Device>::shutdown(self);
         async { x }
     }
+
+    #[allow(clippy::needless_lifetimes)]
+    fn reconnect<'this>(&'this mut self) -> Self::ReconnectResult<'this> {
+        let x = <Self as Device>::reconnect(self);
+        async { x }
+    }
 }
@@ -10,4 +10,4 @@ pub use device::Device;
 #[cfg(feature = "usb")]
 pub use usb::USBDevice;
 
-pub use device::FrameBuffer;
+pub use device::{BlendMode, Blended, FrameBuffer};
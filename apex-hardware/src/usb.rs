@@ -28,6 +28,10 @@ enum SupportedDevice {
 pub struct USBDevice {
     /// An exclusive handle to the Keyboard.
     handle: HidDevice,
+    /// The last image that was sent to the keyboard.
+    /// The protocol only knows how to receive whole frames so there are no
+    /// partial updates, but we can at least skip frames that didn't change.
+    last: Option<FrameBuffer>,
 }
 
 impl USBDevice {
@@ -48,7 +52,7 @@ impl USBDevice {
         // This requires udev rules to be setup properly.
         let handle = device.open_device(&api)?;
 
-        Ok(Self { handle })
+        Ok(Self { handle, last: None })
     }
 
     pub fn fill(&mut self) -> Result<()> {
@@ -64,9 +68,16 @@ impl USBDevice {
 
 impl Device for USBDevice {
     fn draw(&mut self, display: &FrameBuffer) -> Result<()> {
-        Ok(self
-            .handle
-            .send_feature_report(display.framebuffer.as_raw_slice())?)
+        if self.last.as_ref() == Some(display) {
+            return Ok(());
+        }
+
+        // Forget about the last frame first, if sending fails we don't know what's on the screen
+        self.last = None;
+        self.handle
+            .send_feature_report(display.framebuffer.as_raw_slice())?;
+        self.last = Some(*display);
+        Ok(())
     }
 
     fn clear(&mut self) -> Result<()> {
@@ -77,4 +88,11 @@ impl Device for USBDevice {
     fn shutdown(&mut self) -> Result<()> {
         Ok(())
     }
+
+    /// The handle goes stale if the keyboard was unplugged or woke up from
+    /// sleep, opening it again picks up where it left off
+    fn reconnect(&mut self) -> Result<()> {
+        *self = Self::try_connect()?;
+        Ok(())
+    }
 }
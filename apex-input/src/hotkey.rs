@@ -1,9 +1,10 @@
 use crate::Command;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use global_hotkey::{
     hotkey::{Code, HotKey, Modifiers},
     GlobalHotKeyEvent, GlobalHotKeyManager,
 };
+use std::collections::HashMap;
 use tokio::sync::broadcast;
 
 pub struct InputManager {
@@ -11,25 +12,67 @@ pub struct InputManager {
 }
 
 impl InputManager {
-    pub fn new(sender: broadcast::Sender<Command>) -> Result<Self> {
+    /// Registers the default hotkeys as well as `screens`, which maps the name
+    /// of a provider to a hotkey like `ALT+SHIFT+1` that jumps right to it,
+    /// and `dismiss` for taking notifications off the screen
+    pub fn new(
+        sender: broadcast::Sender<Command>,
+        screens: &HashMap<String, String>,
+        dismiss: &str,
+    ) -> Result<Self> {
         let hkm = GlobalHotKeyManager::new().unwrap();
 
         let modifiers = Some(Modifiers::ALT | Modifiers::SHIFT);
 
         let hotkey_previous = HotKey::new(modifiers, Code::KeyA);
         let hotkey_next = HotKey::new(modifiers, Code::KeyD);
+        let hotkey_select = HotKey::new(modifiers, Code::KeyS);
+        let hotkey_focus = HotKey::new(modifiers, Code::KeyF);
+        let hotkey_menu = HotKey::new(modifiers, Code::KeyM);
+        let hotkey_dnd = HotKey::new(modifiers, Code::KeyN);
+        let hotkey_large_text = HotKey::new(modifiers, Code::KeyL);
+        let hotkey_next_player = HotKey::new(modifiers, Code::KeyP);
 
         hkm.register(hotkey_previous).unwrap();
         hkm.register(hotkey_next).unwrap();
+        hkm.register(hotkey_select).unwrap();
+        hkm.register(hotkey_focus).unwrap();
+        hkm.register(hotkey_menu).unwrap();
+        hkm.register(hotkey_dnd).unwrap();
+        hkm.register(hotkey_large_text).unwrap();
+        hkm.register(hotkey_next_player).unwrap();
+
+        let mut commands = HashMap::from([
+            (hotkey_previous.id(), Command::PreviousSource),
+            (hotkey_next.id(), Command::NextSource),
+            (hotkey_select.id(), Command::Select),
+            (hotkey_focus.id(), Command::ToggleFocus),
+            (hotkey_menu.id(), Command::ToggleMenu),
+            (hotkey_dnd.id(), Command::ToggleDnd),
+            (hotkey_large_text.id(), Command::ToggleLargeText),
+            (hotkey_next_player.id(), Command::NextPlayer),
+        ]);
+
+        let hotkey_dismiss = dismiss
+            .parse::<HotKey>()
+            .map_err(|e| anyhow!("Invalid hotkey `{}` for `dismiss`: {}", dismiss, e))?;
+        hkm.register(hotkey_dismiss)
+            .map_err(|e| anyhow!("Couldn't register the hotkey for `dismiss`: {}", e))?;
+        commands.insert(hotkey_dismiss.id(), Command::DismissNotification);
+
+        for (name, hotkey) in screens {
+            let hotkey = hotkey
+                .parse::<HotKey>()
+                .map_err(|e| anyhow!("Invalid hotkey `{}` for `{}`: {}", hotkey, name, e))?;
+            hkm.register(hotkey)
+                .map_err(|e| anyhow!("Couldn't register the hotkey for `{}`: {}", name, e))?;
+            commands.insert(hotkey.id(), Command::SetSource(name.clone()));
+        }
 
         let hotkey_handler = move |event: GlobalHotKeyEvent| {
-            if event.id == hotkey_previous.id() {
-                sender
-                    .send(Command::PreviousSource)
-                    .expect("Failed to send command!");
-            } else {
+            if let Some(command) = commands.get(&event.id) {
                 sender
-                    .send(Command::NextSource)
+                    .send(command.clone())
                     .expect("Failed to send command!");
             }
         };
@@ -1,6 +1,57 @@
-#[derive(Debug, Copy, Clone)]
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Command {
     PreviousSource,
     NextSource,
+    /// Jumps right to the provider with the given name
+    SetSource(String),
+    /// Performs the action of the provider that's currently on screen
+    Select,
+    /// Shows the next music player if several are around
+    NextPlayer,
+    /// Turns focus mode on or off, see the `[focus]` section of the settings
+    ToggleFocus,
+    /// Turns do not disturb on or off, it hides all notifications while the
+    /// screens keep changing as usual
+    ToggleDnd,
+    /// Turns large text mode on or off, see the `[accessibility]` section of
+    /// the settings
+    ToggleLargeText,
+    /// Takes the notification that's currently shown off the screen
+    DismissNotification,
+    /// Opens or closes the menu, while it's open the other commands navigate it
+    ToggleMenu,
+    /// Re-reads the settings and restarts all providers with them
+    ReloadConfig,
     Shutdown,
 }
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parses the textual form of a command, e.g. `next` or `show clock`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, argument) = match s.split_once(' ') {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (s, None),
+        };
+
+        Ok(match (name, argument) {
+            ("previous", None) => Self::PreviousSource,
+            ("next", None) => Self::NextSource,
+            ("show", Some(provider)) => Self::SetSource(provider.to_string()),
+            ("select", None) => Self::Select,
+            ("next-player", None) => Self::NextPlayer,
+            ("focus", None) => Self::ToggleFocus,
+            ("dnd", None) => Self::ToggleDnd,
+            ("large-text", None) => Self::ToggleLargeText,
+            ("dismiss", None) => Self::DismissNotification,
+            ("menu", None) => Self::ToggleMenu,
+            ("reload", None) => Self::ReloadConfig,
+            ("shutdown", None) => Self::Shutdown,
+            _ => return Err(format!("Unknown command `{}`", s)),
+        })
+    }
+}
@@ -0,0 +1,7 @@
+#![feature(type_alias_impl_trait, impl_trait_in_assoc_type)]
+#[cfg(target_os = "macos")]
+mod media_remote;
+#[cfg(target_os = "macos")]
+mod music;
+#[cfg(target_os = "macos")]
+pub use music::{Metadata, Player};
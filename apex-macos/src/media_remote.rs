@@ -0,0 +1,160 @@
+//! The few functions of the private MediaRemote framework that are needed to
+//! find out what's playing, this is what the Now Playing widget of macOS
+//! uses as well
+
+use anyhow::{anyhow, Result};
+use block::{Block, ConcreteBlock};
+use core_foundation::{
+    base::{CFType, TCFType},
+    date::CFDate,
+    dictionary::{CFDictionary, CFDictionaryRef},
+    number::CFNumber,
+    string::{CFString, CFStringRef},
+};
+use libloading::Library;
+use std::{
+    ffi::c_void,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+const FRAMEWORK: &str = "/System/Library/PrivateFrameworks/MediaRemote.framework/MediaRemote";
+
+type Queue = *const c_void;
+type GetNowPlayingInfo = unsafe extern "C" fn(Queue, &Block<(CFDictionaryRef,), ()>);
+type GetNowPlayingClient = unsafe extern "C" fn(Queue, &Block<(*const c_void,), ()>);
+type GetBundleIdentifier = unsafe extern "C" fn(*const c_void) -> CFStringRef;
+
+extern "C" {
+    fn dispatch_get_global_queue(identifier: isize, flags: usize) -> Queue;
+}
+
+/// What the app that's playing reported the last time it told macOS
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NowPlayingInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    /// In seconds
+    pub duration: f64,
+    /// In seconds, as of `timestamp`
+    pub elapsed: f64,
+    /// 0 while paused
+    pub rate: f64,
+    /// When `elapsed` was reported, in seconds since 2001 like `CFDate` counts
+    pub timestamp: Option<f64>,
+}
+
+impl NowPlayingInfo {
+    /// Copies everything out of the dictionary, it's only valid during the
+    /// callback
+    fn from_dictionary(dictionary: CFDictionaryRef) -> Option<Self> {
+        if dictionary.is_null() {
+            return None;
+        }
+        let dictionary: CFDictionary<CFString, CFType> =
+            unsafe { CFDictionary::wrap_under_get_rule(dictionary) };
+
+        let value = |key: &'static str| {
+            dictionary
+                .find(CFString::from_static_string(key))
+                .map(|value| value.clone())
+        };
+        let string = |key| value(key)?.downcast::<CFString>().map(|s| s.to_string());
+        let number = |key| value(key)?.downcast::<CFNumber>()?.to_f64();
+
+        Some(Self {
+            title: string("kMRMediaRemoteNowPlayingInfoTitle"),
+            artist: string("kMRMediaRemoteNowPlayingInfoArtist"),
+            album: string("kMRMediaRemoteNowPlayingInfoAlbum"),
+            track_number: number("kMRMediaRemoteNowPlayingInfoTrackNumber")
+                .map(|number| number as u32),
+            duration: number("kMRMediaRemoteNowPlayingInfoDuration").unwrap_or(0.0),
+            elapsed: number("kMRMediaRemoteNowPlayingInfoElapsedTime").unwrap_or(0.0),
+            rate: number("kMRMediaRemoteNowPlayingInfoPlaybackRate").unwrap_or(0.0),
+            timestamp: value("kMRMediaRemoteNowPlayingInfoTimestamp")
+                .and_then(|date| date.downcast::<CFDate>())
+                .map(|date| date.abs_time()),
+        })
+    }
+}
+
+/// The seconds since 2001 like `CFDate` counts them
+pub(crate) fn now() -> f64 {
+    CFDate::now().abs_time()
+}
+
+/// The framework stays loaded as long as this is around
+pub(crate) struct MediaRemote {
+    library: Library,
+}
+
+impl MediaRemote {
+    pub fn load() -> Result<Self> {
+        let library = unsafe { Library::new(FRAMEWORK) }
+            .map_err(|e| anyhow!("Couldn't load MediaRemote: {}", e))?;
+        Ok(Self { library })
+    }
+
+    /// Runs `call` with a block that hands what MediaRemote answers over to
+    /// the future
+    async fn ask<A: 'static, T: Send + 'static>(
+        &self,
+        call: impl FnOnce(Queue, &Block<(A,), ()>),
+        convert: impl Fn(A) -> T + 'static,
+    ) -> Result<T> {
+        let (tx, rx) = oneshot::channel();
+        // The block may be called from any thread and has to be `Fn`
+        let tx = Arc::new(Mutex::new(Some(tx)));
+        let block = ConcreteBlock::new(move |answer: A| {
+            if let Some(tx) = tx.lock().ok().and_then(|mut tx| tx.take()) {
+                let _ = tx.send(convert(answer));
+            }
+        })
+        .copy();
+
+        call(unsafe { dispatch_get_global_queue(0, 0) }, &block);
+        // MediaRemote keeps a copy of its own until it answered
+        drop(block);
+        rx.await.map_err(|_| anyhow!("MediaRemote didn't answer"))
+    }
+
+    /// `None` if nothing is playing or paused
+    pub async fn now_playing_info(&self) -> Result<Option<NowPlayingInfo>> {
+        let function = *unsafe {
+            self.library
+                .get::<GetNowPlayingInfo>(b"MRMediaRemoteGetNowPlayingInfo\0")?
+        };
+        self.ask(
+            |queue, block| unsafe { function(queue, block) },
+            NowPlayingInfo::from_dictionary,
+        )
+        .await
+    }
+
+    /// The bundle identifier of the app that's playing, e.g.
+    /// `com.spotify.client`
+    pub async fn bundle_identifier(&self) -> Result<Option<String>> {
+        let client = *unsafe {
+            self.library
+                .get::<GetNowPlayingClient>(b"MRMediaRemoteGetNowPlayingClient\0")?
+        };
+        let identifier = *unsafe {
+            self.library
+                .get::<GetBundleIdentifier>(b"MRNowPlayingClientGetBundleIdentifier\0")?
+        };
+        self.ask(
+            |queue, block| unsafe { client(queue, block) },
+            move |client: *const c_void| {
+                if client.is_null() {
+                    return None;
+                }
+                let identifier = unsafe { identifier(client) };
+                (!identifier.is_null())
+                    .then(|| unsafe { CFString::wrap_under_get_rule(identifier) }.to_string())
+            },
+        )
+        .await
+    }
+}
@@ -0,0 +1,194 @@
+use crate::media_remote::{self, MediaRemote, NowPlayingInfo};
+use anyhow::{anyhow, Result};
+use apex_music::{AsyncPlayer, Metadata as MetadataTrait, PlaybackStatus, PlayerEvent, Progress};
+use async_stream::stream;
+use futures_core::stream::Stream;
+use std::future::Future;
+use tokio::time::{Duration, MissedTickBehavior};
+
+/// How often macOS is asked whether something else is playing, the progress
+/// moves on in between
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    title: String,
+    artists: String,
+    album: Option<String>,
+    track_number: Option<u32>,
+    /// In microseconds
+    length: u64,
+}
+
+impl MetadataTrait for Metadata {
+    fn title(&self) -> Result<String> {
+        Ok(self.title.clone())
+    }
+
+    fn artists(&self) -> Result<String> {
+        Ok(self.artists.clone())
+    }
+
+    fn length(&self) -> Result<u64> {
+        Ok(self.length)
+    }
+
+    fn album(&self) -> Option<String> {
+        self.album.clone()
+    }
+
+    fn track_number(&self) -> Option<u32> {
+        self.track_number.filter(|number| *number > 0)
+    }
+}
+
+impl From<&NowPlayingInfo> for Metadata {
+    fn from(info: &NowPlayingInfo) -> Self {
+        Self {
+            title: info.title.clone().unwrap_or_default(),
+            artists: info.artist.clone().unwrap_or_default(),
+            album: info.album.clone().filter(|album| !album.is_empty()),
+            track_number: info.track_number,
+            length: (info.duration.max(0.0) * 1_000_000.0) as u64,
+        }
+    }
+}
+
+/// Follows whatever the Now Playing widget of macOS shows
+pub struct Player {
+    media_remote: MediaRemote,
+    /// Part of the bundle identifier of the app to follow, e.g. `spotify`
+    preferred: Option<String>,
+}
+
+impl Player {
+    /// Like on Windows macOS decides which app is shown. With a `preferred`
+    /// app nothing is shown while another one is playing.
+    pub fn new(preferred: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            media_remote: MediaRemote::load()?,
+            preferred: preferred.map(str::to_lowercase),
+        })
+    }
+
+    /// What's playing right now, this fails if it's not the preferred app
+    async fn info(&self) -> Result<NowPlayingInfo> {
+        if let Some(preferred) = &self.preferred {
+            let identifier = self.media_remote.bundle_identifier().await?;
+            if !identifier.map_or(false, |id| id.to_lowercase().contains(preferred)) {
+                return Err(anyhow!("`{}` isn't playing anything", preferred));
+            }
+        }
+
+        self.media_remote
+            .now_playing_info()
+            .await?
+            .ok_or_else(|| anyhow!("Nothing is playing"))
+    }
+
+    /// How fast the position moves on while playing, 1 is the normal speed
+    pub async fn rate(&self) -> Result<f64> {
+        Ok(self.info().await?.rate)
+    }
+
+    pub async fn progress(&self) -> Result<Progress<Metadata>> {
+        Ok(Progress {
+            metadata: self.metadata().await?,
+            position: self.position().await?,
+            status: self.playback_status().await?,
+        })
+    }
+
+    /// There's no public way to be told about changes so macOS is asked every
+    /// second whether the track or the status changed
+    pub async fn stream(&self) -> Result<impl Stream<Item = PlayerEvent> + '_> {
+        let mut timer = tokio::time::interval(Duration::from_millis(100));
+        timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(stream! {
+            // The track and whether it's playing, the position changes all the time
+            let mut last: Option<(Metadata, bool)> = None;
+            loop {
+                tokio::select! {
+                    _ = poll.tick() => {
+                        let current = self
+                            .info()
+                            .await
+                            .ok()
+                            .map(|info| (Metadata::from(&info), info.rate > 0.0));
+                        if current != last {
+                            last = current;
+                            yield PlayerEvent::Properties;
+                        }
+                    },
+                    _ = timer.tick() => {
+                        yield PlayerEvent::Timer;
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl AsyncPlayer for Player {
+    type Metadata = Metadata;
+
+    type MetadataFuture<'b> = impl Future<Output = Result<Self::Metadata>> + 'b
+    where
+        Self: 'b;
+    type NameFuture<'b> = impl Future<Output = String> + 'b
+    where
+        Self: 'b;
+    type PlaybackStatusFuture<'b> = impl Future<Output = Result<PlaybackStatus>> + 'b
+    where
+        Self: 'b;
+    type PositionFuture<'b> = impl Future<Output = Result<i64>> + 'b
+    where
+        Self: 'b;
+
+    #[allow(clippy::needless_lifetimes)]
+    fn metadata<'this>(&'this self) -> Self::MetadataFuture<'this> {
+        async { Ok(Metadata::from(&self.info().await?)) }
+    }
+
+    #[allow(clippy::needless_lifetimes)]
+    fn playback_status<'this>(&'this self) -> Self::PlaybackStatusFuture<'this> {
+        async {
+            Ok(match self.info().await {
+                Ok(info) if info.rate > 0.0 => PlaybackStatus::Playing,
+                Ok(_) => PlaybackStatus::Paused,
+                Err(_) => PlaybackStatus::Stopped,
+            })
+        }
+    }
+
+    #[allow(clippy::needless_lifetimes)]
+    fn name<'this>(&'this self) -> Self::NameFuture<'this> {
+        async {
+            self.media_remote
+                .bundle_identifier()
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| String::from("macos-api"))
+        }
+    }
+
+    /// Apps only report the position when it changes on its own, e.g. when
+    /// seeking, it's moved along to now from there
+    #[allow(clippy::needless_lifetimes)]
+    fn position<'this>(&'this self) -> Self::PositionFuture<'this> {
+        async {
+            let info = self.info().await?;
+            let mut position = info.elapsed;
+            if let Some(timestamp) = info.timestamp {
+                position += (media_remote::now() - timestamp).max(0.0) * info.rate;
+            }
+
+            let position = position.clamp(0.0, info.duration.max(0.0));
+            Ok((position * 1_000_000.0) as i64)
+        }
+    }
+}
@@ -1,19 +1,28 @@
 use crate::generated::MediaPlayer2Player;
 use anyhow::{anyhow, Result};
-use apex_music::{AsyncPlayer, Metadata as MetadataTrait, PlaybackStatus, PlayerEvent, Progress};
+use apex_music::{
+    AsyncPlayer, Chapter, Metadata as MetadataTrait, PlaybackStatus, PlayerEvent, Progress,
+};
 use async_stream::stream;
 use dbus::{
-    arg::PropMap,
+    arg::{PropMap, RefArg},
     message::MatchRule,
     nonblock::{Proxy, SyncConnection},
     strings::BusName,
 };
 use dbus_tokio::connection;
 use futures_core::stream::Stream;
-use futures_util::StreamExt;
+use futures_util::{pin_mut, StreamExt};
 use std::{future::Future, sync::Arc, time::Duration};
 use tokio::{task::JoinHandle, time, time::MissedTickBehavior};
 
+/// Every player owns a name with this prefix on the session bus
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// Players don't say when they start playing so they're checked this often,
+/// in seconds
+const RECHECK_DELAY: u64 = 5;
+
 #[derive(Clone)]
 pub struct Player<'a>(Proxy<'a, Arc<SyncConnection>>);
 
@@ -28,6 +37,28 @@ impl Metadata {
     }
 }
 
+/// Reads a chapter from a dictionary with its `title` and its `start` in
+/// microseconds
+fn chapter(dict: &dyn RefArg) -> Option<Chapter> {
+    let mut title = None;
+    let mut start = None;
+
+    // Dictionaries are iterated as their keys and values in turn
+    let mut entries = dict.as_iter()?;
+    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+        match key.as_str()? {
+            "title" => title = value.as_str().map(String::from),
+            "start" => start = value.as_u64().or_else(|| value.as_i64().map(|v| v as u64)),
+            _ => {}
+        }
+    }
+
+    Some(Chapter {
+        title: title?,
+        start: start?,
+    })
+}
+
 impl MetadataTrait for Metadata {
     fn title(&self) -> Result<String> {
         ::dbus::arg::prop_cast::<String>(&self.0, "xesam:title")
@@ -50,8 +81,49 @@ impl MetadataTrait for Metadata {
             (_, _) => Err(anyhow!("Couldn't get length!")),
         }
     }
+
+    /// Chapters aren't part of the MPRIS specification, players that have
+    /// them put them under their own prefix like `<player>:chapters` as an
+    /// array of dictionaries
+    fn chapters(&self) -> Vec<Chapter> {
+        let mut chapters = self
+            .0
+            .iter()
+            .filter(|(key, _)| key.ends_with(":chapters"))
+            .filter_map(|(_, value)| value.0.as_iter())
+            .flat_map(|chapters| chapters.filter_map(chapter).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        chapters.sort_by_key(|chapter| chapter.start);
+        chapters
+    }
+
+    fn url(&self) -> Option<String> {
+        ::dbus::arg::prop_cast::<String>(&self.0, "xesam:url").cloned()
+    }
+
+    fn album(&self) -> Option<String> {
+        ::dbus::arg::prop_cast::<String>(&self.0, "xesam:album")
+            .filter(|album| !album.is_empty())
+            .cloned()
+    }
+
+    /// This is an `i32` according to the specification, some players send it
+    /// unsigned anyway
+    fn track_number(&self) -> Option<u32> {
+        let number = self.0.get("xesam:trackNumber")?.0.as_i64()?;
+        u32::try_from(number).ok().filter(|number| *number > 0)
+    }
 }
 
+// Ok so the plan for the MPRIS2 module is to wait for two DBUS events
+// - PropertiesChanged to see if the song changed
+// - Seeked to see if the progress was changed manually
+// There's an existing mpris2 crate but it doesn't support async operation which
+// is kind of painful to use in this architecture.
+// When we received these events they should be mapped and put into another
+// queue. Upon receiving the event our code should pull the metadata from the
+// player.
+
 pub struct MPRIS2 {
     handle: JoinHandle<()>,
     conn: Arc<SyncConnection>,
@@ -128,51 +200,131 @@ impl MPRIS2 {
 
         let result = result
             .iter()
-            .filter(|name| name.starts_with("org.mpris.MediaPlayer2."))
+            .filter(|name| name.starts_with(PREFIX))
             .cloned()
             .collect::<Vec<_>>();
 
         Ok(result)
     }
 
-    pub async fn wait_for_player(&self, name: Option<Arc<String>>) -> Result<Player<'_>> {
-        let mut interval = time::interval(Duration::from_secs(5));
-        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    /// Announces players that show up on or leave the session bus
+    pub async fn players(&self) -> Result<impl Stream<Item = PlayerEvent>> {
+        let mr = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged");
 
-        let name = name.map(|n| n.to_string());
+        let (name_match, mut names) = self
+            .conn
+            .add_match(mr)
+            .await?
+            .stream::<(String, String, String)>();
 
-        // TODO: Instead of having a hard delay we might be able to wait on a
-        // notification from DBus instead?
+        Ok(stream! {
+            while let Some((_, (name, old, new))) = names.next().await {
+                if !name.starts_with(PREFIX) {
+                    continue;
+                }
 
-        loop {
-            let names = self.list_names().await?;
+                // Names without an owner before or after the change are new or gone
+                match (old.is_empty(), new.is_empty()) {
+                    (true, false) => yield PlayerEvent::Appeared(name),
+                    (false, true) => yield PlayerEvent::Vanished(name),
+                    _ => {}
+                }
+            }
+            drop(name_match);
+        })
+    }
 
-            if let Some(name) = &name {
-                // We have a player preference, let's check if it exists
-                if let Some(player) = names.into_iter().find(|p| p.contains(name)) {
-                    // Hell yeah, we found a player
-                    return Ok(Player::new(player, self.conn.clone()));
+    /// Returns the players that match the preference or, without a
+    /// preference, all players that are either playing or paused. The ones
+    /// that are playing come first, the order stays the same otherwise.
+    pub async fn active_players(
+        &self,
+        name: Option<&str>,
+    ) -> Result<Vec<(Player<'_>, PlaybackStatus)>> {
+        let mut players = Vec::new();
+
+        for bus_name in self.list_names().await? {
+            if let Some(name) = name {
+                if !bus_name.contains(name) {
+                    continue;
                 }
-            } else {
-                // Let's try to find a player that's either playing or paused
-                for name in names {
-                    let player = Player::new(name, self.conn.clone());
-
-                    match player.playback_status().await {
-                        // Something is playing or paused right now, let's use that
-                        Ok(PlaybackStatus::Playing | PlaybackStatus::Paused) => {
-                            return Ok(player);
-                        }
-                        // Stopped players could be remnants of browser tabs that were playing in
-                        // the past but are dead now and we'd just get stuck here.
-                        _ => {
-                            continue;
-                        }
-                    }
+            }
+
+            let player = Player::new(bus_name, self.conn.clone());
+            match player.playback_status().await {
+                Ok(status @ (PlaybackStatus::Playing | PlaybackStatus::Paused)) => {
+                    players.push((player, status));
                 }
+                // The preferred player is shown no matter what it's doing
+                _ if name.is_some() => players.push((player, PlaybackStatus::Stopped)),
+                // Stopped players could be remnants of browser tabs that were playing in
+                // the past but are dead now and we'd just get stuck here.
+                _ => {}
             }
+        }
+
+        players.sort_by_key(|(_, status)| !matches!(status, PlaybackStatus::Playing));
+        Ok(players)
+    }
+
+    /// Looks for the preferred player or, without a preference, for any
+    /// player that's either playing or paused. Players that are playing win.
+    pub async fn find_player(&self, name: Option<&str>) -> Result<Option<Player<'_>>> {
+        Ok(self
+            .active_players(name)
+            .await?
+            .into_iter()
+            .next()
+            .map(|(player, _)| player))
+    }
+
+    pub async fn wait_for_player(&self, name: Option<Arc<String>>) -> Result<Player<'_>> {
+        let players = self.players().await?;
+        pin_mut!(players);
+
+        let mut interval = time::interval(Duration::from_secs(RECHECK_DELAY));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-            interval.tick().await;
+        let name = name.as_deref().map(String::as_str);
+
+        loop {
+            if let Some(player) = self.find_player(name).await? {
+                // Hell yeah, we found a player
+                return Ok(player);
+            }
+
+            tokio::select! {
+                _ = players.next() => {},
+                _ = interval.tick() => {},
+            }
+        }
+    }
+
+    /// Tells `found` whether the player `find_player` looks for is around,
+    /// right away and then whenever players come and go or may have started
+    /// playing. This keeps going until `found` returns `false`.
+    pub async fn watch_players(
+        &self,
+        name: Option<&str>,
+        mut found: impl FnMut(bool) -> bool,
+    ) -> Result<()> {
+        let players = self.players().await?;
+        pin_mut!(players);
+
+        let mut interval = time::interval(Duration::from_secs(RECHECK_DELAY));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        // The first tick elapses instantaneously
+        interval.tick().await;
+
+        loop {
+            if !found(self.find_player(name).await?.is_some()) {
+                return Ok(());
+            }
+
+            tokio::select! {
+                _ = players.next() => {},
+                _ = interval.tick() => {},
+            }
         }
     }
 }
@@ -193,6 +345,12 @@ impl<'a> Player<'a> {
         ))
     }
 
+    /// The name the player owns on the bus, e.g.
+    /// `org.mpris.MediaPlayer2.spotify`
+    pub fn bus_name(&self) -> String {
+        self.0.destination.to_string()
+    }
+
     pub async fn progress(&self) -> Result<Progress<Metadata>> {
         Ok(Progress {
             metadata: self.metadata().await?,
@@ -200,6 +358,26 @@ impl<'a> Player<'a> {
             status: self.playback_status().await?,
         })
     }
+
+    /// How fast the position moves on while playing, 1 is the normal speed
+    pub async fn rate(&self) -> Result<f64> {
+        Ok(self.0.rate().await?)
+    }
+
+    /// Whether the player picks the next track at random
+    pub async fn shuffle(&self) -> Result<bool> {
+        Ok(self.0.shuffle().await?)
+    }
+
+    /// Either `None`, `Track` or `Playlist`
+    pub async fn loop_status(&self) -> Result<String> {
+        Ok(self.0.loop_status().await?)
+    }
+
+    /// Between 0 and 1
+    pub async fn volume(&self) -> Result<f64> {
+        Ok(self.0.volume().await?)
+    }
 }
 
 impl<'a> AsyncPlayer for Player<'a> {
@@ -239,7 +417,7 @@ impl<'a> AsyncPlayer for Player<'a> {
 
     #[allow(clippy::needless_lifetimes)]
     fn name<'this>(&'this self) -> Self::NameFuture<'this> {
-        async { self.0.destination.to_string() }
+        async { self.bus_name() }
     }
 
     #[allow(clippy::needless_lifetimes)]
@@ -2,5 +2,5 @@
 #![feature(impl_trait_in_assoc_type)]
 mod player;
 pub use player::{
-    AsyncMetadata, AsyncPlayer, Metadata, PlaybackStatus, Player, PlayerEvent, Progress,
+    AsyncMetadata, AsyncPlayer, Chapter, Metadata, PlaybackStatus, Player, PlayerEvent, Progress,
 };
@@ -14,12 +14,46 @@ pub enum PlayerEvent {
     Seeked,
     Properties,
     Timer,
+    /// A player with this name showed up
+    Appeared(String),
+    /// The player with this name went away
+    Vanished(String),
+}
+
+/// A chapter of e.g. a podcast or an audio book
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    /// Where the chapter starts, in microseconds like the position
+    pub start: u64,
 }
 
 pub trait Metadata {
     fn title(&self) -> Result<String>;
     fn artists(&self) -> Result<String>;
     fn length(&self) -> Result<u64>;
+
+    /// The chapters of the track ordered by their start. There's no standard
+    /// for them so most players don't have any.
+    fn chapters(&self) -> Vec<Chapter> {
+        Vec::new()
+    }
+
+    /// Where the track comes from, e.g. `file:///music/song.flac`. Not every
+    /// player tells.
+    fn url(&self) -> Option<String> {
+        None
+    }
+
+    /// The album the track is on, if the player knows
+    fn album(&self) -> Option<String> {
+        None
+    }
+
+    /// Where the track is on its album, starting at 1
+    fn track_number(&self) -> Option<u32> {
+        None
+    }
 }
 
 pub trait Player {
@@ -0,0 +1,16 @@
+use std::{env, process::Command};
+
+/// Plugins are only compatible with apex-tux if both were built by the same
+/// compiler, its version ends up in `ABI`
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=APEX_PROVIDER_RUSTC={}", version.trim());
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
@@ -0,0 +1,129 @@
+//! A screen that greets whoever is in the `[hello]` section of the settings
+//! along with a counter, e.g.
+//!
+//! ```toml
+//! [hello]
+//! enabled = true
+//! name = "Tux"
+//! ```
+//!
+//! A provider crate only needs something like `register` below, apex-tux
+//! calls it with its registry. `main` stands in for apex-tux here and prints
+//! the first few frames.
+#![feature(type_alias_impl_trait, impl_trait_in_assoc_type)]
+
+use anyhow::Result;
+use apex_provider::{
+    Config, ContentInit, ContentProvider, ContentWrapper, FrameBuffer, NotificationInit, Registry,
+};
+use async_stream::try_stream;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Baseline, Text},
+    Drawable,
+};
+use futures::{pin_mut, Stream, StreamExt};
+use tokio::time::{self, Duration, MissedTickBehavior};
+
+/// Registers everything this crate provides
+pub fn register(registry: &mut impl Registry) {
+    registry.register(create);
+}
+
+fn create(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    let name = config
+        .get_str("hello.name")
+        .unwrap_or_else(|_| String::from("world"));
+
+    Ok(Box::new(Hello { name }))
+}
+
+struct Hello {
+    name: String,
+}
+
+impl Hello {
+    fn render(&self, count: u32) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        Text::with_baseline(
+            &format!("Hello, {}!", self.name),
+            Point::new(0, 5),
+            style,
+            Baseline::Top,
+        )
+        .draw(&mut buffer)?;
+        Text::with_baseline(&count.to_string(), Point::new(0, 25), style, Baseline::Top)
+            .draw(&mut buffer)?;
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Hello {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            let mut count = 0;
+            loop {
+                interval.tick().await;
+                yield self.render(count)?;
+                count += 1;
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "hello"
+    }
+}
+
+/// Collects what's registered like apex-tux does
+#[derive(Default)]
+struct Providers {
+    content: Vec<ContentInit>,
+    notifications: Vec<NotificationInit>,
+}
+
+impl Registry for Providers {
+    fn register(&mut self, init: ContentInit) -> &mut Self {
+        self.content.push(init);
+        self
+    }
+
+    fn register_notifications(&mut self, init: NotificationInit) -> &mut Self {
+        self.notifications.push(init);
+        self
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut providers = Providers::default();
+    register(&mut providers);
+
+    let config = Config::default();
+    for init in &providers.content {
+        let mut provider = init(&config)?;
+        let name = provider.provider_name();
+        let frames = provider.proxy_stream()?;
+        let frames = Box::into_pin(frames).take(3);
+        pin_mut!(frames);
+
+        while let Some(frame) = frames.next().await {
+            frame?;
+            println!("{} drew a frame", name);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! A stopwatch that's loaded at runtime, `Select` starts it over. Build it
+//! with `cargo build -p apex-provider --example plugin` and copy the library
+//! from `target/debug/examples/` into the plugin directory of apex-tux, it
+//! has to be built with the `plugins` feature.
+//!
+//! A plugin can't use tokio, it's a copy of its own that no runtime is
+//! running on. apex-tux asks it for a frame every `interval` instead, so
+//! everything in here is synchronous.
+
+use anyhow::Result;
+use apex_provider::{Command, Config, FrameBuffer, Plugin, PluginScreen};
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use std::time::{Duration, Instant};
+
+fn register(plugin: &mut Plugin) {
+    plugin.screen(create);
+}
+
+apex_provider::export_plugin!(register);
+
+fn create(_config: &Config) -> Result<Box<dyn PluginScreen>> {
+    Ok(Box::new(Stopwatch {
+        started: Instant::now(),
+    }))
+}
+
+struct Stopwatch {
+    started: Instant,
+}
+
+impl PluginScreen for Stopwatch {
+    fn name(&self) -> &'static str {
+        "stopwatch"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(100)
+    }
+
+    fn frame(&mut self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_10X20, BinaryColor::On);
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+
+        let elapsed = self.started.elapsed();
+        let text = format!(
+            "{}:{:02}.{}",
+            elapsed.as_secs() / 60,
+            elapsed.as_secs() % 60,
+            elapsed.subsec_millis() / 100
+        );
+        Text::with_text_style(&text, Point::new(64, 20), style, centered).draw(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    fn command(&mut self, command: Command) {
+        if command == Command::Select {
+            self.started = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Everything needed to write a screen or a source of notifications for
+//! apex-tux outside of its repository. A provider crate exposes a function
+//! that registers its providers, apex-tux calls it with its registry before
+//! the scheduler starts, see `examples/hello.rs`. Crates that are loaded as
+//! plugins at runtime implement `PluginScreen` and use `export_plugin!`
+//! instead, see `examples/plugin.rs`.
+mod notification;
+mod plugin;
+mod provider;
+mod registry;
+
+pub use apex_hardware::FrameBuffer;
+pub use apex_input::Command;
+pub use config::Config;
+pub use notification::{Notification, NotificationProvider, NotificationWrapper, Urgency};
+pub use plugin::{
+    NotifierInit, Plugin, PluginAbi, PluginEntry, PluginNotifier, PluginScreen, ScreenInit, ABI,
+    PLUGIN_ABI, PLUGIN_ENTRY,
+};
+pub use provider::{ContentProvider, ContentWrapper, ProviderInput};
+pub use registry::{ContentInit, NotificationInit, Registry};
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+
+/// How important a notification is, these are the urgency levels of the
+/// desktop notification specification
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+/// What a notification says, apex-tux lays it out the same way as its own
+/// notifications
+#[derive(Debug, Clone, Default)]
+pub struct Notification {
+    /// Scrolls by if it's too long for the screen
+    pub title: String,
+    pub content: String,
+    pub urgency: Urgency,
+    /// A BMP of 24x24 pixels shown on the left, e.g. from `include_bytes!`
+    pub icon: Option<Vec<u8>>,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            content: content.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = urgency;
+        self
+    }
+
+    pub fn with_icon(mut self, icon: impl Into<Vec<u8>>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+}
+
+pub trait NotificationProvider {
+    type NotificationStream<'a>: Stream<Item = Result<Notification>> + 'a
+    where
+        Self: 'a;
+
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>>;
+}
+
+/// `NotificationProvider` without the associated type so providers can be
+/// boxed, every `NotificationProvider` is one
+pub trait NotificationWrapper {
+    fn proxy_stream<'a>(&'a mut self) -> Result<Box<dyn Stream<Item = Result<Notification>> + 'a>>;
+}
+
+impl<T: NotificationProvider> NotificationWrapper for T {
+    fn proxy_stream<'this>(
+        &'this mut self,
+    ) -> Result<Box<dyn Stream<Item = Result<Notification>> + 'this>> {
+        let x = <T as NotificationProvider>::stream(self)?;
+        Ok(Box::new(x.fuse()))
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{Command, Config, FrameBuffer, Notification};
+use anyhow::Result;
+use std::{os::raw::c_char, time::Duration};
+
+/// The symbol apex-tux looks for in the shared libraries in its plugin
+/// directory, see `export_plugin!`
+pub const PLUGIN_ENTRY: &[u8] = b"apex_plugin_register\0";
+
+/// The symbol with the `ABI` a plugin was built for, apex-tux checks it before
+/// it calls anything else
+pub const PLUGIN_ABI: &[u8] = b"apex_plugin_abi\0";
+
+/// The version of this crate and of the compiler that built it, as a C string.
+/// A plugin that was built with a different one of either is skipped.
+pub const ABI: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " ",
+    env!("APEX_PROVIDER_RUSTC"),
+    "\0"
+);
+
+/// The signature of `apex_plugin_abi`, unlike the entry it only uses the C ABI
+/// so it's safe to call into any plugin
+pub type PluginAbi = unsafe extern "C" fn() -> *const c_char;
+
+/// The signature of `apex_plugin_register`. The returned plugin is owned by
+/// apex-tux from then on.
+pub type PluginEntry = unsafe extern "C" fn() -> *mut Plugin;
+
+/// Creates a screen of a plugin from the settings
+pub type ScreenInit = fn(&Config) -> Result<Box<dyn PluginScreen>>;
+/// Creates a source of notifications of a plugin from the settings
+pub type NotifierInit = fn(&Config) -> Result<Box<dyn PluginNotifier>>;
+
+/// A screen of a plugin. A plugin links a copy of tokio of its own that no
+/// runtime is running on, everything that needs one panics in there: timers,
+/// `spawn` and the I/O types. That's why plugins don't have a stream like
+/// `ContentProvider` does, apex-tux calls `frame` on its own schedule
+/// instead.
+pub trait PluginScreen {
+    fn name(&self) -> &'static str;
+
+    /// The time between two frames
+    fn interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    /// Draws the next frame. This runs on the runtime of apex-tux, it must not
+    /// block for long.
+    fn frame(&mut self) -> Result<FrameBuffer>;
+
+    /// Gets `Select` while the screen is shown, a new frame is drawn right
+    /// after
+    fn command(&mut self, _command: Command) {}
+}
+
+/// A source of notifications of a plugin, it's asked for new ones every
+/// `interval` for the same reason screens are asked for their frames
+pub trait PluginNotifier {
+    fn interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    /// The notifications since the last call, it must not block for long
+    fn poll(&mut self) -> Result<Vec<Notification>>;
+}
+
+/// What a plugin registered, it's handed over to apex-tux through
+/// `apex_plugin_register`
+#[derive(Debug, Clone, Default)]
+pub struct Plugin {
+    pub screens: Vec<ScreenInit>,
+    pub notifiers: Vec<NotifierInit>,
+}
+
+impl Plugin {
+    pub fn screen(&mut self, init: ScreenInit) -> &mut Self {
+        self.screens.push(init);
+        self
+    }
+
+    pub fn notifier(&mut self, init: NotifierInit) -> &mut Self {
+        self.notifiers.push(init);
+        self
+    }
+}
+
+/// Turns a crate of type `cdylib` into a plugin apex-tux loads at startup.
+/// `$register` is called with the plugin to add its screens to, e.g.
+///
+/// ```ignore
+/// fn register(plugin: &mut apex_provider::Plugin) {
+///     plugin.screen(create);
+/// }
+///
+/// apex_provider::export_plugin!(register);
+/// ```
+///
+/// Rust doesn't have a stable ABI so the plugin has to be built with the same
+/// compiler and the same version of this crate as apex-tux, the macro exports
+/// `apex_plugin_abi` so apex-tux can tell. See `examples/plugin.rs` for a
+/// whole plugin.
+#[macro_export]
+macro_rules! export_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        pub extern "C" fn apex_plugin_abi() -> *const ::std::os::raw::c_char {
+            $crate::ABI.as_ptr().cast()
+        }
+
+        // Only apex-tux looks at the plugin, it's built the same way
+        #[allow(improper_ctypes_definitions)]
+        #[no_mangle]
+        pub extern "C" fn apex_plugin_register() -> *mut $crate::Plugin {
+            let mut plugin = $crate::Plugin::default();
+            $register(&mut plugin);
+            ::std::boxed::Box::into_raw(::std::boxed::Box::new(plugin))
+        }
+    };
+}
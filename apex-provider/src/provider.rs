@@ -0,0 +1,79 @@
+use crate::{Command, FrameBuffer};
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use tokio::sync::{mpsc, watch};
+
+/// Lets a provider react to the hotkeys while it's on screen. The provider
+/// itself is busy producing frames so this is usually a channel into its
+/// stream.
+pub trait ProviderInput {
+    /// Returns `true` if the provider used `command`, the scheduler only acts
+    /// on the commands that weren't used.
+    fn handle_input(&mut self, command: Command) -> bool;
+}
+
+/// Providers that only have a single action get `Select`, switching screens
+/// keeps working as usual
+impl ProviderInput for mpsc::UnboundedSender<Command> {
+    fn handle_input(&mut self, command: Command) -> bool {
+        match command {
+            Command::Select => self.send(command).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+pub trait ContentProvider {
+    type ContentStream<'a>: Stream<Item = Result<FrameBuffer>> + 'a
+    where
+        Self: 'a;
+
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>>;
+    fn name(&self) -> &'static str;
+
+    /// Returns the handler for the input meant for this provider while it is
+    /// on screen. Most providers don't care about input so there is none by
+    /// default.
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        None
+    }
+
+    /// Returns a channel that tells whether the provider has anything to show
+    /// right now, e.g. the music player needs a running player. Inactive
+    /// providers are skipped by the automatic rotation. Providers without it
+    /// are always active.
+    fn activity(&self) -> Option<watch::Receiver<bool>> {
+        None
+    }
+}
+
+/// `ContentProvider` without the associated type so providers can be boxed,
+/// every `ContentProvider` is one
+pub trait ContentWrapper {
+    fn proxy_stream<'a>(&'a mut self) -> Result<Box<dyn Stream<Item = Result<FrameBuffer>> + 'a>>;
+    fn provider_name(&self) -> &'static str;
+    fn input_handler(&self) -> Option<Box<dyn ProviderInput>>;
+    fn activity_receiver(&self) -> Option<watch::Receiver<bool>>;
+}
+
+impl<T: ContentProvider> ContentWrapper for T {
+    fn proxy_stream<'this>(
+        &'this mut self,
+    ) -> Result<Box<dyn Stream<Item = Result<FrameBuffer>> + 'this>> {
+        let x = <T as ContentProvider>::stream(self)?;
+        Ok(Box::new(x.fuse()))
+    }
+
+    fn provider_name(&self) -> &'static str {
+        self.name()
+    }
+
+    fn input_handler(&self) -> Option<Box<dyn ProviderInput>> {
+        self.input()
+    }
+
+    fn activity_receiver(&self) -> Option<watch::Receiver<bool>> {
+        self.activity()
+    }
+}
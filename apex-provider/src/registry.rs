@@ -0,0 +1,19 @@
+use crate::{Config, ContentWrapper, NotificationWrapper};
+use anyhow::Result;
+
+/// Creates a screen from the settings, an error if they're broken. Every
+/// screen has a section of its own in the settings, see
+/// `config::Config::get`.
+pub type ContentInit = fn(&Config) -> Result<Box<dyn ContentWrapper>>;
+/// Creates a source of notifications from the settings
+pub type NotificationInit = fn(&Config) -> Result<Box<dyn NotificationWrapper>>;
+
+/// Where the providers are registered, apex-tux hands its own registry to
+/// the provider crates before the scheduler starts
+pub trait Registry {
+    /// Adds a screen, they're shown in the order they're registered in unless
+    /// the settings say otherwise
+    fn register(&mut self, init: ContentInit) -> &mut Self;
+
+    fn register_notifications(&mut self, init: NotificationInit) -> &mut Self;
+}
@@ -3,7 +3,7 @@ use apex_hardware::{Device, FrameBuffer};
 use apex_input::Command;
 use embedded_graphics::{geometry::Size, pixelcolor::BinaryColor, Drawable};
 use embedded_graphics_simulator::{
-    sdl2::Keycode, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+    sdl2::Keycode, OutputSettings, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
 };
 use std::{sync::mpsc, thread, thread::JoinHandle, time::Duration};
 
@@ -21,11 +21,29 @@ pub struct Simulator {
 
 impl Simulator {
     pub fn connect(sender: tokio::sync::broadcast::Sender<Command>) -> Self {
+        Self::open(sender, OutputSettingsBuilder::new().scale(4).build())
+    }
+
+    /// Leaves a gap between the pixels so it's easy to count them, this is
+    /// meant for working on the layout of a screen
+    pub fn with_pixel_grid(sender: tokio::sync::broadcast::Sender<Command>) -> Self {
+        Self::open(
+            sender,
+            OutputSettingsBuilder::new()
+                .scale(6)
+                .pixel_spacing(1)
+                .build(),
+        )
+    }
+
+    fn open(
+        sender: tokio::sync::broadcast::Sender<Command>,
+        output_settings: OutputSettings,
+    ) -> Self {
         let (tx, rx) = mpsc::channel::<FrameBuffer>();
         let handle = thread::spawn(move || {
             let mut display = SimulatorDisplay::<BinaryColor>::new(Size::new(128, 40));
 
-            let output_settings = OutputSettingsBuilder::new().scale(4).build();
             let mut window = Window::new(WINDOW_TITLE, &output_settings);
 
             'outer: loop {
@@ -42,6 +60,20 @@ impl Simulator {
                                 sender.send(Command::PreviousSource)?;
                             } else if keycode == Keycode::Right {
                                 sender.send(Command::NextSource)?;
+                            } else if keycode == Keycode::Space {
+                                sender.send(Command::Select)?;
+                            } else if keycode == Keycode::F {
+                                sender.send(Command::ToggleFocus)?;
+                            } else if keycode == Keycode::M {
+                                sender.send(Command::ToggleMenu)?;
+                            } else if keycode == Keycode::N {
+                                sender.send(Command::ToggleDnd)?;
+                            } else if keycode == Keycode::L {
+                                sender.send(Command::ToggleLargeText)?;
+                            } else if keycode == Keycode::P {
+                                sender.send(Command::NextPlayer)?;
+                            } else if keycode == Keycode::X {
+                                sender.send(Command::DismissNotification)?;
                             }
                             Ok::<(), anyhow::Error>(())
                         }
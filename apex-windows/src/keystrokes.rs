@@ -0,0 +1,149 @@
+//! Counts the keys pressed anywhere through raw input, only the fact that a
+//! key was pressed is passed on and not which one
+
+use anyhow::{anyhow, Result};
+use std::{
+    mem::size_of,
+    sync::{Mutex, OnceLock},
+    thread,
+};
+use tokio::sync::mpsc;
+use windows::{
+    core::PCWSTR,
+    w,
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::{
+                GetRawInputData, RegisterRawInputDevices, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE,
+                RAWINPUTHEADER, RIDEV_INPUTSINK, RID_INPUT, RIM_TYPEKEYBOARD,
+            },
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+                HMENU, HWND_MESSAGE, MSG, RI_KEY_BREAK, WINDOW_EX_STYLE, WINDOW_STYLE, WM_INPUT,
+                WNDCLASSW,
+            },
+        },
+    },
+};
+
+/// The window procedure has no state of its own, this is where it sends the
+/// keystrokes to
+static KEYSTROKES: Mutex<Option<mpsc::UnboundedSender<()>>> = Mutex::new(None);
+
+/// Every key that's pressed sends a message, releasing it doesn't. Calling
+/// this again hands the keystrokes to the new receiver instead.
+pub fn keystrokes() -> Result<mpsc::UnboundedReceiver<()>> {
+    // There's only ever one window, it outlives the receivers
+    static WINDOW: OnceLock<Result<(), String>> = OnceLock::new();
+    WINDOW
+        .get_or_init(|| start().map_err(|e| e.to_string()))
+        .clone()
+        .map_err(|e| anyhow!(e))?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    if let Ok(mut keystrokes) = KEYSTROKES.lock() {
+        *keystrokes = Some(tx);
+    }
+
+    Ok(rx)
+}
+
+fn start() -> Result<()> {
+    // Raw input arrives as messages of a window, this one is never shown
+    let (started, result) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        let window = unsafe { create_window() };
+        let failed = window.is_err();
+        let _ = started.send(window);
+        if !failed {
+            unsafe { run_message_loop() };
+        }
+    });
+
+    result.recv()?
+}
+
+unsafe fn create_window() -> Result<()> {
+    let instance = GetModuleHandleW(PCWSTR::null())?;
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(window_procedure),
+        hInstance: instance,
+        lpszClassName: w!("apex-tux-keystrokes"),
+        ..Default::default()
+    };
+    if RegisterClassW(&class) == 0 {
+        return Err(anyhow!("Couldn't register the window class"));
+    }
+
+    let window = CreateWindowExW(
+        WINDOW_EX_STYLE::default(),
+        class.lpszClassName,
+        w!("apex-tux"),
+        WINDOW_STYLE::default(),
+        0,
+        0,
+        0,
+        0,
+        HWND_MESSAGE,
+        HMENU::default(),
+        instance,
+        None,
+    );
+    if window.0 == 0 {
+        return Err(anyhow!("Couldn't create a window for raw input"));
+    }
+
+    // Generic desktop controls, keyboard. Sinks receive input in the
+    // background as well.
+    let device = RAWINPUTDEVICE {
+        usUsagePage: 0x01,
+        usUsage: 0x06,
+        dwFlags: RIDEV_INPUTSINK,
+        hwndTarget: window,
+    };
+    if !RegisterRawInputDevices(&[device], size_of::<RAWINPUTDEVICE>() as u32).as_bool() {
+        return Err(anyhow!("Couldn't register for raw keyboard input"));
+    }
+
+    Ok(())
+}
+
+unsafe fn run_message_loop() {
+    let mut message = MSG::default();
+    while GetMessageW(&mut message, HWND::default(), 0, 0).as_bool() {
+        DispatchMessageW(&message);
+    }
+}
+
+unsafe extern "system" fn window_procedure(
+    window: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if message == WM_INPUT && is_key_press(HRAWINPUT(lparam.0)) {
+        if let Ok(Some(keystrokes)) = KEYSTROKES.lock().as_deref() {
+            let _ = keystrokes.send(());
+        }
+    }
+
+    DefWindowProcW(window, message, wparam, lparam)
+}
+
+unsafe fn is_key_press(handle: HRAWINPUT) -> bool {
+    let mut input = RAWINPUT::default();
+    let mut size = size_of::<RAWINPUT>() as u32;
+    let read = GetRawInputData(
+        handle,
+        RID_INPUT,
+        Some(&mut input as *mut RAWINPUT as *mut _),
+        &mut size,
+        size_of::<RAWINPUTHEADER>() as u32,
+    );
+
+    read != u32::MAX
+        && input.header.dwType == RIM_TYPEKEYBOARD.0
+        && u32::from(input.data.keyboard.Flags) & RI_KEY_BREAK == 0
+}
@@ -1,3 +1,11 @@
 #![feature(type_alias_impl_trait, async_iterator, impl_trait_in_assoc_type)]
+#[cfg(feature = "keystrokes")]
+mod keystrokes;
 mod music;
+#[cfg(feature = "sensors")]
+mod sensors;
+#[cfg(feature = "keystrokes")]
+pub use keystrokes::keystrokes;
 pub use music::{Metadata, Player};
+#[cfg(feature = "sensors")]
+pub use sensors::{Sensor, Sensors};
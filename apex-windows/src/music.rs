@@ -4,23 +4,48 @@ use futures_core::stream::Stream;
 use std::future::Future;
 
 use async_stream::stream;
-use std::time::Duration;
-use tokio::time::MissedTickBehavior;
-use windows::Media::{
-    Control,
-    Control::{
-        GlobalSystemMediaTransportControlsSession,
-        GlobalSystemMediaTransportControlsSessionManager,
-        GlobalSystemMediaTransportControlsSessionMediaProperties,
-        GlobalSystemMediaTransportControlsSessionPlaybackInfo,
-        GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use windows::{
+    Foundation::{EventRegistrationToken, TypedEventHandler},
+    Media::{
+        Control,
+        Control::{
+            GlobalSystemMediaTransportControlsSession,
+            GlobalSystemMediaTransportControlsSessionManager,
+            GlobalSystemMediaTransportControlsSessionMediaProperties,
+            GlobalSystemMediaTransportControlsSessionPlaybackInfo,
+            GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+        },
     },
 };
 
+/// Windows counts time in ticks of 100 nanoseconds, `DateTime` starts in
+/// 1601 instead of 1970
+const UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
+
+/// Turns ticks into microseconds like the position of MPRIS
+fn micros(ticks: i64) -> i64 {
+    ticks / 10
+}
+
+/// The current time as a `DateTime` would have it
+fn now() -> i64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    UNIX_EPOCH_TICKS + (since_epoch.as_nanos() / 100) as i64
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Metadata {
     title: String,
     artists: String,
+    album: String,
+    /// 0 if the app doesn't say
+    track_number: i32,
+    /// In microseconds
+    length: u64,
 }
 
 impl MetadataTrait for Metadata {
@@ -33,29 +58,113 @@ impl MetadataTrait for Metadata {
     }
 
     fn length(&self) -> Result<u64> {
-        Ok(0)
+        Ok(self.length)
+    }
+
+    fn album(&self) -> Option<String> {
+        Some(self.album.clone()).filter(|album| !album.is_empty())
+    }
+
+    fn track_number(&self) -> Option<u32> {
+        u32::try_from(self.track_number)
+            .ok()
+            .filter(|number| *number > 0)
     }
 }
 
 pub struct Player {
     session_manager: GlobalSystemMediaTransportControlsSessionManager,
+    /// Part of the AppUserModelId of the app to follow, e.g. `Spotify`
+    preferred: Option<String>,
+}
+
+/// Removes the event handlers of the session manager once the stream of
+/// events is dropped
+struct Subscription {
+    session_manager: GlobalSystemMediaTransportControlsSessionManager,
+    current_session_changed: EventRegistrationToken,
+    sessions_changed: EventRegistrationToken,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let _ = self
+            .session_manager
+            .RemoveCurrentSessionChanged(self.current_session_changed);
+        let _ = self
+            .session_manager
+            .RemoveSessionsChanged(self.sessions_changed);
+    }
 }
 
 impl Player {
-    pub fn new() -> Result<Self> {
+    /// Follows whatever Windows considers the current session unless there's
+    /// a `preferred` app. Like the preferred player on Linux nothing else is
+    /// shown then, even if other apps are playing.
+    pub fn new(preferred: Option<&str>) -> Result<Self> {
         let session_manager =
             Control::GlobalSystemMediaTransportControlsSessionManager::RequestAsync()
                 .map_err(|_| anyhow!("Windows"))?
                 .get()
                 .map_err(|_| anyhow!("Windows"))?;
 
-        Ok(Self { session_manager })
+        Ok(Self {
+            session_manager,
+            preferred: preferred.map(str::to_lowercase),
+        })
     }
 
+    /// The session is looked up every time as apps come and go and Windows
+    /// changes its current session whenever another app starts playing
     pub fn current_session(&self) -> Result<GlobalSystemMediaTransportControlsSession> {
-        self.session_manager
-            .GetCurrentSession()
-            .map_err(|e| anyhow!("Couldn't get current session: {}", e))
+        let preferred = match &self.preferred {
+            Some(preferred) => preferred,
+            None => {
+                return self
+                    .session_manager
+                    .GetCurrentSession()
+                    .map_err(|e| anyhow!("Couldn't get current session: {}", e))
+            }
+        };
+
+        let sessions = self
+            .session_manager
+            .GetSessions()
+            .map_err(|e| anyhow!("Couldn't get the sessions: {}", e))?;
+
+        // AppUserModelIds are rather long, e.g. `SpotifyAB.SpotifyMusic_<hash>!Spotify`
+        sessions
+            .into_iter()
+            .find(|session| {
+                session.SourceAppUserModelId().map_or(false, |id| {
+                    id.to_string_lossy().to_lowercase().contains(preferred)
+                })
+            })
+            .ok_or_else(|| anyhow!("Couldn't find a session of `{}`", preferred))
+    }
+
+    /// Sends a message whenever the current session or the list of sessions
+    /// changes
+    fn subscribe(&self, tx: mpsc::UnboundedSender<()>) -> Result<Subscription> {
+        let current_tx = tx.clone();
+        let current_session_changed =
+            self.session_manager
+                .CurrentSessionChanged(&TypedEventHandler::new(move |_, _| {
+                    let _ = current_tx.send(());
+                    Ok(())
+                }))?;
+        let sessions_changed = self
+            .session_manager
+            .SessionsChanged(&TypedEventHandler::new(move |_, _| {
+                let _ = tx.send(());
+                Ok(())
+            }))?;
+
+        Ok(Subscription {
+            session_manager: self.session_manager.clone(),
+            current_session_changed,
+            sessions_changed,
+        })
     }
 
     pub async fn media_properties(
@@ -70,6 +179,12 @@ impl Player {
         Ok(x)
     }
 
+    /// How fast the position moves on while playing, 1 is the normal speed
+    pub async fn rate(&self) -> Result<f64> {
+        let playback = self.current_session()?.GetPlaybackInfo()?;
+        Ok(playback.PlaybackRate()?.Value()?)
+    }
+
     pub async fn progress(&self) -> Result<Progress<Metadata>> {
         Ok(Progress {
             metadata: self.metadata().await?,
@@ -82,11 +197,23 @@ impl Player {
     pub async fn stream(&self) -> Result<impl Stream<Item = PlayerEvent>> {
         let mut timer = tokio::time::interval(Duration::from_millis(100));
         timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let subscription = self.subscribe(tx)?;
+
         Ok(stream! {
             loop {
-                timer.tick().await;
-                yield PlayerEvent::Timer;
+                tokio::select! {
+                    Some(_) = rx.recv() => {
+                        yield PlayerEvent::Properties;
+                    },
+                    _ = timer.tick() => {
+                        yield PlayerEvent::Timer;
+                    }
+                }
             }
+            // The event handlers are removed once this is dropped
+            drop(subscription);
         })
     }
 }
@@ -112,7 +239,17 @@ impl AsyncPlayer for Player {
             let session = self.media_properties().await?;
             let title = session.Title()?.to_string_lossy();
             let artists = session.Artist()?.to_string_lossy();
-            Ok(Metadata { title, artists })
+            let album = session.AlbumTitle()?.to_string_lossy();
+            let track_number = session.TrackNumber()?;
+            let timeline = self.current_session()?.GetTimelineProperties()?;
+            let length = timeline.EndTime()?.Duration - timeline.StartTime()?.Duration;
+            Ok(Metadata {
+                title,
+                artists,
+                album,
+                track_number,
+                length: micros(length).max(0) as u64,
+            })
         }
     }
 
@@ -144,14 +281,34 @@ impl AsyncPlayer for Player {
 
     #[allow(clippy::needless_lifetimes)]
     fn name<'this>(&'this self) -> Self::NameFuture<'this> {
-        // There might be a Windows API to find the name of the player but the user most
-        // likely will never see this anyway
-        async { String::from("windows-api") }
+        async {
+            self.current_session()
+                .and_then(|session| Ok(session.SourceAppUserModelId()?.to_string_lossy()))
+                .unwrap_or_else(|_| String::from("windows-api"))
+        }
     }
 
+    /// Apps only report the position every few seconds or when seeking, it's
+    /// moved along to now from there
     #[allow(clippy::needless_lifetimes)]
     fn position<'this>(&'this self) -> Self::PositionFuture<'this> {
-        // TODO: Find the API for this?
-        async { Ok(0) }
+        async {
+            let session = self.current_session()?;
+            let timeline = session.GetTimelineProperties()?;
+            let start = timeline.StartTime()?.Duration;
+            let end = timeline.EndTime()?.Duration;
+            let mut position = timeline.Position()?.Duration - start;
+
+            let playback = session.GetPlaybackInfo()?;
+            if let Ok(GlobalSystemMediaTransportControlsSessionPlaybackStatus::Playing) =
+                playback.PlaybackStatus()
+            {
+                let updated = timeline.LastUpdatedTime()?.UniversalTime;
+                let elapsed = (now() - updated).max(0) as f64 * self.rate().await.unwrap_or(1.0);
+                position += elapsed as i64;
+            }
+
+            Ok(micros(position.clamp(0, (end - start).max(0))))
+        }
     }
 }
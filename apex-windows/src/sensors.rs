@@ -0,0 +1,161 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use wmi::{COMLibrary, WMIConnection};
+
+const NAMESPACE: &str = "root\\LibreHardwareMonitor";
+const QUERY: &str = "SELECT Name, Identifier, SensorType, Value FROM Sensor";
+/// The thermal zones of ACPI are there without LibreHardwareMonitor but
+/// only readable as an administrator and only few boards report them
+const THERMAL_NAMESPACE: &str = "root\\WMI";
+const THERMAL_QUERY: &str =
+    "SELECT InstanceName, CurrentTemperature FROM MSAcpi_ThermalZoneTemperature";
+
+/// A sensor as LibreHardwareMonitor reports it over WMI
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename = "Sensor", rename_all = "PascalCase")]
+pub struct Sensor {
+    /// E.g. `CPU Package`, this isn't unique if there are several devices of
+    /// the same kind
+    pub name: String,
+    /// E.g. `/amdcpu/0/temperature/2`
+    pub identifier: String,
+    /// E.g. `Temperature`, `Load` or `Clock`
+    pub sensor_type: String,
+    pub value: f32,
+}
+
+/// A thermal zone as ACPI reports it over WMI
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename = "MSAcpi_ThermalZoneTemperature", rename_all = "PascalCase")]
+struct ThermalZone {
+    /// E.g. `ACPI\ThermalZone\TZ00_0`
+    instance_name: String,
+    /// In tenths of Kelvin
+    current_temperature: u32,
+}
+
+impl From<ThermalZone> for Sensor {
+    fn from(zone: ThermalZone) -> Self {
+        Self {
+            name: zone.instance_name.clone(),
+            identifier: zone.instance_name,
+            sensor_type: String::from("Temperature"),
+            value: zone.current_temperature as f32 / 10.0 - 273.15,
+        }
+    }
+}
+
+/// Where the readings come from
+enum Source {
+    LibreHardwareMonitor(WMIConnection),
+    ThermalZones(WMIConnection),
+}
+
+impl Source {
+    /// LibreHardwareMonitor knows about far more sensors, the thermal zones
+    /// are only asked if it's not running
+    fn connect() -> Result<Self> {
+        let com = COMLibrary::new()?;
+        // The namespace is gone once LibreHardwareMonitor is closed
+        if let Ok(connection) = WMIConnection::with_namespace_path(NAMESPACE, com) {
+            if connection.raw_query::<Sensor>(QUERY).is_ok() {
+                return Ok(Source::LibreHardwareMonitor(connection));
+            }
+        }
+
+        let connection = WMIConnection::with_namespace_path(THERMAL_NAMESPACE, com)?;
+        connection.raw_query::<ThermalZone>(THERMAL_QUERY)?;
+        Ok(Source::ThermalZones(connection))
+    }
+
+    fn read(&self) -> Result<Vec<Sensor>> {
+        Ok(match self {
+            Source::LibreHardwareMonitor(connection) => connection.raw_query(QUERY)?,
+            Source::ThermalZones(connection) => connection
+                .raw_query::<ThermalZone>(THERMAL_QUERY)?
+                .into_iter()
+                .map(Sensor::from)
+                .collect(),
+        })
+    }
+}
+
+impl Sensor {
+    /// Sensors can be picked by their name or their identifier
+    pub fn is(&self, name: &str) -> bool {
+        self.name == name || self.identifier == name
+    }
+}
+
+/// Reads the sensors of LibreHardwareMonitor, it has to be running for them
+/// to show up. Without it only the thermal zones of ACPI are there. The WMI
+/// connection can't be moved between threads so it lives on a thread of its own
+/// that keeps the latest readings around.
+#[derive(Clone)]
+pub struct Sensors {
+    latest: Arc<Mutex<Vec<Sensor>>>,
+}
+
+impl Sensors {
+    pub fn spawn(interval: Duration) -> Result<Self> {
+        let latest = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::clone(&latest);
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        thread::Builder::new()
+            .name(String::from("sensors"))
+            .spawn(move || {
+                let source = match Source::connect() {
+                    Ok(source) => source,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+
+                let mut ready = Some(ready_tx);
+                // The readings are only shared with `Sensors`, once every clone of it is
+                // dropped nobody needs them anymore
+                while Arc::strong_count(&shared) > 1 {
+                    if let Ok(sensors) = source.read() {
+                        if let Ok(mut latest) = shared.lock() {
+                            *latest = sensors;
+                        }
+                    }
+
+                    // Connecting already made sure there's something to read
+                    if let Some(ready) = ready.take() {
+                        let _ = ready.send(Ok(()));
+                    }
+
+                    thread::sleep(interval);
+                }
+            })?;
+
+        ready_rx.recv()??;
+
+        Ok(Self { latest })
+    }
+
+    pub fn all(&self) -> Vec<Sensor> {
+        self.latest
+            .lock()
+            .map(|sensors| sensors.clone())
+            .unwrap_or_default()
+    }
+
+    /// Looks up a sensor by its name or identifier
+    pub fn find(&self, name: &str) -> Option<Sensor> {
+        self.latest
+            .lock()
+            .ok()?
+            .iter()
+            .find(|sensor| sensor.is(name))
+            .cloned()
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use apex_input::Command;
+use log::{debug, info, warn};
+use std::{
+    fs,
+    os::unix::net,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::broadcast,
+};
+
+/// The socket lives in the runtime directory of the user so nobody else can
+/// send commands, there's no socket without one
+pub(crate) fn socket_path() -> Result<PathBuf> {
+    let dir = dirs::runtime_dir().ok_or_else(|| anyhow!("XDG_RUNTIME_DIR isn't set"))?;
+    Ok(dir.join("apex-tux.sock"))
+}
+
+/// Accepts the same commands as the hotkeys on a Unix socket, one per line.
+/// Every command is answered with `ok` or the reason it was rejected, e.g.
+/// `echo dnd | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/apex-tux.sock`
+pub(crate) fn listen(path: &Path, sender: broadcast::Sender<Command>) -> Result<()> {
+    // A socket that was left behind by a previous run can't be bound again,
+    // one that still answers belongs to another instance
+    if path.exists() {
+        if net::UnixStream::connect(path).is_ok() {
+            return Err(anyhow!(
+                "Another instance is listening on {}",
+                path.display()
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    info!("Listening for commands on {}", path.display());
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle(stream, sender.clone()));
+                }
+                Err(e) => warn!("Couldn't accept a connection on the control socket: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn handle(stream: UnixStream, sender: broadcast::Sender<Command>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match line.parse::<Command>() {
+            Ok(command) => {
+                debug!("Received {:?} on the control socket", command);
+                match sender.send(command) {
+                    Ok(_) => String::from("ok"),
+                    Err(e) => format!("error: {}", e),
+                }
+            }
+            Err(e) => format!("error: {}", e),
+        };
+
+        let reply = format!("{}\n", reply);
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
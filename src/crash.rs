@@ -0,0 +1,168 @@
+use crate::{render::util, state};
+use apex_hardware::FrameBuffer;
+use apex_input::Command;
+use chrono::Local;
+use config::Config;
+use lazy_static::lazy_static;
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fmt::{Display, Write as _},
+    fs, panic,
+    path::PathBuf,
+    sync::{Mutex, TryLockError},
+};
+
+/// How many of the last frames and commands end up in the report
+const HISTORY: usize = 5;
+
+/// Settings containing any of these are left out of the report, tables like
+/// `headers` are left out as a whole since any of their keys could be a secret
+const SECRETS: [&str; 10] = [
+    "token",
+    "password",
+    "secret",
+    "key",
+    "url",
+    "auth",
+    "headers",
+    "cookie",
+    "client_id",
+    "user",
+];
+
+/// What was going on before the crash
+#[derive(Debug, Default)]
+struct Context {
+    /// The frames that were sent last along with the name of what drew them
+    frames: VecDeque<(String, FrameBuffer)>,
+    commands: VecDeque<Command>,
+    settings: String,
+}
+
+lazy_static! {
+    static ref CONTEXT: Mutex<Context> = Mutex::new(Context::default());
+}
+
+fn with_context(f: impl FnOnce(&mut Context)) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        f(&mut context);
+    }
+}
+
+fn remember<T>(history: &mut VecDeque<T>, value: T) {
+    if history.len() >= HISTORY {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+/// Remembers a frame that was sent to the device
+pub(crate) fn record_frame(source: &str, frame: &FrameBuffer) {
+    with_context(|context| remember(&mut context.frames, (source.to_string(), *frame)));
+}
+
+pub(crate) fn record_command(command: &Command) {
+    with_context(|context| remember(&mut context.commands, command.clone()));
+}
+
+/// Remembers the settings with everything that looks like a secret left out
+pub(crate) fn record_settings(config: &Config) {
+    let summary = match config.clone().try_into::<toml::Value>() {
+        Ok(mut settings) => {
+            redact(&mut settings);
+            toml::to_string(&settings).unwrap_or_else(|e| e.to_string())
+        }
+        Err(e) => e.to_string(),
+    };
+
+    with_context(|context| context.settings = summary);
+}
+
+fn redact(value: &mut toml::Value) {
+    if let toml::Value::Table(table) = value {
+        for (key, value) in table.iter_mut() {
+            let key = key.to_lowercase();
+            if SECRETS.iter().any(|secret| key.contains(secret)) {
+                *value = toml::Value::String(String::from("<redacted>"));
+            } else {
+                redact(value);
+            }
+        }
+    }
+}
+
+/// `info` is what the panic hook gets, its type was renamed on nightly
+fn report(info: &dyn Display, context: Option<&Context>) -> String {
+    let mut report = String::new();
+
+    let _ = writeln!(
+        report,
+        "apex-tux {} crashed at {}",
+        env!("CARGO_PKG_VERSION"),
+        Local::now()
+    );
+    let _ = writeln!(report, "\n{}", info);
+    let _ = writeln!(report, "\nBacktrace:\n{}", Backtrace::force_capture());
+
+    let context = match context {
+        Some(context) => context,
+        None => {
+            let _ = writeln!(report, "\nThe context was locked when the crash happened");
+            return report;
+        }
+    };
+
+    if let Some((source, _)) = context.frames.back() {
+        let _ = writeln!(report, "\nLast shown: {}", source);
+    }
+
+    let _ = writeln!(report, "\nLast commands:");
+    for command in &context.commands {
+        let _ = writeln!(report, "\t{:?}", command);
+    }
+
+    let _ = writeln!(report, "\nSettings:\n{}", context.settings);
+
+    let _ = writeln!(report, "Last frames, the latest one comes last:");
+    for (source, frame) in &context.frames {
+        let _ = writeln!(report, "\n{}:\n{}", source, util::ascii(frame));
+    }
+
+    report
+}
+
+fn write_report(report: &str) -> std::io::Result<PathBuf> {
+    let directory = state::state_dir().join("crashes");
+    fs::create_dir_all(&directory)?;
+
+    let path = directory.join(format!(
+        "crash-{}.txt",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, report)?;
+
+    Ok(path)
+}
+
+/// Writes a report to the state directory whenever something panics, the
+/// usual message is still printed as well
+pub(crate) fn install() {
+    let default = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        default(info);
+
+        // Waiting for the lock could deadlock if the panic happened while it was held
+        let contents = match CONTEXT.try_lock() {
+            Ok(context) => report(info, Some(&*context)),
+            Err(TryLockError::Poisoned(context)) => report(info, Some(&*context.into_inner())),
+            Err(TryLockError::WouldBlock) => report(info, None),
+        };
+
+        match write_report(&contents) {
+            Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+            Err(e) => eprintln!("Couldn't write the crash report: {}", e),
+        }
+    }));
+}
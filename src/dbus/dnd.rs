@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use dbus::{arg::PropMap, nonblock, nonblock::SyncConnection};
+use dbus_tokio::connection;
+use log::{error, info};
+use std::{process, sync::Arc, time::Duration};
+
+/// How the desktop's notifications were silenced so it can be undone later
+#[derive(Debug, Copy, Clone)]
+enum Inhibition {
+    /// The cookie returned by `org.freedesktop.Notifications.Inhibit`
+    Cookie(u32),
+    /// GNOME's notification banners were turned off
+    Gnome,
+}
+
+/// Turns on the do not disturb mode of the desktop. KDE supports inhibiting
+/// notifications through the notification server itself while GNOME only has
+/// a setting for it.
+pub(crate) struct DesktopDnd {
+    connection: Arc<SyncConnection>,
+    inhibition: Option<Inhibition>,
+}
+
+/// Shows or hides GNOME's notification banners
+fn show_banners(show: bool) -> Result<()> {
+    let status = process::Command::new("gsettings")
+        .args([
+            "set",
+            "org.gnome.desktop.notifications",
+            "show-banners",
+            if show { "true" } else { "false" },
+        ])
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("`gsettings` failed with {}", status))
+    }
+}
+
+impl DesktopDnd {
+    pub fn new() -> Result<Self> {
+        let (resource, connection) = connection::new_session_sync()?;
+
+        tokio::spawn(async {
+            let err = resource.await;
+            error!("Lost connection to D-Bus: {}", err);
+        });
+
+        Ok(Self {
+            connection,
+            inhibition: None,
+        })
+    }
+
+    fn proxy(&self) -> nonblock::Proxy<'_, Arc<SyncConnection>> {
+        nonblock::Proxy::new(
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            Duration::from_millis(5000),
+            self.connection.clone(),
+        )
+    }
+
+    pub async fn enable(&mut self) -> Result<()> {
+        if self.inhibition.is_some() {
+            return Ok(());
+        }
+
+        // Only notification servers that implement the inhibition extension know this
+        // call. KDE lifts the inhibition by itself once our connection closes.
+        let inhibit = self
+            .proxy()
+            .method_call::<(u32,), _, _, _>(
+                "org.freedesktop.Notifications",
+                "Inhibit",
+                ("apex-tux", "Focus mode", PropMap::new()),
+            )
+            .await;
+
+        self.inhibition = Some(match inhibit {
+            Ok((cookie,)) => Inhibition::Cookie(cookie),
+            Err(_) => {
+                show_banners(false)?;
+                Inhibition::Gnome
+            }
+        });
+
+        info!("Silenced the desktop notifications");
+        Ok(())
+    }
+
+    pub async fn disable(&mut self) -> Result<()> {
+        match self.inhibition.take() {
+            Some(Inhibition::Cookie(cookie)) => {
+                self.proxy()
+                    .method_call::<(), _, _, _>(
+                        "org.freedesktop.Notifications",
+                        "UnInhibit",
+                        (cookie,),
+                    )
+                    .await?;
+            }
+            Some(Inhibition::Gnome) => show_banners(true)?,
+            None => return Ok(()),
+        }
+
+        info!("Restored the desktop notifications");
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use crate::render::{
+    scheduler::Status,
+    timers::{self, Timer, Timers},
+};
+use anyhow::Result;
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    channel::{MatchingReceiver, Sender},
+    message::{MatchRule, SignalArgs},
+    nonblock::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged,
+    Path,
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder, MethodErr};
+use dbus_tokio::connection;
+use log::{error, info};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH},
+};
+use tokio::sync::watch;
+
+const NAME: &str = "io.github.not_jan.ApexTux";
+const PATH: &str = "/io/github/not_jan/ApexTux";
+const INTERFACE: &str = "io.github.not_jan.ApexTux.State";
+const TIMERS_INTERFACE: &str = "io.github.not_jan.ApexTux.Timers";
+
+/// What's published, the status and the timers change independently
+#[derive(Debug, Default)]
+struct Published {
+    status: Status,
+    timers: Timers,
+}
+
+type Shared = Arc<Mutex<Published>>;
+
+fn properties(status: &Status) -> PropMap {
+    let mut properties = PropMap::new();
+    properties.insert(
+        String::from("Screen"),
+        Variant(Box::new(status.screen.clone())),
+    );
+    properties.insert(
+        String::from("Notification"),
+        Variant(Box::new(status.notification.clone())),
+    );
+    properties.insert(String::from("DoNotDisturb"), Variant(Box::new(status.dnd)));
+    properties.insert(String::from("Focus"), Variant(Box::new(status.focus)));
+    properties
+}
+
+/// Every timer gets a path of its own like the tracks of an MPRIS track list,
+/// e.g. `/io/github/not_jan/ApexTux/Timers/chess_clock/0`
+fn timer_path(screen: &str, index: usize) -> Path<'static> {
+    Path::from(format!("{}/Timers/{}/{}", PATH, screen, index))
+}
+
+fn timer_paths(timers: &Timers) -> Vec<Path<'static>> {
+    timers
+        .iter()
+        .flat_map(|(screen, timers)| (0..timers.len()).map(|index| timer_path(screen, index)))
+        .collect()
+}
+
+fn micros(duration: Duration) -> i64 {
+    duration.as_micros().try_into().unwrap_or(i64::MAX)
+}
+
+/// The metadata of a timer in the style of MPRIS. `apextux:end` is when a
+/// running timer is up in microseconds since the epoch, it's 0 while the timer
+/// is paused and `apextux:remaining` has the time that's left instead.
+fn metadata(path: Path<'static>, screen: &str, timer: &Timer) -> PropMap {
+    let end = timer
+        .end
+        .and_then(|end| end.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, micros);
+
+    let mut metadata = PropMap::new();
+    let mut insert = |key: &str, value: Box<dyn RefArg>| {
+        metadata.insert(String::from(key), Variant(value));
+    };
+    insert("mpris:trackid", Box::new(path));
+    insert("xesam:title", Box::new(timer.title.clone()));
+    insert("apextux:screen", Box::new(screen.to_string()));
+    insert("apextux:running", Box::new(timer.end.is_some()));
+    insert("apextux:end", Box::new(end));
+    insert("apextux:remaining", Box::new(micros(timer.remaining)));
+    metadata
+}
+
+/// Publishes what's on the keyboard as properties on the session bus so
+/// widgets can follow along, e.g.
+/// `busctl --user get-property io.github.not_jan.ApexTux
+/// /io/github/not_jan/ApexTux io.github.not_jan.ApexTux.State Screen`. Changes
+/// are announced with `PropertiesChanged`.
+///
+/// The timers of the screens, e.g. the countdown and the chess clock, are
+/// listed like the tracks of an MPRIS track list: `Timers` has their paths and
+/// `GetTimersMetadata` their titles and when they're up.
+pub(crate) async fn export(mut status: watch::Receiver<Status>) -> Result<()> {
+    let (resource, conn) = connection::new_session_sync()?;
+
+    tokio::spawn(async {
+        let err = resource.await;
+        error!("Lost connection to D-Bus: {}", err);
+    });
+
+    conn.request_name(NAME, false, true, false).await?;
+
+    let mut timers = timers::subscribe();
+    let shared: Shared = Arc::new(Mutex::new(Published {
+        status: status.borrow().clone(),
+        timers: timers.borrow().clone(),
+    }));
+    let mut crossroads = Crossroads::new();
+    let interface = crossroads.register(INTERFACE, |builder: &mut IfaceBuilder<Shared>| {
+        // The name of the screen, `menu`, `notification` or `screen saver`
+        builder.property("Screen").get(|_, shared| {
+            Ok(shared
+                .lock()
+                .map(|p| p.status.screen.clone())
+                .unwrap_or_default())
+        });
+        builder.property("Notification").get(|_, shared| {
+            Ok(shared
+                .lock()
+                .map(|p| p.status.notification.clone())
+                .unwrap_or_default())
+        });
+        builder
+            .property("DoNotDisturb")
+            .get(|_, shared| Ok(shared.lock().map(|p| p.status.dnd).unwrap_or_default()));
+        builder
+            .property("Focus")
+            .get(|_, shared| Ok(shared.lock().map(|p| p.status.focus).unwrap_or_default()));
+    });
+    let timers_interface =
+        crossroads.register(TIMERS_INTERFACE, |builder: &mut IfaceBuilder<Shared>| {
+            builder.property("Timers").get(|_, shared| {
+                Ok(shared
+                    .lock()
+                    .map(|p| timer_paths(&p.timers))
+                    .unwrap_or_default())
+            });
+            // Unknown paths are left out, the timers may have changed in the meantime
+            builder.method(
+                "GetTimersMetadata",
+                ("timer_ids",),
+                ("metadata",),
+                |_, shared, (ids,): (Vec<Path<'static>>,)| {
+                    let published = shared.lock().map_err(|_| MethodErr::failed("Poisoned"))?;
+                    let requested = published
+                        .timers
+                        .iter()
+                        .flat_map(|(screen, timers)| {
+                            timers.iter().enumerate().map(move |(index, timer)| {
+                                (timer_path(screen, index), *screen, timer)
+                            })
+                        })
+                        .filter(|(path, _, _)| ids.contains(path))
+                        .map(|(path, screen, timer)| metadata(path, screen, timer))
+                        .collect::<Vec<_>>();
+                    Ok((requested,))
+                },
+            );
+        });
+    crossroads.insert(PATH, &[interface, timers_interface], Arc::clone(&shared));
+
+    conn.start_receive(
+        MatchRule::new_method_call(),
+        Box::new(move |msg, conn| {
+            let _ = crossroads.handle_message(msg, conn);
+            true
+        }),
+    );
+    info!("Publishing the state on D-Bus as {}", NAME);
+
+    loop {
+        let signal = tokio::select! {
+            changed = status.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let current = status.borrow().clone();
+                if let Ok(mut shared) = shared.lock() {
+                    shared.status = current.clone();
+                }
+
+                PropertiesPropertiesChanged {
+                    interface_name: String::from(INTERFACE),
+                    changed_properties: properties(&current),
+                    invalidated_properties: Vec::new(),
+                }
+            },
+            // The timers are never closed, they live as long as the program
+            Ok(()) = timers.changed() => {
+                let current = timers.borrow().clone();
+                let mut changed_properties = PropMap::new();
+                changed_properties.insert(
+                    String::from("Timers"),
+                    Variant(Box::new(timer_paths(&current))),
+                );
+                if let Ok(mut shared) = shared.lock() {
+                    shared.timers = current;
+                }
+
+                PropertiesPropertiesChanged {
+                    interface_name: String::from(TIMERS_INTERFACE),
+                    changed_properties,
+                    invalidated_properties: Vec::new(),
+                }
+            },
+        };
+        let _ = conn.send(signal.to_emit_message(&Path::from(PATH)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use crate::render::{
+    assets,
+    notifications::{Icon, Notification, NotificationBuilder, NotificationProvider, Urgency},
+    scheduler::NotificationWrapper,
+};
+use anyhow::Result;
+use async_stream::try_stream;
+use config::Config;
+use dbus::message::MatchRule;
+use dbus_tokio::connection;
+use embedded_graphics::pixelcolor::BinaryColor;
+use futures::StreamExt;
+use futures_core::Stream;
+use lazy_static::lazy_static;
+use log::{debug, error, info};
+use tinybmp::Bmp;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_callback;
+
+#[allow(clippy::unnecessary_wraps)]
+fn register_callback(_config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering KDE Connect notification source.");
+    Ok(Box::new(KdeConnect {}))
+}
+
+static PHONE_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/phone.bmp"));
+lazy_static! {
+    static ref PHONE_ICON_BMP: Bmp<'static, BinaryColor> = assets::bundled(PHONE_ICON);
+}
+
+/// Shows calls and text messages of phones paired with KDE Connect
+pub struct KdeConnect {}
+
+/// Describes the events of the telephony plugin, `talking` is left out as
+/// the call was already shown while it was ringing
+fn describe(event: &str) -> Option<&'static str> {
+    match event {
+        "ringing" => Some("Incoming call"),
+        "missedCall" => Some("Missed call"),
+        "sms" => Some("New SMS"),
+        _ => None,
+    }
+}
+
+/// Calls and messages go before everything else, a ringing phone can't wait
+/// until the other notifications are done
+fn render(event: &str, number: &str, name: &str) -> Option<Result<Notification>> {
+    let description = describe(event)?;
+
+    // The name is only known for contacts and the number may be withheld
+    let (title, content) = match (name.is_empty(), number.is_empty()) {
+        (false, false) => (name, format!("{}: {}", description, number)),
+        (false, true) => (name, description.to_string()),
+        (true, false) => (number, description.to_string()),
+        (true, true) => ("Unknown caller", description.to_string()),
+    };
+
+    Some(
+        NotificationBuilder::new()
+            .with_icon(Icon::new(*PHONE_ICON_BMP))
+            .with_title(title)
+            .with_content(content)
+            .with_urgency(Urgency::Critical)
+            .build(),
+    )
+}
+
+impl NotificationProvider for KdeConnect {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        let (resource, conn) = connection::new_session_sync()?;
+
+        tokio::spawn(async {
+            let err = resource.await;
+            error!("Lost connection to D-Bus: {}", err);
+        });
+
+        // Every paired device emits this on its own object, the signal carries the
+        // event, the number and the name of the contact
+        let rule = MatchRule::new_signal("org.kde.kdeconnect.device.telephony", "callReceived");
+
+        Ok(try_stream! {
+            let (_signal, mut calls) = conn
+                .add_match(rule)
+                .await?
+                .stream::<(String, String, String)>();
+
+            while let Some((_, (event, number, name))) = calls.next().await {
+                debug!("KDE Connect reported `{}` for {:?}", event, number);
+
+                if let Some(notification) = render(&event, &number, &name) {
+                    yield notification?;
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,111 @@
+//! Notification bodies may contain the small subset of markup the
+//! notification spec allows (`<b>`, `<i>`, `<u>`, `<a href="...">` and
+//! `<img>`) and XML entities. None of that can be shown on the display so it's
+//! turned into plain text.
+
+/// Decodes an entity without the surrounding `&` and `;`
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Removes tags, decodes entities and puts everything on one line. Text that
+/// only looks like markup, e.g. `a < b`, is left alone.
+pub(crate) fn strip(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => {
+                if let Some(end) = rest.find('>') {
+                    let tag = &rest[1..end];
+                    let name = tag.trim_start_matches('/').trim();
+                    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                        // Line breaks become spaces so the words don't run into each other
+                        if name.starts_with("br") {
+                            plain.push(' ');
+                        }
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+            }
+            '&' => {
+                if let Some(end) = rest.find(';') {
+                    if let Some(decoded) = decode_entity(&rest[1..end]) {
+                        plain.push(decoded);
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    // There's only a single line of text on the display
+    plain.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_removed() {
+        assert_eq!(strip("<b>Hello</b> <i>world</i>"), "Hello world");
+        assert_eq!(strip("<a href=\"https://example.com\">link</a>"), "link");
+        assert_eq!(strip("<img src=\"icon.png\" alt=\"icon\"/>text"), "text");
+    }
+
+    #[test]
+    fn line_breaks_become_spaces() {
+        assert_eq!(strip("first<br/>second"), "first second");
+        assert_eq!(
+            strip("  several\n lines\tof text "),
+            "several lines of text"
+        );
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(strip("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(
+            strip("&quot;quoted&quot; &apos;too&apos;"),
+            "\"quoted\" 'too'"
+        );
+        assert_eq!(strip("&#65;&#x42;&#X43;"), "ABC");
+    }
+
+    #[test]
+    fn decoded_entities_are_not_markup() {
+        assert_eq!(strip("&lt;b&gt;bold&lt;/b&gt;"), "<b>bold</b>");
+        assert_eq!(strip("&amp;amp;"), "&amp;");
+    }
+
+    #[test]
+    fn text_that_only_looks_like_markup_is_kept() {
+        assert_eq!(strip("a < b"), "a < b");
+        assert_eq!(strip("1 <2> 3"), "1 <2> 3");
+        assert_eq!(strip("AT&T"), "AT&T");
+        assert_eq!(strip("&unknown; &#xZZ;"), "&unknown; &#xZZ;");
+    }
+}
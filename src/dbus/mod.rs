@@ -1,2 +1,10 @@
 #[cfg(feature = "dbus-support")]
+pub(crate) mod dnd;
+#[cfg(feature = "dbus-support")]
+pub(crate) mod export;
+#[cfg(feature = "dbus-support")]
+pub(crate) mod kdeconnect;
+#[cfg(feature = "dbus-support")]
+mod markup;
+#[cfg(feature = "dbus-support")]
 pub(crate) mod notifications;
@@ -1,12 +1,14 @@
 use crate::{
+    dbus::markup,
     render::{
-        notifications::{Icon, Notification, NotificationBuilder, NotificationProvider},
+        assets,
+        notifications::{Icon, Notification, NotificationBuilder, NotificationProvider, Urgency},
         scheduler::NotificationWrapper,
     },
-    scheduler::NOTIFICATION_PROVIDERS,
 };
 use anyhow::{anyhow, Result};
 use async_stream::try_stream;
+use config::Config;
 use dbus::{
     arg::messageitem::MessageItem,
     channel::MatchingReceiver,
@@ -20,31 +22,48 @@ use embedded_graphics::pixelcolor::BinaryColor;
 use futures::{channel::mpsc, StreamExt};
 use futures_core::Stream;
 use lazy_static::lazy_static;
-use linkme::distributed_slice;
 use log::{debug, info};
 use std::{convert::TryFrom, time::Duration};
 use tinybmp::Bmp;
 
-#[distributed_slice(NOTIFICATION_PROVIDERS)]
-static PROVIDER_INIT: fn() -> Result<Box<dyn NotificationWrapper>> = register_callback;
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_callback;
 
 #[allow(clippy::unnecessary_wraps)]
-fn register_callback() -> Result<Box<dyn NotificationWrapper>> {
+fn register_callback(_config: &Config) -> Result<Box<dyn NotificationWrapper>> {
     info!("Registering DBUS notification source.");
     let dbus = Box::new(Dbus {});
     Ok(dbus)
 }
 
-static DISCORD_ICON: &[u8] = include_bytes!("./../../assets/discord.bmp");
+static DISCORD_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/discord.bmp"));
+static TELEGRAM_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/telegram.bmp"));
+static SLACK_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/slack.bmp"));
 lazy_static! {
-    static ref DISCORD_ICON_BMP: Bmp<'static, BinaryColor> =
-        Bmp::<BinaryColor>::from_slice(DISCORD_ICON).expect("Failed to parse BMP");
+    static ref DISCORD_ICON_BMP: Bmp<'static, BinaryColor> = assets::bundled(DISCORD_ICON);
+    static ref TELEGRAM_ICON_BMP: Bmp<'static, BinaryColor> = assets::bundled(TELEGRAM_ICON);
+    static ref SLACK_ICON_BMP: Bmp<'static, BinaryColor> = assets::bundled(SLACK_ICON);
 }
 
 pub struct Dbus {}
 
 enum NotificationType {
-    Discord { title: String, content: String },
+    Discord {
+        title: String,
+        content: String,
+        urgency: Urgency,
+    },
+    Telegram {
+        title: String,
+        content: String,
+        urgency: Urgency,
+    },
+    Slack {
+        title: String,
+        content: String,
+        urgency: Urgency,
+    },
     Unsupported,
 }
 
@@ -52,17 +71,34 @@ impl NotificationType {
     pub fn render(&self) -> Result<Notification> {
         let builder = NotificationBuilder::new();
 
-        match self {
-            NotificationType::Discord { title, content } => {
-                let icon = Icon::new(*DISCORD_ICON_BMP);
-                builder
-                    .with_icon(icon)
-                    .with_content(content)
-                    .with_title(title)
-                    .build()
-            }
-            NotificationType::Unsupported => Err(anyhow!("Unsupported notification type!")),
-        }
+        let (icon, title, content, urgency) = match self {
+            NotificationType::Discord {
+                title,
+                content,
+                urgency,
+            } => (*DISCORD_ICON_BMP, title, content, urgency),
+            NotificationType::Telegram {
+                title,
+                content,
+                urgency,
+            } => (*TELEGRAM_ICON_BMP, title, content, urgency),
+            NotificationType::Slack {
+                title,
+                content,
+                urgency,
+            } => (*SLACK_ICON_BMP, title, content, urgency),
+            NotificationType::Unsupported => return Err(anyhow!("Unsupported notification type!")),
+        };
+
+        // The body may contain markup, some applications use it in the summary as well
+        let (title, content) = (markup::strip(title), markup::strip(content));
+
+        builder
+            .with_icon(Icon::new(icon))
+            .with_content(content)
+            .with_title(&title)
+            .with_urgency(*urgency)
+            .build()
     }
 }
 
@@ -84,15 +120,72 @@ impl TryFrom<Message> for NotificationType {
                     }
                 }
 
-                NotificationType::Discord { title, content }
+                NotificationType::Discord {
+                    title,
+                    content,
+                    urgency: value.get_urgency(),
+                }
+            }
+            "Telegram Desktop" | "telegram-desktop" => {
+                let (_, _, _, summary, body) =
+                    value.read5::<String, u32, String, String, String>()?;
+                let (title, content) = telegram_sender(&summary, &body);
+
+                NotificationType::Telegram {
+                    title: title.to_string(),
+                    content: content.to_string(),
+                    urgency: value.get_urgency(),
+                }
+            }
+            "Slack" => {
+                let (_, _, _, summary, content) =
+                    value.read5::<String, u32, String, String, String>()?;
+
+                NotificationType::Slack {
+                    title: slack_sender(&summary).to_string(),
+                    content,
+                    urgency: value.get_urgency(),
+                }
             }
             _ => NotificationType::Unsupported,
         })
     }
 }
 
+/// Slack puts the sender in the summary, either as `New message from <name>`
+/// for direct messages or as `<name> in #<channel>` for channels. The channel
+/// is dropped as there's barely enough room for the name.
+fn slack_sender(summary: &str) -> &str {
+    let sender = summary.strip_prefix("New message from ").unwrap_or(summary);
+    sender
+        .split_once(" in #")
+        .map_or(sender, |(sender, _)| sender)
+        .trim()
+}
+
+/// The summary of Telegram is the name of the chat, that's the sender in
+/// direct chats. In groups the sender comes first in the body in bold if the
+/// notification server supports markup, otherwise the summary is
+/// `<sender> (<chat>)`. The chat is dropped like it is for Slack. Entities
+/// are left alone, `render` decodes them along with the rest of the markup.
+fn telegram_sender<'a>(summary: &'a str, body: &'a str) -> (&'a str, &'a str) {
+    if let Some(group) = body
+        .strip_prefix("<b>")
+        .and_then(|body| body.split_once("</b>\n"))
+    {
+        return group;
+    }
+
+    let sender = summary
+        .strip_suffix(')')
+        .and_then(|summary| summary.rsplit_once(" ("))
+        .map_or(summary, |(sender, _)| sender);
+    (sender.trim(), body)
+}
+
 trait MessageExt {
     fn get_source(&self) -> Result<String>;
+    fn get_urgency(&self) -> Urgency;
 }
 
 impl MessageExt for Message {
@@ -100,6 +193,26 @@ impl MessageExt for Message {
         self.get1::<String>()
             .ok_or_else(|| anyhow!("Couldn't get source"))
     }
+
+    /// Reads the `urgency` hint, notifications without one are of normal
+    /// urgency
+    fn get_urgency(&self) -> Urgency {
+        if let Some(MessageItem::Dict(hints)) = self.get_items().get(6) {
+            for (key, value) in hints.iter() {
+                if let (MessageItem::Str(key), MessageItem::Variant(value)) = (key, value) {
+                    if key == "urgency" {
+                        return match **value {
+                            MessageItem::Byte(0) => Urgency::Low,
+                            MessageItem::Byte(2) => Urgency::Critical,
+                            _ => Urgency::Normal,
+                        };
+                    }
+                }
+            }
+        }
+
+        Urgency::default()
+    }
 }
 
 impl NotificationProvider for Dbus {
@@ -170,3 +283,51 @@ impl NotificationProvider for Dbus {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slack_direct_messages() {
+        assert_eq!(slack_sender("New message from Alice"), "Alice");
+        assert_eq!(slack_sender("Alice"), "Alice");
+        assert_eq!(slack_sender(" Alice "), "Alice");
+    }
+
+    #[test]
+    fn slack_channels() {
+        assert_eq!(slack_sender("Bob in #general"), "Bob");
+        assert_eq!(slack_sender("New message from Carol in #random"), "Carol");
+    }
+
+    #[test]
+    fn telegram_direct_chats() {
+        assert_eq!(telegram_sender("Alice", "Hi"), ("Alice", "Hi"));
+    }
+
+    #[test]
+    fn telegram_groups() {
+        assert_eq!(
+            telegram_sender("Family", "<b>Mom</b>\nDinner's ready"),
+            ("Mom", "Dinner's ready")
+        );
+        assert_eq!(telegram_sender("Bob (Work)", "Meeting"), ("Bob", "Meeting"));
+        assert_eq!(
+            telegram_sender("Bob (Smith) (Work)", "Meeting"),
+            ("Bob (Smith)", "Meeting")
+        );
+    }
+
+    #[test]
+    fn telegram_leaves_markup_alone() {
+        assert_eq!(
+            telegram_sender("Tom &amp; Jerry", "&lt;3"),
+            ("Tom &amp; Jerry", "&lt;3")
+        );
+        assert_eq!(
+            telegram_sender("Alice", "<b>bold</b> text"),
+            ("Alice", "<b>bold</b> text")
+        );
+    }
+}
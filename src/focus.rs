@@ -0,0 +1,102 @@
+#[cfg(all(feature = "dbus-support", target_os = "linux"))]
+use crate::dbus::dnd::DesktopDnd;
+use log::info;
+#[cfg(all(feature = "dbus-support", target_os = "linux"))]
+use log::warn;
+use serde::Deserialize;
+
+/// The settings of the `[focus]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct FocusSettings {
+    /// The screen that's shown while focusing
+    provider: String,
+    /// Whether the desktop notifications are silenced as well
+    #[cfg_attr(
+        not(all(feature = "dbus-support", target_os = "linux")),
+        allow(dead_code)
+    )]
+    desktop: bool,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self {
+            provider: String::from("clock"),
+            desktop: true,
+        }
+    }
+}
+
+/// Focus mode hides all notifications, switches to a calm screen and turns on
+/// the do not disturb mode of the desktop as well. Turning it off again
+/// reverses all of that.
+#[derive(Default)]
+pub(crate) struct Focus {
+    active: bool,
+    /// The screen that was shown before focus mode was turned on
+    previous: Option<String>,
+    #[cfg(all(feature = "dbus-support", target_os = "linux"))]
+    desktop: Option<DesktopDnd>,
+}
+
+impl Focus {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Turns focus mode on while `current` is shown. Returns the screen that
+    /// should be shown while focusing.
+    pub async fn enable(
+        &mut self,
+        settings: &FocusSettings,
+        current: Option<&str>,
+    ) -> Option<String> {
+        if self.active {
+            return None;
+        }
+
+        info!("Turning on focus mode");
+        self.active = true;
+        self.previous = current.map(ToString::to_string);
+
+        #[cfg(all(feature = "dbus-support", target_os = "linux"))]
+        if settings.desktop {
+            let desktop = match self.desktop.take() {
+                Some(desktop) => Ok(desktop),
+                None => DesktopDnd::new(),
+            };
+
+            match desktop {
+                Ok(mut desktop) => {
+                    if let Err(e) = desktop.enable().await {
+                        warn!("Couldn't silence the desktop notifications: {}", e);
+                    }
+                    self.desktop = Some(desktop);
+                }
+                Err(e) => warn!("Couldn't silence the desktop notifications: {}", e),
+            }
+        }
+
+        Some(settings.provider.clone())
+    }
+
+    /// Turns focus mode off again. Returns the screen that was shown before.
+    pub async fn disable(&mut self) -> Option<String> {
+        if !self.active {
+            return None;
+        }
+
+        info!("Turning off focus mode");
+        self.active = false;
+
+        #[cfg(all(feature = "dbus-support", target_os = "linux"))]
+        if let Some(desktop) = &mut self.desktop {
+            if let Err(e) = desktop.disable().await {
+                warn!("Couldn't restore the desktop notifications: {}", e);
+            }
+        }
+
+        self.previous.take()
+    }
+}
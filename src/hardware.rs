@@ -0,0 +1,118 @@
+use anyhow::Result;
+#[cfg(feature = "engine")]
+use apex_engine::Engine;
+#[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+use apex_hardware::USBDevice;
+use apex_hardware::{AsyncDevice, FrameBuffer};
+use apex_input::Command;
+#[cfg(feature = "simulator")]
+use apex_simulator::Simulator;
+use std::future::Future;
+use tokio::sync::broadcast;
+
+/// Whatever the frames end up on, the features of the build decide which of
+/// these are around
+pub(crate) enum Hardware {
+    #[cfg(feature = "simulator")]
+    Simulator(Simulator),
+    #[cfg(feature = "engine")]
+    Engine(Engine),
+    #[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+    Usb(USBDevice),
+}
+
+/// Picks the device, the simulator sends the keys pressed in its window
+/// through `sender`
+#[allow(unreachable_code)]
+#[cfg_attr(not(feature = "simulator"), allow(unused_variables))]
+pub(crate) async fn connect(
+    simulator: bool,
+    sender: broadcast::Sender<Command>,
+) -> Result<Hardware> {
+    // Builds without any hardware support always use the simulator
+    if simulator || cfg!(not(any(feature = "usb", feature = "engine"))) {
+        #[cfg(feature = "simulator")]
+        return Ok(Hardware::Simulator(Simulator::connect(sender)));
+        anyhow::bail!("This build doesn't include the simulator, enable the `simulator` feature!");
+    }
+
+    #[cfg(feature = "engine")]
+    return Ok(Hardware::Engine(Engine::new().await?));
+    #[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+    return Ok(Hardware::Usb(USBDevice::try_connect()?));
+
+    anyhow::bail!("This build can't talk to the keyboard here, enable the `engine` feature!")
+}
+
+/// The simulator with a grid between the pixels, previews of a single screen
+/// always use it
+#[cfg_attr(not(feature = "simulator"), allow(unused_variables))]
+pub(crate) fn preview(sender: broadcast::Sender<Command>) -> Result<Hardware> {
+    #[cfg(feature = "simulator")]
+    return Ok(Hardware::Simulator(Simulator::with_pixel_grid(sender)));
+    #[cfg(not(feature = "simulator"))]
+    anyhow::bail!("Previews need the simulator, enable the `simulator` feature!")
+}
+
+impl AsyncDevice for Hardware {
+    type ClearResult<'a> = impl Future<Output = Result<()>> + 'a;
+    type DrawResult<'a> = impl Future<Output = Result<()>> + 'a;
+    type ReconnectResult<'a> = impl Future<Output = Result<()>> + 'a;
+    type ShutdownResult<'a> = impl Future<Output = Result<()>> + 'a;
+
+    #[allow(clippy::needless_lifetimes)]
+    fn draw<'this>(&'this mut self, display: &'this FrameBuffer) -> Self::DrawResult<'this> {
+        async move {
+            match *self {
+                #[cfg(feature = "simulator")]
+                Self::Simulator(ref mut device) => device.draw(display).await,
+                #[cfg(feature = "engine")]
+                Self::Engine(ref mut device) => device.draw(display).await,
+                #[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+                Self::Usb(ref mut device) => device.draw(display).await,
+            }
+        }
+    }
+
+    #[allow(clippy::needless_lifetimes)]
+    fn clear<'this>(&'this mut self) -> Self::ClearResult<'this> {
+        async move {
+            match *self {
+                #[cfg(feature = "simulator")]
+                Self::Simulator(ref mut device) => device.clear().await,
+                #[cfg(feature = "engine")]
+                Self::Engine(ref mut device) => device.clear().await,
+                #[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+                Self::Usb(ref mut device) => device.clear().await,
+            }
+        }
+    }
+
+    #[allow(clippy::needless_lifetimes)]
+    fn shutdown<'this>(&'this mut self) -> Self::ShutdownResult<'this> {
+        async move {
+            match *self {
+                #[cfg(feature = "simulator")]
+                Self::Simulator(ref mut device) => device.shutdown().await,
+                #[cfg(feature = "engine")]
+                Self::Engine(ref mut device) => device.shutdown().await,
+                #[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+                Self::Usb(ref mut device) => device.shutdown().await,
+            }
+        }
+    }
+
+    #[allow(clippy::needless_lifetimes)]
+    fn reconnect<'this>(&'this mut self) -> Self::ReconnectResult<'this> {
+        async move {
+            match *self {
+                #[cfg(feature = "simulator")]
+                Self::Simulator(ref mut device) => device.reconnect().await,
+                #[cfg(feature = "engine")]
+                Self::Engine(ref mut device) => device.reconnect().await,
+                #[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
+                Self::Usb(ref mut device) => device.reconnect().await,
+            }
+        }
+    }
+}
@@ -35,67 +35,300 @@ use log::warn;
 #[cfg(all(feature = "dbus-support", target_os = "linux"))]
 mod dbus;
 
+#[cfg(unix)]
+mod control;
+mod crash;
+mod focus;
+mod hardware;
+// Screens from shared libraries that are loaded at runtime
+#[cfg(feature = "plugins")]
+mod plugins;
 mod providers;
+// Not every provider needs everything PulseAudio knows
+#[cfg(all(feature = "pulse", target_os = "linux"))]
+#[allow(dead_code)]
+mod pulse;
 mod render;
+// Windows starts services in a way of its own
+#[cfg(all(feature = "service", target_os = "windows"))]
+mod service;
+mod settings;
+// Not every provider needs to persist state
+#[allow(dead_code)]
+mod state;
 
-#[cfg(all(feature = "simulator", feature = "usb"))]
-compile_error!(
-    "The features `simulator` and `usb` are mutually exclusive. Use --no-default-features!"
-);
-
-#[cfg(feature = "simulator")]
-use apex_simulator::Simulator;
-
-use crate::render::{scheduler, scheduler::Scheduler};
-#[cfg(feature = "engine")]
-use apex_engine::Engine;
+use crate::{
+    render::{preview::Preview, registry::ProviderRegistry, scheduler::Scheduler},
+    settings::Settings,
+};
 use apex_hardware::AsyncDevice;
-#[cfg(all(feature = "usb", target_os = "linux", not(feature = "engine")))]
-use apex_hardware::USBDevice;
+use clap::{Parser, Subcommand};
 use log::{info, LevelFilter};
 use simplelog::{Config as LoggerConfig, SimpleLogger};
+use std::path::PathBuf;
 use tokio::sync::broadcast;
 
 use apex_input::Command;
 
+#[derive(Parser, Debug)]
+#[clap(version, author = "not-jan")]
+struct Opts {
+    /// Read the settings from this file instead of the default locations
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// The most verbose log messages to show (off, error, warn, info, debug or
+    /// trace)
+    #[arg(long, default_value = "info")]
+    log_level: LevelFilter,
+    /// Print the names of all available screens and exit
+    #[arg(long)]
+    list_providers: bool,
+    /// The name of the screen to start on, see `--list-providers`
+    #[arg(short, long)]
+    provider: Option<String>,
+    /// Show everything in a window instead of on the keyboard. This needs the
+    /// `simulator` feature.
+    #[arg(long)]
+    simulator: bool,
+    #[command(subcommand)]
+    mode: Option<Mode>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Mode {
+    /// Show only one screen in the simulator with a grid on top and create it
+    /// again whenever the settings change, M toggles the grid. This needs the
+    /// `simulator` feature.
+    Preview {
+        /// The name of the screen, see `--list-providers`
+        provider: String,
+    },
+    /// Register apex-tux as a Windows service that starts along with Windows,
+    /// `--config`, `--log-level` and `--provider` are passed on to it.
+    /// `--config` is required. This needs the `service` feature and an
+    /// administrator.
+    #[cfg(all(feature = "service", target_os = "windows"))]
+    Install,
+    /// Stop and remove the Windows service again
+    #[cfg(all(feature = "service", target_os = "windows"))]
+    Uninstall,
+    /// How Windows starts the service
+    #[cfg(all(feature = "service", target_os = "windows"))]
+    #[command(hide = true)]
+    Service,
+}
+
+/// Everything this build can show, the features decide what's in there
+fn registry() -> ProviderRegistry {
+    use crate::providers::*;
+
+    let mut registry = ProviderRegistry::new();
+    registry.register(clock::PROVIDER_INIT);
+    #[cfg(feature = "ambient")]
+    registry.register(ambient::PROVIDER_INIT);
+    #[cfg(feature = "banner")]
+    registry.register(banner::PROVIDER_INIT);
+    #[cfg(feature = "chess-clock")]
+    registry.register(chess_clock::PROVIDER_INIT);
+    #[cfg(feature = "ci")]
+    registry
+        .register(ci::PROVIDER_INIT)
+        .register_notifications("ci", ci::NOTIFICATION_INIT);
+    #[cfg(feature = "cmus")]
+    registry.register(cmus::PROVIDER_INIT);
+    #[cfg(feature = "crypto")]
+    registry.register(coindesk::PROVIDER_INIT);
+    #[cfg(feature = "countdown")]
+    registry
+        .register(countdown::PROVIDER_INIT)
+        .register_notifications("countdown", countdown::NOTIFICATION_INIT);
+    #[cfg(feature = "sysinfo")]
+    registry
+        .register(cpu_graph::PROVIDER_INIT)
+        .register(net_graph::PROVIDER_INIT)
+        .register(sysinfo::PROVIDER_INIT);
+    #[cfg(feature = "dice")]
+    registry.register(dice::PROVIDER_INIT);
+    #[cfg(feature = "gsi")]
+    registry
+        .register(gsi::PROVIDER_INIT)
+        .register_notifications("gsi", gsi::NOTIFICATION_INIT);
+    #[cfg(feature = "habits")]
+    registry.register(habits::PROVIDER_INIT);
+    #[cfg(feature = "image")]
+    registry.register(image::PROVIDER_INIT);
+    #[cfg(feature = "json")]
+    registry.register(json::PROVIDER_INIT);
+    #[cfg(feature = "lastfm")]
+    registry.register(lastfm::PROVIDER_INIT);
+    #[cfg(feature = "mail")]
+    registry
+        .register(mail::PROVIDER_INIT)
+        .register_notifications("mail", mail::NOTIFICATION_INIT);
+    #[cfg(feature = "metronome")]
+    registry.register(metronome::PROVIDER_INIT);
+    #[cfg(all(feature = "mic", target_os = "linux"))]
+    registry.register_overlay(mic::OVERLAY_INIT);
+    #[cfg(feature = "mpd")]
+    registry.register(mpd::PROVIDER_INIT);
+    #[cfg(feature = "mqtt")]
+    registry
+        .register(mqtt::PROVIDER_INIT)
+        .register_notifications("mqtt", mqtt::NOTIFICATION_INIT);
+    #[cfg(any(feature = "dbus-support", target_os = "windows", target_os = "macos"))]
+    registry.register(music::PROVIDER_INIT);
+    #[cfg(feature = "obs")]
+    registry.register(obs::PROVIDER_INIT);
+    #[cfg(feature = "ping")]
+    registry.register(ping::PROVIDER_INIT);
+    #[cfg(feature = "rss")]
+    registry.register(rss::PROVIDER_INIT);
+    #[cfg(feature = "sports")]
+    registry
+        .register(sports::PROVIDER_INIT)
+        .register_notifications("sports", sports::NOTIFICATION_INIT);
+    #[cfg(feature = "spotify")]
+    registry.register(spotify::PROVIDER_INIT);
+    #[cfg(feature = "twitch")]
+    registry
+        .register(twitch::PROVIDER_INIT)
+        .register_notifications("twitch", twitch::NOTIFICATION_INIT);
+    #[cfg(all(feature = "typing", any(target_os = "linux", target_os = "windows")))]
+    registry.register(typing::PROVIDER_INIT);
+    #[cfg(feature = "ups")]
+    registry
+        .register(ups::PROVIDER_INIT)
+        .register_notifications("ups", ups::NOTIFICATION_INIT);
+    #[cfg(all(feature = "volume", target_os = "linux"))]
+    registry.register_overlay(volume::OVERLAY_INIT);
+    #[cfg(all(feature = "vu-meter", target_os = "linux"))]
+    registry.register(vu_meter::PROVIDER_INIT);
+    #[cfg(feature = "wasm")]
+    registry.register(wasm::PROVIDER_INIT);
+    #[cfg(all(feature = "wifi", target_os = "linux"))]
+    registry.register(wifi::PROVIDER_INIT);
+    #[cfg(feature = "world-clock")]
+    registry.register(world_clock::PROVIDER_INIT);
+    #[cfg(feature = "debug")]
+    registry.register(render::debug::PROVIDER_INIT);
+
+    #[cfg(all(feature = "dbus-support", target_os = "linux"))]
+    registry
+        .register_notifications("dbus", dbus::notifications::NOTIFICATION_INIT)
+        .register_notifications("kdeconnect", dbus::kdeconnect::NOTIFICATION_INIT);
+
+    // Providers that still register themselves the old way
+    #[cfg(feature = "linkme")]
+    registry.register_distributed();
+
+    #[cfg(feature = "plugins")]
+    plugins::register(&mut registry);
+
+    registry
+}
+
+async fn run<T: AsyncDevice>(
+    mut device: T,
+    rx: broadcast::Receiver<Command>,
+    settings: &Settings,
+    provider: Option<String>,
+) -> Result<()> {
+    device.clear().await?;
+
+    let mut scheduler = Scheduler::new(device).with_registry(registry());
+
+    // Widgets can follow what's on the keyboard through D-Bus
+    #[cfg(all(feature = "dbus-support", target_os = "linux"))]
+    {
+        let status = scheduler.status();
+        tokio::spawn(async move {
+            if let Err(e) = dbus::export::export(status).await {
+                warn!("Couldn't publish the state on D-Bus: {}", e);
+            }
+        });
+    }
+
+    if let Some(provider) = provider {
+        scheduler = scheduler.with_initial_provider(provider);
+    }
+    scheduler.start(rx, settings).await
+}
+
 #[tokio::main]
 #[allow(clippy::missing_errors_doc)]
 pub async fn main() -> Result<()> {
-    SimpleLogger::init(LevelFilter::Info, LoggerConfig::default())?;
+    let opts = Opts::parse();
+
+    #[cfg(all(feature = "service", target_os = "windows"))]
+    match opts.mode {
+        Some(Mode::Install) => return service::install(&opts),
+        Some(Mode::Uninstall) => return service::uninstall(),
+        // The service logs to the event log and runs on a runtime of its own
+        Some(Mode::Service) => return tokio::task::block_in_place(service::dispatch),
+        _ => {}
+    }
+
+    SimpleLogger::init(opts.log_level, LoggerConfig::default())?;
+    crash::install();
 
     // This channel is used to send commands to the scheduler
     let (tx, rx) = broadcast::channel::<Command>(100);
-    #[cfg(all(feature = "usb", target_family = "unix", not(feature = "engine")))]
-    let mut device = USBDevice::try_connect()?;
+    start(opts, tx, rx).await
+}
+
+/// Everything after the logger is set up, the Windows service gets here too
+async fn start(
+    opts: Opts,
+    tx: broadcast::Sender<Command>,
+    rx: broadcast::Receiver<Command>,
+) -> Result<()> {
+    let settings = Settings::new(opts.config);
+
+    if opts.list_providers {
+        for name in registry().names(&settings.load()?) {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
 
+    // `[hotkeys]` maps the names of providers to hotkeys that jump right to them
     #[cfg(feature = "hotkeys")]
-    let hkm = apex_input::InputManager::new(tx.clone());
-
-    #[cfg(feature = "engine")]
-    let mut device = Engine::new().await?;
-
-    let mut settings = config::Config::default();
-    // Add in `$USER_CONFIG_DIR/apex-tux/settings.toml`
-    if let Some(user_config_dir) = dirs::config_dir() {
-        settings.merge(
-            config::File::with_name(&user_config_dir.join("apex-tux/settings").to_string_lossy())
-                .required(false),
-        )?;
+    let hkm = {
+        let config = settings.load()?;
+        let notifications: render::notifications::NotificationSettings =
+            settings::section(&config, "notifications")?;
+        apex_input::InputManager::new(
+            tx.clone(),
+            &settings::section(&config, "hotkeys")?,
+            &notifications.dismiss,
+        )?
     };
-    settings
-        // Add in `./settings.toml`
-        .merge(config::File::with_name("settings").required(false))?
-        // Add in settings from the environment (with a prefix of APEX)
-        // Eg.. `APEX_DEBUG=1 ./target/app` would set the `debug` key
-        .merge(config::Environment::with_prefix("APEX_"))?;
 
-    #[cfg(feature = "simulator")]
-    let mut device = Simulator::connect(tx.clone());
+    // Keep the watcher around, the settings are only watched as long as it's alive
+    let _watcher = settings
+        .watch(tx.clone())
+        .map_err(|e| warn!("Changes to the settings won't be picked up: {}", e))
+        .ok();
 
-    device.clear().await?;
+    // Scripts can send the same commands as the hotkeys through this socket
+    #[cfg(unix)]
+    if let Err(e) = control::socket_path().and_then(|path| control::listen(&path, tx.clone())) {
+        warn!("Couldn't open the control socket: {}", e);
+    }
+
+    if let Some(Mode::Preview { provider }) = opts.mode {
+        let device = hardware::preview(tx)?;
+        return Preview::new(device, registry(), provider)
+            .start(rx, &settings)
+            .await;
+    }
+
+    // Home automation can send them through MQTT as well, just not to the preview
+    #[cfg(feature = "mqtt")]
+    providers::mqtt::listen(settings.clone(), tx.clone());
 
-    let mut scheduler = Scheduler::new(device);
-    scheduler.start(tx.clone(), rx, settings).await?;
+    let device = hardware::connect(opts.simulator, tx.clone()).await?;
+    run(device, rx, &settings, opts.provider).await?;
 
     ctrlc::set_handler(move || {
         info!("Ctrl + C received, shutting down!");
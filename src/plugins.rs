@@ -0,0 +1,191 @@
+use crate::render::{
+    display::{ContentProvider, ProviderInput},
+    registry::{External, ProviderRegistry},
+    scheduler::{ContentWrapper, NotificationWrapper},
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_input::Command;
+use apex_provider::{
+    NotificationProvider, Plugin, PluginAbi, PluginEntry, PluginNotifier, PluginScreen, ABI,
+    PLUGIN_ABI, PLUGIN_ENTRY,
+};
+use async_stream::try_stream;
+use config::Config;
+use futures::Stream;
+use libloading::Library;
+use log::{info, warn};
+use std::{
+    env::consts::DLL_EXTENSION,
+    ffi::CStr,
+    fs,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    sync::mpsc,
+    time::{self, MissedTickBehavior},
+};
+
+/// Where the plugins are loaded from, e.g. `~/.config/apex-tux/plugins` on
+/// Linux
+fn plugin_dir() -> PathBuf {
+    crate::state::config_dir().join("plugins")
+}
+
+/// Loads every shared library in the plugin directory and registers what they
+/// provide. Broken plugins are skipped, a missing directory means there are
+/// none.
+pub(crate) fn register(registry: &mut ProviderRegistry) {
+    let entries = fs::read_dir(plugin_dir()).into_iter().flatten();
+
+    for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        if path
+            .extension()
+            .map_or(true, |extension| extension != DLL_EXTENSION)
+        {
+            continue;
+        }
+
+        match load(&path) {
+            Ok(plugin) => {
+                info!("Loaded the plugin {}", path.display());
+                registry.register_plugin(plugin);
+            }
+            Err(e) => warn!("Couldn't load the plugin {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<Plugin> {
+    // Loading a library runs its initializers, there's no way around trusting
+    // what's in the plugin directory
+    let library = unsafe { Library::new(path) }?;
+
+    // Everything past this point relies on the plugin laying out its types the
+    // same way, only the version check goes through the C ABI
+    let abi = *unsafe { library.get::<PluginAbi>(PLUGIN_ABI) }
+        .map_err(|_| anyhow!("It doesn't say which version of apex-tux it was built for"))?;
+    let abi = unsafe { CStr::from_ptr(abi()) }.to_string_lossy();
+    let expected = ABI.trim_end_matches('\0');
+    if abi != expected {
+        return Err(anyhow!(
+            "It was built for {} but this is {}, it has to be rebuilt",
+            abi,
+            expected
+        ));
+    }
+
+    let entry = *unsafe { library.get::<PluginEntry>(PLUGIN_ENTRY) }.map_err(|_| {
+        anyhow!(
+            "It doesn't export `{}`",
+            String::from_utf8_lossy(&PLUGIN_ENTRY[..PLUGIN_ENTRY.len() - 1])
+        )
+    })?;
+
+    let plugin = unsafe { entry() };
+    if plugin.is_null() {
+        return Err(anyhow!("It didn't register anything"));
+    }
+    let plugin = unsafe { Box::from_raw(plugin) };
+
+    // The providers point into the library so it stays loaded until apex-tux
+    // exits
+    std::mem::forget(library);
+
+    Ok(*plugin)
+}
+
+/// Instantiates the screens of every plugin
+pub(crate) fn screens(plugins: &[Plugin], config: &Config) -> Vec<Result<Box<dyn ContentWrapper>>> {
+    plugins
+        .iter()
+        .flat_map(|plugin| &plugin.screens)
+        .map(|init| {
+            let (input, commands) = mpsc::unbounded_channel();
+            Ok(Box::new(Screen {
+                screen: init(config)?,
+                input,
+                commands: Some(commands),
+            }) as Box<dyn ContentWrapper>)
+        })
+        .collect()
+}
+
+/// Instantiates the sources of notifications of every plugin
+pub(crate) fn notifiers(
+    plugins: &[Plugin],
+    config: &Config,
+) -> Vec<Result<Box<dyn NotificationWrapper>>> {
+    plugins
+        .iter()
+        .flat_map(|plugin| &plugin.notifiers)
+        .map(|init| {
+            let notifier = Notifier(init(config)?);
+            Ok(Box::new(External(Box::new(notifier))) as Box<dyn NotificationWrapper>)
+        })
+        .collect()
+}
+
+/// Drives a screen of a plugin, the timers have to run on this side of the
+/// library
+struct Screen {
+    screen: Box<dyn PluginScreen>,
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+impl ContentProvider for Screen {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The stream of a plugin can only be started once!"))?;
+        let mut interval = time::interval(self.screen.interval());
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    Some(command) = commands.recv() => self.screen.command(command),
+                }
+                yield self.screen.frame()?;
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        self.screen.name()
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(self.input.clone()))
+    }
+}
+
+/// Asks a source of notifications of a plugin for new ones
+struct Notifier(Box<dyn PluginNotifier>);
+
+impl NotificationProvider for Notifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<apex_provider::Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        let mut interval = time::interval(self.0.interval());
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                for notification in self.0.poll()? {
+                    yield notification;
+                }
+            }
+        })
+    }
+}
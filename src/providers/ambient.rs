@@ -0,0 +1,376 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper},
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use chrono::{Local, Timelike};
+use config::Config;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Circle, Line, Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use reqwest::{Client, ClientBuilder};
+use serde::Deserialize;
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The weather glyph takes the top right corner
+const GLYPH_ORIGIN: Point = Point::new(102, 1);
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+/// The settings of the `[ambient]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AmbientSettings {
+    /// Where to get the weather for, only the time and the date are shown
+    /// without them
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    unit: Unit,
+    twelve_hour: bool,
+    /// How often the weather is fetched, in minutes
+    polling_interval: u64,
+}
+
+impl Default for AmbientSettings {
+    fn default() -> Self {
+        Self {
+            latitude: None,
+            longitude: None,
+            unit: Unit::Celsius,
+            twelve_hour: false,
+            polling_interval: 15,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Ambient display source.");
+
+    let settings: AmbientSettings = settings::section(config, "ambient")?;
+    let location = settings.latitude.zip(settings.longitude);
+    if location.is_none() {
+        warn!("There's no `latitude` and `longitude` in [ambient], the weather stays empty");
+    }
+
+    Ok(Box::new(Ambient {
+        client: ClientBuilder::new().user_agent(APP_USER_AGENT).build()?,
+        location,
+        unit: settings.unit,
+        twelve_hour: settings.twelve_hour,
+        polling_interval: Duration::from_secs(settings.polling_interval.max(1) * 60),
+        weather: None,
+    }))
+}
+
+/// The WMO weather interpretation codes, simplified to what can be told
+/// apart in a few pixels
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Sky {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Thunder,
+}
+
+impl From<u8> for Sky {
+    fn from(code: u8) -> Self {
+        match code {
+            0 => Self::Clear,
+            1 | 2 => Self::PartlyCloudy,
+            45 | 48 => Self::Fog,
+            51..=67 | 80..=82 => Self::Rain,
+            71..=77 | 85 | 86 => Self::Snow,
+            95..=99 => Self::Thunder,
+            _ => Self::Cloudy,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Forecast {
+    current_weather: CurrentWeather,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentWeather {
+    temperature: f32,
+    weathercode: u8,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Weather {
+    temperature: f32,
+    sky: Sky,
+}
+
+/// Draws a filled cloud into a 24x14 box at `origin`
+fn draw_cloud<D>(target: &mut D, origin: Point) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+    Circle::new(origin + Point::new(2, 4), 10)
+        .into_styled(fill)
+        .draw(target)?;
+    Circle::new(origin + Point::new(8, 0), 12)
+        .into_styled(fill)
+        .draw(target)?;
+    Rectangle::new(origin + Point::new(7, 8), Size::new(15, 6))
+        .into_styled(fill)
+        .draw(target)
+}
+
+fn draw_sun<D>(target: &mut D, center: Point, radius: i32) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    Circle::with_center(center, (radius * 2) as u32)
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(target)?;
+
+    // The rays start a bit away from the disc
+    for (x, y) in [
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+    ] {
+        let direction = Point::new(x, y);
+        Line::new(
+            center + direction * (radius + 2),
+            center + direction * (radius + 4),
+        )
+        .into_styled(stroke)
+        .draw(target)?;
+    }
+
+    Ok(())
+}
+
+/// Draws a 24x24 glyph for `sky` with `origin` as its top left corner
+fn draw_glyph<D>(target: &mut D, origin: Point, sky: Sky) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+    match sky {
+        Sky::Clear => draw_sun(target, origin + Point::new(12, 12), 5)?,
+        Sky::PartlyCloudy => {
+            draw_sun(target, origin + Point::new(16, 7), 3)?;
+            draw_cloud(target, origin + Point::new(-2, 9))?;
+        }
+        Sky::Cloudy => draw_cloud(target, origin + Point::new(0, 5))?,
+        Sky::Fog => {
+            for (row, indent) in [(4, 0), (9, 3), (14, 0), (19, 3)] {
+                Line::new(
+                    origin + Point::new(indent, row),
+                    origin + Point::new(20 + indent, row),
+                )
+                .into_styled(stroke)
+                .draw(target)?;
+            }
+        }
+        Sky::Rain => {
+            draw_cloud(target, origin)?;
+            for x in [6, 12, 18] {
+                Line::new(origin + Point::new(x, 17), origin + Point::new(x - 3, 23))
+                    .into_styled(stroke)
+                    .draw(target)?;
+            }
+        }
+        Sky::Snow => {
+            draw_cloud(target, origin)?;
+            for (x, y) in [(5, 17), (11, 20), (17, 17), (8, 23), (14, 23), (20, 22)] {
+                Rectangle::new(origin + Point::new(x, y), Size::new(2, 1))
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(target)?;
+            }
+        }
+        Sky::Thunder => {
+            draw_cloud(target, origin)?;
+            let bolt = [(13, 15), (9, 20), (14, 20), (10, 24)];
+            for pair in bolt.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                Line::new(
+                    origin + Point::new(from.0, from.1),
+                    origin + Point::new(to.0, to.1),
+                )
+                .into_styled(stroke)
+                .draw(target)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How long it takes until the next minute starts, the time doesn't show
+/// seconds
+fn until_next_minute() -> Duration {
+    let now = Local::now();
+    let nanos = u64::from(now.nanosecond() % 1_000_000_000);
+    Duration::from_nanos(1_000_000_000 - nanos) + Duration::from_secs(u64::from(59 - now.second()))
+}
+
+/// The time, the date and the weather on one screen so they don't have to
+/// take turns
+struct Ambient {
+    client: Client,
+    /// The latitude and longitude to get the weather for
+    location: Option<(f64, f64)>,
+    unit: Unit,
+    twelve_hour: bool,
+    polling_interval: Duration,
+    /// `None` until the weather was fetched for the first time
+    weather: Option<Weather>,
+}
+
+impl Ambient {
+    async fn fetch(&self, (latitude, longitude): (f64, f64)) -> Result<Weather> {
+        let unit = match self.unit {
+            Unit::Celsius => "celsius",
+            Unit::Fahrenheit => "fahrenheit",
+        };
+
+        let forecast = self
+            .client
+            .get(FORECAST_URL)
+            .query(&[
+                ("latitude", latitude.to_string()),
+                ("longitude", longitude.to_string()),
+                ("current_weather", String::from("true")),
+                ("temperature_unit", String::from(unit)),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Forecast>()
+            .await?;
+
+        Ok(Weather {
+            temperature: forecast.current_weather.temperature,
+            sky: Sky::from(forecast.current_weather.weathercode),
+        })
+    }
+
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let now = Local::now();
+
+        let large = MonoTextStyle::new(&iso_8859_15::FONT_10X20, BinaryColor::On);
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        let time = now
+            .format(if self.twelve_hour { "%I:%M" } else { "%H:%M" })
+            .to_string();
+        let end =
+            Text::with_baseline(&time, Point::new(0, 1), large, Baseline::Top).draw(&mut buffer)?;
+        if self.twelve_hour {
+            Text::with_baseline(
+                &now.format("%p").to_string(),
+                Point::new(end.x + 2, 10),
+                small,
+                Baseline::Top,
+            )
+            .draw(&mut buffer)?;
+        }
+
+        Text::with_baseline(
+            &now.format("%a %-d %b").to_string(),
+            Point::new(0, 28),
+            small,
+            Baseline::Top,
+        )
+        .draw(&mut buffer)?;
+
+        if let Some(weather) = self.weather {
+            draw_glyph(&mut buffer, GLYPH_ORIGIN, weather.sky)?;
+
+            let right = TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Top)
+                .build();
+            Text::with_text_style(
+                &format!("{:.0}\u{b0}", weather.temperature),
+                Point::new(127, 28),
+                small,
+                right,
+            )
+            .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Ambient {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut refetch = time::interval(self.polling_interval);
+        refetch.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                tokio::select! {
+                    _ = refetch.tick(), if self.location.is_some() => {
+                        if let Some(location) = self.location {
+                            // The last weather stays until the next fetch works out
+                            match self.fetch(location).await {
+                                Ok(weather) => self.weather = Some(weather),
+                                Err(e) => warn!("Couldn't get the weather: {}", e),
+                            }
+                        }
+                    }
+                    _ = time::sleep(until_next_minute()) => {}
+                }
+
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "ambient"
+    }
+}
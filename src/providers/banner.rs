@@ -0,0 +1,189 @@
+use crate::{
+    render::{
+        assets::UserIcon,
+        display::ContentProvider,
+        scheduler::ContentWrapper,
+        template::Template,
+        text::{ScrollableBuilder, StatefulScrollable},
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{OriginDimensions, Point, Size},
+    image::Image,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{renderer::TextRenderer, Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use log::info;
+use serde::Deserialize;
+use std::path::Path;
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// The font is 13 pixels high, this centers it vertically
+const TEXT_Y: i32 = 13;
+/// How many ticks every character of the typewriter effect takes
+const TYPEWRITER_SPEED: usize = 2;
+/// How many ticks the full text stays on screen before the typewriter
+/// starts over
+const TYPEWRITER_HOLD: usize = 40;
+/// How many ticks the text stays on and off while blinking
+const BLINK_SPEED: usize = 10;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Effect {
+    /// Shows the text as is, text that's too long for the screen scrolls anyway
+    #[default]
+    Static,
+    /// Scrolls the text from right to left
+    Marquee,
+    /// Reveals the text one character at a time
+    Typewriter,
+    /// Turns the text on and off
+    Blink,
+}
+
+/// The settings of the `[banner]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BannerSettings {
+    text: String,
+    effect: Effect,
+    /// A monochrome BMP that's shown left of the text
+    icon: Option<String>,
+}
+
+impl Default for BannerSettings {
+    fn default() -> Self {
+        Self {
+            text: String::from("Hello, world!"),
+            effect: Effect::default(),
+            icon: None,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Banner display source.");
+
+    let BannerSettings { text, effect, icon } = settings::section(config, "banner")?;
+    let text = Template::new(text);
+    let icon = icon
+        .map(|path| UserIcon::load(Path::new(&path)))
+        .transpose()?;
+
+    // The text uses the space right of the icon
+    let left = icon
+        .as_ref()
+        .map_or(0, |icon| icon.bmp().size().width as i32 + 3);
+
+    let scroller: StatefulScrollable = ScrollableBuilder::new()
+        .with_text(text.render())
+        .with_custom_font(&iso_8859_15::FONT_8X13_BOLD)
+        .with_custom_spacing(16)
+        .with_position(Point::new(left, TEXT_Y))
+        .with_projection(Size::new((128 - left).max(0) as u32, 13))
+        .try_into()?;
+
+    Ok(Box::new(Banner {
+        text,
+        effect,
+        icon,
+        left,
+        scroller,
+    }))
+}
+
+struct Banner {
+    /// The template variables are filled in on every frame
+    text: Template,
+    effect: Effect,
+    icon: Option<UserIcon>,
+    /// Where the text starts
+    left: i32,
+    scroller: StatefulScrollable,
+}
+
+impl Banner {
+    pub fn render(&mut self, tick: usize) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        if let Some(icon) = &self.icon {
+            let bmp = icon.bmp();
+            let y = (40 - bmp.size().height as i32) / 2;
+            Image::new(&bmp, Point::new(0, y)).draw(&mut buffer)?;
+        }
+
+        let text = self.text.render();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_8X13_BOLD, BinaryColor::On);
+        let metrics = style.measure_string(&text, Point::zero(), Baseline::Top);
+        let width = metrics.bounding_box.size.width as i32;
+        let space = 128 - self.left;
+
+        if self.effect == Effect::Marquee || (self.effect == Effect::Static && width > space) {
+            if let Ok(false) = self.scroller.update(&text) {
+                self.scroller.text.scroll();
+            }
+            self.scroller.text.draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        let visible = match self.effect {
+            Effect::Typewriter => {
+                let length = text.chars().count();
+                let cycle = length * TYPEWRITER_SPEED + TYPEWRITER_HOLD;
+                let shown = (tick % cycle.max(1)) / TYPEWRITER_SPEED;
+                text.chars().take(shown).collect()
+            }
+            Effect::Blink if (tick / BLINK_SPEED) % 2 == 1 => String::new(),
+            _ => text,
+        };
+
+        // Center the full text even while only a part of it is shown so it
+        // doesn't move around
+        let x = self.left + (space - width).max(0) / 2;
+        Text::with_baseline(&visible, Point::new(x, TEXT_Y), style, Baseline::Top)
+            .draw(&mut buffer)?;
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Banner {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(50));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            let mut tick = 0_usize;
+            loop {
+                if let Ok(image) = self.render(tick) {
+                    yield image;
+                }
+                tick = tick.wrapping_add(1);
+                interval.tick().await;
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "banner"
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{
+    render::{
+        alert::Alert,
+        digits::BigDigits,
+        display::{ContentProvider, ProviderInput},
+        scheduler::ContentWrapper,
+        timers::{Publisher, Timer},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::{BlendMode, FrameBuffer};
+use apex_input::Command;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    pixelcolor::BinaryColor,
+    primitives::{Line, Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use futures::Stream;
+use log::info;
+use serde::Deserialize;
+use std::time::{Instant, SystemTime};
+use tokio::{
+    sync::mpsc,
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// How often the side that ran out of time flashes before it stays inverted
+const FLAG_FLASHES: u32 = 4;
+/// What the clocks are called on D-Bus
+const SIDES: [&str; 2] = ["Left", "Right"];
+
+/// The settings of the `[chess_clock]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ChessClockSettings {
+    /// The time each player starts with
+    minutes: u64,
+    /// The seconds a player gets back after each move
+    increment: u64,
+}
+
+impl Default for ChessClockSettings {
+    fn default() -> Self {
+        Self {
+            minutes: 5,
+            increment: 0,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Chess clock display source.");
+
+    let settings: ChessClockSettings = settings::section(config, "chess_clock")?;
+    let total = Duration::from_secs(settings.minutes.max(1) * 60);
+
+    let (input, commands) = mpsc::unbounded_channel();
+
+    let clock = ChessClock {
+        total,
+        increment: Duration::from_secs(settings.increment),
+        remaining: [total; 2],
+        active: None,
+        started: Instant::now(),
+        alert: None,
+        timers: Publisher::new("chess_clock"),
+        input,
+        commands: Some(commands),
+    };
+    clock.publish();
+
+    Ok(Box::new(clock))
+}
+
+/// Formats the remaining time as `MM:SS`, or `H:MM` once there's an hour or
+/// more left so it still fits on half of the screen
+fn format_remaining(remaining: Duration) -> String {
+    // Round up so the clock only shows zero once the time is actually up
+    let seconds = (remaining.as_millis() as u64 + 999) / 1000;
+
+    if seconds >= 60 * 60 {
+        format!("{}:{:02}", seconds / 3600, seconds / 60 % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// The half of the screen that belongs to `side`
+fn half(side: usize) -> Rectangle {
+    Rectangle::new(Point::new(64 * side as i32, 0), Size::new(63, 40))
+}
+
+struct ChessClock {
+    /// The time every side starts out with
+    total: Duration,
+    /// The time that gets added after every move
+    increment: Duration,
+    /// The time left for both sides, not accounting for the currently running
+    /// turn
+    remaining: [Duration; 2],
+    /// The side whose clock is running, if any
+    active: Option<usize>,
+    /// When the current turn started
+    started: Instant,
+    /// Goes off once a side ran out of time
+    alert: Option<Alert>,
+    timers: Publisher,
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+impl ChessClock {
+    fn remaining(&self, side: usize) -> Duration {
+        if self.active == Some(side) {
+            self.remaining[side].saturating_sub(self.started.elapsed())
+        } else {
+            self.remaining[side]
+        }
+    }
+
+    /// Returns the side that ran out of time, if any
+    fn flagged(&self) -> Option<usize> {
+        self.active.filter(|side| self.remaining(*side).is_zero())
+    }
+
+    fn reset(&mut self) {
+        self.remaining = [self.total; 2];
+        self.active = None;
+        self.alert = None;
+    }
+
+    /// Ends the current turn and starts the clock of the other side. The first
+    /// press starts the clock and once a side ran out of time the next press
+    /// resets both.
+    fn toggle(&mut self) {
+        if self.flagged().is_some() {
+            self.reset();
+            return;
+        }
+
+        self.active = match self.active {
+            None => Some(0),
+            Some(side) => {
+                self.remaining[side] = self.remaining(side) + self.increment;
+                Some(1 - side)
+            }
+        };
+        self.started = Instant::now();
+    }
+
+    /// Publishes both clocks, the one that's running counts down on its own
+    fn publish(&self) {
+        let now = SystemTime::now();
+        let timers = SIDES
+            .iter()
+            .enumerate()
+            .map(|(side, title)| {
+                if self.active == Some(side) {
+                    Timer::running(*title, now + self.remaining(side))
+                } else {
+                    Timer::paused(*title, self.remaining[side])
+                }
+            })
+            .collect();
+        self.timers.publish(timers);
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let digits = BigDigits::new(Size::new(9, 24), 2);
+        let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+
+        for side in 0..2 {
+            let center = 32 + 64 * side as i32;
+
+            let text = format_remaining(self.remaining(side));
+            let width = digits.width(&text) as i32;
+            digits.draw(&text, Point::new(center - width / 2, 6), &mut buffer)?;
+
+            // Underline the side whose clock is running
+            if self.active == Some(side) {
+                Rectangle::new(Point::new(center - 24, 35), Size::new(48, 3))
+                    .into_styled(fill)
+                    .draw(&mut buffer)?;
+            }
+        }
+
+        Line::new(Point::new(63, 4), Point::new(63, 35))
+            .into_styled(style)
+            .draw(&mut buffer)?;
+
+        // The side that ran out of time flashes and stays inverted afterwards
+        if let Some(side) = self.flagged() {
+            match &self.alert {
+                Some(alert) if !alert.is_over() => alert.apply(&mut buffer)?,
+                _ => {
+                    half(side)
+                        .into_styled(fill)
+                        .draw(&mut buffer.blend(BlendMode::Xor))?;
+                }
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for ChessClock {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(100));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The chess clock stream can only be started once!"))?;
+
+        Ok(try_stream! {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    Some(command) = commands.recv() => {
+                        if let Command::Select = command {
+                            self.toggle();
+                            self.publish();
+                        }
+                    }
+                }
+
+                if let Some(side) = self.flagged() {
+                    if self.alert.is_none() {
+                        self.alert = Some(Alert::region(half(side), FLAG_FLASHES));
+                    }
+                }
+
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "chess_clock"
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(self.input.clone()))
+    }
+}
@@ -0,0 +1,488 @@
+use crate::{
+    render::{
+        accessibility::{self, LARGE_TEXT_ROWS},
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        poller::{Refresh, SharedPoller},
+        scheduler::{ContentWrapper, NotificationWrapper},
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Circle, Line, Polyline, Primitive, PrimitiveStyle},
+    text::{renderer::TextRenderer, Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{future::BoxFuture, Stream};
+use log::{debug, info};
+use reqwest::{header, Client, ClientBuilder, RequestBuilder};
+use serde::Deserialize;
+use tokio::{sync::watch, time::Duration};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The maximum amount of rows that fit on the display with the font we're
+/// using
+const MAX_ENTRIES: usize = 4;
+
+/// The status icons are 8x8 pixels, the names start right after them
+const NAME_LEFT: i32 = 11;
+
+/// The latest pipeline of every project in the order of the settings, see
+/// `Checker::poll`
+type Runs = Vec<Option<Run>>;
+
+/// The screen and the notifications ask the forges together
+static POLLER: SharedPoller<CiSettings, Runs> = SharedPoller::new();
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Forge {
+    GitHub,
+    GitLab,
+}
+
+/// A branch of a repository whose pipelines are watched
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Project {
+    forge: Forge,
+    /// `owner/name` on GitHub, the path or the ID of the project on GitLab
+    repo: String,
+    branch: String,
+    /// What's shown on the screen, the name of the repository if it's not set
+    #[serde(default)]
+    name: Option<String>,
+}
+
+impl Project {
+    fn label(&self) -> &str {
+        self.name
+            .as_deref()
+            .unwrap_or_else(|| self.repo.rsplit('/').next().unwrap_or(&self.repo))
+    }
+}
+
+/// The settings of the `[ci]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CiSettings {
+    projects: Vec<Project>,
+    github_token: Option<String>,
+    gitlab_token: Option<String>,
+    /// Self-hosted GitLab instances are supported as well
+    gitlab_url: String,
+    /// In seconds
+    polling_interval: u64,
+}
+
+impl Default for CiSettings {
+    fn default() -> Self {
+        Self {
+            projects: Vec::new(),
+            github_token: None,
+            gitlab_token: None,
+            gitlab_url: String::from("https://gitlab.com"),
+            polling_interval: 120,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering CI display source.");
+
+    let settings: CiSettings = settings::section(config, "ci")?;
+    let max_entries = if accessibility::large_text() {
+        LARGE_TEXT_ROWS
+    } else {
+        MAX_ENTRIES
+    };
+
+    Ok(Box::new(Ci {
+        runs: subscribe(&settings)?,
+        projects: settings.projects,
+        entries: max_entries,
+    }))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering CI notification source.");
+
+    let settings: CiSettings = settings::section(config, "ci")?;
+
+    Ok(Box::new(CiNotifier {
+        runs: subscribe(&settings)?,
+        projects: settings.projects,
+    }))
+}
+
+fn subscribe(settings: &CiSettings) -> Result<watch::Receiver<Runs>> {
+    let interval = Duration::from_secs(settings.polling_interval.max(1));
+    POLLER.subscribe(settings, Vec::new(), interval, || {
+        Checker::new(settings.clone())
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Status {
+    Running,
+    Passed,
+    Failed,
+    /// Cancelled, skipped or waiting for someone to start it
+    Other,
+}
+
+impl Status {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Passed | Self::Failed)
+    }
+
+    /// Draws an 8x8 icon with `origin` as its top left corner
+    fn draw_icon(self, buffer: &mut FrameBuffer, origin: Point) -> Result<()> {
+        let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+        match self {
+            Self::Passed => {
+                let points = [Point::new(0, 4), Point::new(2, 6), Point::new(7, 1)]
+                    .map(|point| origin + point);
+                Polyline::new(&points).into_styled(stroke).draw(buffer)?;
+            }
+            Self::Failed => {
+                Line::new(origin, origin + Point::new(7, 7))
+                    .into_styled(stroke)
+                    .draw(buffer)?;
+                Line::new(origin + Point::new(7, 0), origin + Point::new(0, 7))
+                    .into_styled(stroke)
+                    .draw(buffer)?;
+            }
+            Self::Running => {
+                Circle::new(origin, 8).into_styled(stroke).draw(buffer)?;
+                Circle::new(origin + Point::new(3, 3), 2)
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(buffer)?;
+            }
+            Self::Other => {
+                Line::new(origin + Point::new(1, 4), origin + Point::new(6, 4))
+                    .into_styled(stroke)
+                    .draw(buffer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The latest pipeline of a project
+#[derive(Debug, Copy, Clone)]
+struct Run {
+    id: u64,
+    status: Status,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRuns {
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRun {
+    id: u64,
+    status: String,
+    conclusion: Option<String>,
+}
+
+impl From<WorkflowRun> for Run {
+    fn from(run: WorkflowRun) -> Self {
+        let status = match (run.status.as_str(), run.conclusion.as_deref()) {
+            ("completed", Some("success")) => Status::Passed,
+            ("completed", Some("failure" | "timed_out" | "startup_failure")) => Status::Failed,
+            ("completed", _) => Status::Other,
+            _ => Status::Running,
+        };
+
+        Self { id: run.id, status }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Pipeline {
+    id: u64,
+    status: String,
+}
+
+impl From<Pipeline> for Run {
+    fn from(pipeline: Pipeline) -> Self {
+        let status = match pipeline.status.as_str() {
+            "success" => Status::Passed,
+            "failed" => Status::Failed,
+            "created" | "waiting_for_resource" | "preparing" | "pending" | "running" => {
+                Status::Running
+            }
+            _ => Status::Other,
+        };
+
+        Self {
+            id: pipeline.id,
+            status,
+        }
+    }
+}
+
+/// Asks GitHub and GitLab about the latest pipelines of the projects
+struct Checker {
+    client: Client,
+    settings: CiSettings,
+}
+
+impl Checker {
+    fn new(settings: CiSettings) -> Result<Self> {
+        Ok(Self {
+            client: ClientBuilder::new().user_agent(APP_USER_AGENT).build()?,
+            settings,
+        })
+    }
+
+    fn request(&self, project: &Project) -> RequestBuilder {
+        match project.forge {
+            Forge::GitHub => {
+                let url = format!("https://api.github.com/repos/{}/actions/runs", project.repo);
+                let request = self
+                    .client
+                    .get(url)
+                    .header(header::ACCEPT, "application/vnd.github+json")
+                    .query(&[("branch", project.branch.as_str()), ("per_page", "1")]);
+                match &self.settings.github_token {
+                    Some(token) => request.bearer_auth(token),
+                    None => request,
+                }
+            }
+            Forge::GitLab => {
+                // The path of the project has to be passed as a single segment
+                let url = format!(
+                    "{}/api/v4/projects/{}/pipelines",
+                    self.settings.gitlab_url.trim_end_matches('/'),
+                    project.repo.replace('/', "%2F")
+                );
+                let request = self
+                    .client
+                    .get(url)
+                    .query(&[("ref", project.branch.as_str()), ("per_page", "1")]);
+                match &self.settings.gitlab_token {
+                    Some(token) => request.header("PRIVATE-TOKEN", token),
+                    None => request,
+                }
+            }
+        }
+    }
+
+    /// Returns the latest pipeline on the branch of `project`, `None` if
+    /// there never was one
+    async fn latest(&self, project: &Project) -> Result<Option<Run>> {
+        let response = self.request(project).send().await?.error_for_status()?;
+
+        Ok(match project.forge {
+            Forge::GitHub => response
+                .json::<WorkflowRuns>()
+                .await?
+                .workflow_runs
+                .into_iter()
+                .next()
+                .map(Run::from),
+            Forge::GitLab => response
+                .json::<Vec<Pipeline>>()
+                .await?
+                .into_iter()
+                .next()
+                .map(Run::from),
+        })
+    }
+
+    /// Returns the latest pipeline of every project in the order of the
+    /// settings, projects that couldn't be checked are `None`
+    async fn poll(&self) -> Runs {
+        let mut runs = Vec::with_capacity(self.settings.projects.len());
+
+        for project in &self.settings.projects {
+            runs.push(match self.latest(project).await {
+                Ok(run) => run,
+                Err(e) => {
+                    debug!("Couldn't check the pipelines of {}: {}", project.repo, e);
+                    None
+                }
+            });
+        }
+
+        runs
+    }
+}
+
+impl Refresh for Checker {
+    type Output = Runs;
+
+    fn refresh(&mut self) -> BoxFuture<'_, Option<Runs>> {
+        Box::pin(async move { Some(self.poll().await) })
+    }
+}
+
+struct Ci {
+    projects: Vec<Project>,
+    /// The latest pipelines in the order of the projects
+    runs: watch::Receiver<Runs>,
+    entries: usize,
+}
+
+impl Ci {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let font = if accessibility::large_text() {
+            &iso_8859_15::FONT_9X15
+        } else {
+            &iso_8859_15::FONT_6X10
+        };
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let char_width = style.font.character_size.width as i32;
+        let char_height = style.font.character_size.height as i32;
+
+        let projects = &self.projects;
+        if projects.is_empty() {
+            let centered = TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Middle)
+                .build();
+            Text::with_text_style("No projects", Point::new(64, 20), style, centered)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        let row_height = 40 / self.entries as i32;
+
+        for (row, project) in projects.iter().take(self.entries).enumerate() {
+            let y = row as i32 * row_height + (row_height - char_height) / 2;
+
+            // Projects are `None` until they were checked successfully
+            match self.runs.borrow().get(row).copied().flatten() {
+                Some(run) => run
+                    .status
+                    .draw_icon(&mut buffer, Point::new(0, y + (char_height - 8) / 2))?,
+                None => {
+                    Text::with_baseline("?", Point::new(0, y), style, Baseline::Top)
+                        .draw(&mut buffer)?;
+                }
+            }
+
+            let metrics = style.measure_string(&project.branch, Point::zero(), Baseline::Top);
+            let branch_x = 128 - metrics.bounding_box.size.width as i32;
+            Text::with_baseline(
+                &project.branch,
+                Point::new(branch_x, y),
+                style,
+                Baseline::Top,
+            )
+            .draw(&mut buffer)?;
+
+            // Cut off the name so it doesn't run into the branch
+            let max_chars = ((branch_x - NAME_LEFT - char_width) / char_width).max(0) as usize;
+            let name = project.label().chars().take(max_chars).collect::<String>();
+            Text::with_baseline(&name, Point::new(NAME_LEFT, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Ci {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        Ok(try_stream! {
+            loop {
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                if self.runs.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "ci"
+    }
+}
+
+/// Tells when a pipeline on one of the watched branches finishes
+struct CiNotifier {
+    projects: Vec<Project>,
+    runs: watch::Receiver<Runs>,
+}
+
+impl NotificationProvider for CiNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        Ok(try_stream! {
+            // Pipelines that were already done when we started aren't announced
+            let mut previous: Runs = Vec::new();
+
+            while self.runs.changed().await.is_ok() {
+                let current = self.runs.borrow().clone();
+                previous.resize(current.len(), None);
+
+                for (index, project) in self.projects.iter().enumerate() {
+                    // A failed check keeps what we knew before
+                    let run = match current[index] {
+                        Some(run) => run,
+                        None => continue,
+                    };
+                    let before = previous[index].replace(run);
+
+                    // Pipelines that started and finished between two polls count as well
+                    let finished = match before {
+                        Some(before) => {
+                            run.status.is_finished()
+                                && (before.id != run.id || !before.status.is_finished())
+                        }
+                        None => false,
+                    };
+                    if !finished {
+                        continue;
+                    }
+
+                    let content = match run.status {
+                        Status::Passed => format!("passed on {}", project.branch),
+                        _ => format!("failed on {}", project.branch),
+                    };
+                    if let Ok(notification) = NotificationBuilder::new()
+                        .with_title(project.label())
+                        .with_content(content)
+                        .build()
+                    {
+                        yield notification;
+                    }
+                }
+            }
+        })
+    }
+}
@@ -1,32 +1,38 @@
 use crate::{
     render::{display::ContentProvider, scheduler::ContentWrapper},
-    scheduler::CONTENT_PROVIDERS,
+    settings,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use apex_hardware::FrameBuffer;
 use async_stream::try_stream;
-use chrono::{DateTime, Local};
+use chrono::{
+    format::{Fixed, Item, Numeric, StrftimeItems},
+    DateTime, Local, Timelike,
+};
 use config::Config;
 use embedded_graphics::{
     geometry::Point,
     mono_font::{iso_8859_15, MonoTextStyle},
     pixelcolor::BinaryColor,
-    text::{renderer::TextRenderer, Baseline, Text},
+    primitives::{Circle, Line, Primitive, PrimitiveStyle},
+    text::{renderer::TextRenderer, Alignment, Baseline, Text, TextStyleBuilder},
     Drawable,
 };
 use futures::Stream;
-use linkme::distributed_slice;
 use log::info;
-use tokio::{
-    time,
-    time::{Duration, MissedTickBehavior},
-};
+use serde::Deserialize;
+use std::f32::consts::PI;
+use tokio::{time, time::Duration};
 
 #[doc(hidden)]
-#[distributed_slice(CONTENT_PROVIDERS)]
 pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
 
-#[derive(Debug, Copy, Clone)]
+/// How many lines of the small font fit on the screen
+const MAX_LINES: usize = 3;
+/// The gap between two lines of text
+const LINE_SPACING: i32 = 3;
+
+#[derive(Debug, Clone)]
 /// Represents the options a user can choose for the clock format
 enum ClockFormat {
     /// 12hr clock format with AM / PM
@@ -35,52 +41,371 @@ enum ClockFormat {
     TwentyFour,
     /// This setting will use the current locales clock format instead
     Locale,
+    /// A `strftime` format of the user, every line of it is centered on its own
+    Custom(String),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Style {
+    /// The time as text
+    #[default]
+    Digital,
+    /// A round face with hands and the date next to it
+    Analog,
+    /// The digits of the time as dots, one column of bits each
+    Binary,
+    /// The time in words, e.g. "quarter past ten"
+    Fuzzy,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ClockSettings {
+    style: Style,
+    /// Falls back to the format of the current locale if unset
+    twelve_hour: Option<bool>,
+    /// Replaces the format `twelve_hour` picks, e.g. `%a %H:%M`
+    format: Option<String>,
+    /// Adds a line with the date below the time
+    show_date: bool,
+    /// The `strftime` format of the date line
+    date_format: Option<String>,
 }
 
 #[doc(hidden)]
-#[allow(clippy::unnecessary_wraps)]
 fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
     info!("Registering Clock display source.");
 
-    let clock_format = match config.get_bool("clock.twelve_hour") {
-        Ok(true) => ClockFormat::Twelve,
-        Ok(false) => ClockFormat::TwentyFour,
-        _ => ClockFormat::Locale,
+    let settings: ClockSettings = settings::section(config, "clock")?;
+    let clock_format = match (settings.format, settings.twelve_hour) {
+        (Some(format), _) => {
+            check_format(&format)?;
+            ClockFormat::Custom(format)
+        }
+        (None, Some(true)) => ClockFormat::Twelve,
+        (None, Some(false)) => ClockFormat::TwentyFour,
+        (None, None) => ClockFormat::Locale,
     };
 
-    Ok(Box::new(Clock { clock_format }))
+    let date_format = settings.show_date.then(|| {
+        settings
+            .date_format
+            .unwrap_or_else(|| String::from("%a, %d %b %Y"))
+    });
+    if let Some(format) = &date_format {
+        check_format(format)?;
+    }
+
+    let lines = clock_format.format_string().lines().count() + usize::from(date_format.is_some());
+    if lines > MAX_LINES {
+        return Err(anyhow!(
+            "Invalid settings in [clock]: Only {} lines fit on the screen",
+            MAX_LINES
+        ));
+    }
+
+    Ok(Box::new(Clock {
+        style: settings.style,
+        clock_format,
+        date_format,
+    }))
 }
 
 pub struct Clock {
+    style: Style,
     clock_format: ClockFormat,
+    /// `None` if the date isn't shown
+    date_format: Option<String>,
 }
 
-impl Clock {
-    pub fn render(&self) -> Result<FrameBuffer> {
-        let local: DateTime<Local> = Local::now();
-        let format_string = match self.clock_format {
+/// chrono panics while formatting if the format is broken so it's checked
+/// right away
+fn check_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(anyhow!(
+            "Invalid settings in [clock]: `{}` isn't a valid strftime format",
+            format
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks whether a `strftime` format string contains any seconds, chrono
+/// expands `%T`, `%X` and the like into their parts so padded and fractional
+/// seconds are found as well
+fn shows_seconds(format: &str) -> bool {
+    StrftimeItems::new(format).any(|item| match item {
+        Item::Numeric(numeric, _) => matches!(
+            numeric,
+            Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp
+        ),
+        // `Internal` is `%3f` and the like, ticking every second for the odd `%#z` is fine
+        Item::Fixed(fixed) => matches!(
+            fixed,
+            Fixed::Nanosecond
+                | Fixed::Nanosecond3
+                | Fixed::Nanosecond6
+                | Fixed::Nanosecond9
+                | Fixed::Internal(_)
+                | Fixed::RFC2822
+                | Fixed::RFC3339
+        ),
+        _ => false,
+    })
+}
+
+impl ClockFormat {
+    fn format_string(&self) -> &str {
+        match self {
             ClockFormat::Twelve => "%I:%M:%S %p",
             ClockFormat::TwentyFour => "%H:%M:%S",
             ClockFormat::Locale => "%X",
+            ClockFormat::Custom(format) => format,
+        }
+    }
+}
+
+impl Clock {
+    fn format_string(&self) -> &str {
+        self.clock_format.format_string()
+    }
+
+    /// Calculates how long it takes until the rendered time changes, which
+    /// is the next full second if seconds are shown or the next full minute
+    /// otherwise.
+    fn until_next_change(&self) -> Duration {
+        let now = Local::now();
+        // The nanoseconds may exceed a second during a leap second
+        let nanos = u64::from(now.nanosecond() % 1_000_000_000);
+        let until_next_second = Duration::from_nanos(1_000_000_000 - nanos);
+
+        let every_second = match self.style {
+            // The second hand and the seconds column move every second
+            Style::Analog | Style::Binary => true,
+            Style::Fuzzy => false,
+            Style::Digital => shows_seconds(self.format_string()),
         };
 
-        let text = local.format(format_string).to_string();
+        if every_second {
+            until_next_second
+        } else {
+            until_next_second + Duration::from_secs(u64::from(59 - now.second()))
+        }
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let local: DateTime<Local> = Local::now();
+        match self.style {
+            Style::Analog => return render_analog(&local),
+            Style::Binary => return render_binary(&local),
+            Style::Fuzzy => {
+                let mut buffer = FrameBuffer::new();
+                draw_centered(&fuzzy_lines(&local), &mut buffer)?;
+                return Ok(buffer);
+            }
+            Style::Digital => {}
+        }
+
+        let time = local.format(self.format_string()).to_string();
+        let date_lines = i32::from(self.date_format.is_some());
+        let time_lines = time.lines().count() as i32;
+
+        // The time is bold unless that doesn't leave enough room for everything
+        let bold = &iso_8859_15::FONT_8X13_BOLD;
+        let height = bold.character_size.height as i32 * time_lines
+            + 10 * date_lines
+            + LINE_SPACING * (time_lines + date_lines - 1);
+        let font = if height <= 40 {
+            bold
+        } else {
+            &iso_8859_15::FONT_6X10
+        };
+
+        let mut lines = time
+            .lines()
+            .map(|line| (line.to_string(), MonoTextStyle::new(font, BinaryColor::On)))
+            .collect::<Vec<_>>();
+        if let Some(format) = &self.date_format {
+            lines.push((
+                local.format(format).to_string(),
+                MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On),
+            ));
+        }
+
         let mut buffer = FrameBuffer::new();
-        let style = MonoTextStyle::new(&iso_8859_15::FONT_8X13_BOLD, BinaryColor::On);
-        let metrics = style.measure_string(&text, Point::zero(), Baseline::Top);
-        let height: i32 = (metrics.bounding_box.size.height / 2) as i32;
-        let width: i32 = (metrics.bounding_box.size.width / 2) as i32;
-
-        Text::with_baseline(
-            &text,
-            Point::new(128 / 2 - width, 40 / 2 - height),
-            style,
-            Baseline::Top,
+        draw_centered(&lines, &mut buffer)?;
+
+        Ok(buffer)
+    }
+}
+
+/// Draws every line centered on its own, the lines as a whole are centered on
+/// the screen as well
+fn draw_centered(
+    lines: &[(String, MonoTextStyle<'_, BinaryColor>)],
+    buffer: &mut FrameBuffer,
+) -> Result<()> {
+    let heights = lines
+        .iter()
+        .map(|(_, style)| style.font.character_size.height as i32)
+        .collect::<Vec<_>>();
+    let total = heights.iter().sum::<i32>() + LINE_SPACING * (lines.len() as i32 - 1);
+
+    let mut y = 40 / 2 - total / 2;
+    for ((text, style), height) in lines.iter().zip(heights) {
+        let metrics = style.measure_string(text, Point::zero(), Baseline::Top);
+        let width = (metrics.bounding_box.size.width / 2) as i32;
+        Text::with_baseline(text, Point::new(128 / 2 - width, y), *style, Baseline::Top)
+            .draw(buffer)?;
+        y += height + LINE_SPACING;
+    }
+
+    Ok(())
+}
+
+/// Draws every digit of the time as a column of dots, the lowest bit at the
+/// bottom. Tens only get as many dots as they need.
+fn render_binary(local: &DateTime<Local>) -> Result<FrameBuffer> {
+    const DOT: u32 = 7;
+    const COLUMN: i32 = 10;
+    const GAP: i32 = 8;
+
+    let mut buffer = FrameBuffer::new();
+    let on = PrimitiveStyle::with_fill(BinaryColor::On);
+    let off = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+    let digits = [
+        (local.hour() / 10, 2),
+        (local.hour() % 10, 4),
+        (local.minute() / 10, 3),
+        (local.minute() % 10, 4),
+        (local.second() / 10, 3),
+        (local.second() % 10, 4),
+    ];
+    let width = COLUMN * digits.len() as i32 + GAP * 2;
+    let left = (128 - width) / 2;
+
+    for (index, (digit, bits)) in digits.into_iter().enumerate() {
+        let x = left + COLUMN * index as i32 + GAP * (index as i32 / 2);
+        for bit in 0..bits {
+            let y = 40 - (bit as i32 + 1) * 10 + 1;
+            let style = if digit & (1 << bit) == 0 { off } else { on };
+            Circle::new(Point::new(x, y), DOT)
+                .into_styled(style)
+                .draw(&mut buffer)?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// The time in words, rounded to five minutes
+fn fuzzy_lines(local: &DateTime<Local>) -> Vec<(String, MonoTextStyle<'static, BinaryColor>)> {
+    const HOURS: [&str; 12] = [
+        "twelve", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven",
+    ];
+    const MINUTES: [&str; 12] = [
+        "",
+        "five past",
+        "ten past",
+        "quarter past",
+        "twenty past",
+        "twenty-five past",
+        "half past",
+        "twenty-five to",
+        "twenty to",
+        "quarter to",
+        "ten to",
+        "five to",
+    ];
+
+    // Rounded to the nearest five minutes, 58 minutes past ten is almost eleven
+    let steps = (local.minute() + 2) / 5;
+    let mut hour = local.hour() as usize;
+    if steps > 6 {
+        hour += 1;
+    }
+    let hour = HOURS[hour % 12];
+
+    let style = MonoTextStyle::new(&iso_8859_15::FONT_8X13_BOLD, BinaryColor::On);
+    let lines = match MINUTES[steps as usize % 12] {
+        "" => [hour, "o'clock"],
+        minutes => [minutes, hour],
+    };
+
+    lines.iter().map(|line| (line.to_string(), style)).collect()
+}
+
+/// The point `length` pixels away from `center` in the direction of a hand that
+/// made `fraction` of a full turn
+fn hand_end(center: Point, fraction: f32, length: f32) -> Point {
+    let angle = fraction * 2.0 * PI;
+    center
+        + Point::new(
+            (angle.sin() * length).round() as i32,
+            (-angle.cos() * length).round() as i32,
         )
+}
+
+/// Draws a face in the middle of the screen with the day of the week on its
+/// left and the date on its right
+fn render_analog(local: &DateTime<Local>) -> Result<FrameBuffer> {
+    let mut buffer = FrameBuffer::new();
+    let center = Point::new(64, 20);
+    let thin = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let thick = PrimitiveStyle::with_stroke(BinaryColor::On, 2);
+
+    Circle::with_center(center, 39)
+        .into_styled(thin)
         .draw(&mut buffer)?;
+    for hour in 0..12 {
+        let fraction = hour as f32 / 12.0;
+        Line::new(
+            hand_end(center, fraction, 15.0),
+            hand_end(center, fraction, 17.0),
+        )
+        .into_styled(thin)
+        .draw(&mut buffer)?;
+    }
 
-        Ok(buffer)
+    // The hands move on smoothly instead of jumping to the next hour
+    let seconds = local.second() as f32;
+    let minutes = local.minute() as f32 + seconds / 60.0;
+    let hours = (local.hour() % 12) as f32 + minutes / 60.0;
+    for (fraction, length, style) in [
+        (hours / 12.0, 9.0, thick),
+        (minutes / 60.0, 14.0, thick),
+        (seconds / 60.0, 16.0, thin),
+    ] {
+        Line::new(center, hand_end(center, fraction, length))
+            .into_styled(style)
+            .draw(&mut buffer)?;
     }
+
+    let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    Text::with_text_style(
+        &local.format("%a").to_string(),
+        Point::new(22, 20),
+        style,
+        centered,
+    )
+    .draw(&mut buffer)?;
+    Text::with_text_style(
+        &local.format("%d %b").to_string(),
+        Point::new(106, 20),
+        style,
+        centered,
+    )
+    .draw(&mut buffer)?;
+
+    Ok(buffer)
 }
 
 impl ContentProvider for Clock {
@@ -89,14 +414,13 @@ impl ContentProvider for Clock {
     // This needs to be enabled until full GAT support is here
     #[allow(clippy::needless_lifetimes)]
     fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
-        let mut interval = time::interval(Duration::from_millis(50));
-        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
         Ok(try_stream! {
             loop {
                 if let Ok(image) = self.render() {
                     yield image;
                 }
-                interval.tick().await;
+                // There's no point in waking up before the displayed time actually changes
+                time::sleep(self.until_next_change()).await;
             }
         })
     }
@@ -105,3 +429,70 @@ impl ContentProvider for Clock {
         "clock"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    #[test]
+    fn formats_without_seconds() {
+        assert!(!shows_seconds("%H:%M"));
+        assert!(!shows_seconds("%I:%M %p"));
+        assert!(!shows_seconds("%R"));
+        assert!(!shows_seconds("%Y-%m-%d"));
+        assert!(!shows_seconds("%%S"));
+    }
+
+    #[test]
+    fn formats_with_seconds() {
+        assert!(shows_seconds("%H:%M:%S"));
+        assert!(shows_seconds("%T"));
+        assert!(shows_seconds("%X"));
+        assert!(shows_seconds("%c"));
+        assert!(shows_seconds("%s"));
+        assert!(shows_seconds("%+"));
+    }
+
+    #[test]
+    fn fractional_seconds() {
+        assert!(shows_seconds("%H:%M:%S%.3f"));
+        assert!(shows_seconds("%H:%M%.f"));
+        assert!(shows_seconds("%3f"));
+    }
+
+    fn fuzzy(hour: u32, minute: u32) -> Vec<String> {
+        let time = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap();
+        let local = Local.from_local_datetime(&time).unwrap();
+        fuzzy_lines(&local)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect()
+    }
+
+    #[test]
+    fn full_hours() {
+        assert_eq!(fuzzy(10, 0), ["ten", "o'clock"]);
+        assert_eq!(fuzzy(10, 2), ["ten", "o'clock"]);
+        assert_eq!(fuzzy(0, 0), ["twelve", "o'clock"]);
+        assert_eq!(fuzzy(12, 1), ["twelve", "o'clock"]);
+    }
+
+    #[test]
+    fn minutes_round_to_the_nearest_five() {
+        assert_eq!(fuzzy(10, 3), ["five past", "ten"]);
+        assert_eq!(fuzzy(12, 15), ["quarter past", "twelve"]);
+        assert_eq!(fuzzy(10, 30), ["half past", "ten"]);
+        assert_eq!(fuzzy(10, 32), ["half past", "ten"]);
+    }
+
+    #[test]
+    fn the_second_half_counts_to_the_next_hour() {
+        assert_eq!(fuzzy(10, 33), ["twenty-five to", "eleven"]);
+        assert_eq!(fuzzy(23, 45), ["quarter to", "twelve"]);
+        assert_eq!(fuzzy(10, 58), ["eleven", "o'clock"]);
+        assert_eq!(fuzzy(11, 59), ["twelve", "o'clock"]);
+    }
+}
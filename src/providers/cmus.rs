@@ -0,0 +1,231 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        player::{MediaPlayerRenderer, IDLE_TEMPLATE},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_music::{Metadata, PlaybackStatus, Progress};
+use async_stream::try_stream;
+use config::Config;
+use futures::Stream;
+use log::{debug, info};
+use serde::Deserialize;
+use tokio::{
+    process::Command,
+    time,
+    time::{Duration, Instant, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// The settings of the `[cmus]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CmusSettings {
+    /// How often cmus is asked, in milliseconds. The progress moves on in
+    /// between.
+    polling_interval: u64,
+    /// The socket of cmus if it's not the default one, see `--server` of
+    /// `cmus-remote`
+    server: Option<String>,
+    /// Only needed if `server` is a TCP address
+    password: Option<String>,
+}
+
+impl Default for CmusSettings {
+    fn default() -> Self {
+        Self {
+            polling_interval: 1000,
+            server: None,
+            password: None,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering cmus display source.");
+
+    let settings: CmusSettings = settings::section(config, "cmus")?;
+
+    Ok(Box::new(Cmus { settings }))
+}
+
+#[derive(Debug, Clone, Default)]
+struct Track {
+    title: Option<String>,
+    artist: Option<String>,
+    /// Tracks without tags are at least known by their file
+    file: String,
+    /// In microseconds
+    length: u64,
+}
+
+impl Metadata for Track {
+    fn title(&self) -> Result<String> {
+        self.title
+            .clone()
+            .or_else(|| self.file.rsplit('/').next().map(String::from))
+            .ok_or_else(|| anyhow!("Couldn't get title!"))
+    }
+
+    fn artists(&self) -> Result<String> {
+        self.artist
+            .clone()
+            .ok_or_else(|| anyhow!("Couldn't get artist!"))
+    }
+
+    fn length(&self) -> Result<u64> {
+        Ok(self.length)
+    }
+}
+
+/// What `cmus-remote -Q` answered the last time
+#[derive(Debug, Clone)]
+struct Status {
+    track: Track,
+    status: PlaybackStatus,
+    /// In microseconds
+    position: u64,
+    /// When the answer came in, the position moves on from there while it's
+    /// playing
+    at: Instant,
+}
+
+impl Status {
+    fn progress(&self) -> Progress<Track> {
+        let mut position = self.position;
+        if let PlaybackStatus::Playing = self.status {
+            position += self.at.elapsed().as_micros() as u64;
+        }
+
+        Progress {
+            metadata: self.track.clone(),
+            position: position.min(self.track.length) as i64,
+            status: self.status,
+        }
+    }
+}
+
+/// Parses the output of `cmus-remote -Q`, e.g. `status playing`,
+/// `duration 253` or `tag title Some song`
+fn parse_status(output: &str) -> Status {
+    let mut track = Track::default();
+    let mut status = PlaybackStatus::Stopped;
+    let mut position = 0;
+
+    for line in output.lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        match key {
+            "status" => {
+                status = match value {
+                    "playing" => PlaybackStatus::Playing,
+                    "paused" => PlaybackStatus::Paused,
+                    _ => PlaybackStatus::Stopped,
+                };
+            }
+            "file" => track.file = value.to_string(),
+            "duration" => track.length = value.parse::<u64>().unwrap_or(0) * 1_000_000,
+            "position" => position = value.parse::<u64>().unwrap_or(0) * 1_000_000,
+            "tag" => match value.split_once(' ') {
+                Some(("title", title)) => track.title = Some(title.to_string()),
+                Some(("artist", artist)) => track.artist = Some(artist.to_string()),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    Status {
+        track,
+        status,
+        position,
+        at: Instant::now(),
+    }
+}
+
+/// Shows what cmus is playing, the same way the MPRIS players are shown
+struct Cmus {
+    settings: CmusSettings,
+}
+
+impl Cmus {
+    /// Asks cmus what it's playing, this fails if it isn't running
+    async fn query(&self) -> Result<Status> {
+        let mut command = Command::new("cmus-remote");
+        if let Some(server) = &self.settings.server {
+            command.args(["--server", server.as_str()]);
+        }
+        if let Some(password) = &self.settings.password {
+            command.args(["--passwd", password.as_str()]);
+        }
+
+        let output = command.arg("-Q").output().await?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "cmus-remote failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(parse_status(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+impl ContentProvider for Cmus {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut renderer = MediaPlayerRenderer::new()?;
+        let mut poll = time::interval(Duration::from_millis(
+            self.settings.polling_interval.max(100),
+        ));
+        poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut render = time::interval(Duration::from_millis(100));
+        render.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            // `None` while cmus isn't running
+            let mut current: Option<Status> = None;
+
+            loop {
+                tokio::select! {
+                    _ = poll.tick() => {
+                        current = match self.query().await {
+                            Ok(status) => Some(status),
+                            Err(e) => {
+                                debug!("Couldn't ask cmus what's playing: {}", e);
+                                None
+                            }
+                        };
+                        if current.is_none() {
+                            yield *IDLE_TEMPLATE;
+                        }
+                    },
+                    _ = render.tick(), if current.is_some() => {
+                        if let Some(current) = &current {
+                            if let Ok(image) = renderer.update(&current.progress()) {
+                                yield image;
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "cmus"
+    }
+}
@@ -1,6 +1,6 @@
-use crate::render::{
-    display::ContentProvider,
-    scheduler::{ContentWrapper, CONTENT_PROVIDERS},
+use crate::{
+    render::{assets, display::ContentProvider, scheduler::ContentWrapper},
+    settings,
 };
 use anyhow::{anyhow, Result};
 use apex_hardware::FrameBuffer;
@@ -17,25 +17,24 @@ use embedded_graphics::{
 };
 use futures::Stream;
 use lazy_static::lazy_static;
-use linkme::distributed_slice;
 use log::info;
 use reqwest::{header, Client, ClientBuilder};
 use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, time::Duration};
 use tinybmp::Bmp;
-use tokio::{time, time::MissedTickBehavior};
+use tokio::{sync::watch, time, time::MissedTickBehavior};
 
-static BTC_ICON: &[u8] = include_bytes!("./../../assets/btc.bmp");
+static BTC_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/btc.bmp"));
 
 lazy_static! {
-    static ref BTC_BMP: Bmp<'static, BinaryColor> =
-        Bmp::<BinaryColor>::from_slice(BTC_ICON).expect("Failed to parse BMP for BTC icon!");
+    static ref BTC_BMP: Bmp<'static, BinaryColor> = assets::bundled(BTC_ICON);
 }
 
-#[distributed_slice(CONTENT_PROVIDERS)]
+#[doc(hidden)]
 pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(try_from = "String")]
 pub enum Target {
     Eur,
     Usd,
@@ -71,14 +70,42 @@ impl Target {
     }
 }
 
-#[allow(clippy::unnecessary_wraps)]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CoindeskSettings {
+    currency: Target,
+}
+
 fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
     info!("Registering Coindesk display source.");
-    let currency = config
-        .get_str("crypto.currency")
-        .unwrap_or_else(|_| String::from("USD"));
-    let currency = Target::try_from(currency).unwrap_or_default();
-    Ok(Box::new(Coindesk::new(currency)?))
+    let settings: CoindeskSettings = settings::section(config, "coindesk")?;
+    let mut coindesk = Coindesk::new(settings.currency)?;
+    coindesk.activity = Some(watch_network(coindesk.client.clone()));
+    Ok(Box::new(coindesk))
+}
+
+/// Checks every minute whether Coindesk can be reached so the rotation can
+/// skip the price while the network is down
+fn watch_network(client: Client) -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                // The provider is gone, e.g. because the settings were reloaded
+                _ = tx.closed() => break,
+                _ = interval.tick() => {
+                    let reachable = client.head(COINDESK_URL).send().await.is_ok();
+                    tx.send_replace(reachable);
+                }
+            }
+        }
+    });
+
+    rx
 }
 
 const COINDESK_URL: &str = "https://api.coindesk.com/v1/bpi/currentprice.json";
@@ -146,6 +173,8 @@ impl Status {
 struct Coindesk {
     client: Client,
     target: Target,
+    /// Whether the network is up
+    activity: Option<watch::Receiver<bool>>,
 }
 
 impl Coindesk {
@@ -161,6 +190,7 @@ impl Coindesk {
                 .default_headers(headers)
                 .build()?,
             target,
+            activity: None,
         })
     }
 
@@ -217,4 +247,8 @@ impl ContentProvider for Coindesk {
     fn name(&self) -> &'static str {
         "coindesk"
     }
+
+    fn activity(&self) -> Option<watch::Receiver<bool>> {
+        self.activity.clone()
+    }
 }
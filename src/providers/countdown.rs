@@ -0,0 +1,403 @@
+use crate::{
+    render::{
+        accessibility::{self, LARGE_TEXT_ROWS},
+        digits::BigDigits,
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        scheduler::{ContentWrapper, NotificationWrapper},
+        timers::{Publisher, Timer},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone};
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{renderer::TextRenderer, Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use log::info;
+use serde::Deserialize;
+use std::{collections::HashSet, time::SystemTime};
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+/// The maximum amount of rows that fit on the display with the font we're
+/// using
+const MAX_ENTRIES: usize = 4;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Style {
+    /// The nearest events below each other
+    #[default]
+    List,
+    /// One event at a time with the time left in large digits
+    Large,
+}
+
+/// The settings of the `[countdown]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CountdownSettings {
+    style: Style,
+    /// How many events are shown at once in the list
+    entries: usize,
+    /// How long every event is shown in the large style, in seconds
+    rotation: u64,
+    events: Vec<Event>,
+}
+
+impl Default for CountdownSettings {
+    fn default() -> Self {
+        Self {
+            style: Style::default(),
+            entries: 3,
+            rotation: 5,
+            events: Vec::new(),
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Countdown display source.");
+
+    let settings: CountdownSettings = settings::section(config, "countdown")?;
+
+    let max_entries = if accessibility::large_text() {
+        LARGE_TEXT_ROWS
+    } else {
+        MAX_ENTRIES
+    };
+
+    let countdown = Countdown {
+        events: settings.events,
+        style: settings.style,
+        entries: settings.entries.clamp(1, max_entries),
+        rotation: Duration::from_secs(settings.rotation.max(1)),
+        shown: 0,
+        timers: Publisher::new("countdown"),
+    };
+    countdown.publish();
+
+    Ok(Box::new(countdown))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering Countdown notification source.");
+
+    let events = settings::section::<CountdownSettings>(config, "countdown")?.events;
+
+    Ok(Box::new(CountdownNotifier { events }))
+}
+
+/// An event the way it's written in the settings
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEvent {
+    name: String,
+    date: String,
+    #[serde(default)]
+    yearly: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawEvent")]
+struct Event {
+    name: String,
+    /// The moment the countdown runs out
+    target: NaiveDateTime,
+    /// Whether the event repeats every year, e.g. birthdays
+    yearly: bool,
+}
+
+impl TryFrom<RawEvent> for Event {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawEvent) -> Result<Self, Self::Error> {
+        let RawEvent { name, date, yearly } = raw;
+
+        // Deadlines may come with a time of day, birthdays usually don't
+        let target = NaiveDateTime::parse_from_str(&date, "%Y-%m-%d %H:%M")
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .ok_or_else(|| anyhow!("Bad date `{}` for `{}`", date, name))?;
+
+        Ok(Self {
+            name,
+            target,
+            yearly,
+        })
+    }
+}
+
+impl Event {
+    /// Returns the next time this event happens. Events stay around for the
+    /// whole day they happen on and are dropped afterwards unless they repeat.
+    fn next_occurrence(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if !self.yearly {
+            return (self.target.date() >= now.date()).then_some(self.target);
+        }
+
+        // February 29th only exists every four years so we may need to skip ahead a bit
+        (now.year()..=now.year() + 8)
+            .filter_map(|year| self.target.date().with_year(year))
+            .map(|date| date.and_time(self.target.time()))
+            .find(|candidate| candidate.date() >= now.date())
+    }
+}
+
+/// Formats the time left until `target` as "X days", switching to hours when
+/// less than two days are left.
+fn format_remaining(target: NaiveDateTime, now: NaiveDateTime) -> String {
+    match remaining_parts(target, now) {
+        None => String::from("today"),
+        Some((hours, "hour" | "hours")) => format!("{}h", hours),
+        Some((count, unit)) => format!("{} {}", count, unit),
+    }
+}
+
+/// Splits the time left until `target` into a number and its unit, `None` on
+/// the day of the event once less than an hour is left
+fn remaining_parts(target: NaiveDateTime, now: NaiveDateTime) -> Option<(i64, &'static str)> {
+    let remaining = target - now;
+
+    if target.date() == now.date() && remaining.num_hours() < 1 {
+        None
+    } else if remaining.num_hours() < 48 {
+        match remaining.num_hours() {
+            1 => Some((1, "hour")),
+            n => Some((n, "hours")),
+        }
+    } else {
+        match (target.date() - now.date()).num_days() {
+            1 => Some((1, "day")),
+            n => Some((n, "days")),
+        }
+    }
+}
+
+/// Returns the upcoming events, nearest first
+fn upcoming(events: &[Event], now: NaiveDateTime) -> Vec<(&Event, NaiveDateTime)> {
+    let mut upcoming = events
+        .iter()
+        .filter_map(|event| event.next_occurrence(now).map(|next| (event, next)))
+        .collect::<Vec<_>>();
+    upcoming.sort_by_key(|(_, next)| *next);
+    upcoming
+}
+
+struct Countdown {
+    events: Vec<Event>,
+    style: Style,
+    entries: usize,
+    rotation: Duration,
+    /// The upcoming event that's shown in the large style
+    shown: usize,
+    timers: Publisher,
+}
+
+impl Countdown {
+    /// Publishes the upcoming events, they only change once one of them is
+    /// over
+    fn publish(&self) {
+        let timers = upcoming(&self.events, Local::now().naive_local())
+            .into_iter()
+            .filter_map(|(event, next)| {
+                let end = Local.from_local_datetime(&next).earliest()?;
+                Some(Timer::running(&event.name, SystemTime::from(end)))
+            })
+            .collect();
+        self.timers.publish(timers);
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let now = Local::now().naive_local();
+        let mut buffer = FrameBuffer::new();
+        let font = if accessibility::large_text() {
+            &iso_8859_15::FONT_9X15
+        } else {
+            &iso_8859_15::FONT_6X10
+        };
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let char_width = style.font.character_size.width as i32;
+        let char_height = style.font.character_size.height as i32;
+
+        let upcoming = upcoming(&self.events, now);
+
+        if upcoming.is_empty() {
+            // The long version doesn't fit in large text
+            let text = if accessibility::large_text() {
+                "No events"
+            } else {
+                "No upcoming events"
+            };
+            let metrics = style.measure_string(text, Point::zero(), Baseline::Top);
+            let width = metrics.bounding_box.size.width as i32;
+            let y = (40 - char_height) / 2;
+            Text::with_baseline(text, Point::new(64 - width / 2, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        if self.style == Style::Large {
+            let (event, next) = upcoming[self.shown % upcoming.len()];
+            Self::render_large(event, next, now, &mut buffer)?;
+            return Ok(buffer);
+        }
+
+        let row_height = 40 / self.entries as i32;
+
+        for (row, (event, next)) in upcoming.into_iter().take(self.entries).enumerate() {
+            let y = row as i32 * row_height + (row_height - char_height) / 2;
+
+            let remaining = format_remaining(next, now);
+            let metrics = style.measure_string(&remaining, Point::zero(), Baseline::Top);
+            let remaining_x = 128 - metrics.bounding_box.size.width as i32;
+            Text::with_baseline(&remaining, Point::new(remaining_x, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            // Cut off the name so it doesn't run into the remaining time
+            let max_chars = ((remaining_x - char_width) / char_width).max(0) as usize;
+            let name = event.name.chars().take(max_chars).collect::<String>();
+            Text::with_baseline(&name, Point::new(0, y), style, Baseline::Top).draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+
+    /// The name on top and the days or hours left below it in large digits
+    fn render_large(
+        event: &Event,
+        next: NaiveDateTime,
+        now: NaiveDateTime,
+        buffer: &mut FrameBuffer,
+    ) -> Result<()> {
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+        let name = event.name.chars().take(21).collect::<String>();
+        let metrics = small.measure_string(&name, Point::zero(), Baseline::Top);
+        let x = 64 - metrics.bounding_box.size.width as i32 / 2;
+        Text::with_baseline(&name, Point::new(x, 0), small, Baseline::Top).draw(buffer)?;
+
+        let (number, unit) = match remaining_parts(next, now) {
+            Some(parts) => parts,
+            None => {
+                let large = MonoTextStyle::new(&iso_8859_15::FONT_10X20, BinaryColor::On);
+                let metrics = large.measure_string("today", Point::zero(), Baseline::Top);
+                let x = 64 - metrics.bounding_box.size.width as i32 / 2;
+                Text::with_baseline("today", Point::new(x, 16), large, Baseline::Top)
+                    .draw(buffer)?;
+                return Ok(());
+            }
+        };
+
+        // The number and its unit are centered together
+        let digits = BigDigits::new(Size::new(12, 26), 3);
+        let number = number.to_string();
+        let number_width = digits.width(&number) as i32;
+        let unit_width = small.measure_string(unit, Point::zero(), Baseline::Top);
+        let width = number_width + 4 + unit_width.bounding_box.size.width as i32;
+        let x = 64 - width / 2;
+        digits.draw(&number, Point::new(x, 13), buffer)?;
+        Text::with_baseline(
+            unit,
+            Point::new(x + number_width + 4, 39),
+            small,
+            Baseline::Bottom,
+        )
+        .draw(buffer)?;
+
+        Ok(())
+    }
+}
+
+impl ContentProvider for Countdown {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(500));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut rotation = time::interval(self.rotation);
+        rotation.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        Ok(try_stream! {
+            // The first tick completes right away
+            rotation.tick().await;
+            loop {
+                self.publish();
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = rotation.tick() => self.shown = self.shown.wrapping_add(1),
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "countdown"
+    }
+}
+
+struct CountdownNotifier {
+    events: Vec<Event>,
+}
+
+impl NotificationProvider for CountdownNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        let mut interval = time::interval(Duration::from_secs(60));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        // Remember which events we already announced so we only do it once per day
+        let mut notified = HashSet::new();
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                let now = Local::now().naive_local();
+
+                for (event, next) in upcoming(&self.events, now) {
+                    if next.date() != now.date() || !notified.insert((event.name.clone(), next.date())) {
+                        continue;
+                    }
+
+                    if let Ok(notification) = NotificationBuilder::new()
+                        .with_title(&event.name)
+                        .with_content("is today!")
+                        .build()
+                    {
+                        yield notification;
+                    }
+                }
+            }
+        })
+    }
+}
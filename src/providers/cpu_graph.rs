@@ -0,0 +1,208 @@
+use crate::{
+    render::{
+        accessibility,
+        display::ContentProvider,
+        graph::{Graph, GraphStyle},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::info;
+use serde::Deserialize;
+use sysinfo::{CpuExt, CpuRefreshKind, RefreshKind, System, SystemExt};
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// How much room the bars of the cores take, every core gets at least a
+/// pixel so only this many of them are shown
+const CORES_WIDTH: i32 = 32;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CpuGraphSettings {
+    /// How far back the graph goes
+    minutes: u64,
+    /// Shows a bar for every core right of the graph
+    cores: bool,
+}
+
+impl Default for CpuGraphSettings {
+    fn default() -> Self {
+        Self {
+            minutes: 5,
+            cores: false,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering CPU graph display source.");
+
+    let settings: CpuGraphSettings = settings::section(config, "cpu_graph")?;
+
+    // The graph is drawn inside of a border
+    let width = if settings.cores {
+        128 - CORES_WIDTH - 1
+    } else {
+        128
+    };
+    let columns = (width - 2) as u64;
+
+    // sysinfo measures the load between two refreshes so every column is the
+    // average load of its time span
+    let period = Duration::from_millis(settings.minutes.max(1) * 60 * 1000 / columns);
+
+    Ok(Box::new(CpuGraph {
+        sys: System::new(),
+        graph: Graph::new(columns as usize, GraphStyle::Filled),
+        width,
+        period: period.max(Duration::from_millis(500)),
+        minutes: settings.minutes.max(1),
+        cores: settings.cores,
+    }))
+}
+
+/// The graph starts below the line with the current load
+fn graph_top() -> i32 {
+    accessibility::font(&iso_8859_15::FONT_4X6)
+        .character_size
+        .height as i32
+        + 2
+}
+
+struct CpuGraph {
+    sys: System,
+    graph: Graph,
+    /// The width of the graph including its border
+    width: i32,
+    /// How long every column of the graph covers
+    period: Duration,
+    minutes: u64,
+    cores: bool,
+}
+
+impl CpuGraph {
+    fn refresh(&mut self) {
+        let refreshes = RefreshKind::new().with_cpu(CpuRefreshKind::new().with_cpu_usage());
+        self.sys.refresh_specifics(refreshes);
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style =
+            MonoTextStyle::new(accessibility::font(&iso_8859_15::FONT_4X6), BinaryColor::On);
+
+        let load = self.sys.global_cpu_info().cpu_usage();
+        Text::with_baseline(
+            &format!("CPU {:>3.0}%", load),
+            Point::zero(),
+            style,
+            Baseline::Top,
+        )
+        .draw(&mut buffer)?;
+
+        let right = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(
+            &format!("{} min", self.minutes),
+            Point::new(127, 0),
+            style,
+            right,
+        )
+        .draw(&mut buffer)?;
+
+        let area = Rectangle::new(
+            Point::new(0, graph_top()),
+            Size::new(self.width as u32, (40 - graph_top()) as u32),
+        );
+        area.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut buffer)?;
+        self.graph.draw(&mut buffer, area.offset(-1), 100.0)?;
+
+        if self.cores {
+            self.render_cores(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+
+    /// Draws a bar for every core, they share the room right of the graph
+    fn render_cores(&self, buffer: &mut FrameBuffer) -> Result<()> {
+        let cpus = self.sys.cpus();
+        let shown = cpus.len().min(CORES_WIDTH as usize) as i32;
+        if shown == 0 {
+            return Ok(());
+        }
+
+        let pitch = CORES_WIDTH / shown;
+        // There's a gap between the bars unless they're only a pixel wide
+        let width = (pitch - 1).max(1);
+        let left = 128 - pitch * shown;
+        let height = 40 - graph_top();
+        let style = PrimitiveStyle::with_fill(BinaryColor::On);
+
+        for (index, cpu) in cpus.iter().take(shown as usize).enumerate() {
+            let filled = ((cpu.cpu_usage() / 100.0).clamp(0.0, 1.0) * height as f32).round();
+            Rectangle::new(
+                Point::new(left + index as i32 * pitch, 40 - filled as i32),
+                Size::new(width as u32, filled as u32),
+            )
+            .into_styled(style)
+            .draw(buffer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ContentProvider for CpuGraph {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(self.period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            // The first refresh only tells sysinfo where to measure the load from
+            self.refresh();
+            yield self.render()?;
+            interval.reset();
+
+            loop {
+                interval.tick().await;
+                self.refresh();
+                self.graph.push(self.sys.global_cpu_info().cpu_usage());
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "cpu_graph"
+    }
+}
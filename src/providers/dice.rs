@@ -0,0 +1,333 @@
+use crate::{
+    render::{
+        digits::BigDigits,
+        display::{ContentProvider, ProviderInput},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_input::Command;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{renderer::TextRenderer, Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use itertools::Itertools;
+use log::info;
+use rand::Rng;
+use serde::Deserialize;
+use std::time::Instant;
+use tokio::{
+    sync::mpsc,
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// The maximum amount of dice rolled at once, the individual results won't fit
+/// on the screen otherwise
+const MAX_DICE: u32 = 6;
+/// How long the dice keep tumbling before they settle
+const ROLL_LENGTH: Duration = Duration::from_secs(1);
+
+/// The settings of the `[dice]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DiceSettings {
+    /// The dice to roll, e.g. `2d6+1`
+    roll: String,
+    /// Picks one of these instead of rolling dice if there are any
+    items: Vec<String>,
+}
+
+impl Default for DiceSettings {
+    fn default() -> Self {
+        Self {
+            roll: String::from("d6"),
+            items: Vec::new(),
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Dice display source.");
+
+    let settings: DiceSettings = settings::section(config, "dice")?;
+    let choice = if settings.items.is_empty() {
+        parse_dice(&settings.roll).ok_or_else(|| {
+            anyhow!(
+                "Invalid settings in [dice]: `{}` is not a valid roll",
+                settings.roll
+            )
+        })?
+    } else {
+        Choice::Items(settings.items)
+    };
+
+    let (input, commands) = mpsc::unbounded_channel();
+
+    Ok(Box::new(Dice {
+        choice,
+        result: None,
+        rolled: Instant::now(),
+        input,
+        commands: Some(commands),
+    }))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Choice {
+    /// Rolls `count` dice with `sides` sides each and adds `modifier` to the
+    /// total
+    Dice {
+        count: u32,
+        sides: u32,
+        modifier: i64,
+    },
+    /// Picks one of these at random
+    Items(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    Dice(Vec<u32>),
+    /// The index of the picked item
+    Item(usize),
+}
+
+/// Parses dice notation like `d20`, `2d6` or `3d6+2`
+fn parse_dice(notation: &str) -> Option<Choice> {
+    let notation = notation.trim().to_lowercase();
+    let (count, rest) = notation.split_once('d')?;
+
+    let count = if count.is_empty() {
+        1
+    } else {
+        count.parse().ok()?
+    };
+
+    let (sides, modifier) = match rest.find(|c| c == '+' || c == '-') {
+        Some(index) => (&rest[..index], rest[index..].parse().ok()?),
+        None => (rest, 0),
+    };
+    let sides = sides.parse().ok()?;
+
+    if !(1..=MAX_DICE).contains(&count) || sides < 2 {
+        return None;
+    }
+
+    Some(Choice::Dice {
+        count,
+        sides,
+        modifier,
+    })
+}
+
+impl Choice {
+    fn roll(&self) -> Outcome {
+        let mut rng = rand::thread_rng();
+
+        match self {
+            Choice::Dice { count, sides, .. } => {
+                Outcome::Dice((0..*count).map(|_| rng.gen_range(1..=*sides)).collect())
+            }
+            Choice::Items(items) => Outcome::Item(rng.gen_range(0..items.len())),
+        }
+    }
+
+    /// The roll in dice notation, e.g. `2d6+1`
+    fn notation(&self) -> String {
+        match self {
+            Choice::Dice {
+                count,
+                sides,
+                modifier,
+            } => match modifier {
+                0 => format!("{}d{}", count, sides),
+                _ => format!("{}d{}{:+}", count, sides, modifier),
+            },
+            Choice::Items(_) => String::new(),
+        }
+    }
+}
+
+struct Dice {
+    choice: Choice,
+    /// The result of the last roll, `None` until the first roll
+    result: Option<Outcome>,
+    /// When the last roll started
+    rolled: Instant,
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+impl Dice {
+    fn roll(&mut self) {
+        self.result = Some(self.choice.roll());
+        self.rolled = Instant::now();
+    }
+
+    fn is_rolling(&self) -> bool {
+        self.result.is_some() && self.rolled.elapsed() < ROLL_LENGTH
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        // While rolling every frame shows a different random result so it looks
+        // like the dice are tumbling
+        let outcome = if self.is_rolling() {
+            Some(self.choice.roll())
+        } else {
+            self.result.clone()
+        };
+
+        match &self.choice {
+            Choice::Dice {
+                count, modifier, ..
+            } => {
+                let mut header = self.choice.notation();
+                let total = match &outcome {
+                    Some(Outcome::Dice(rolls)) => {
+                        // Show the individual dice as well if there's more than one of them
+                        if *count > 1 {
+                            header += ": ";
+                            header += &rolls.iter().map(u32::to_string).join(" ");
+                        }
+                        (rolls.iter().map(|r| i64::from(*r)).sum::<i64>() + modifier).to_string()
+                    }
+                    _ => String::from("-"),
+                };
+
+                Text::with_baseline(&header, Point::zero(), small, Baseline::Top)
+                    .draw(&mut buffer)?;
+
+                let digits = BigDigits::new(Size::new(12, 26), 3);
+                let width = digits.width(&total) as i32;
+                digits.draw(&total, Point::new(64 - width / 2, 12), &mut buffer)?;
+            }
+            Choice::Items(items) => {
+                let text = match outcome {
+                    Some(Outcome::Item(index)) => items[index].as_str(),
+                    _ => "?",
+                };
+
+                let large = MonoTextStyle::new(&iso_8859_15::FONT_10X20, BinaryColor::On);
+                let metrics = large.measure_string(text, Point::zero(), Baseline::Top);
+                let (style, y) = if metrics.bounding_box.size.width <= 128 {
+                    (large, 10)
+                } else {
+                    (small, 15)
+                };
+
+                let metrics = style.measure_string(text, Point::zero(), Baseline::Top);
+                let x = (128 - metrics.bounding_box.size.width as i32).max(0) / 2;
+                Text::with_baseline(text, Point::new(x, y), style, Baseline::Top)
+                    .draw(&mut buffer)?;
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Dice {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(80));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The dice stream can only be started once!"))?;
+
+        Ok(try_stream! {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    Some(command) = commands.recv() => {
+                        if let Command::Select = command {
+                            self.roll();
+                        }
+                    }
+                }
+
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "dice"
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(self.input.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dice(count: u32, sides: u32, modifier: i64) -> Option<Choice> {
+        Some(Choice::Dice {
+            count,
+            sides,
+            modifier,
+        })
+    }
+
+    #[test]
+    fn plain_dice() {
+        assert_eq!(parse_dice("d20"), dice(1, 20, 0));
+        assert_eq!(parse_dice("2d6"), dice(2, 6, 0));
+        assert_eq!(parse_dice(" 2D6 "), dice(2, 6, 0));
+    }
+
+    #[test]
+    fn modifiers() {
+        assert_eq!(parse_dice("3d6+2"), dice(3, 6, 2));
+        assert_eq!(parse_dice("1d8-1"), dice(1, 8, -1));
+    }
+
+    #[test]
+    fn invalid_notation() {
+        for notation in ["", "20", "d", "2dx", "2d6+", "2d6+1+1", "-1d6", "d6d6"] {
+            assert_eq!(parse_dice(notation), None, "{}", notation);
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(parse_dice("0d6"), None);
+        assert_eq!(parse_dice("7d6"), None);
+        assert_eq!(parse_dice("2d1"), None);
+        assert_eq!(parse_dice("2d0"), None);
+    }
+
+    #[test]
+    fn notation_round_trips() {
+        for notation in ["1d20", "2d6", "3d6+2", "1d8-1"] {
+            let choice = parse_dice(notation).unwrap();
+            assert_eq!(choice.notation(), notation);
+        }
+    }
+}
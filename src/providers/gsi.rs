@@ -0,0 +1,537 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        scheduler::{ContentWrapper, NotificationWrapper},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+    task::JoinHandle,
+    time,
+    time::{Duration, Instant, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+/// The games send a few kilobytes at most
+const MAX_BODY: usize = 1 << 20;
+/// The application IDs on Steam
+const COUNTER_STRIKE: u64 = 730;
+const DOTA: u64 = 570;
+
+lazy_static! {
+    /// The screen and the notifications share one listener, the games only
+    /// send to a single address
+    static ref GAME: watch::Sender<Option<Game>> = watch::channel(None).0;
+}
+
+/// The listener and the settings it was started with, it's restarted when they
+/// change
+static LISTENER: Mutex<Option<(GsiSettings, JoinHandle<()>)>> = Mutex::new(None);
+
+/// The settings of the `[gsi]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GsiSettings {
+    /// The port in the `uri` of the configuration of the game
+    port: u16,
+    /// Has to match the `token` in the configuration of the game if it's set
+    token: Option<String>,
+    /// Seconds without an update until the game counts as closed, the games
+    /// send a heartbeat at least every `heartbeat` seconds
+    timeout: u64,
+}
+
+impl Default for GsiSettings {
+    fn default() -> Self {
+        Self {
+            port: 3000,
+            token: None,
+            timeout: 30,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Game State Integration display source.");
+
+    let settings: GsiSettings = settings::section(config, "gsi")?;
+    if settings.timeout == 0 {
+        return Err(anyhow!(
+            "Invalid settings in [gsi]: The `timeout` has to be at least a second"
+        ));
+    }
+
+    // The screen is created even when it's disabled, the port stays free then
+    let game = if settings::common(config, "gsi")?.enabled {
+        subscribe(&settings)?
+    } else {
+        stop()?;
+        watch::channel(None).1
+    };
+
+    Ok(Box::new(Gsi {
+        activity: playing(game.clone()),
+        game,
+    }))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering Game State Integration notification source.");
+
+    let game = subscribe(&settings::section(config, "gsi")?)?;
+
+    Ok(Box::new(GsiNotifier { game }))
+}
+
+/// Starts the listener unless it's already running with `settings`, a listener
+/// with other settings is replaced
+fn subscribe(settings: &GsiSettings) -> Result<watch::Receiver<Option<Game>>> {
+    let mut listener = LISTENER
+        .lock()
+        .map_err(|_| anyhow!("The game state listener isn't available anymore"))?;
+    let previous = match listener.take() {
+        Some((current, handle)) if &current == settings && !handle.is_finished() => {
+            *listener = Some((current, handle));
+            return Ok(GAME.subscribe());
+        }
+        previous => previous.map(|(_, handle)| handle),
+    };
+
+    let handle = tokio::spawn(listen(settings.clone(), previous));
+    *listener = Some((settings.clone(), handle));
+
+    Ok(GAME.subscribe())
+}
+
+/// Stops the listener, e.g. because the screen was disabled
+fn stop() -> Result<()> {
+    let mut listener = LISTENER
+        .lock()
+        .map_err(|_| anyhow!("The game state listener isn't available anymore"))?;
+    if let Some((_, handle)) = listener.take() {
+        handle.abort();
+        GAME.send_replace(None);
+    }
+
+    Ok(())
+}
+
+/// Turns the state of the game into the activity of the screen, the rotation
+/// only stops at it during a match
+fn playing(mut game: watch::Receiver<Option<Game>>) -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                // The provider is gone, e.g. because the settings were reloaded
+                _ = tx.closed() => break,
+                changed = game.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let playing = game.borrow().as_ref().map_or(false, |game| game.playing);
+                    tx.send_replace(playing);
+                },
+            }
+        }
+    });
+
+    rx
+}
+
+async fn listen(settings: GsiSettings, previous: Option<JoinHandle<()>>) {
+    // The port is only free again once the previous listener is gone
+    if let Some(previous) = previous {
+        previous.abort();
+        let _ = previous.await;
+    }
+
+    let listener = match TcpListener::bind(("127.0.0.1", settings.port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(
+                "Couldn't listen for game state on port {}: {}",
+                settings.port, e
+            );
+            return;
+        }
+    };
+    info!("Listening for game state on port {}", settings.port);
+
+    let timeout = Duration::from_secs(settings.timeout);
+    let mut interval = time::interval(Duration::from_secs(1));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(receive(stream, settings.token.clone()));
+                }
+                Err(e) => debug!("Couldn't accept a connection of a game: {}", e),
+            },
+            _ = interval.tick() => {
+                // The games just stop sending once they're closed
+                GAME.send_if_modified(|game| {
+                    let gone = game.as_ref().map_or(false, |game| game.updated.elapsed() > timeout);
+                    if gone {
+                        *game = None;
+                    }
+                    gone
+                });
+            },
+        }
+    }
+}
+
+/// Takes the updates of a game, it keeps the connection open between them
+async fn receive(stream: TcpStream, token: Option<String>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let body = match read_request(&mut reader).await {
+            Ok(Some(body)) => body,
+            Ok(None) => break,
+            Err(e) => {
+                debug!("Couldn't read the game state: {}", e);
+                break;
+            }
+        };
+        // The game retries anything that isn't a success, that won't help
+        // with a broken update either
+        if writer
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .is_err()
+        {
+            break;
+        }
+
+        let state = match serde_json::from_slice::<Value>(&body) {
+            Ok(state) => state,
+            Err(e) => {
+                debug!("Ignoring game state that isn't JSON: {}", e);
+                continue;
+            }
+        };
+        if let Some(token) = &token {
+            if state.pointer("/auth/token").and_then(Value::as_str) != Some(token.as_str()) {
+                warn!("Ignoring game state with the wrong token");
+                continue;
+            }
+        }
+
+        if let Some(game) = Game::parse(&state) {
+            GAME.send_replace(Some(game));
+        }
+    }
+}
+
+/// Reads the body of the next request, `None` once the game closed the
+/// connection
+async fn read_request(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<Vec<u8>>> {
+    // Only the length matters, the games always `POST` to the same place
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse()?;
+            }
+        }
+    }
+
+    if length > MAX_BODY {
+        return Err(anyhow!("The request is {} bytes long", length));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Some(body))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Title {
+    CounterStrike,
+    Dota,
+}
+
+/// What's shown of the latest update of a game
+#[derive(Debug, Clone)]
+struct Game {
+    title: Title,
+    /// In a match and not in a menu
+    playing: bool,
+    health: Option<u64>,
+    /// The ammo of the active weapon in Counter-Strike, the mana in Dota
+    resource: Option<String>,
+    /// The seconds left in the round in Counter-Strike, the game clock in
+    /// Dota. Both are as of `updated`.
+    clock: Option<f64>,
+    kills: u64,
+    deaths: u64,
+    assists: u64,
+    updated: Instant,
+}
+
+impl Game {
+    /// `None` for games that aren't known
+    fn parse(state: &Value) -> Option<Self> {
+        let number = |pointer: &str| state.pointer(pointer).and_then(Value::as_u64);
+        let text = |pointer: &str| state.pointer(pointer).and_then(Value::as_str);
+
+        let game = match state.pointer("/provider/appid").and_then(Value::as_u64)? {
+            COUNTER_STRIKE => {
+                // Knives and grenades have no ammo
+                let weapon = state
+                    .pointer("/player/weapons")
+                    .and_then(Value::as_object)
+                    .and_then(|weapons| {
+                        weapons.values().find(|weapon| weapon["state"] == "active")
+                    });
+                let ammo = weapon.and_then(|weapon| {
+                    Some(format!(
+                        "{}/{}",
+                        weapon.get("ammo_clip")?.as_u64()?,
+                        weapon.get("ammo_reserve")?.as_u64()?
+                    ))
+                });
+
+                Self {
+                    title: Title::CounterStrike,
+                    playing: state.get("map").is_some()
+                        && text("/player/activity") == Some("playing"),
+                    health: number("/player/state/health"),
+                    resource: ammo,
+                    // This is a string, unlike everything else
+                    clock: text("/phase_countdowns/phase_ends_in").and_then(|s| s.parse().ok()),
+                    kills: number("/player/match_stats/kills").unwrap_or_default(),
+                    deaths: number("/player/match_stats/deaths").unwrap_or_default(),
+                    assists: number("/player/match_stats/assists").unwrap_or_default(),
+                    updated: Instant::now(),
+                }
+            }
+            DOTA => Self {
+                title: Title::Dota,
+                playing: matches!(
+                    text("/map/game_state"),
+                    Some("DOTA_GAMERULES_STATE_PRE_GAME" | "DOTA_GAMERULES_STATE_GAME_IN_PROGRESS")
+                ),
+                health: number("/hero/health"),
+                resource: number("/hero/mana").map(|mana| mana.to_string()),
+                clock: state
+                    .pointer("/map/clock_time")
+                    .and_then(Value::as_i64)
+                    .map(|clock| clock as f64),
+                kills: number("/player/kills").unwrap_or_default(),
+                deaths: number("/player/deaths").unwrap_or_default(),
+                assists: number("/player/assists").unwrap_or_default(),
+                updated: Instant::now(),
+            },
+            _ => return None,
+        };
+
+        Some(game)
+    }
+
+    /// The clock as it is now, the games don't send an update every second
+    fn clock(&self) -> Option<String> {
+        let elapsed = self.updated.elapsed().as_secs_f64();
+        let seconds = match self.title {
+            Title::CounterStrike => (self.clock? - elapsed).max(0.0).ceil(),
+            Title::Dota => (self.clock? + elapsed).floor(),
+        } as i64;
+
+        let sign = if seconds < 0 { "-" } else { "" };
+        Some(format!(
+            "{}{}:{:02}",
+            sign,
+            seconds.abs() / 60,
+            seconds.abs() % 60
+        ))
+    }
+
+    fn score(&self) -> String {
+        format!("K {}  D {}  A {}", self.kills, self.deaths, self.assists)
+    }
+}
+
+/// Shows the health, the ammo or mana and the clock of the match that's
+/// played right now
+struct Gsi {
+    game: watch::Receiver<Option<Game>>,
+    activity: watch::Receiver<bool>,
+}
+
+impl Gsi {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+        let large = MonoTextStyle::new(&iso_8859_15::FONT_8X13_BOLD, BinaryColor::On);
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build();
+
+        let game = match &*self.game.borrow() {
+            Some(game) if game.playing => game.clone(),
+            _ => {
+                let middle = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                Text::with_text_style("Waiting for a match", Point::new(64, 20), small, middle)
+                    .draw(&mut buffer)?;
+                return Ok(buffer);
+            }
+        };
+
+        let resource = match game.title {
+            Title::CounterStrike => "AMMO",
+            Title::Dota => "MANA",
+        };
+        let columns = [
+            ("HP", game.health.map(|health| health.to_string())),
+            (resource, game.resource.clone()),
+            ("TIME", game.clock()),
+        ];
+        for (column, (label, value)) in columns.iter().enumerate() {
+            let x = column as i32 * 43 + 21;
+            let value = value.as_deref().unwrap_or("-");
+            Text::with_text_style(label, Point::new(x, 0), small, centered).draw(&mut buffer)?;
+            Text::with_text_style(value, Point::new(x, 12), large, centered).draw(&mut buffer)?;
+        }
+
+        Text::with_text_style(&game.score(), Point::new(64, 29), small, centered)
+            .draw(&mut buffer)?;
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Gsi {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        // The clock keeps ticking between the updates
+        let mut interval = time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = self.game.changed() => {},
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "gsi"
+    }
+
+    fn activity(&self) -> Option<watch::Receiver<bool>> {
+        Some(self.activity.clone())
+    }
+}
+
+/// Tells about the kills and deaths of the player, like the kill feed does
+struct GsiNotifier {
+    game: watch::Receiver<Option<Game>>,
+}
+
+/// Describes what happened between two updates, `None` if it's not worth a
+/// notification
+fn describe(previous: &Game, current: &Game) -> Option<&'static str> {
+    // The counts start from zero in a new match, that's never an increase
+    if previous.title != current.title || !current.playing {
+        return None;
+    }
+
+    if current.kills > previous.kills {
+        Some("Kill")
+    } else if current.deaths > previous.deaths {
+        Some("Killed")
+    } else {
+        None
+    }
+}
+
+impl NotificationProvider for GsiNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        Ok(try_stream! {
+            let mut previous: Option<Game> = None;
+
+            while self.game.changed().await.is_ok() {
+                let current = self.game.borrow().clone();
+
+                if let (Some(previous), Some(current)) = (&previous, &current) {
+                    if let Some(title) = describe(previous, current) {
+                        if let Ok(notification) = NotificationBuilder::new()
+                            .with_title(title)
+                            .with_content(current.score())
+                            .build()
+                        {
+                            yield notification;
+                        }
+                    }
+                }
+
+                previous = current;
+            }
+        })
+    }
+}
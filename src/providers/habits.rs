@@ -0,0 +1,262 @@
+use crate::{
+    render::{
+        display::{ContentProvider, ProviderInput},
+        scheduler::ContentWrapper,
+    },
+    settings,
+    state::state_dir,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_input::Command;
+use async_stream::try_stream;
+use chrono::{Local, NaiveDate};
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Line, Primitive, PrimitiveStyle, Rectangle},
+    text::{renderer::TextRenderer, Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    time::SystemTime,
+};
+use tokio::{
+    sync::mpsc,
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// The maximum amount of habits that fit on the display
+const MAX_HABITS: usize = 4;
+
+/// The settings of the `[habits]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HabitSettings {
+    names: Vec<String>,
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Habit display source.");
+
+    let mut habits = settings::section::<HabitSettings>(config, "habits")?.names;
+    if habits.len() > MAX_HABITS {
+        warn!("Only the first {} habits fit on the screen", MAX_HABITS);
+        habits.truncate(MAX_HABITS);
+    }
+
+    let (input, commands) = mpsc::unbounded_channel();
+
+    Ok(Box::new(Habits {
+        habits,
+        // `apex-ctl habit done` writes to the same file
+        path: state_dir().join("habits"),
+        log: HashMap::new(),
+        modified: None,
+        input,
+        commands: Some(commands),
+    }))
+}
+
+/// Counts the days in a row the habit has been done. A streak isn't broken
+/// until the day is over so if it's not done yet today we start counting from
+/// yesterday.
+fn streak(days: &HashSet<NaiveDate>, today: NaiveDate) -> u32 {
+    let mut day = if days.contains(&today) {
+        Some(today)
+    } else {
+        today.pred_opt()
+    };
+
+    let mut streak = 0;
+    while let Some(current) = day.filter(|d| days.contains(d)) {
+        streak += 1;
+        day = current.pred_opt();
+    }
+
+    streak
+}
+
+struct Habits {
+    habits: Vec<String>,
+    /// The log of completed habits, one `YYYY-MM-DD\tname` entry per line
+    path: PathBuf,
+    /// The days every habit was done on as of the last time the log changed
+    log: HashMap<String, HashSet<NaiveDate>>,
+    modified: Option<SystemTime>,
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+impl Habits {
+    /// Reads the days every habit was done on from the log
+    fn load(&self) -> HashMap<String, HashSet<NaiveDate>> {
+        let mut log: HashMap<String, HashSet<NaiveDate>> = HashMap::new();
+
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            // Nothing has been done yet
+            Err(_) => return log,
+        };
+
+        for (date, name) in content.lines().filter_map(|line| line.split_once('\t')) {
+            if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                log.entry(name.to_string()).or_default().insert(date);
+            }
+        }
+
+        log
+    }
+
+    /// Reads the log again if it changed since the last time, e.g. because
+    /// `apex-ctl habit done` wrote to it
+    fn refresh(&mut self) {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified != self.modified {
+            self.log = self.load();
+            self.modified = modified;
+        }
+    }
+
+    /// Marks the first habit that isn't done yet today as done
+    fn mark_next_done(&mut self) -> Result<()> {
+        self.refresh();
+        let today = Local::now().naive_local().date();
+
+        let name = match self.habits.iter().find(|name| {
+            !self
+                .log
+                .get(*name)
+                .map_or(false, |days| days.contains(&today))
+        }) {
+            Some(name) => name.clone(),
+            None => return Ok(()),
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}\t{}", today.format("%Y-%m-%d"), name)?;
+
+        info!("Marked `{}` as done for today", name);
+        // The time of the file may be too coarse to tell it changed again
+        self.log.entry(name).or_default().insert(today);
+        Ok(())
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        if self.habits.is_empty() {
+            let text = "No habits configured";
+            let metrics = style.measure_string(text, Point::zero(), Baseline::Top);
+            let width = metrics.bounding_box.size.width as i32;
+            Text::with_baseline(text, Point::new(64 - width / 2, 15), style, Baseline::Top)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        let today = Local::now().naive_local().date();
+        let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+        for (row, name) in self.habits.iter().enumerate() {
+            let y = row as i32 * 10;
+            let days = self.log.get(name);
+
+            if days.map_or(false, |days| days.contains(&today)) {
+                Line::new(Point::new(0, y + 5), Point::new(2, y + 7))
+                    .into_styled(stroke)
+                    .draw(&mut buffer)?;
+                Line::new(Point::new(2, y + 7), Point::new(6, y + 1))
+                    .into_styled(stroke)
+                    .draw(&mut buffer)?;
+            } else {
+                Rectangle::new(Point::new(0, y + 1), Size::new(7, 7))
+                    .into_styled(stroke)
+                    .draw(&mut buffer)?;
+            }
+
+            let count = days.map_or(0, |days| streak(days, today));
+            let count = format!("{}d", count);
+            let metrics = style.measure_string(&count, Point::zero(), Baseline::Top);
+            let count_x = 128 - metrics.bounding_box.size.width as i32;
+            Text::with_baseline(&count, Point::new(count_x, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            // Cut off the name so it doesn't run into the streak counter
+            let max_chars = ((count_x - 10 - 6) / 6).max(0) as usize;
+            let name = name.chars().take(max_chars).collect::<String>();
+            Text::with_baseline(&name, Point::new(10, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Habits {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(500));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The habit stream can only be started once!"))?;
+
+        Ok(try_stream! {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    Some(command) = commands.recv() => {
+                        if let Command::Select = command {
+                            if let Err(e) = self.mark_next_done() {
+                                warn!("Failed to save habit: {}", e);
+                            }
+                        }
+                    }
+                }
+
+                self.refresh();
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "habits"
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(self.input.clone()))
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    render::{display::ContentProvider, image, scheduler::ContentWrapper},
-    scheduler::CONTENT_PROVIDERS,
+    render::{display::ContentProvider, image, scheduler::ContentWrapper, util},
+    settings,
 };
 use anyhow::Result;
 use apex_hardware::FrameBuffer;
@@ -8,53 +8,137 @@ use async_stream::try_stream;
 use config::Config;
 use embedded_graphics::geometry::Point;
 use futures::Stream;
-use linkme::distributed_slice;
 use log::info;
-use std::fs::File;
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
 use tokio::{
-    time,
+    sync::mpsc,
+    task, time,
     time::{Duration, MissedTickBehavior},
 };
 
 #[doc(hidden)]
-#[distributed_slice(CONTENT_PROVIDERS)]
 pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
 
+const ORIGIN: Point = Point::new(0, 0);
+const STOP: Point = Point::new(128, 40);
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ImageSettings {
+    path: String,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        Self {
+            path: String::from("images/sample_1.gif"),
+        }
+    }
+}
+
+/// Lists the images in `path` sorted by their name if it's a directory,
+/// otherwise `path` is the only image
+fn image_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut paths = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| ::image::ImageFormat::from_path(path).is_ok())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    Ok(paths)
+}
+
 #[doc(hidden)]
-#[allow(clippy::unnecessary_wraps)]
 fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
     info!("Registering Image display source.");
 
-    let image_path = config
-        .get_str("image.path")
-        .unwrap_or_else(|_| String::from("images/sample_1.gif"));
-    let image_file = File::open(&image_path);
+    let image_path = settings::section::<ImageSettings>(config, "image")?.path;
 
-    let image = match image_file {
-        Ok(file) => image::ImageRenderer::new(Point::new(0, 0), Point::new(128, 40), file),
-        Err(err) => {
-            log::error!("Failed to open the image '{}': {}", image_path, err);
+    // The images are only decoded once the provider starts so this is quick
+    let (pending, images) = match image_paths(Path::new(&image_path)) {
+        Ok(paths) if !paths.is_empty() => {
+            let images = paths.iter().map(|_| None).collect();
+            (paths, images)
+        }
+        result => {
+            match result {
+                Ok(_) => log::error!("There are no images in '{}'", image_path),
+                Err(err) => log::error!("Failed to open the image '{}': {}", image_path, err),
+            }
 
             // Use the `new_error` function to create an error GIF
-            image::ImageRenderer::new_error(Point::new(0, 0), Point::new(128, 40))
+            let error = image::ImageRenderer::new_error(ORIGIN, STOP);
+            (Vec::new(), vec![Some(error)])
         }
     };
 
-    Ok(Box::new(Image { image }))
+    Ok(Box::new(Image {
+        pending,
+        images,
+        current: 0,
+        placeholder: util::loading()?,
+    }))
 }
 
 pub struct Image {
-    image: image::ImageRenderer,
+    /// The images that still need to be decoded, they fill the slots of
+    /// `images` with the same index
+    pending: Vec<PathBuf>,
+    images: Vec<Option<image::ImageRenderer>>,
+    /// The image that's shown right now, every image is played to the end
+    /// before the next one is shown
+    current: usize,
+    /// Shown while the current image is still being decoded
+    placeholder: FrameBuffer,
 }
 
 impl Image {
-    pub fn render(&self) -> Result<FrameBuffer> {
-        let mut buffer = FrameBuffer::new();
+    pub fn render(&mut self) -> Result<FrameBuffer> {
+        let image = match self.images.get(self.current).and_then(Option::as_ref) {
+            Some(image) => image,
+            None => return Ok(self.placeholder),
+        };
 
-        self.image.draw(&mut buffer);
+        // The frames are rendered up front so this is only a copy
+        let buffer = *image.frame();
+        if image.advance() {
+            self.current = (self.current + 1) % self.images.len();
+        }
 
         Ok(buffer)
     }
+
+    /// Decodes the pending images in parallel, every image is sent back with
+    /// its index once it's done
+    fn decode(&mut self) -> mpsc::UnboundedReceiver<(usize, image::ImageRenderer)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        for (index, path) in self.pending.drain(..).enumerate() {
+            let tx = tx.clone();
+            task::spawn_blocking(move || {
+                let image = match File::open(&path) {
+                    Ok(file) => image::ImageRenderer::new(ORIGIN, STOP, file),
+                    Err(err) => {
+                        log::error!("Failed to open the image '{}': {}", path.display(), err);
+                        image::ImageRenderer::new_error(ORIGIN, STOP)
+                    }
+                };
+
+                // Nobody is waiting for the image anymore if the provider was stopped
+                let _ = tx.send((index, image));
+            });
+        }
+
+        rx
+    }
 }
 
 impl ContentProvider for Image {
@@ -68,8 +152,14 @@ impl ContentProvider for Image {
         // from wikipedia (in the table, look for the byte 324)
         // https://en.wikipedia.org/w/index.php?title=GIF&oldid=1157626024#Animated_GIF
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut decoded = self.decode();
+
         Ok(try_stream! {
             loop {
+                while let Ok((index, image)) = decoded.try_recv() {
+                    self.images[index] = Some(image);
+                }
+
                 if let Ok(image) = self.render() {
                     yield image;
                 }
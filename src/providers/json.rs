@@ -0,0 +1,231 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper, util},
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use log::{debug, info, warn};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, ClientBuilder,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::collections::HashMap;
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// How many characters of the font fit in one row
+const MAX_CHARS: usize = 21;
+/// How many rows of the font fit on the screen
+const MAX_LINES: usize = 4;
+
+/// The settings of the `[json]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct JsonSettings {
+    url: String,
+    /// Sent along with every request, e.g. for an API key
+    headers: HashMap<String, String>,
+    /// In seconds
+    polling_interval: u64,
+    lines: Vec<LineSettings>,
+}
+
+impl Default for JsonSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            headers: HashMap::new(),
+            polling_interval: 60,
+            lines: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LineSettings {
+    /// A JSONPath expression like `$.main.temp`
+    path: String,
+    /// `{}` is replaced with the value
+    #[serde(default = "default_format")]
+    format: String,
+}
+
+fn default_format() -> String {
+    String::from("{}")
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering JSON display source.");
+
+    let settings: JsonSettings = settings::section(config, "json")?;
+    if settings.url.is_empty() {
+        warn!("There's no `url` in [json], the screen stays empty");
+    }
+    if settings.lines.len() > MAX_LINES {
+        return Err(anyhow!(
+            "Invalid settings in [json]: Only {} `lines` fit on the screen",
+            MAX_LINES
+        ));
+    }
+
+    let lines = settings
+        .lines
+        .iter()
+        .map(|line| {
+            let path = JsonPath::parse(&line.path).map_err(|e| {
+                anyhow!(
+                    "Invalid settings in [json]: `{}` isn't a JSONPath expression: {}",
+                    line.path,
+                    e
+                )
+            })?;
+            Ok(Line {
+                path,
+                format: line.format.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let headers = settings
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            Ok((
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            ))
+        })
+        .collect::<Result<HeaderMap>>()
+        .map_err(|e| anyhow!("Invalid settings in [json]: Broken `headers`: {}", e))?;
+
+    Ok(Box::new(Json {
+        client: ClientBuilder::new()
+            .user_agent(APP_USER_AGENT)
+            .default_headers(headers)
+            .build()?,
+        url: settings.url,
+        polling_interval: Duration::from_secs(settings.polling_interval.max(1)),
+        lines,
+        values: None,
+    }))
+}
+
+struct Line {
+    path: JsonPath,
+    format: String,
+}
+
+impl Line {
+    /// The formatted value, a dash if the answer doesn't have it
+    fn extract(&self, answer: &Value) -> String {
+        let value = match self.path.query(answer).first() {
+            // Strings would end up in quotes otherwise
+            Some(Value::String(string)) => string.clone(),
+            Some(Value::Null) | None => String::from("-"),
+            Some(value) => value.to_string(),
+        };
+
+        self.format.replace("{}", &value)
+    }
+}
+
+/// Puts values out of any JSON API on the screen, one line each
+struct Json {
+    client: Client,
+    url: String,
+    polling_interval: Duration,
+    lines: Vec<Line>,
+    /// `None` until the API answered for the first time
+    values: Option<Vec<String>>,
+}
+
+impl Json {
+    async fn fetch(&self) -> Result<Vec<String>> {
+        let answer = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        Ok(self
+            .lines
+            .iter()
+            .map(|line| line.extract(&answer))
+            .collect())
+    }
+
+    fn render(&self) -> Result<FrameBuffer> {
+        let values = match &self.values {
+            Some(values) => values,
+            None if self.url.is_empty() => return util::placeholder("No URL configured"),
+            None => return util::placeholder("Loading"),
+        };
+
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        for (row, value) in values.iter().enumerate() {
+            let value = value.chars().take(MAX_CHARS).collect::<String>();
+            Text::with_baseline(&value, Point::new(0, row as i32 * 10), style, Baseline::Top)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Json {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(self.polling_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                // The last answer stays on the screen if the API can't be reached
+                if !self.url.is_empty() {
+                    match self.fetch().await {
+                        Ok(values) => self.values = Some(values),
+                        Err(e) => debug!("Couldn't get {}: {}", self.url, e),
+                    }
+                }
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "json"
+    }
+}
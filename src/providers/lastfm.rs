@@ -0,0 +1,274 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper},
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use chrono::{Local, Timelike};
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{debug, info};
+use reqwest::{Client, ClientBuilder};
+use serde::Deserialize;
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// How many characters of the font fit in one row
+const MAX_CHARS: usize = 21;
+
+/// The settings of the `[lastfm]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LastFmSettings {
+    /// The user whose scrobbles are shown
+    user: String,
+    /// The key of an API account created on last.fm/api
+    api_key: String,
+    /// In seconds
+    polling_interval: u64,
+}
+
+impl Default for LastFmSettings {
+    fn default() -> Self {
+        Self {
+            user: String::new(),
+            api_key: String::new(),
+            polling_interval: 120,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Last.fm display source.");
+
+    let settings: LastFmSettings = settings::section(config, "lastfm")?;
+
+    Ok(Box::new(LastFm {
+        client: ClientBuilder::new().user_agent(APP_USER_AGENT).build()?,
+        settings,
+        stats: None,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentTracksResponse {
+    recenttracks: RecentTracks,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentTracks {
+    track: Vec<Track>,
+    #[serde(rename = "@attr")]
+    attributes: Page,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    /// Last.fm sends all numbers as strings
+    total: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Track {
+    name: String,
+    artist: Named,
+    /// Only the track that's playing right now has these
+    #[serde(rename = "@attr")]
+    attributes: Option<NowPlaying>,
+}
+
+/// Names that come as `{"#text": "..."}`
+#[derive(Debug, Deserialize)]
+struct Named {
+    #[serde(rename = "#text")]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NowPlaying {
+    nowplaying: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopArtistsResponse {
+    topartists: TopArtists,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopArtists {
+    artist: Vec<Artist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artist {
+    name: String,
+    playcount: String,
+}
+
+/// What Last.fm answered the last time it was asked
+#[derive(Debug, Clone)]
+struct Stats {
+    /// Scrobbles since midnight
+    today: u64,
+    /// The artist and the title reported as playing right now
+    now_playing: Option<(String, String)>,
+    /// The most played artist of the last seven days along with the plays
+    top_artist: Option<(String, u64)>,
+}
+
+/// Shows today's scrobbles, what's playing and the top artist of the week of
+/// a Last.fm user
+struct LastFm {
+    client: Client,
+    settings: LastFmSettings,
+    /// `None` until Last.fm answered for the first time
+    stats: Option<Stats>,
+}
+
+impl LastFm {
+    async fn call<T: for<'de> Deserialize<'de>>(&self, parameters: &[(&str, &str)]) -> Result<T> {
+        Ok(self
+            .client
+            .get(API_URL)
+            .query(&[
+                ("user", self.settings.user.as_str()),
+                ("api_key", self.settings.api_key.as_str()),
+                ("format", "json"),
+            ])
+            .query(parameters)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<T>()
+            .await?)
+    }
+
+    async fn stats(&self) -> Result<Stats> {
+        let now = Local::now();
+        let midnight = (now.timestamp() - i64::from(now.num_seconds_from_midnight())).to_string();
+
+        // The total counts every scrobble since `from`, the track that's playing right
+        // now comes first without being scrobbled yet
+        let recent = self
+            .call::<RecentTracksResponse>(&[
+                ("method", "user.getrecenttracks"),
+                ("from", &midnight),
+                ("limit", "1"),
+            ])
+            .await?
+            .recenttracks;
+        let top = self
+            .call::<TopArtistsResponse>(&[
+                ("method", "user.gettopartists"),
+                ("period", "7day"),
+                ("limit", "1"),
+            ])
+            .await?
+            .topartists;
+
+        Ok(Stats {
+            today: recent.attributes.total.parse().unwrap_or(0),
+            now_playing: recent
+                .track
+                .into_iter()
+                .find(|track| {
+                    track
+                        .attributes
+                        .as_ref()
+                        .map_or(false, |attributes| attributes.nowplaying == "true")
+                })
+                .map(|track| (track.artist.text, track.name)),
+            top_artist: top
+                .artist
+                .into_iter()
+                .next()
+                .map(|artist| (artist.name, artist.playcount.parse().unwrap_or(0))),
+        })
+    }
+
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        let stats = match &self.stats {
+            Some(stats) => stats,
+            None => {
+                let centered = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                Text::with_text_style("Asking Last.fm", Point::new(64, 20), style, centered)
+                    .draw(&mut buffer)?;
+                return Ok(buffer);
+            }
+        };
+
+        let lines = [
+            format!("Today: {} scrobbles", stats.today),
+            match &stats.now_playing {
+                Some((artist, title)) => format!("Now: {} - {}", artist, title),
+                None => String::from("Nothing playing"),
+            },
+            match &stats.top_artist {
+                Some((artist, plays)) => format!("Top: {} ({})", artist, plays),
+                None => String::from("No top artist yet"),
+            },
+        ];
+
+        for (row, line) in lines.iter().enumerate() {
+            let line = line.chars().take(MAX_CHARS).collect::<String>();
+            Text::with_baseline(&line, Point::new(0, row as i32 * 14), style, Baseline::Top)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for LastFm {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval =
+            time::interval(Duration::from_secs(self.settings.polling_interval.max(1)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                // The last answer stays on the screen if Last.fm can't be reached
+                match self.stats().await {
+                    Ok(stats) => self.stats = Some(stats),
+                    Err(e) => debug!("Couldn't get the stats from Last.fm: {}", e),
+                }
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "lastfm"
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    render::{
+        accessibility::{self, LARGE_TEXT_ROWS},
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        scheduler::{ContentWrapper, NotificationWrapper},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_imap::{extensions::idle::IdleResponse, types::Fetch, Session};
+use async_native_tls::{TlsConnector, TlsStream};
+use async_stream::{stream, try_stream};
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{renderer::TextRenderer, Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use tokio::{net::TcpStream, time, time::Duration};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+/// The maximum amount of rows that fit on the display with the font we're
+/// using
+const MAX_ENTRIES: usize = 4;
+
+/// Servers may drop connections that are idle for 30 minutes so IDLE is
+/// restarted a bit before that
+const IDLE_TIMEOUT: Duration = Duration::from_secs(29 * 60);
+
+/// How long to wait before connecting again after the connection was lost
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// More mails than this arriving at once are summed up in one notification
+const MAX_NOTIFICATIONS: usize = 3;
+
+/// The settings of the `[mail]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MailSettings {
+    host: String,
+    /// Only IMAP over TLS is supported
+    port: u16,
+    username: String,
+    password: String,
+    /// The folders whose unread mails are counted, each of them needs a
+    /// connection of its own
+    folders: Vec<String>,
+}
+
+impl Default for MailSettings {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 993,
+            username: String::new(),
+            password: String::new(),
+            folders: vec![String::from("INBOX")],
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Mail display source.");
+
+    let settings: MailSettings = settings::section(config, "mail")?;
+    let max_entries = if accessibility::large_text() {
+        LARGE_TEXT_ROWS
+    } else {
+        MAX_ENTRIES
+    };
+
+    Ok(Box::new(Mail {
+        unread: vec![None; settings.folders.len()],
+        entries: max_entries,
+        settings,
+    }))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering Mail notification source.");
+
+    let settings = settings::section(config, "mail")?;
+
+    Ok(Box::new(MailNotifier { settings }))
+}
+
+/// Who sent a new mail and what it's about
+#[derive(Debug, Clone)]
+struct Envelope {
+    sender: String,
+    subject: String,
+}
+
+/// Subjects and names may be MIME encoded words like `=?UTF-8?B?...?=`
+fn decode(bytes: &[u8]) -> String {
+    rfc2047_decoder::decode(bytes).unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned())
+}
+
+impl From<&Fetch> for Envelope {
+    fn from(fetch: &Fetch) -> Self {
+        let envelope = fetch.envelope();
+
+        let subject = envelope
+            .and_then(|envelope| envelope.subject.as_deref())
+            .map_or_else(|| String::from("(no subject)"), decode);
+
+        // The display name is preferred, the address is the fallback
+        let sender = envelope
+            .and_then(|envelope| envelope.from.as_ref())
+            .and_then(|from| from.first())
+            .map(
+                |address| match (&address.name, &address.mailbox, &address.host) {
+                    (Some(name), ..) => decode(name),
+                    (None, Some(mailbox), Some(host)) => {
+                        format!("{}@{}", decode(mailbox), decode(host))
+                    }
+                    _ => String::from("Unknown sender"),
+                },
+            )
+            .unwrap_or_else(|| String::from("Unknown sender"));
+
+        Self { sender, subject }
+    }
+}
+
+/// What happened in one of the folders
+#[derive(Debug, Clone)]
+enum Change {
+    Unread(usize),
+    Arrived(Vec<Envelope>),
+    /// The connection was lost, the folder is watched again after a while
+    Disconnected,
+}
+
+async fn connect(settings: &MailSettings) -> Result<Session<TlsStream<TcpStream>>> {
+    let tcp = TcpStream::connect((settings.host.as_str(), settings.port)).await?;
+    let tls = TlsConnector::new().connect(&settings.host, tcp).await?;
+
+    async_imap::Client::new(tls)
+        .login(&settings.username, &settings.password)
+        .await
+        .map_err(|(e, _)| anyhow!("Couldn't log in to {}: {}", settings.host, e))
+}
+
+/// Selects `folder` and waits for changes with IDLE until the connection
+/// breaks. The mails that are unread when it starts aren't announced.
+fn session<'a>(
+    settings: &'a MailSettings,
+    folder: &'a str,
+) -> impl Stream<Item = Result<Change>> + 'a {
+    try_stream! {
+        let mut session = connect(settings).await?;
+        session.select(folder).await?;
+
+        let mut known = session.uid_search("UNSEEN").await?;
+        yield Change::Unread(known.len());
+
+        loop {
+            let mut idle = session.idle();
+            idle.init().await?;
+            // Dropping the interrupt ends IDLE right away so it has to stay around
+            let (wait, _interrupt) = idle.wait_with_timeout(IDLE_TIMEOUT);
+            if let IdleResponse::NewData(data) = wait.await? {
+                debug!("{} changed: {:?}", folder, data.parsed());
+            }
+            session = idle.done().await?;
+
+            // Whatever the server told us, searching again is the simplest way to find out
+            // what changed
+            let unseen = session.uid_search("UNSEEN").await?;
+            let new = unseen.difference(&known).join(",");
+            if !new.is_empty() {
+                let envelopes = session
+                    .uid_fetch(&new, "ENVELOPE")
+                    .await?
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                yield Change::Arrived(envelopes.iter().map(Envelope::from).collect());
+            }
+
+            known = unseen;
+            yield Change::Unread(known.len());
+        }
+    }
+}
+
+/// Watches `folder` for as long as the stream is around, lost connections
+/// are opened again
+fn watch<'a>(settings: &'a MailSettings, folder: &'a str) -> impl Stream<Item = Change> + 'a {
+    stream! {
+        loop {
+            let mut changes = Box::pin(session(settings, folder));
+            while let Some(change) = changes.next().await {
+                match change {
+                    Ok(change) => yield change,
+                    Err(e) => {
+                        warn!("Lost the connection to {} on {}: {}", folder, settings.host, e);
+                        break;
+                    }
+                }
+            }
+
+            yield Change::Disconnected;
+            time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Watches every folder of the settings at once, the changes come with the
+/// index of their folder
+fn watch_all(settings: &MailSettings) -> impl Stream<Item = (usize, Change)> + '_ {
+    stream::select_all(settings.folders.iter().enumerate().map(|(index, folder)| {
+        Box::pin(watch(settings, folder).map(move |change| (index, change)))
+    }))
+}
+
+struct Mail {
+    settings: MailSettings,
+    /// The unread mails of every folder, `None` while it's not connected
+    unread: Vec<Option<usize>>,
+    entries: usize,
+}
+
+impl Mail {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let font = if accessibility::large_text() {
+            &iso_8859_15::FONT_9X15
+        } else {
+            &iso_8859_15::FONT_6X10
+        };
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let char_width = style.font.character_size.width as i32;
+        let char_height = style.font.character_size.height as i32;
+
+        if self.settings.folders.is_empty() {
+            let centered = TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Middle)
+                .build();
+            Text::with_text_style("No folders", Point::new(64, 20), style, centered)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        let row_height = 40 / self.entries as i32;
+
+        for (row, (folder, unread)) in self
+            .settings
+            .folders
+            .iter()
+            .zip(&self.unread)
+            .take(self.entries)
+            .enumerate()
+        {
+            let y = row as i32 * row_height + (row_height - char_height) / 2;
+
+            let count = unread.map_or_else(|| String::from("?"), |unread| unread.to_string());
+            let metrics = style.measure_string(&count, Point::zero(), Baseline::Top);
+            let count_x = 128 - metrics.bounding_box.size.width as i32;
+            Text::with_baseline(&count, Point::new(count_x, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            // Cut off the name so it doesn't run into the count
+            let max_chars = ((count_x - char_width) / char_width).max(0) as usize;
+            let name = folder.chars().take(max_chars).collect::<String>();
+            Text::with_baseline(&name, Point::new(0, y), style, Baseline::Top).draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Mail {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        Ok(try_stream! {
+            // Show the folders right away, the counts show up once they're connected
+            if let Ok(image) = self.render() {
+                yield image;
+            }
+
+            let mut changes = Box::pin(watch_all(&self.settings));
+            while let Some((index, change)) = changes.next().await {
+                self.unread[index] = match change {
+                    Change::Unread(unread) => Some(unread),
+                    Change::Arrived(_) => continue,
+                    Change::Disconnected => None,
+                };
+
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "mail"
+    }
+}
+
+/// Tells who sent a new mail and what it's about
+struct MailNotifier {
+    settings: MailSettings,
+}
+
+impl NotificationProvider for MailNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        Ok(try_stream! {
+            let mut changes = Box::pin(watch_all(&self.settings));
+
+            while let Some((_, change)) = changes.next().await {
+                let envelopes = match change {
+                    Change::Arrived(envelopes) => envelopes,
+                    _ => continue,
+                };
+
+                if envelopes.len() > MAX_NOTIFICATIONS {
+                    if let Ok(notification) = NotificationBuilder::new()
+                        .with_title("New mail")
+                        .with_content(format!("{} new mails", envelopes.len()))
+                        .build()
+                    {
+                        yield notification;
+                    }
+                    continue;
+                }
+
+                for envelope in envelopes {
+                    if let Ok(notification) = NotificationBuilder::new()
+                        .with_title(&envelope.sender)
+                        .with_content(envelope.subject)
+                        .build()
+                    {
+                        yield notification;
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    render::{
+        digits::BigDigits,
+        display::{ContentProvider, ProviderInput},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::{BlendMode, FrameBuffer};
+use apex_input::Command;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Circle, Primitive, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use log::info;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use tokio::{
+    sync::mpsc,
+    time,
+    time::{Duration, Instant},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+const MIN_BPM: f64 = 20.0;
+const MAX_BPM: f64 = 300.0;
+/// How long the screen lights up on every beat
+const FLASH_LENGTH: Duration = Duration::from_millis(80);
+/// Taps that are further apart than this start a new tempo
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// The amount of taps that are averaged to calculate the tempo
+const MAX_TAPS: usize = 5;
+
+/// The settings of the `[metronome]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MetronomeSettings {
+    bpm: f64,
+    /// The amount of beats per bar
+    beats: u64,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        Self {
+            bpm: 120.0,
+            beats: 4,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Metronome display source.");
+
+    let settings: MetronomeSettings = settings::section(config, "metronome")?;
+    let bpm = settings.bpm.clamp(MIN_BPM, MAX_BPM);
+    let beats = settings.beats.clamp(1, 9);
+
+    let (input, commands) = mpsc::unbounded_channel();
+
+    Ok(Box::new(Metronome {
+        bpm,
+        beats,
+        start: Instant::now(),
+        taps: VecDeque::with_capacity(MAX_TAPS),
+        input,
+        commands: Some(commands),
+    }))
+}
+
+struct Metronome {
+    bpm: f64,
+    /// The amount of beats per bar
+    beats: u64,
+    /// When the first beat happened, all other beats are relative to this
+    start: Instant,
+    taps: VecDeque<Instant>,
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+impl Metronome {
+    fn beat_length(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / self.bpm)
+    }
+
+    /// Returns the index of the beat at `now` and when it started
+    fn beat_at(&self, now: Instant) -> (u64, Instant) {
+        let beat_length = self.beat_length();
+        let index = (now.duration_since(self.start).as_secs_f64() / beat_length.as_secs_f64())
+            .floor() as u64;
+
+        (index, self.start + beat_length * index as u32)
+    }
+
+    /// Calculates when the display needs to change next, which is either when
+    /// the flash of the current beat ends or when the next beat starts.
+    fn next_change(&self, now: Instant) -> Instant {
+        let (_, beat_start) = self.beat_at(now);
+        let flash_end = beat_start + FLASH_LENGTH;
+
+        if now < flash_end {
+            flash_end
+        } else {
+            beat_start + self.beat_length()
+        }
+    }
+
+    /// Derives the tempo from the time between the last few taps. Every tap
+    /// also starts a new bar so the metronome lines up with the user.
+    fn tap(&mut self) {
+        let now = Instant::now();
+
+        if let Some(last) = self.taps.back() {
+            if now.duration_since(*last) > TAP_TIMEOUT {
+                self.taps.clear();
+            }
+        }
+
+        if self.taps.len() == MAX_TAPS {
+            self.taps.pop_front();
+        }
+        self.taps.push_back(now);
+
+        if let (Some(first), Some(last)) = (self.taps.front(), self.taps.back()) {
+            let span = last.duration_since(*first).as_secs_f64();
+            if span > 0.0 {
+                let bpm = 60.0 * (self.taps.len() - 1) as f64 / span;
+                self.bpm = bpm.clamp(MIN_BPM, MAX_BPM).round();
+            }
+        }
+
+        self.start = now;
+    }
+
+    pub fn render(&self, now: Instant) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let (index, beat_start) = self.beat_at(now);
+        let beat = index % self.beats;
+        let flash = now.duration_since(beat_start) < FLASH_LENGTH;
+
+        let digits = BigDigits::new(Size::new(16, 34), 3);
+        digits.draw(&(beat + 1).to_string(), Point::new(8, 3), &mut buffer)?;
+
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+        Text::with_baseline(
+            &format!("{} BPM", self.bpm),
+            Point::new(40, 4),
+            style,
+            Baseline::Top,
+        )
+        .draw(&mut buffer)?;
+
+        let outline = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+
+        for n in 0..self.beats {
+            let circle = Circle::new(Point::new(40 + 10 * n as i32, 24), 8);
+            if n == beat {
+                circle.into_styled(fill).draw(&mut buffer)?;
+            } else {
+                circle.into_styled(outline).draw(&mut buffer)?;
+            }
+        }
+
+        if flash {
+            // The first beat of every bar lights up the whole screen, the others only the
+            // border
+            if beat == 0 {
+                Rectangle::new(Point::zero(), Size::new(128, 40))
+                    .into_styled(fill)
+                    .draw(&mut buffer.blend(BlendMode::Xor))?;
+            } else {
+                Rectangle::new(Point::zero(), Size::new(128, 40))
+                    .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 2))
+                    .draw(&mut buffer)?;
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Metronome {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The metronome stream can only be started once!"))?;
+
+        Ok(try_stream! {
+            loop {
+                let now = Instant::now();
+                if let Ok(image) = self.render(now) {
+                    yield image;
+                }
+
+                // Sleep until exactly the next beat instead of polling so the beats don't drift
+                let next = self.next_change(now);
+                tokio::select! {
+                    _ = time::sleep_until(next) => {},
+                    Some(command) = commands.recv() => {
+                        if let Command::Select = command {
+                            self.tap();
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "metronome"
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(self.input.clone()))
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    pulse::{self, Event},
+    render::{
+        overlay::{Overlay, OverlayProvider},
+        scheduler::OverlayWrapper,
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::{BlendMode, FrameBuffer};
+use async_stream::{stream, try_stream};
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Line, Polyline, Primitive, PrimitiveStyle, Rectangle, RoundedRectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{Stream, StreamExt};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use tokio::{time, time::Duration};
+
+#[doc(hidden)]
+pub static OVERLAY_INIT: fn(&Config) -> Result<Box<dyn OverlayWrapper>> = register_callback;
+
+/// How long to wait before asking PulseAudio again after it went away
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The top left corner of the icon, it's 9x11 pixels large
+const ICON_ORIGIN: Point = Point::new(118, 0);
+
+/// The settings of the `[mic]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MicSettings {
+    /// Shows a small icon on top of every screen while the microphone is
+    /// muted
+    icon: bool,
+    /// Shows a banner whenever the microphone is muted or unmuted
+    banner: bool,
+    /// How long the banner is shown, in milliseconds
+    banner_duration: u64,
+}
+
+impl Default for MicSettings {
+    fn default() -> Self {
+        Self {
+            icon: true,
+            banner: true,
+            banner_duration: 1500,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn OverlayWrapper>> {
+    info!("Registering Mic overlay source.");
+
+    // Overlay sources aren't filtered by the scheduler
+    let settings = if settings::common(config, "mic")?.enabled {
+        Some(settings::section(config, "mic")?)
+    } else {
+        None
+    };
+
+    Ok(Box::new(Mic { settings }))
+}
+
+/// A crossed out microphone
+fn draw_icon(buffer: &mut FrameBuffer) -> Result<()> {
+    let on = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+    RoundedRectangle::with_equal_corners(
+        Rectangle::new(ICON_ORIGIN + Point::new(2, 0), Size::new(5, 7)),
+        Size::new(2, 2),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(buffer)?;
+    let stand = [(0, 4), (0, 6), (2, 8), (6, 8), (8, 6), (8, 4)]
+        .map(|(x, y)| ICON_ORIGIN + Point::new(x, y));
+    Polyline::new(&stand).into_styled(on).draw(buffer)?;
+    Line::new(
+        ICON_ORIGIN + Point::new(4, 8),
+        ICON_ORIGIN + Point::new(4, 10),
+    )
+    .into_styled(on)
+    .draw(buffer)?;
+    Line::new(
+        ICON_ORIGIN + Point::new(2, 10),
+        ICON_ORIGIN + Point::new(6, 10),
+    )
+    .into_styled(on)
+    .draw(buffer)?;
+
+    // The gap around the line keeps it apart from the microphone
+    let slash = Line::new(
+        ICON_ORIGIN + Point::new(0, 10),
+        ICON_ORIGIN + Point::new(8, 0),
+    );
+    slash
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::Off, 3))
+        .draw(buffer)?;
+    slash.into_styled(on).draw(buffer)?;
+
+    Ok(())
+}
+
+/// What's drawn on top of the screens, `banner` covers them altogether
+fn render(settings: &MicSettings, muted: bool, banner: bool) -> Result<Option<Overlay>> {
+    let mut buffer = FrameBuffer::new();
+
+    if banner {
+        Rectangle::new(Point::zero(), Size::new(128, 40))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut buffer)?;
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_10X20, BinaryColor::On);
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        let text = if muted { "MIC MUTED" } else { "MIC LIVE" };
+        Text::with_text_style(text, Point::new(64, 20), style, centered).draw(&mut buffer)?;
+        return Ok(Some(Overlay::new(buffer, BlendMode::Replace)));
+    }
+
+    if !settings.icon || !muted {
+        return Ok(None);
+    }
+
+    draw_icon(&mut buffer)?;
+    Ok(Some(Overlay::new(buffer, BlendMode::Xor)))
+}
+
+/// Asks PulseAudio again after something changed, `None` if the microphone
+/// is still muted or unmuted
+async fn changed(muted: bool) -> Option<bool> {
+    match pulse::source_muted().await {
+        Ok(now) => (now != muted).then_some(now),
+        Err(e) => {
+            debug!("Couldn't find out whether the microphone is muted: {}", e);
+            None
+        }
+    }
+}
+
+/// Follows the microphone for as long as the stream is around, PulseAudio is
+/// asked again after a while if it goes away
+fn watch(settings: &MicSettings) -> impl Stream<Item = Option<Overlay>> + '_ {
+    let banner_duration = settings
+        .banner
+        .then_some(Duration::from_millis(settings.banner_duration));
+
+    stream! {
+        loop {
+            // Subscribing first so no change gets lost in between
+            let mut events = Box::pin(pulse::events());
+            // The state at startup doesn't get a banner
+            let mut muted = match pulse::source_muted().await {
+                Ok(muted) => muted,
+                Err(e) => {
+                    warn!("Couldn't find out whether the microphone is muted: {}", e);
+                    time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            if let Ok(overlay) = render(settings, muted, false) {
+                yield overlay;
+            }
+
+            let mut banner_until: Option<time::Instant> = None;
+            loop {
+                tokio::select! {
+                    event = events.next() => match event {
+                        // Switching to another microphone is a change of the server
+                        Some(Ok(Event::Source | Event::Server)) => {
+                            if let Some(now) = changed(muted).await {
+                                muted = now;
+                                banner_until =
+                                    banner_duration.map(|duration| time::Instant::now() + duration);
+                                let banner = banner_until.is_some();
+                                if let Ok(overlay) = render(settings, muted, banner) {
+                                    yield overlay;
+                                }
+                            }
+                        },
+                        Some(Ok(_)) => {},
+                        Some(Err(e)) => {
+                            warn!("Lost track of PulseAudio: {}", e);
+                            break;
+                        },
+                        None => break,
+                    },
+                    _ = time::sleep_until(banner_until.unwrap_or_else(time::Instant::now)),
+                        if banner_until.is_some() => {
+                        banner_until = None;
+                        if let Ok(overlay) = render(settings, muted, false) {
+                            yield overlay;
+                        }
+                    }
+                }
+            }
+
+            // The icon can't be trusted while PulseAudio is gone
+            yield None;
+            time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Shows whether the default source of PulseAudio or PipeWire is muted
+struct Mic {
+    /// `None` if the overlay is turned off
+    settings: Option<MicSettings>,
+}
+
+impl OverlayProvider for Mic {
+    type OverlayStream<'a> = impl Stream<Item = Result<Option<Overlay>>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::OverlayStream<'this>> {
+        Ok(try_stream! {
+            if let Some(settings) = &self.settings {
+                let mut overlays = Box::pin(watch(settings));
+                while let Some(overlay) = overlays.next().await {
+                    yield overlay;
+                }
+            }
+        })
+    }
+}
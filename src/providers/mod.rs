@@ -1,9 +1,71 @@
+#[cfg(feature = "ambient")]
+pub(crate) mod ambient;
+#[cfg(feature = "banner")]
+pub(crate) mod banner;
+#[cfg(feature = "chess-clock")]
+pub(crate) mod chess_clock;
+#[cfg(feature = "ci")]
+pub(crate) mod ci;
 pub(crate) mod clock;
+#[cfg(feature = "cmus")]
+pub(crate) mod cmus;
 #[cfg(feature = "crypto")]
 pub(crate) mod coindesk;
+#[cfg(feature = "countdown")]
+pub(crate) mod countdown;
+#[cfg(feature = "sysinfo")]
+pub(crate) mod cpu_graph;
+#[cfg(feature = "dice")]
+pub(crate) mod dice;
+#[cfg(feature = "gsi")]
+pub(crate) mod gsi;
+#[cfg(feature = "habits")]
+pub(crate) mod habits;
 #[cfg(feature = "image")]
 pub(crate) mod image;
-#[cfg(any(feature = "dbus-support", target_os = "windows"))]
+#[cfg(feature = "json")]
+pub(crate) mod json;
+#[cfg(feature = "lastfm")]
+pub(crate) mod lastfm;
+#[cfg(feature = "mail")]
+pub(crate) mod mail;
+#[cfg(feature = "metronome")]
+pub(crate) mod metronome;
+#[cfg(all(feature = "mic", target_os = "linux"))]
+pub(crate) mod mic;
+#[cfg(feature = "mpd")]
+pub(crate) mod mpd;
+#[cfg(feature = "mqtt")]
+pub(crate) mod mqtt;
+#[cfg(any(feature = "dbus-support", target_os = "windows", target_os = "macos"))]
 pub(crate) mod music;
 #[cfg(feature = "sysinfo")]
+pub(crate) mod net_graph;
+#[cfg(feature = "obs")]
+pub(crate) mod obs;
+#[cfg(feature = "ping")]
+pub(crate) mod ping;
+#[cfg(feature = "rss")]
+pub(crate) mod rss;
+#[cfg(feature = "sports")]
+pub(crate) mod sports;
+#[cfg(feature = "spotify")]
+pub(crate) mod spotify;
+#[cfg(feature = "sysinfo")]
 pub(crate) mod sysinfo;
+#[cfg(feature = "twitch")]
+pub(crate) mod twitch;
+#[cfg(all(feature = "typing", any(target_os = "linux", target_os = "windows")))]
+pub(crate) mod typing;
+#[cfg(feature = "ups")]
+pub(crate) mod ups;
+#[cfg(all(feature = "volume", target_os = "linux"))]
+pub(crate) mod volume;
+#[cfg(all(feature = "vu-meter", target_os = "linux"))]
+pub(crate) mod vu_meter;
+#[cfg(feature = "wasm")]
+pub(crate) mod wasm;
+#[cfg(all(feature = "wifi", target_os = "linux"))]
+pub(crate) mod wifi;
+#[cfg(feature = "world-clock")]
+pub(crate) mod world_clock;
@@ -0,0 +1,311 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        player::{MediaPlayerRenderer, IDLE_TEMPLATE},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_music::{Metadata, PlaybackStatus, Progress};
+use async_stream::try_stream;
+use config::Config;
+use futures::Stream;
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    time,
+    time::{Duration, Instant, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// How long to wait before connecting again after MPD went away
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// The settings of the `[mpd]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MpdSettings {
+    host: String,
+    port: u16,
+    /// Empty if MPD doesn't need one
+    password: String,
+}
+
+impl Default for MpdSettings {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 6600,
+            password: String::new(),
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering MPD display source.");
+
+    let settings: MpdSettings = settings::section(config, "mpd")?;
+
+    Ok(Box::new(Mpd { settings }))
+}
+
+/// The current song as `currentsong` describes it
+#[derive(Debug, Clone, Default)]
+struct Song {
+    title: Option<String>,
+    artist: Option<String>,
+    /// Songs without tags are at least known by their file
+    file: String,
+    /// In microseconds
+    length: u64,
+}
+
+impl Metadata for Song {
+    fn title(&self) -> Result<String> {
+        self.title
+            .clone()
+            .or_else(|| self.file.rsplit('/').next().map(String::from))
+            .ok_or_else(|| anyhow!("Couldn't get title!"))
+    }
+
+    fn artists(&self) -> Result<String> {
+        self.artist
+            .clone()
+            .ok_or_else(|| anyhow!("Couldn't get artist!"))
+    }
+
+    fn length(&self) -> Result<u64> {
+        Ok(self.length)
+    }
+}
+
+/// What MPD answered the last time it was asked
+#[derive(Debug, Clone)]
+struct Status {
+    song: Song,
+    status: PlaybackStatus,
+    /// In microseconds
+    elapsed: u64,
+    /// When the answer came in, the position moves on from there while it's
+    /// playing
+    at: Instant,
+}
+
+impl Status {
+    fn progress(&self) -> Progress<Song> {
+        let mut position = self.elapsed;
+        if let PlaybackStatus::Playing = self.status {
+            position += self.at.elapsed().as_micros() as u64;
+        }
+
+        Progress {
+            metadata: self.song.clone(),
+            position: position.min(self.song.length) as i64,
+            status: self.status,
+        }
+    }
+}
+
+/// Seconds with a fraction, e.g. `123.456`, in microseconds
+fn parse_seconds(seconds: &str) -> Option<u64> {
+    seconds
+        .parse::<f64>()
+        .ok()
+        .map(|seconds| (seconds * 1_000_000.0) as u64)
+}
+
+/// A connection to MPD that's logged in
+struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    async fn connect(settings: &MpdSettings) -> Result<Self> {
+        let stream = TcpStream::connect((settings.host.as_str(), settings.port)).await?;
+        let (reader, writer) = stream.into_split();
+        let mut connection = Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        };
+
+        // MPD greets with its version, e.g. `OK MPD 0.23.5`
+        match connection.lines.next_line().await? {
+            Some(greeting) if greeting.starts_with("OK MPD") => {}
+            greeting => return Err(anyhow!("This doesn't look like MPD: {:?}", greeting)),
+        }
+
+        if !settings.password.is_empty() {
+            connection
+                .command(&format!("password \"{}\"", settings.password))
+                .await
+                .map_err(|e| anyhow!("MPD didn't accept the password: {}", e))?;
+        }
+
+        Ok(connection)
+    }
+
+    async fn send(&mut self, command: &str) -> Result<()> {
+        self.writer.write_all(command.as_bytes()).await?;
+        self.writer.write_all(b"\n").await?;
+        Ok(())
+    }
+
+    /// Reads the `key: value` pairs of an answer up to the `OK` at the end
+    async fn response(&mut self) -> Result<HashMap<String, String>> {
+        let mut pairs = HashMap::new();
+        while let Some(line) = self.lines.next_line().await? {
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(error) = line.strip_prefix("ACK ") {
+                return Err(anyhow!("MPD answered with an error: {}", error));
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        Err(anyhow!("MPD closed the connection"))
+    }
+
+    async fn command(&mut self, command: &str) -> Result<HashMap<String, String>> {
+        self.send(command).await?;
+        self.response().await
+    }
+
+    async fn status(&mut self) -> Result<Status> {
+        let status = self.command("status").await?;
+        let song = self.command("currentsong").await?;
+
+        let length = song
+            .get("duration")
+            .or_else(|| status.get("duration"))
+            .and_then(|duration| parse_seconds(duration))
+            .or_else(|| {
+                // Older versions only have the whole seconds
+                song.get("Time")
+                    .and_then(|time| time.parse::<u64>().ok())
+                    .map(|seconds| seconds * 1_000_000)
+            })
+            .unwrap_or(0);
+
+        Ok(Status {
+            song: Song {
+                title: song.get("Title").cloned(),
+                artist: song.get("Artist").cloned(),
+                file: song.get("file").cloned().unwrap_or_default(),
+                length,
+            },
+            status: match status.get("state").map(String::as_str) {
+                Some("play") => PlaybackStatus::Playing,
+                Some("pause") => PlaybackStatus::Paused,
+                _ => PlaybackStatus::Stopped,
+            },
+            elapsed: status
+                .get("elapsed")
+                .and_then(|elapsed| parse_seconds(elapsed))
+                .unwrap_or(0),
+            at: Instant::now(),
+        })
+    }
+}
+
+/// Shows what MPD is playing without going through MPRIS, e.g. if it runs
+/// headless on another machine
+struct Mpd {
+    settings: MpdSettings,
+}
+
+impl ContentProvider for Mpd {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut renderer = MediaPlayerRenderer::new()?;
+        let mut render = time::interval(Duration::from_millis(100));
+        render.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                yield *IDLE_TEMPLATE;
+
+                let mut connection = match Connection::connect(&self.settings).await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        warn!("Couldn't connect to MPD: {}", e);
+                        time::sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                info!("Connected to MPD on {}", self.settings.host);
+
+                let mut current = match connection.status().await {
+                    Ok(status) => status,
+                    Err(e) => {
+                        warn!("Couldn't ask MPD what's playing: {}", e);
+                        time::sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                // MPD only answers `idle` once something changed, the position is moved along
+                // in between
+                let mut idle = false;
+
+                let error = loop {
+                    if !idle {
+                        if let Err(e) = connection.send("idle player").await {
+                            break e;
+                        }
+                        idle = true;
+                    }
+
+                    tokio::select! {
+                        // Reading line by line so nothing gets lost when the timer goes off
+                        line = connection.lines.next_line() => match line {
+                            Ok(Some(line)) if line == "OK" => {
+                                idle = false;
+                                match connection.status().await {
+                                    Ok(status) => current = status,
+                                    Err(e) => break e,
+                                }
+                            },
+                            // The changed subsystems come first, e.g. `changed: player`
+                            Ok(Some(line)) => {
+                                if let Some(error) = line.strip_prefix("ACK ") {
+                                    break anyhow!("MPD answered with an error: {}", error);
+                                }
+                            },
+                            Ok(None) => break anyhow!("MPD closed the connection"),
+                            Err(e) => break e.into(),
+                        },
+                        _ = render.tick() => {
+                            if let Ok(image) = renderer.update(&current.progress()) {
+                                yield image;
+                            }
+                        }
+                    }
+                };
+
+                warn!("Lost the connection to MPD: {}", error);
+                time::sleep(RETRY_DELAY).await;
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "mpd"
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        scheduler::{ContentWrapper, NotificationWrapper},
+        util,
+    },
+    settings::{self, Settings},
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_input::Command;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Baseline, Text},
+    Drawable,
+};
+use futures::{future, Stream};
+use log::{debug, info, warn};
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, Publish, QoS};
+use serde::Deserialize;
+use tokio::{
+    sync::{broadcast, broadcast::error::RecvError},
+    task::JoinHandle,
+    time::{self, Duration},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+/// How many characters of the font fit in one row
+const MAX_CHARS: usize = 21;
+/// How many rows of the font fit on the screen
+const MAX_LINES: usize = 4;
+/// How long to wait before connecting again after the broker went away
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Messages on the control topic that start with this become notifications,
+/// everything else is a command
+const NOTIFY_PREFIX: &str = "notify ";
+
+/// The settings of the `[mqtt]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MqttSettings {
+    /// Where the broker is listening
+    host: String,
+    port: u16,
+    username: Option<String>,
+    password: Option<String>,
+    /// The topics whose latest message is shown, one line each
+    topics: Vec<TopicSettings>,
+    /// Takes the same commands as the control socket, e.g. `next`, and
+    /// `notify <text>`
+    control_topic: Option<String>,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 1883,
+            username: None,
+            password: None,
+            topics: Vec::new(),
+            control_topic: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TopicSettings {
+    topic: String,
+    /// `{}` is replaced with the message
+    #[serde(default = "default_format")]
+    format: String,
+}
+
+fn default_format() -> String {
+    String::from("{}")
+}
+
+impl MqttSettings {
+    /// Every part of apex-tux has a connection of its own, `part` tells them
+    /// apart at the broker
+    fn connect(&self, part: &str) -> (AsyncClient, EventLoop) {
+        let id = format!("apex-tux-{}-{}", part, std::process::id());
+        let mut options = MqttOptions::new(id, &self.host, self.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &self.username {
+            options.set_credentials(username, self.password.as_deref().unwrap_or_default());
+        }
+
+        AsyncClient::new(options, 10)
+    }
+}
+
+/// Waits for the next message on one of `topics`. The subscriptions are
+/// renewed whenever the connection is, the broker forgets them otherwise.
+async fn next_message(client: &AsyncClient, events: &mut EventLoop, topics: &[&str]) -> Publish {
+    loop {
+        match events.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                for topic in topics {
+                    if let Err(e) = client.try_subscribe(*topic, QoS::AtMostOnce) {
+                        warn!("Couldn't subscribe to {}: {}", topic, e);
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(message))) => return message,
+            Ok(_) => {}
+            Err(e) => {
+                debug!("Lost the connection to the MQTT broker: {}", e);
+                time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+fn payload(message: &Publish) -> String {
+    String::from_utf8_lossy(&message.payload).trim().to_string()
+}
+
+/// Passes the commands on the control topic on to the scheduler. The listener
+/// is started again whenever the settings are reloaded.
+pub(crate) fn listen(settings: Settings, sender: broadcast::Sender<Command>) {
+    let mut commands = sender.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            let listener = match settings
+                .load()
+                .and_then(|config| control(&config, sender.clone()))
+            {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Couldn't listen on the MQTT control topic: {}", e);
+                    None
+                }
+            };
+
+            // The other commands are up to the scheduler
+            let reload = loop {
+                match commands.recv().await {
+                    Ok(Command::ReloadConfig) => break true,
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break false,
+                }
+            };
+            if let Some(listener) = listener {
+                listener.abort();
+            }
+            if !reload {
+                break;
+            }
+        }
+    });
+}
+
+/// Starts listening on the control topic, there's nothing to do if `[mqtt]`
+/// is disabled or there's no `control_topic`
+fn control(config: &Config, sender: broadcast::Sender<Command>) -> Result<Option<JoinHandle<()>>> {
+    if !settings::common(config, "mqtt")?.enabled {
+        return Ok(None);
+    }
+    let settings: MqttSettings = settings::section(config, "mqtt")?;
+    let topic = match settings.control_topic.clone() {
+        Some(topic) => topic,
+        None => return Ok(None),
+    };
+
+    let (client, mut events) = settings.connect("control");
+    info!("Listening for commands on the MQTT topic {}", topic);
+
+    let listener = tokio::spawn(async move {
+        loop {
+            let message = payload(&next_message(&client, &mut events, &[&topic]).await);
+            // The notification source takes care of these
+            if message.starts_with(NOTIFY_PREFIX) {
+                continue;
+            }
+
+            match message.parse::<Command>() {
+                Ok(command) => {
+                    debug!("Received {:?} on the MQTT control topic", command);
+                    if sender.send(command).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => warn!("Ignoring `{}` on {}: {}", message, topic, e),
+            }
+        }
+    });
+
+    Ok(Some(listener))
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering MQTT display source.");
+
+    let settings: MqttSettings = settings::section(config, "mqtt")?;
+    if settings.topics.is_empty() {
+        warn!("There are no `topics` in [mqtt], the screen stays empty");
+    }
+    if settings.topics.len() > MAX_LINES {
+        return Err(anyhow!(
+            "Invalid settings in [mqtt]: Only {} `topics` fit on the screen",
+            MAX_LINES
+        ));
+    }
+
+    Ok(Box::new(Mqtt {
+        values: vec![None; settings.topics.len()],
+        settings,
+    }))
+}
+
+/// Shows the latest message of a few topics, e.g. the sensors around the house
+struct Mqtt {
+    settings: MqttSettings,
+    /// The latest message of every topic, `None` until there's one
+    values: Vec<Option<String>>,
+}
+
+impl Mqtt {
+    fn render(&self) -> Result<FrameBuffer> {
+        if self.settings.topics.is_empty() {
+            return util::placeholder("No topics configured");
+        }
+
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        for (row, (topic, value)) in self.settings.topics.iter().zip(&self.values).enumerate() {
+            let line = topic.format.replace("{}", value.as_deref().unwrap_or("-"));
+            let line = line.chars().take(MAX_CHARS).collect::<String>();
+            Text::with_baseline(&line, Point::new(0, row as i32 * 10), style, Baseline::Top)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Mqtt {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let (client, mut events) = self.settings.connect("screen");
+        let topics = self
+            .settings
+            .topics
+            .iter()
+            .map(|topic| topic.topic.clone())
+            .collect::<Vec<_>>();
+
+        Ok(try_stream! {
+            yield self.render()?;
+            // There's nothing to wait for
+            if topics.is_empty() {
+                future::pending::<()>().await;
+            }
+
+            let topics = topics.iter().map(String::as_str).collect::<Vec<_>>();
+            loop {
+                let message = next_message(&client, &mut events, &topics).await;
+                // The same topic may be shown in several ways
+                for (index, topic) in topics.iter().enumerate() {
+                    if *topic == message.topic {
+                        self.values[index] = Some(payload(&message));
+                    }
+                }
+
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "mqtt"
+    }
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering MQTT notification source.");
+
+    let settings: MqttSettings = settings::section(config, "mqtt")?;
+
+    Ok(Box::new(MqttNotifier { settings }))
+}
+
+/// Shows `notify <text>` on the control topic as a notification
+struct MqttNotifier {
+    settings: MqttSettings,
+}
+
+impl NotificationProvider for MqttNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        let connection = self
+            .settings
+            .control_topic
+            .clone()
+            .map(|topic| (topic, self.settings.connect("notifications")));
+
+        Ok(try_stream! {
+            if let Some((topic, (client, mut events))) = connection {
+                loop {
+                    let message = payload(&next_message(&client, &mut events, &[&topic]).await);
+                    let text = match message.strip_prefix(NOTIFY_PREFIX) {
+                        Some(text) => text.trim(),
+                        None => continue,
+                    };
+
+                    if let Ok(notification) = NotificationBuilder::new()
+                        .with_title(text)
+                        .with_content("MQTT")
+                        .build()
+                    {
+                        yield notification;
+                    }
+                }
+            }
+        })
+    }
+}
@@ -1,221 +1,248 @@
-use crate::render::display::ContentProvider;
-#[cfg(not(target_os = "windows"))]
-use anyhow::anyhow;
-use anyhow::Result;
+use crate::render::display::{ContentProvider, ProviderInput};
+use anyhow::{anyhow, Result};
 use async_stream::try_stream;
-#[cfg(not(target_os = "windows"))]
-use embedded_graphics::prelude::Primitive;
-#[cfg(not(target_os = "windows"))]
-use embedded_graphics::primitives::{Line, PrimitiveStyle};
-use embedded_graphics::{
-    geometry::Size, image::Image, pixelcolor::BinaryColor, prelude::Point, Drawable,
-};
 use futures_core::stream::Stream;
-use linkme::distributed_slice;
 
+#[cfg(target_os = "linux")]
+use crate::render::player::{Controls, Repeat};
 use log::info;
-use tinybmp::Bmp;
-use tokio::time;
+#[cfg(target_os = "linux")]
+use log::warn;
+use tokio::{
+    sync::{mpsc, watch},
+    time,
+};
 
-use crate::render::{
-    scheduler::{ContentWrapper, CONTENT_PROVIDERS},
-    text::{ScrollableBuilder, StatefulScrollable},
+use crate::{
+    render::{
+        lyrics::{self, Lyrics, LyricsSettings},
+        player::{Layout, MediaPlayerRenderer, IDLE_TEMPLATE},
+        scheduler::ContentWrapper,
+    },
+    settings,
 };
-use apex_music::{AsyncPlayer, Metadata, Progress};
+use apex_input::Command;
+use apex_music::{AsyncPlayer, Metadata, PlaybackStatus, PlayerEvent, Progress};
 use config::Config;
-use embedded_graphics::{
-    mono_font::{iso_8859_15, MonoTextStyle},
-    text::{Baseline, Text},
-};
 use futures::StreamExt;
-use std::{convert::TryInto, sync::Arc};
-use tokio::time::{Duration, MissedTickBehavior};
+use serde::Deserialize;
+use std::{path::PathBuf, sync::Arc};
+use tokio::time::{Duration, Instant, MissedTickBehavior};
 
 use apex_hardware::FrameBuffer;
-use apex_music::PlaybackStatus;
 use futures::pin_mut;
-use lazy_static::lazy_static;
 
-static NOTE_ICON: &[u8] = include_bytes!("./../../assets/note.bmp");
-static PAUSE_ICON: &[u8] = include_bytes!("./../../assets/pause.bmp");
+const RECONNECT_DELAY: u64 = 5;
 
-lazy_static! {
-    static ref PAUSE_BMP: Bmp<'static, BinaryColor> =
-        Bmp::<BinaryColor>::from_slice(PAUSE_ICON).expect("Failed to parse BMP for pause icon!");
+/// How often the progress is asked for even if the player didn't say anything
+/// changed, not every player sends `Seeked` when it should
+const RESYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the name of the player is shown after switching to it
+const ANNOUNCE_DURATION: Duration = Duration::from_millis(1500);
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MusicSettings {
+    /// Players like web browsers may steal the focus of the actual music player
+    preferred_player: Option<String>,
+    /// A folder with lyrics named like `Artist - Title.lrc`
+    lyrics_directory: Option<PathBuf>,
+    /// Whether to look for lyrics online that aren't found locally
+    online_lyrics: bool,
+    layout: Layout,
 }
 
-lazy_static! {
-    static ref NOTE_BMP: Bmp<'static, BinaryColor> =
-        Bmp::<BinaryColor>::from_slice(NOTE_ICON).expect("Failed to parse BMP for note icon!");
-}
-#[cfg(target_os = "windows")]
-lazy_static! {
-// Windows doesn't expose the current progress within the song so we don't draw
-// it here TODO: Spice this up?
-static ref PLAYER_TEMPLATE: FrameBuffer = FrameBuffer::new();
-}
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering MPRIS2 display source.");
 
-#[cfg(not(target_os = "windows"))]
-lazy_static! {
-static ref PLAYER_TEMPLATE: FrameBuffer = {
-    let mut base = FrameBuffer::new();
-    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
-
-    let points = vec![
-        (Point::new(0, 39), Point::new(127, 39)),
-        (Point::new(0, 39), Point::new(0, 39 - 5)),
-        (Point::new(127, 39), Point::new(127, 39 - 5)),
-    ];
-
-    // Draw a border for the progress bar
-    points
-        .iter()
-        .try_for_each(|(first, second)| {
-            Line::new(*first, *second)
-                .into_styled(style)
-                .draw(&mut base)
+    let settings: MusicSettings = settings::section(config, "mpris2")?;
+    let player = MediaPlayerBuilder::new()
+        .with_lyrics(LyricsSettings {
+            directory: settings.lyrics_directory,
+            online: settings.online_lyrics,
         })
-        .expect("Failed to prepare template image for music player!");
-
-    base
-};
-}
-lazy_static! {
-    static ref PLAY_TEMPLATE: FrameBuffer = {
-        let mut base = *PLAYER_TEMPLATE;
-        Image::new(&*NOTE_BMP, Point::new(5, 5))
-            .draw(&mut base)
-            .expect("Failed to prepare 'play' template for music player");
-        base
-    };
-}
-lazy_static! {
-    static ref PAUSE_TEMPLATE: FrameBuffer = {
-        let mut base = *PLAYER_TEMPLATE;
-        Image::new(&*PAUSE_BMP, Point::new(5, 5))
-            .draw(&mut base)
-            .expect("Failed to prepare 'pause' template for music player");
-        base
-    };
-}
-lazy_static! {
-    static ref IDLE_TEMPLATE: FrameBuffer = {
-        let mut base = *PAUSE_TEMPLATE;
-        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
-        Text::with_baseline(
-            "No player found",
-            Point::new(5 + 3 + 24, 3),
-            style,
-            Baseline::Top,
-        )
-        .draw(&mut base)
-        .expect("Failed to prepare 'idle' template for music player");
-        base
+        .with_layout(settings.layout);
+    let player = match settings.preferred_player {
+        Some(name) => player.with_player_name(name),
+        None => player,
     };
-}
-
-static UNKNOWN_TITLE: &str = "Unknown title";
-static UNKNOWN_ARTIST: &str = "Unknown artist";
-
-const RECONNECT_DELAY: u64 = 5;
 
-#[distributed_slice(CONTENT_PROVIDERS)]
-static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
-
-#[allow(clippy::unnecessary_wraps)]
-fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
-    info!("Registering MPRIS2 display source.");
-
-    let player = match config.get_str("mpris2.preferred_player") {
-        Ok(name) => MediaPlayerBuilder::new().with_player_name(name),
-        Err(_) => MediaPlayerBuilder::new(),
+    #[cfg(target_os = "linux")]
+    let player = {
+        let (tx, rx) = watch::channel(true);
+        let name = player.name.clone();
+        tokio::spawn(async move {
+            // Losing DBus for a moment shouldn't hide the screen for good
+            while let Err(e) = watch_players(name.clone(), &tx).await {
+                warn!("Failed to check for music players: {}", e);
+                // The screen isn't skipped while nobody knows whether there's a player
+                if tx.send(true).is_err() {
+                    break;
+                }
+                time::sleep(Duration::from_secs(RECONNECT_DELAY)).await;
+            }
+        });
+        player.with_activity(rx)
     };
 
     Ok(Box::new(player))
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct MediaPlayerBuilder {
-    /// If a preference for the player is wanted specify this field
-    name: Option<Arc<String>>,
+/// Keeps checking whether there's a player around so the rotation can skip
+/// the music screen while there's nothing to show
+#[cfg(target_os = "linux")]
+async fn watch_players(name: Option<Arc<String>>, activity: &watch::Sender<bool>) -> Result<()> {
+    let mpris = apex_mpris2::MPRIS2::new().await?;
+    let name = name.as_deref().map(String::as_str);
+
+    // Sending fails once the provider is gone, e.g. after a reload
+    mpris
+        .watch_players(name, |found| activity.send(found).is_ok())
+        .await
 }
 
-// Ok so the plan for the MPRIS2 module is to wait for two DBUS events
-// - PropertiesChanged to see if the song changed
-// - Seeked to see if the progress was changed manually
-// There's an existing mpris2 crate but it doesn't support async operation which
-// is kind of painful to use in this architecture.
-// When we received these events they should be mapped and put into another
-// queue. Upon receiving the event our code should pull the metadata from the
-// player.
-
-#[derive(Debug, Clone)]
-pub struct MediaPlayerRenderer {
-    artist: StatefulScrollable,
-    title: StatefulScrollable,
+/// Shortens the bus name of a player for the display, e.g.
+/// `org.mpris.MediaPlayer2.firefox.instance_1_84` is `firefox`
+#[cfg(target_os = "linux")]
+fn short_name(bus_name: &str) -> String {
+    let name = bus_name
+        .strip_prefix("org.mpris.MediaPlayer2.")
+        .unwrap_or(bus_name);
+    name.split('.').next().unwrap_or(name).to_string()
 }
 
-impl MediaPlayerRenderer {
-    fn new() -> Result<Self> {
-        let artist = ScrollableBuilder::new()
-            .with_text(UNKNOWN_ARTIST)
-            .with_custom_spacing(10)
-            .with_position(Point::new(5 + 3 + 24, 3 + 10))
-            .with_projection(Size::new(16 * 6, 10));
-        let title = ScrollableBuilder::new()
-            .with_text(UNKNOWN_TITLE)
-            .with_custom_spacing(10)
-            .with_position(Point::new(5 + 3 + 24, 3))
-            .with_projection(Size::new(16 * 6, 10));
-
-        Ok(Self {
-            artist: artist.try_into()?,
-            title: title.try_into()?,
-        })
+/// Picks which of `players` to show instead of the `current` one, if any.
+/// Cycling goes on to the next one, otherwise a player that's playing takes
+/// over from one that isn't unless the current one was picked by hand.
+#[cfg(target_os = "linux")]
+fn pick_player(
+    players: &[(String, PlaybackStatus)],
+    current: &str,
+    pinned: bool,
+    cycle: bool,
+) -> Option<usize> {
+    let index = players.iter().position(|(name, _)| name == current);
+    if cycle {
+        return (!players.is_empty()).then(|| index.map_or(0, |index| (index + 1) % players.len()));
     }
 
-    pub fn update<T: Metadata>(&mut self, progress: &Progress<T>) -> Result<FrameBuffer> {
-        let mut display = match progress.status {
-            PlaybackStatus::Playing => *PLAY_TEMPLATE,
-            PlaybackStatus::Paused | PlaybackStatus::Stopped => *PAUSE_TEMPLATE,
-        };
-
-        let metadata = &progress.metadata;
+    let playing = |index: usize| matches!(players[index].1, PlaybackStatus::Playing);
+    match index {
+        Some(_) if pinned => None,
+        Some(index) if playing(index) => None,
+        // The players that are playing come first
+        Some(_) => playing(0).then_some(0),
+        // The player is gone or stopped, e.g. a browser tab that was closed
+        None => (!players.is_empty()).then_some(0),
+    }
+}
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            let length = metadata.length().unwrap_or(0) as f64;
+/// Asks the player for shuffle, repeat and its volume, this is `None` if it
+/// doesn't have any of them
+#[cfg(target_os = "linux")]
+async fn controls(player: &apex_mpris2::Player<'_>) -> Option<Controls> {
+    let shuffle = player.shuffle().await.ok();
+    let repeat = player
+        .loop_status()
+        .await
+        .ok()
+        .map(|status| match status.as_str() {
+            "Track" => Repeat::Track,
+            "Playlist" => Repeat::Playlist,
+            _ => Repeat::Off,
+        });
+    let volume = player.volume().await.ok();
+
+    if shuffle.is_none() && repeat.is_none() && volume.is_none() {
+        return None;
+    }
 
-            let current = progress.position as f64;
+    Some(Controls {
+        shuffle: shuffle.unwrap_or(false),
+        repeat: repeat.unwrap_or_default(),
+        volume,
+    })
+}
 
-            let completion = (current / length).clamp(0_f64, 1_f64);
+/// `Select` shows the lyrics, the music screen also cycles through the
+/// players
+struct MusicInput(mpsc::UnboundedSender<Command>);
 
-            let pixels = (128_f64 - 2_f64 * 3_f64) * completion;
-            let style = PrimitiveStyle::with_stroke(BinaryColor::On, 3);
-            Line::new(Point::new(3, 35), Point::new(pixels as i32 + 3, 35))
-                .into_styled(style)
-                .draw(&mut display)?;
+impl ProviderInput for MusicInput {
+    fn handle_input(&mut self, command: Command) -> bool {
+        match command {
+            Command::Select | Command::NextPlayer => self.0.send(command).is_ok(),
+            _ => false,
         }
+    }
+}
 
-        let artists = metadata.artists()?;
-        let title = metadata.title()?;
+/// The progress the player reported last along with how fast it moves on, so
+/// the bar advances every frame without asking the player every time
+struct Tracked<T: Metadata> {
+    progress: Progress<T>,
+    /// The position that was reported, in microseconds
+    position: i64,
+    /// 1 is the normal speed, e.g. podcasts may be played faster
+    rate: f64,
+    /// When the position was reported
+    at: Instant,
+}
 
-        if let Ok(false) = self.artist.update(&artists) {
-            if artists.len() > 16 {
-                self.artist.text.scroll();
-            }
+impl<T: Metadata> Tracked<T> {
+    fn new(progress: Progress<T>, rate: f64) -> Self {
+        Self {
+            position: progress.position,
+            progress,
+            rate,
+            at: Instant::now(),
         }
+    }
 
-        if let Ok(false) = self.title.update(&title) {
-            if title.len() > 16 {
-                self.title.text.scroll();
-            }
+    /// The player jumped to `position`, the rest stays the same
+    fn seek(&mut self, position: i64) {
+        self.position = position;
+        self.progress.position = position;
+        self.at = Instant::now();
+    }
+
+    /// Moves the position along to where it should be by now
+    fn advance(&mut self) -> &Progress<T> {
+        if let PlaybackStatus::Playing = self.progress.status {
+            let elapsed = self.at.elapsed().as_micros() as f64 * self.rate;
+            let position = self.position + elapsed as i64;
+            self.progress.position = match self.progress.metadata.length() {
+                Ok(length) if length > 0 => position.min(length as i64),
+                _ => position,
+            };
         }
 
-        self.title.text.draw(&mut display)?;
-        self.artist.text.draw(&mut display)?;
+        &self.progress
+    }
+}
 
-        Ok(display)
+#[derive(Debug)]
+pub struct MediaPlayerBuilder {
+    /// If a preference for the player is wanted specify this field
+    name: Option<Arc<String>>,
+    /// Whether there's a player to show
+    activity: Option<watch::Receiver<bool>>,
+    /// Where to look for the lyrics, `Select` shows them in place of the
+    /// artists
+    lyrics: LyricsSettings,
+    layout: Layout,
+    /// Gets `Select` and `NextPlayer`
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+impl Default for MediaPlayerBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -225,8 +252,32 @@ impl MediaPlayerBuilder {
         self
     }
 
+    #[cfg(target_os = "linux")]
+    fn with_activity(mut self, activity: watch::Receiver<bool>) -> Self {
+        self.activity = Some(activity);
+        self
+    }
+
+    fn with_lyrics(mut self, lyrics: LyricsSettings) -> Self {
+        self.lyrics = lyrics;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     pub fn new() -> Self {
-        Self::default()
+        let (input, commands) = mpsc::unbounded_channel();
+        Self {
+            name: None,
+            activity: None,
+            lyrics: LyricsSettings::default(),
+            layout: Layout::default(),
+            input,
+            commands: Some(commands),
+        }
     }
 }
 
@@ -241,11 +292,21 @@ impl ContentProvider for MediaPlayerBuilder {
             self.name
         );
 
-        let mut renderer = MediaPlayerRenderer::new()?;
+        let mut renderer = MediaPlayerRenderer::new()?.with_layout(self.layout)?;
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The music stream can only be started once!"))?;
+        let mut show_lyrics = false;
+        // The lyrics of the track they were looked up for, `None` inside if there
+        // weren't any
+        let mut current_lyrics: Option<(String, Option<Lyrics>)> = None;
 
         Ok(try_stream! {
             #[cfg(target_os = "windows")]
-            let mpris = apex_windows::Player::new()?;
+            let mpris = apex_windows::Player::new(self.name.as_deref().map(String::as_str))?;
+            #[cfg(target_os = "macos")]
+            let mpris = apex_macos::Player::new(self.name.as_deref().map(String::as_str))?;
             #[cfg(target_os = "linux")]
             let mpris = apex_mpris2::MPRIS2::new().await?;
             pin_mut!(mpris);
@@ -258,10 +319,15 @@ impl ContentProvider for MediaPlayerBuilder {
                     self.name
                 );
                 yield *IDLE_TEMPLATE;
-                #[cfg(target_os = "windows")]
+                #[cfg(any(target_os = "windows", target_os = "macos"))]
                 let player = &mpris;
                 #[cfg(target_os = "linux")]
-                let player = mpris.wait_for_player(self.name.clone()).await?;
+                let mut player = mpris.wait_for_player(self.name.clone()).await?;
+                // Whether the player was picked with `NextPlayer`, it stays until it goes away
+                #[cfg(target_os = "linux")]
+                let mut pinned = false;
+                // The name of the player that was switched to along with until when it's shown
+                let mut announcement: Option<(String, Instant)> = None;
 
                 info!("Connected to music player: {:?}", player.name().await);
 
@@ -269,15 +335,119 @@ impl ContentProvider for MediaPlayerBuilder {
                 let tracker = mpris.stream().await?;
                 pin_mut!(tracker);
 
-                while let Some(_) = tracker.next().await {
-                    // TODO: We could probably save *some* resources here by making use of the event
-                    // that's being called but I don't see enough of a reason to do so at the moment
-                    if let Ok(progress) = player.progress().await {
-                        if let Ok(image) = renderer.update(&progress) {
-                            yield image;
+                // The progress is only asked for again when the player says something changed,
+                // it's moved along in between
+                let mut tracked = None;
+                let mut synced = Instant::now();
+
+                loop {
+                    let mut cycle = false;
+                    let event = tokio::select! {
+                        event = tracker.next() => match event {
+                            Some(event) => Some(event),
+                            None => break,
+                        },
+                        Some(command) = commands.recv() => {
+                            match command {
+                                Command::Select => show_lyrics = !show_lyrics,
+                                Command::NextPlayer => cycle = true,
+                                _ => {}
+                            }
+                            None
+                        }
+                    };
+
+                    let mut outdated = tracked.is_none()
+                        || matches!(event, Some(PlayerEvent::Properties))
+                        || synced.elapsed() > RESYNC_INTERVAL;
+
+                    // Another player may have started playing in the meantime
+                    #[cfg(target_os = "linux")]
+                    if outdated || cycle {
+                        let mut players = mpris
+                            .active_players(self.name.as_deref().map(String::as_str))
+                            .await?;
+                        let names = players
+                            .iter()
+                            .map(|(player, status)| (player.bus_name(), *status))
+                            .collect::<Vec<_>>();
+
+                        let current = player.bus_name();
+                        if let Some(index) = pick_player(&names, &current, pinned, cycle) {
+                            player = players.swap_remove(index).0;
+                            pinned = cycle;
+                            outdated = true;
+
+                            let name = short_name(&player.bus_name());
+                            info!("Switched to music player: {}", name);
+                            announcement = Some((name, Instant::now() + ANNOUNCE_DURATION));
+                        }
+                    }
+                    // Windows and macOS decide which app is shown, cycling only tells which one
+                    // it is
+                    #[cfg(any(target_os = "windows", target_os = "macos"))]
+                    if cycle {
+                        let name = player.name().await;
+                        announcement = Some((name, Instant::now() + ANNOUNCE_DURATION));
+                    }
+
+                    if outdated {
+                        let progress = match player.progress().await {
+                            Ok(progress) => progress,
+                            Err(_) => continue 'outer,
+                        };
+                        let rate = player.rate().await.unwrap_or(1.0);
+
+                        #[cfg(target_os = "linux")]
+                        renderer.set_controls(controls(&player).await);
+
+                        tracked = Some(Tracked::new(progress, rate));
+                        synced = Instant::now();
+                    } else if let Some(PlayerEvent::Seeked) = event {
+                        // Only the position changed
+                        match (player.position().await, tracked.as_mut()) {
+                            (Ok(position), Some(tracked)) => tracked.seek(position),
+                            (Ok(_), None) => {},
+                            (Err(_), _) => continue 'outer,
                         }
+                    }
+
+                    let progress = match tracked.as_mut() {
+                        Some(tracked) => tracked.advance(),
+                        None => continue,
+                    };
+
+                    let announced = announcement
+                        .as_ref()
+                        .filter(|(_, until)| Instant::now() < *until)
+                        .map(|(name, _)| name.as_str());
+                    let lyric = if announced.is_some() {
+                        announced
+                    } else if show_lyrics {
+                        // Tracks are told apart by their title and artists, not every player
+                        // has a URL for them
+                        let metadata = &progress.metadata;
+                        let key = format!(
+                            "{}\n{}",
+                            metadata.title().unwrap_or_default(),
+                            metadata.artists().unwrap_or_default()
+                        );
+                        if current_lyrics.as_ref().map(|(track, _)| track) != Some(&key) {
+                            let found = lyrics::find(metadata, &self.lyrics).await;
+                            current_lyrics = Some((key, found));
+                        }
+
+                        let position = progress.position.max(0) as u64;
+                        Some(match current_lyrics.as_ref().and_then(|(_, lyrics)| lyrics.as_ref()) {
+                            Some(lyrics) => lyrics.line(position).unwrap_or_default(),
+                            None => "No lyrics found",
+                        })
                     } else {
-                        continue 'outer;
+                        None
+                    };
+
+                    if let Ok(image) = renderer.update_with_lyric(progress, lyric) {
+                        yield image;
                     }
                 }
             }
@@ -287,4 +457,12 @@ impl ContentProvider for MediaPlayerBuilder {
     fn name(&self) -> &'static str {
         "mpris2"
     }
+
+    fn activity(&self) -> Option<watch::Receiver<bool>> {
+        self.activity.clone()
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(MusicInput(self.input.clone())))
+    }
 }
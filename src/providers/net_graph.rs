@@ -0,0 +1,236 @@
+use crate::{
+    providers::sysinfo::DEFAULT_NAMES,
+    render::{
+        accessibility,
+        display::ContentProvider,
+        graph::{Graph, GraphStyle},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use serde::Deserialize;
+use sysinfo::{NetworkExt, NetworksExt, System, SystemExt};
+use tokio::{
+    time,
+    time::{Duration, Instant, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// The graph is drawn inside of a border
+const COLUMNS: u64 = 128 - 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NetGraphSettings {
+    /// How far back the graph goes
+    minutes: u64,
+    /// Defaults to the same interface as the sysinfo screen
+    net_interface_name: String,
+}
+
+impl Default for NetGraphSettings {
+    fn default() -> Self {
+        let (net_interface_name, _) = DEFAULT_NAMES;
+
+        Self {
+            minutes: 5,
+            net_interface_name: String::from(net_interface_name),
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering network graph display source.");
+
+    let settings: NetGraphSettings = settings::section(config, "net_graph")?;
+
+    // Every column is the average rate of its time span
+    let period = Duration::from_millis(settings.minutes.max(1) * 60 * 1000 / COLUMNS);
+
+    Ok(Box::new(NetGraph {
+        sys: System::new(),
+        received: Graph::new(COLUMNS as usize, GraphStyle::Line),
+        transmitted: Graph::new(COLUMNS as usize, GraphStyle::Line),
+        rates: (0.0, 0.0),
+        last_refresh: Instant::now(),
+        period: period.max(Duration::from_millis(500)),
+        minutes: settings.minutes.max(1),
+        net_interface_name: settings.net_interface_name,
+    }))
+}
+
+/// Formats bytes per second like `12.3M`, there's only room for a few
+/// characters
+fn format_rate(rate: f64) -> String {
+    let units = ["B", "k", "M", "G"];
+    let mut rate = rate;
+    let mut unit = 0;
+    while rate >= 1024.0 && unit < units.len() - 1 {
+        rate /= 1024.0;
+        unit += 1;
+    }
+
+    if rate < 10.0 && unit > 0 {
+        format!("{:.1}{}", rate, units[unit])
+    } else {
+        format!("{:.0}{}", rate, units[unit])
+    }
+}
+
+struct NetGraph {
+    sys: System,
+    received: Graph,
+    transmitted: Graph,
+    /// The latest received and transmitted bytes per second
+    rates: (f64, f64),
+    last_refresh: Instant,
+    /// How long every column of the graph covers
+    period: Duration,
+    minutes: u64,
+    net_interface_name: String,
+}
+
+impl NetGraph {
+    /// Loads the interfaces, this is only done once the provider is shown
+    fn setup(&mut self) {
+        self.sys.refresh_networks_list();
+        self.last_refresh = Instant::now();
+
+        if !self
+            .sys
+            .networks()
+            .iter()
+            .any(|(name, _)| *name == self.net_interface_name)
+        {
+            warn!(
+                "Couldn't find network interface `{}`",
+                self.net_interface_name
+            );
+            info!("Instead, found those interfaces:");
+            for (interface_name, _) in self.sys.networks() {
+                info!("\t{}", interface_name);
+            }
+        }
+    }
+
+    /// sysinfo counts the bytes since the last refresh, they're turned into
+    /// rates using the time that actually passed
+    fn refresh(&mut self) {
+        self.sys.refresh_networks();
+        let elapsed = self.last_refresh.elapsed().as_secs_f64().max(0.001);
+        self.last_refresh = Instant::now();
+
+        self.rates = self
+            .sys
+            .networks()
+            .iter()
+            .find(|(name, _)| **name == self.net_interface_name)
+            .map(|(_, data)| {
+                (
+                    data.received() as f64 / elapsed,
+                    data.transmitted() as f64 / elapsed,
+                )
+            })
+            .unwrap_or_default();
+
+        let (received, transmitted) = self.rates;
+        self.received.push(received as f32);
+        self.transmitted.push(transmitted as f32);
+    }
+
+    pub fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let font = accessibility::font(&iso_8859_15::FONT_4X6);
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        // The graph starts below the line with the current rates
+        let graph_top = font.character_size.height as i32 + 2;
+
+        let (received, transmitted) = self.rates;
+        Text::with_baseline(
+            &format!(
+                "RX {:>5} TX {:>5}",
+                format_rate(received),
+                format_rate(transmitted)
+            ),
+            Point::zero(),
+            style,
+            Baseline::Top,
+        )
+        .draw(&mut buffer)?;
+
+        // Both graphs share the scale so they can be compared, the top of the
+        // graph is the largest rate that's still visible
+        let max = self.received.max().max(self.transmitted.max());
+        // There's no room for the scale next to the rates in large text mode
+        if !accessibility::large_text() {
+            let right = TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Top)
+                .build();
+            Text::with_text_style(
+                &format!("{}/{} min", format_rate(max as f64), self.minutes),
+                Point::new(127, 0),
+                style,
+                right,
+            )
+            .draw(&mut buffer)?;
+        }
+
+        let area = Rectangle::new(
+            Point::new(0, graph_top),
+            Size::new(128, (40 - graph_top) as u32),
+        );
+        area.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut buffer)?;
+        self.received.draw(&mut buffer, area.offset(-1), max)?;
+        self.transmitted.draw(&mut buffer, area.offset(-1), max)?;
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for NetGraph {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(self.period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            self.setup();
+            yield self.render()?;
+            interval.reset();
+
+            loop {
+                interval.tick().await;
+                self.refresh();
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "net_graph"
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper},
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Circle, Primitive, PrimitiveStyle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{SinkExt, Stream, StreamExt};
+use log::{debug, info};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::{
+    net::TcpStream,
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// How many seconds to wait before trying to reach OBS again
+const RETRY_TICKS: u32 = 5;
+
+/// The op codes of obs-websocket 5
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The settings of the `[obs]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ObsSettings {
+    host: String,
+    port: u16,
+    /// Empty if authentication is turned off in OBS
+    password: String,
+}
+
+impl Default for ObsSettings {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 4455,
+            password: String::new(),
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering OBS display source.");
+
+    let settings: ObsSettings = settings::section(config, "obs")?;
+
+    Ok(Box::new(Obs {
+        settings,
+        socket: None,
+        status: None,
+    }))
+}
+
+/// What `GetRecordStatus` and `GetStreamStatus` answer, the frames are only
+/// part of the stream status
+#[derive(Debug, Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputStatus {
+    output_active: bool,
+    #[serde(default)]
+    output_paused: bool,
+    /// In milliseconds
+    output_duration: u64,
+    #[serde(default)]
+    output_skipped_frames: u64,
+    #[serde(default)]
+    output_total_frames: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Status {
+    record: OutputStatus,
+    stream: OutputStatus,
+}
+
+/// The answer to the challenge of OBS, see the protocol of obs-websocket
+fn authenticate(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+async fn send(socket: &mut Socket, message: &Value) -> Result<()> {
+    socket.send(Message::Text(message.to_string())).await?;
+    Ok(())
+}
+
+/// Waits for the next message with the op code `op`, the events OBS sends in
+/// between are skipped
+async fn receive(socket: &mut Socket, op: u64) -> Result<Value> {
+    while let Some(message) = socket.next().await {
+        let mut message: Value = match message? {
+            Message::Text(text) => serde_json::from_str(&text)?,
+            Message::Close(frame) => {
+                let reason = frame.map(|frame| frame.reason.to_string());
+                return Err(anyhow!("OBS closed the connection: {:?}", reason));
+            }
+            _ => continue,
+        };
+
+        if message["op"].as_u64() == Some(op) {
+            return Ok(message["d"].take());
+        }
+    }
+
+    Err(anyhow!("OBS closed the connection"))
+}
+
+async fn connect(settings: &ObsSettings) -> Result<Socket> {
+    let url = format!("ws://{}:{}", settings.host, settings.port);
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+
+    let hello = receive(&mut socket, OP_HELLO).await?;
+    // OBS only sends a challenge if there's a password
+    let authentication = hello.get("authentication").map(|auth| {
+        authenticate(
+            &settings.password,
+            auth["salt"].as_str().unwrap_or_default(),
+            auth["challenge"].as_str().unwrap_or_default(),
+        )
+    });
+
+    let mut identify = json!({ "rpcVersion": 1, "eventSubscriptions": 0 });
+    if let Some(authentication) = authentication {
+        identify["authentication"] = json!(authentication);
+    }
+    send(&mut socket, &json!({ "op": OP_IDENTIFY, "d": identify })).await?;
+    receive(&mut socket, OP_IDENTIFIED)
+        .await
+        .map_err(|e| anyhow!("OBS didn't accept the password: {}", e))?;
+
+    Ok(socket)
+}
+
+async fn request(socket: &mut Socket, request: &str) -> Result<OutputStatus> {
+    send(
+        socket,
+        &json!({
+            "op": OP_REQUEST,
+            "d": { "requestType": request, "requestId": request },
+        }),
+    )
+    .await?;
+
+    // Only one request is sent at a time so the next response is the one we're
+    // waiting for
+    let mut response = receive(socket, OP_REQUEST_RESPONSE).await?;
+    if response["requestStatus"]["result"].as_bool() != Some(true) {
+        return Err(anyhow!("OBS couldn't answer {}: {}", request, response));
+    }
+
+    Ok(serde_json::from_value(response["responseData"].take())?)
+}
+
+/// Formats milliseconds as `H:MM:SS`
+fn format_duration(milliseconds: u64) -> String {
+    let seconds = milliseconds / 1000;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Shows whether OBS is recording or streaming and for how long
+struct Obs {
+    settings: ObsSettings,
+    /// `None` while OBS can't be reached
+    socket: Option<Socket>,
+    status: Option<Status>,
+}
+
+impl Obs {
+    /// Asks OBS about the outputs, the connection is only kept if that works
+    /// out
+    async fn poll(&mut self) -> Result<Status> {
+        let mut socket = match self.socket.take() {
+            Some(socket) => socket,
+            None => connect(&self.settings).await?,
+        };
+
+        let status = Status {
+            record: request(&mut socket, "GetRecordStatus").await?,
+            stream: request(&mut socket, "GetStreamStatus").await?,
+        };
+        self.socket = Some(socket);
+
+        Ok(status)
+    }
+
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let large = MonoTextStyle::new(&iso_8859_15::FONT_8X13_BOLD, BinaryColor::On);
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        let status = match &self.status {
+            Some(status) => status,
+            None => {
+                let centered = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                Text::with_text_style("OBS isn't running", Point::new(64, 20), small, centered)
+                    .draw(&mut buffer)?;
+                return Ok(buffer);
+            }
+        };
+
+        let right = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+
+        let rows = [("REC", status.record, 1), ("LIVE", status.stream, 15)];
+        for (label, output, y) in rows {
+            // A filled dot while the output is active, an empty one otherwise
+            let dot = Circle::new(Point::new(0, y + 3), 7);
+            if output.output_active && !output.output_paused {
+                dot.into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(&mut buffer)?;
+            } else {
+                dot.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                    .draw(&mut buffer)?;
+            }
+            Text::with_baseline(label, Point::new(10, y), large, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            let time = match (output.output_active, output.output_paused) {
+                (false, _) => String::from("off"),
+                (true, true) => String::from("paused"),
+                (true, false) => format_duration(output.output_duration),
+            };
+            Text::with_text_style(&time, Point::new(127, y), large, right).draw(&mut buffer)?;
+        }
+
+        if status.stream.output_active {
+            let dropped = if status.stream.output_total_frames == 0 {
+                0.0
+            } else {
+                status.stream.output_skipped_frames as f64
+                    / status.stream.output_total_frames as f64
+                    * 100.0
+            };
+            Text::with_text_style(
+                &format!("{:.1}% dropped", dropped),
+                Point::new(127, 30),
+                small,
+                right,
+            )
+            .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Obs {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            // Counts down the seconds until OBS is tried again
+            let mut retry = 0;
+
+            loop {
+                interval.tick().await;
+
+                if self.socket.is_some() || retry == 0 {
+                    self.status = match self.poll().await {
+                        Ok(status) => Some(status),
+                        Err(e) => {
+                            debug!("Couldn't get the status of OBS: {}", e);
+                            retry = RETRY_TICKS;
+                            None
+                        }
+                    };
+                } else {
+                    retry -= 1;
+                }
+
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "obs"
+    }
+}
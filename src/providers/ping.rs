@@ -0,0 +1,243 @@
+use crate::{
+    render::{
+        accessibility::{self, LARGE_TEXT_ROWS},
+        display::ContentProvider,
+        graph::{Graph, GraphStyle},
+        scheduler::ContentWrapper,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::{BlendMode, FrameBuffer};
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+use futures::{future, Stream};
+use log::{debug, info};
+use serde::Deserialize;
+use std::process;
+use tokio::{
+    task, time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// At most this many hosts fit on the screen, every one of them gets a row
+const MAX_HOSTS: usize = 3;
+/// The graph takes the right half of every row
+const GRAPH_LEFT: i32 = 64;
+/// Latencies below this don't fill the graph, otherwise a steady connection
+/// would look like it's all over the place
+const MIN_SCALE: f32 = 10.0;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PingSettings {
+    hosts: Vec<String>,
+    /// In milliseconds
+    polling_interval: u64,
+    /// How long to wait for an answer, in milliseconds. Only Windows takes
+    /// this as is, elsewhere it's rounded up to full seconds.
+    timeout: u64,
+}
+
+impl Default for PingSettings {
+    fn default() -> Self {
+        Self {
+            hosts: vec![String::from("1.1.1.1")],
+            polling_interval: 5000,
+            timeout: 1000,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Ping display source.");
+
+    let mut settings: PingSettings = settings::section(config, "ping")?;
+    if settings.hosts.is_empty() || settings.hosts.len() > MAX_HOSTS {
+        return Err(anyhow!(
+            "Invalid settings in [ping]: There have to be between 1 and {} hosts to ping, {} are \
+             configured",
+            MAX_HOSTS,
+            settings.hosts.len()
+        ));
+    }
+    // Large text only has room for the first few hosts
+    if accessibility::large_text() {
+        settings.hosts.truncate(LARGE_TEXT_ROWS);
+    }
+
+    let hosts = settings
+        .hosts
+        .into_iter()
+        .map(|name| Host {
+            name,
+            latency: None,
+            graph: Graph::new((128 - GRAPH_LEFT) as usize, GraphStyle::Line),
+        })
+        .collect();
+
+    Ok(Box::new(Ping {
+        hosts,
+        polling_interval: settings.polling_interval,
+        timeout: settings.timeout.max(1),
+    }))
+}
+
+struct Host {
+    name: String,
+    /// In milliseconds, `None` if the last ping got lost
+    latency: Option<f32>,
+    graph: Graph,
+}
+
+struct Ping {
+    hosts: Vec<Host>,
+    polling_interval: u64,
+    timeout: u64,
+}
+
+/// Reads the round trip time from the output of `ping`, e.g. `time=12.3 ms`
+/// or `time<1ms` on Windows
+fn parse_latency(output: &str) -> Option<f32> {
+    let start = output.find("time=").or_else(|| output.find("time<"))? + 5;
+    let number = output[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect::<String>();
+    number.parse().ok()
+}
+
+/// Uses the `ping` of the system as sending ICMP packets ourselves needs
+/// elevated privileges
+fn ping(host: &str, timeout: u64) -> Option<f32> {
+    let mut command = process::Command::new("ping");
+    #[cfg(target_os = "windows")]
+    command.args(["-n", "1", "-w", &timeout.to_string(), host]);
+    #[cfg(not(target_os = "windows"))]
+    command
+        .args(["-c", "1", "-W", &((timeout + 999) / 1000).to_string(), host])
+        // `parse_latency` only knows the English output
+        .env("LC_ALL", "C");
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Couldn't run `ping`: {}", e);
+            return None;
+        }
+    };
+
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_latency(&String::from_utf8_lossy(&output.stdout))
+}
+
+impl Ping {
+    async fn poll(&mut self) -> Result<()> {
+        let timeout = self.timeout;
+        let pings = self.hosts.iter().map(|host| {
+            let name = host.name.clone();
+            task::spawn_blocking(move || ping(&name, timeout))
+        });
+
+        for (host, latency) in self.hosts.iter_mut().zip(future::join_all(pings).await) {
+            host.latency = latency?;
+            // Lost packets show up as a gap at the bottom of the graph
+            host.graph.push(host.latency.unwrap_or(0.0));
+        }
+
+        Ok(())
+    }
+
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let font = accessibility::font(&iso_8859_15::FONT_5X8);
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let height = font.character_size.height as i32;
+        let row_height = 40 / self.hosts.len() as i32;
+        // The name stops short of the graph
+        let name_width = ((GRAPH_LEFT - 2) / font.character_size.width as i32) as usize;
+
+        for (row, host) in self.hosts.iter().enumerate() {
+            let top = row as i32 * row_height;
+            let name = host.name.chars().take(name_width).collect::<String>();
+            let latency = match host.latency {
+                Some(latency) => format!("{:.0} ms", latency),
+                None => String::from("lost"),
+            };
+
+            // The name and the latency are stacked if there's room for it
+            if row_height >= 2 * height {
+                // Large text fills the whole row
+                let y = if row_height > 2 * height {
+                    top + 1
+                } else {
+                    top
+                };
+                Text::with_baseline(&name, Point::new(1, y), style, Baseline::Top)
+                    .draw(&mut buffer)?;
+                Text::with_baseline(&latency, Point::new(1, y + height), style, Baseline::Top)
+                    .draw(&mut buffer)?;
+            } else {
+                let text = format!("{:.6} {}", name, latency);
+                Text::with_baseline(&text, Point::new(1, top + 2), style, Baseline::Top)
+                    .draw(&mut buffer)?;
+            }
+
+            let area = Rectangle::new(
+                Point::new(GRAPH_LEFT, top + 1),
+                Size::new((128 - GRAPH_LEFT) as u32, (row_height - 2) as u32),
+            );
+            host.graph
+                .draw(&mut buffer, area, host.graph.max().max(MIN_SCALE))?;
+
+            // Rows of hosts that didn't answer are inverted so they stand out
+            if host.latency.is_none() {
+                Rectangle::new(Point::new(0, top), Size::new(128, row_height as u32))
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(&mut buffer.blend(BlendMode::Xor))?;
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Ping {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(self.polling_interval.max(1)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                self.poll().await?;
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "ping"
+    }
+}
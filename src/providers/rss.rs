@@ -0,0 +1,329 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        scheduler::ContentWrapper,
+        text::{ScrollableBuilder, StatefulScrollable},
+    },
+    settings,
+    state::state_dir,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{OriginDimensions, Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{renderer::TextRenderer, Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use reqwest::{Client, ClientBuilder};
+use serde::Deserialize;
+use std::{
+    cmp::Reverse,
+    collections::{HashSet, VecDeque},
+    fs,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+};
+use tokio::{
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The headline uses the lower part of the screen, the name of the feed sits
+/// above it
+const HEADLINE_Y: i32 = 20;
+
+/// How many ticks a headline that fits on the screen stays there
+const HOLD_TICKS: usize = 100;
+
+/// Headlines are cut off after 120 characters, some feeds put the whole
+/// article into the title and it would take minutes to scroll through
+const MAX_HEADLINE_WIDTH: u32 = 120 * 8;
+
+/// The seen headlines are cut down to this many once there are twice as many
+const MAX_SEEN: usize = 1000;
+
+/// The settings of the `[rss]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RssSettings {
+    /// The URLs of RSS or Atom feeds
+    feeds: Vec<String>,
+    /// How many of the newest headlines of every feed are shown
+    headlines: usize,
+    /// How often the feeds are fetched, in minutes
+    polling_interval: u64,
+}
+
+impl Default for RssSettings {
+    fn default() -> Self {
+        Self {
+            feeds: Vec::new(),
+            headlines: 5,
+            polling_interval: 15,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering RSS display source.");
+
+    let settings: RssSettings = settings::section(config, "rss")?;
+
+    let scroller: StatefulScrollable = ScrollableBuilder::new()
+        .with_custom_font(&iso_8859_15::FONT_8X13_BOLD)
+        .with_custom_spacing(16)
+        .with_max_width(MAX_HEADLINE_WIDTH)
+        .with_position(Point::new(0, HEADLINE_Y))
+        .with_projection(Size::new(128, 13))
+        .try_into()?;
+
+    Ok(Box::new(Rss {
+        client: ClientBuilder::new().user_agent(APP_USER_AGENT).build()?,
+        path: state_dir().join("rss"),
+        seen: HashSet::new(),
+        queue: VecDeque::new(),
+        current: None,
+        ticks: 0,
+        scroller,
+        settings,
+    }))
+}
+
+#[derive(Debug, Clone)]
+struct Headline {
+    /// The ID of the entry, this is what's remembered once it's seen
+    id: String,
+    feed: String,
+    title: String,
+}
+
+/// Reads the newest entries of the feed at `url`, newest first
+async fn fetch(client: &Client, url: &str, headlines: usize) -> Result<Vec<Headline>> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let feed = feed_rs::parser::parse(body.as_ref())?;
+
+    let name = feed
+        .title
+        .map_or_else(|| url.to_string(), |title| title.content);
+    let mut entries = feed.entries;
+    // Feeds are usually sorted already but nothing says they have to be
+    entries.sort_by_key(|entry| Reverse(entry.published.or(entry.updated)));
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| {
+            let title = entry.title?.content.replace(['\r', '\n'], " ");
+            Some(Headline {
+                id: entry.id.replace(['\r', '\n'], ""),
+                feed: name.clone(),
+                title,
+            })
+        })
+        .take(headlines)
+        .collect())
+}
+
+/// Shows every headline of the feeds once, one after another
+struct Rss {
+    client: Client,
+    settings: RssSettings,
+    /// The IDs of the headlines that were shown already, one per line
+    path: PathBuf,
+    seen: HashSet<String>,
+    /// The headlines that weren't shown yet
+    queue: VecDeque<Headline>,
+    current: Option<Headline>,
+    /// How long the current headline has been shown
+    ticks: usize,
+    scroller: StatefulScrollable,
+}
+
+impl Rss {
+    /// Reads the IDs of the seen headlines, the file is cut down to the
+    /// latest ones once it gets too long
+    fn load_seen(&mut self) {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            // Nothing has been shown yet
+            Err(_) => return,
+        };
+
+        let lines = content.lines().collect::<Vec<_>>();
+        if lines.len() > MAX_SEEN * 2 {
+            let kept = lines[lines.len() - MAX_SEEN..].join("\n") + "\n";
+            if let Err(e) = fs::write(&self.path, kept) {
+                warn!("Couldn't clean up the seen headlines: {}", e);
+            }
+        }
+
+        self.seen = lines.into_iter().map(ToString::to_string).collect();
+    }
+
+    fn mark_seen(&mut self, headline: &Headline) -> Result<()> {
+        if !self.seen.insert(headline.id.clone()) {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", headline.id)?;
+
+        Ok(())
+    }
+
+    /// Fetches every feed and queues the headlines that weren't seen yet,
+    /// feeds that can't be fetched are skipped until the next time
+    async fn refresh(&mut self) {
+        let mut headlines = Vec::new();
+        for url in &self.settings.feeds {
+            match fetch(&self.client, url, self.settings.headlines).await {
+                Ok(feed) => headlines.extend(feed),
+                Err(e) => warn!("Couldn't fetch {}: {}", url, e),
+            }
+        }
+
+        let current = self.current.as_ref().map(|headline| &headline.id);
+        self.queue = headlines
+            .into_iter()
+            .filter(|headline| !self.seen.contains(&headline.id))
+            .filter(|headline| Some(&headline.id) != current)
+            .collect();
+    }
+
+    /// Moves on to the next headline once the current one was shown long
+    /// enough
+    fn advance(&mut self) {
+        if let Some(current) = &self.current {
+            let canvas = self.scroller.text.canvas.size().width;
+            let scrolls = canvas > self.scroller.text.projection.width;
+            let done = if scrolls {
+                self.scroller.text.scroll >= canvas
+            } else {
+                self.ticks >= HOLD_TICKS
+            };
+            if !done {
+                return;
+            }
+
+            // The headline comes back after a restart then, that's all
+            let current = current.clone();
+            if let Err(e) = self.mark_seen(&current) {
+                warn!("Couldn't remember the headline as seen: {}", e);
+            }
+        }
+
+        self.current = self.queue.pop_front();
+        self.ticks = 0;
+        if let Some(current) = &self.current {
+            if let Err(e) = self.scroller.update(&current.title) {
+                warn!("Couldn't lay out the headline: {}", e);
+            }
+            self.scroller.text.scroll = 0;
+        }
+    }
+
+    fn render(&mut self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        let current = match &self.current {
+            Some(current) => current,
+            None => {
+                let centered = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                Text::with_text_style("No new headlines", Point::new(64, 20), small, centered)
+                    .draw(&mut buffer)?;
+                return Ok(buffer);
+            }
+        };
+
+        // The feed on the left and how many headlines are left on the right
+        let left = format!("+{}", self.queue.len());
+        let metrics = small.measure_string(&left, Point::zero(), Baseline::Top);
+        let left_x = 128 - metrics.bounding_box.size.width as i32;
+        Text::with_baseline(&left, Point::new(left_x, 2), small, Baseline::Top)
+            .draw(&mut buffer)?;
+        let max_chars = ((left_x - 6) / 6).max(0) as usize;
+        let feed = current.feed.chars().take(max_chars).collect::<String>();
+        Text::with_baseline(&feed, Point::new(0, 2), small, Baseline::Top).draw(&mut buffer)?;
+
+        let canvas = self.scroller.text.canvas.size().width;
+        if canvas > self.scroller.text.projection.width {
+            self.scroller.text.draw(&mut buffer)?;
+            self.scroller.text.scroll();
+        } else {
+            let style = MonoTextStyle::new(&iso_8859_15::FONT_8X13_BOLD, BinaryColor::On);
+            let centered = TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Top)
+                .build();
+            Text::with_text_style(&current.title, Point::new(64, HEADLINE_Y), style, centered)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Rss {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut refetch = time::interval(Duration::from_secs(
+            self.settings.polling_interval.max(1) * 60,
+        ));
+        refetch.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut render = time::interval(Duration::from_millis(50));
+        render.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            self.load_seen();
+
+            loop {
+                tokio::select! {
+                    _ = render.tick() => {
+                        self.advance();
+                        self.ticks += 1;
+                        if let Ok(image) = self.render() {
+                            yield image;
+                        }
+                    },
+                    _ = refetch.tick() => self.refresh().await,
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "rss"
+    }
+}
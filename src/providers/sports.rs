@@ -0,0 +1,527 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        poller::{Refresh, SharedPoller},
+        scheduler::{ContentWrapper, NotificationWrapper},
+        util,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{
+    future::{self, BoxFuture},
+    Stream,
+};
+use log::{debug, info, warn};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, ClientBuilder,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::collections::HashMap;
+use tokio::{sync::watch, time::Duration};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// How many characters of the font fit in half a row, one column of the
+/// standing is that wide as well
+const MAX_NAME: usize = 10;
+/// Two columns of four rows of the standing fit on the screen
+const MAX_POSITIONS: usize = 8;
+
+/// The latest answer of the API, `None` until it answered for the first time
+type Latest = Option<Snapshot>;
+
+/// The screen and the notifications ask the API together
+static POLLER: SharedPoller<SportsSettings, Latest> = SharedPoller::new();
+
+/// The settings of the `[sports]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SportsSettings {
+    url: String,
+    /// Sent along with every request, e.g. for an API key
+    headers: HashMap<String, String>,
+    /// In seconds
+    polling_interval: u64,
+    style: Style,
+    /// JSONPath expressions for the `score` style
+    home: String,
+    away: String,
+    home_score: String,
+    away_score: String,
+    /// E.g. the minute of the match, optional
+    status: Option<String>,
+    /// A JSONPath expression that picks the names in the order of their
+    /// positions for the `standing` style
+    standing: String,
+    /// The names whose positions are worth a notification, the leader if
+    /// there are none
+    follow: Vec<String>,
+}
+
+impl Default for SportsSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            headers: HashMap::new(),
+            polling_interval: 30,
+            style: Style::default(),
+            home: String::new(),
+            away: String::new(),
+            home_score: String::new(),
+            away_score: String::new(),
+            status: None,
+            standing: String::new(),
+            follow: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Style {
+    /// Two sides and their score, e.g. a football match
+    #[default]
+    Score,
+    /// A list of positions, e.g. an F1 session
+    Standing,
+}
+
+fn parse_path(path: &str, key: &str) -> Result<JsonPath> {
+    if path.is_empty() {
+        return Err(anyhow!(
+            "Invalid settings in [sports]: There's no `{}`",
+            key
+        ));
+    }
+
+    JsonPath::parse(path).map_err(|e| {
+        anyhow!(
+            "Invalid settings in [sports]: `{}` isn't a JSONPath expression: {}",
+            path,
+            e
+        )
+    })
+}
+
+/// Where the values are in the answers of the API
+enum Paths {
+    Score {
+        home: JsonPath,
+        away: JsonPath,
+        home_score: JsonPath,
+        away_score: JsonPath,
+        status: Option<JsonPath>,
+    },
+    Standing(JsonPath),
+}
+
+/// Polls the API for the screen and the notifications, see `subscribe`
+struct Source {
+    client: Client,
+    url: String,
+    paths: Paths,
+}
+
+impl Source {
+    fn new(settings: &SportsSettings) -> Result<Self> {
+        let paths = match settings.style {
+            Style::Score => Paths::Score {
+                home: parse_path(&settings.home, "home")?,
+                away: parse_path(&settings.away, "away")?,
+                home_score: parse_path(&settings.home_score, "home_score")?,
+                away_score: parse_path(&settings.away_score, "away_score")?,
+                status: settings
+                    .status
+                    .as_deref()
+                    .map(|status| parse_path(status, "status"))
+                    .transpose()?,
+            },
+            Style::Standing => Paths::Standing(parse_path(&settings.standing, "standing")?),
+        };
+
+        let headers = settings
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                Ok((
+                    HeaderName::from_bytes(name.as_bytes())?,
+                    HeaderValue::from_str(value)?,
+                ))
+            })
+            .collect::<Result<HeaderMap>>()
+            .map_err(|e| anyhow!("Invalid settings in [sports]: Broken `headers`: {}", e))?;
+
+        Ok(Self {
+            client: ClientBuilder::new()
+                .user_agent(APP_USER_AGENT)
+                .default_headers(headers)
+                .build()?,
+            url: settings.url.clone(),
+            paths,
+        })
+    }
+
+    async fn fetch(&self) -> Result<Snapshot> {
+        let answer = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        Ok(match &self.paths {
+            Paths::Score {
+                home,
+                away,
+                home_score,
+                away_score,
+                status,
+            } => Snapshot::Score {
+                home: extract(home, &answer),
+                away: extract(away, &answer),
+                home_score: extract(home_score, &answer),
+                away_score: extract(away_score, &answer),
+                status: status.as_ref().map(|status| extract(status, &answer)),
+            },
+            Paths::Standing(standing) => Snapshot::Standing(
+                standing
+                    .query(&answer)
+                    .all()
+                    .into_iter()
+                    .map(text)
+                    .collect(),
+            ),
+        })
+    }
+}
+
+impl Refresh for Source {
+    type Output = Latest;
+
+    // The last answer stays if the API can't be reached
+    fn refresh(&mut self) -> BoxFuture<'_, Option<Latest>> {
+        Box::pin(async move {
+            match self.fetch().await {
+                Ok(latest) => Some(Some(latest)),
+                Err(e) => {
+                    debug!("Couldn't get {}: {}", self.url, e);
+                    None
+                }
+            }
+        })
+    }
+}
+
+/// Strings would end up in quotes otherwise
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::from("-"),
+        value => value.to_string(),
+    }
+}
+
+/// The value at `path`, a dash if the answer doesn't have it
+fn extract(path: &JsonPath, answer: &Value) -> String {
+    path.query(answer)
+        .first()
+        .map_or_else(|| String::from("-"), text)
+}
+
+#[derive(Debug, Clone)]
+enum Snapshot {
+    Score {
+        home: String,
+        away: String,
+        home_score: String,
+        away_score: String,
+        status: Option<String>,
+    },
+    /// The names from the first position on
+    Standing(Vec<String>),
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Sports display source.");
+
+    let settings: SportsSettings = settings::section(config, "sports")?;
+    if settings.url.is_empty() {
+        warn!("There's no `url` in [sports], the screen stays empty");
+    }
+
+    Ok(Box::new(Sports {
+        snapshot: subscribe(&settings)?,
+        follow: settings.follow,
+    }))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering Sports notification source.");
+
+    let settings: SportsSettings = settings::section(config, "sports")?;
+
+    Ok(Box::new(SportsNotifier {
+        snapshot: subscribe(&settings)?,
+        follow: settings.follow,
+    }))
+}
+
+/// `None` if there's no `url`, the paths don't matter then either
+fn subscribe(settings: &SportsSettings) -> Result<Option<watch::Receiver<Latest>>> {
+    if settings.url.is_empty() {
+        return Ok(None);
+    }
+
+    let interval = Duration::from_secs(settings.polling_interval.max(1));
+    POLLER
+        .subscribe(settings, None, interval, || Source::new(settings))
+        .map(Some)
+}
+
+/// Shows the score of a match or the standing of a race
+struct Sports {
+    /// `None` if there's no `url`
+    snapshot: Option<watch::Receiver<Latest>>,
+    follow: Vec<String>,
+}
+
+impl Sports {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+        let aligned = |alignment| {
+            TextStyleBuilder::new()
+                .alignment(alignment)
+                .baseline(Baseline::Top)
+                .build()
+        };
+        let name = |name: &str| name.chars().take(MAX_NAME).collect::<String>();
+
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.borrow().clone());
+        match &snapshot.flatten() {
+            None if self.snapshot.is_some() => return util::placeholder("Loading"),
+            None => return util::placeholder("No URL configured"),
+            Some(Snapshot::Score {
+                home,
+                away,
+                home_score,
+                away_score,
+                status,
+            }) => {
+                let large = MonoTextStyle::new(&iso_8859_15::FONT_10X20, BinaryColor::On);
+                let score = format!("{} - {}", home_score, away_score);
+
+                Text::with_text_style(&name(home), Point::zero(), small, aligned(Alignment::Left))
+                    .draw(&mut buffer)?;
+                Text::with_text_style(
+                    &name(away),
+                    Point::new(127, 0),
+                    small,
+                    aligned(Alignment::Right),
+                )
+                .draw(&mut buffer)?;
+                Text::with_text_style(
+                    &score,
+                    Point::new(64, 10),
+                    large,
+                    aligned(Alignment::Center),
+                )
+                .draw(&mut buffer)?;
+                if let Some(status) = status {
+                    Text::with_text_style(
+                        status,
+                        Point::new(64, 30),
+                        small,
+                        aligned(Alignment::Center),
+                    )
+                    .draw(&mut buffer)?;
+                }
+            }
+            Some(Snapshot::Standing(names)) => {
+                // The followed names are marked
+                for (index, entry) in names.iter().take(MAX_POSITIONS).enumerate() {
+                    let marker = if self.follow.contains(entry) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    // The position and the marker take three characters of the column
+                    let entry = entry.chars().take(MAX_NAME - 3).collect::<String>();
+                    let line = format!("{:>2}{}{}", index + 1, marker, entry);
+                    let position = Point::new(index as i32 / 4 * 64, index as i32 % 4 * 10);
+                    Text::with_text_style(&line, position, small, aligned(Alignment::Left))
+                        .draw(&mut buffer)?;
+                }
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Sports {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        Ok(try_stream! {
+            loop {
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                match &mut self.snapshot {
+                    Some(snapshot) => {
+                        if snapshot.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                    // Nothing changes without a `url`
+                    None => future::pending::<()>().await,
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "sports"
+    }
+}
+
+/// Tells about goals and changed positions no matter which screen is shown
+struct SportsNotifier {
+    /// `None` if there's no `url`
+    snapshot: Option<watch::Receiver<Latest>>,
+    follow: Vec<String>,
+}
+
+/// Describes what changed between two answers as a title and a content,
+/// `None` if it's not worth a notification
+fn describe(
+    previous: &Snapshot,
+    current: &Snapshot,
+    follow: &[String],
+) -> Option<(String, String)> {
+    match (previous, current) {
+        (
+            Snapshot::Score {
+                home_score: previous_home,
+                away_score: previous_away,
+                ..
+            },
+            Snapshot::Score {
+                home,
+                away,
+                home_score,
+                away_score,
+                ..
+            },
+        ) => {
+            // A dash or a new match going back to zero aren't goals
+            let scored = |previous: &str, current: &str| {
+                matches!(
+                    (previous.parse::<u64>(), current.parse::<u64>()),
+                    (Ok(previous), Ok(current)) if current > previous
+                )
+            };
+            let scorer = if scored(previous_home, home_score) {
+                home
+            } else if scored(previous_away, away_score) {
+                away
+            } else {
+                return None;
+            };
+            Some((
+                format!("Goal {}", scorer),
+                format!("{} {} - {} {}", home, home_score, away_score, away),
+            ))
+        }
+        (Snapshot::Standing(previous), Snapshot::Standing(current)) => {
+            let position = |names: &[String], name: &str| names.iter().position(|n| n == name);
+
+            if follow.is_empty() {
+                let (before, leader) = (previous.first()?, current.first()?);
+                return (before != leader)
+                    .then(|| (format!("{} leads", leader), String::from("P1")));
+            }
+
+            // Only the first change is announced, the screen shows the rest
+            follow.iter().find_map(|name| {
+                let (from, to) = (position(previous, name)?, position(current, name)?);
+                (from != to).then(|| {
+                    (
+                        format!("{} P{}", name, to + 1),
+                        format!("from P{}", from + 1),
+                    )
+                })
+            })
+        }
+        _ => None,
+    }
+}
+
+impl NotificationProvider for SportsNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        Ok(try_stream! {
+            if let Some(snapshot) = &mut self.snapshot {
+                // The score when starting up isn't news
+                let mut previous = snapshot.borrow().clone();
+
+                while snapshot.changed().await.is_ok() {
+                    let current = match snapshot.borrow().clone() {
+                        Some(current) => current,
+                        None => continue,
+                    };
+
+                    if let Some((title, content)) = previous
+                        .as_ref()
+                        .and_then(|previous| describe(previous, &current, &self.follow))
+                    {
+                        if let Ok(notification) = NotificationBuilder::new()
+                            .with_title(&title)
+                            .with_content(content)
+                            .build()
+                        {
+                            yield notification;
+                        }
+                    }
+
+                    previous = Some(current);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,480 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        player::{MediaPlayerRenderer, IDLE_TEMPLATE},
+        scheduler::ContentWrapper,
+    },
+    settings,
+    state::config_dir,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_music::{Metadata, PlaybackStatus, Progress};
+use async_stream::try_stream;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{debug, info, warn};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{Client, ClientBuilder, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{fs, path::PathBuf};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    time,
+    time::{Duration, Instant, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+const AUTHORIZE_URL: &str = "https://accounts.spotify.com/authorize";
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const PLAYER_URL: &str = "https://api.spotify.com/v1/me/player";
+
+/// Reading what's playing is all we need
+const SCOPE: &str = "user-read-playback-state";
+
+/// The settings of the `[spotify]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SpotifySettings {
+    /// The client ID of an app registered on developer.spotify.com
+    client_id: String,
+    /// The port of the redirect URI of the app, it has to be
+    /// `http://127.0.0.1:<port>/callback`
+    port: u16,
+    /// How often Spotify is asked, in seconds. The progress moves on in
+    /// between.
+    polling_interval: u64,
+}
+
+impl Default for SpotifySettings {
+    fn default() -> Self {
+        Self {
+            client_id: String::new(),
+            port: 8888,
+            polling_interval: 5,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Spotify display source.");
+
+    let settings: SpotifySettings = settings::section(config, "spotify")?;
+
+    Ok(Box::new(Spotify {
+        client: ClientBuilder::new().user_agent(APP_USER_AGENT).build()?,
+        path: config_dir().join("spotify"),
+        access_token: None,
+        settings,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct Token {
+    access_token: String,
+    /// Spotify may hand out a new refresh token every time
+    refresh_token: Option<String>,
+    /// In seconds
+    expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Playback {
+    is_playing: bool,
+    progress_ms: Option<u64>,
+    /// Nothing while e.g. an ad is playing
+    item: Option<Item>,
+}
+
+/// A track or an episode of a podcast
+#[derive(Debug, Clone, Deserialize)]
+struct Item {
+    name: String,
+    duration_ms: u64,
+    /// Only tracks have artists
+    #[serde(default)]
+    artists: Vec<Artist>,
+    /// Only episodes belong to a show
+    show: Option<Show>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Artist {
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Show {
+    name: String,
+}
+
+impl Metadata for Item {
+    fn title(&self) -> Result<String> {
+        Ok(self.name.clone())
+    }
+
+    fn artists(&self) -> Result<String> {
+        if let Some(show) = &self.show {
+            return Ok(show.name.clone());
+        }
+        if self.artists.is_empty() {
+            return Err(anyhow!("Couldn't get artist!"));
+        }
+
+        Ok(self
+            .artists
+            .iter()
+            .map(|artist| artist.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "))
+    }
+
+    fn length(&self) -> Result<u64> {
+        Ok(self.duration_ms * 1000)
+    }
+}
+
+/// What Spotify answered the last time it was asked
+#[derive(Debug, Clone)]
+struct Status {
+    item: Item,
+    playing: bool,
+    /// In milliseconds
+    progress: u64,
+    /// When the answer came in, the position moves on from there while it's
+    /// playing
+    at: Instant,
+}
+
+impl Status {
+    fn progress(&self) -> Progress<Item> {
+        let mut position = self.progress * 1000;
+        if self.playing {
+            position += self.at.elapsed().as_micros() as u64;
+        }
+
+        Progress {
+            metadata: self.item.clone(),
+            position: position.min(self.item.duration_ms * 1000) as i64,
+            status: if self.playing {
+                PlaybackStatus::Playing
+            } else {
+                PlaybackStatus::Paused
+            },
+        }
+    }
+}
+
+/// The PKCE challenge for `verifier`, see RFC 7636
+fn challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Whether Spotify turned down the request itself, e.g. because of an
+/// invalid grant, rather than not being reachable
+fn is_bad_request(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        == Some(StatusCode::BAD_REQUEST)
+}
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Waits for the browser to come back to the redirect URI and returns the
+/// code it brings along
+async fn receive_code(port: u16, state: &str) -> Result<String> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let (reader, mut writer) = stream.into_split();
+        // Only the request line matters, e.g. `GET /callback?code=...&state=...
+        // HTTP/1.1`
+        let request = BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await?
+            .unwrap_or_default();
+
+        let query = request
+            .split_whitespace()
+            .nth(1)
+            .and_then(|target| target.strip_prefix("/callback?"))
+            .unwrap_or_default();
+        let parameter = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+
+        // Browsers also ask for e.g. the favicon
+        let (code, valid) = (
+            parameter("code"),
+            parameter("state").as_deref() == Some(state),
+        );
+        let body = match (&code, valid) {
+            (Some(_), true) => "apex-tux is connected to Spotify now, you can close this tab.",
+            _ => "This isn't the answer apex-tux was waiting for.",
+        };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: \
+             {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        writer.write_all(response.as_bytes()).await?;
+
+        if let (Some(code), true) = (code, valid) {
+            return Ok(code);
+        }
+        if let Some(error) = parameter("error") {
+            return Err(anyhow!("Spotify didn't grant access: {}", error));
+        }
+    }
+}
+
+/// Shows what's playing on any Spotify Connect device, e.g. a phone or
+/// speakers, through the Web API
+struct Spotify {
+    client: Client,
+    settings: SpotifySettings,
+    /// The refresh token lives here, it's created once the account is
+    /// connected
+    path: PathBuf,
+    /// Along with when it expires
+    access_token: Option<(String, Instant)>,
+}
+
+impl Spotify {
+    fn redirect_uri(&self) -> String {
+        format!("http://127.0.0.1:{}/callback", self.settings.port)
+    }
+
+    /// Stores the token so the account doesn't have to be connected again on
+    /// the next start
+    fn save(&self, token: &Token) -> Result<()> {
+        if let Some(refresh_token) = &token.refresh_token {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&self.path, refresh_token)?;
+        }
+        Ok(())
+    }
+
+    async fn request_token(&mut self, form: &[(&str, &str)]) -> Result<String> {
+        let token = self
+            .client
+            .post(TOKEN_URL)
+            .form(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Token>()
+            .await?;
+
+        if let Err(e) = self.save(&token) {
+            warn!("Couldn't store the Spotify token: {}", e);
+        }
+        // A little margin so it doesn't expire on the way
+        let expires = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+        self.access_token = Some((token.access_token.clone(), expires));
+        Ok(token.access_token)
+    }
+
+    /// Lets the user connect their account in the browser, this waits until
+    /// they did
+    async fn authorize(&mut self) -> Result<String> {
+        let verifier = random_string(64);
+        let state = random_string(16);
+        let url = reqwest::Url::parse_with_params(
+            AUTHORIZE_URL,
+            &[
+                ("client_id", self.settings.client_id.as_str()),
+                ("response_type", "code"),
+                ("redirect_uri", &self.redirect_uri()),
+                ("scope", SCOPE),
+                ("state", &state),
+                ("code_challenge_method", "S256"),
+                ("code_challenge", &challenge(&verifier)),
+            ],
+        )?;
+        info!("Open {} to connect apex-tux to Spotify", url);
+
+        let code = receive_code(self.settings.port, &state).await?;
+        let client_id = self.settings.client_id.clone();
+        let redirect_uri = self.redirect_uri();
+        self.request_token(&[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", &redirect_uri),
+            ("client_id", &client_id),
+            ("code_verifier", &verifier),
+        ])
+        .await
+    }
+
+    /// Returns a valid access token, `None` if the account isn't connected
+    /// yet
+    async fn access_token(&mut self) -> Result<Option<String>> {
+        if let Some((token, expires)) = &self.access_token {
+            if Instant::now() < *expires {
+                return Ok(Some(token.clone()));
+            }
+        }
+
+        let refresh_token = match fs::read_to_string(&self.path) {
+            Ok(refresh_token) => refresh_token.trim().to_string(),
+            Err(_) => return Ok(None),
+        };
+        let client_id = self.settings.client_id.clone();
+        let token = self
+            .request_token(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+                ("client_id", &client_id),
+            ])
+            .await;
+
+        match token {
+            Ok(token) => Ok(Some(token)),
+            // The access was revoked, the account has to be connected again
+            Err(e) if is_bad_request(&e) => {
+                warn!("Spotify didn't accept the refresh token anymore: {}", e);
+                fs::remove_file(&self.path)?;
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Asks what's playing right now, `None` if nothing is
+    async fn playback(&mut self, token: &str) -> Result<Option<Status>> {
+        let response = self
+            .client
+            .get(PLAYER_URL)
+            .bearer_auth(token)
+            .send()
+            .await?;
+
+        match response.status() {
+            // Nothing is playing on any device
+            StatusCode::NO_CONTENT => return Ok(None),
+            StatusCode::UNAUTHORIZED => {
+                self.access_token = None;
+                return Err(anyhow!("Spotify didn't accept the access token anymore"));
+            }
+            _ => {}
+        }
+
+        let playback = response.error_for_status()?.json::<Playback>().await?;
+        Ok(playback.item.map(|item| Status {
+            item,
+            playing: playback.is_playing,
+            progress: playback.progress_ms.unwrap_or(0),
+            at: Instant::now(),
+        }))
+    }
+
+    fn render_message(message: &str) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        Text::with_text_style(message, Point::new(64, 20), style, centered).draw(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Spotify {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut renderer = MediaPlayerRenderer::new()?;
+        let mut poll = time::interval(Duration::from_secs(self.settings.polling_interval.max(1)));
+        poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut render = time::interval(Duration::from_millis(100));
+        render.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            if self.settings.client_id.is_empty() {
+                yield Self::render_message("Set up [spotify] first")?;
+                return;
+            }
+
+            // `None` while nothing is playing
+            let mut current: Option<Status> = None;
+
+            loop {
+                tokio::select! {
+                    _ = poll.tick() => {
+                        let token = match self.access_token().await {
+                            Ok(Some(token)) => token,
+                            Ok(None) => {
+                                yield Self::render_message("Connect Spotify, see the log")?;
+                                match self.authorize().await {
+                                    Ok(token) => token,
+                                    Err(e) => {
+                                        warn!("Couldn't connect to Spotify: {}", e);
+                                        continue;
+                                    }
+                                }
+                            },
+                            Err(e) => {
+                                debug!("Couldn't get a Spotify token: {}", e);
+                                continue;
+                            }
+                        };
+
+                        match self.playback(&token).await {
+                            Ok(status) => current = status,
+                            Err(e) => debug!("Couldn't ask Spotify what's playing: {}", e),
+                        }
+                        if current.is_none() {
+                            yield *IDLE_TEMPLATE;
+                        }
+                    },
+                    _ = render.tick(), if current.is_some() => {
+                        if let Some(current) = &current {
+                            if let Ok(image) = renderer.update(&current.progress()) {
+                                yield image;
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "spotify"
+    }
+}
@@ -1,104 +1,458 @@
 use crate::{
-    render::{display::ContentProvider, scheduler::ContentWrapper},
-    scheduler::CONTENT_PROVIDERS,
+    render::{
+        accessibility::{self, LARGE_TEXT_ROWS},
+        display::ContentProvider,
+        scheduler::ContentWrapper,
+    },
+    settings,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use apex_hardware::FrameBuffer;
 use async_stream::try_stream;
 use num_traits::{pow, Pow};
 
 use config::Config;
 use embedded_graphics::{
-    geometry::Point,
-    mono_font::{iso_8859_15, MonoTextStyle},
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoFont, MonoTextStyle},
     pixelcolor::BinaryColor,
     primitives::{Primitive, PrimitiveStyle, Rectangle},
-    text::{renderer::TextRenderer, Baseline, Text},
+    text::{renderer::TextRenderer, Alignment, Baseline, Text, TextStyleBuilder},
     Drawable,
 };
 use futures::Stream;
-use linkme::distributed_slice;
-use log::{info, warn};
+use log::{debug, info, warn};
+#[cfg(feature = "nvidia")]
+use nvml_wrapper::{enum_wrappers::device::TemperatureSensor, Nvml};
+use serde::Deserialize;
+#[cfg(target_os = "linux")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use tokio::{
     time,
     time::{Duration, MissedTickBehavior},
 };
 
 use sysinfo::{
-    ComponentExt, CpuExt, CpuRefreshKind, NetworkData, NetworkExt, NetworksExt, RefreshKind,
-    System, SystemExt,
+    ComponentExt, CpuExt, CpuRefreshKind, Disk, DiskExt, NetworkData, NetworkExt, NetworksExt,
+    ProcessExt, ProcessRefreshKind, RefreshKind, System, SystemExt,
 };
 
 #[doc(hidden)]
-#[distributed_slice(CONTENT_PROVIDERS)]
 pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
 
+/// The font for every number of slots, fewer slots leave room for larger
+/// text. The screen has room for five of them.
+const FONTS: [&MonoFont<'static>; 5] = [
+    &iso_8859_15::FONT_10X20,
+    &iso_8859_15::FONT_9X15,
+    &iso_8859_15::FONT_6X10,
+    &iso_8859_15::FONT_5X8,
+    &iso_8859_15::FONT_4X6,
+];
+
 fn tick() -> i64 {
     chrono::offset::Utc::now().timestamp_millis()
 }
 
+/// The average frequency of the cores in MHz as cpufreq reports it. ARM
+/// boards like the Raspberry Pi don't list it in `/proc/cpuinfo` so sysinfo
+/// doesn't find it there.
+#[cfg(target_os = "linux")]
+fn cpufreq() -> Option<f64> {
+    let frequencies = fs::read_dir("/sys/devices/system/cpu")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("cpu").map_or(false, |n| {
+                !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+            })
+        })
+        .filter_map(|entry| fs::read_to_string(entry.path().join("cpufreq/scaling_cur_freq")).ok())
+        // In kHz
+        .filter_map(|frequency| frequency.trim().parse::<f64>().ok())
+        .collect::<Vec<_>>();
+
+    if frequencies.is_empty() {
+        return None;
+    }
+
+    Some(frequencies.iter().sum::<f64>() / frequencies.len() as f64 / 1000.0)
+}
+
+/// The stats that can be shown, every one of them takes a row
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Slot {
+    Cpu,
+    Freq,
+    Mem,
+    Net,
+    Temp,
+    Swap,
+    Gpu,
+    Vram,
+    GpuTemp,
+    /// Every one of these shows the next mount point of `disks`
+    Disk,
+    /// How much all processes read and write
+    DiskIo,
+    /// The load average of the last 1, 5 and 15 minutes
+    Load,
+}
+
+/// What the screen shows
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Mode {
+    /// The configured slots
+    Slots,
+    /// A bar for the load of every logical CPU
+    Cores,
+    /// The processes using the most CPU
+    Processes,
+}
+
+/// At most this many cores get a bar, they're split into two columns once
+/// there are more than `CORES_PER_COLUMN` of them
+const MAX_CORES: usize = 32;
+const CORES_PER_COLUMN: usize = 8;
+/// How many processes fit on the screen
+const TOP_PROCESSES: usize = 3;
+
+impl Slot {
+    fn is_gpu(&self) -> bool {
+        matches!(self, Slot::Gpu | Slot::Vram | Slot::GpuTemp)
+    }
+}
+
+/// The load, memory and temperature of the GPU
+#[cfg_attr(
+    not(any(
+        feature = "nvidia",
+        target_os = "linux",
+        all(target_os = "windows", feature = "lhm")
+    )),
+    allow(dead_code)
+)]
+struct GpuStats {
+    /// In percent
+    load: u32,
+    /// In bytes
+    vram_used: u64,
+    /// In bytes
+    vram_total: u64,
+    /// In degrees Celsius
+    temperature: f32,
+}
+
+/// Where the GPU stats come from, without one of these the GPU slots stay
+/// empty
+enum GpuSource {
+    /// The NVML library and the index of the GPU
+    #[cfg(feature = "nvidia")]
+    Nvml(Nvml, u32),
+    /// The `device` directory of the card in sysfs
+    #[cfg(target_os = "linux")]
+    Amdgpu(PathBuf),
+    /// The sensors of LibreHardwareMonitor and the hardware they belong to,
+    /// e.g. `/gpu-amd/0`
+    #[cfg(all(target_os = "windows", feature = "lhm"))]
+    Lhm(apex_windows::Sensors, String),
+}
+
+impl GpuSource {
+    /// NVML is asked first, it can only be loaded if there's an NVIDIA GPU
+    // The index isn't needed if no source is compiled in
+    #[allow(unused_variables)]
+    fn find(index: u32) -> Option<Self> {
+        #[cfg(feature = "nvidia")]
+        match Nvml::init() {
+            Ok(nvml) => return Some(GpuSource::Nvml(nvml, index)),
+            Err(e) => debug!("Couldn't load NVML: {}", e),
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(device) = amdgpu::devices().into_iter().nth(index as usize) {
+            return Some(GpuSource::Amdgpu(device));
+        }
+
+        None
+    }
+
+    fn read(&self) -> Result<GpuStats> {
+        match *self {
+            #[cfg(feature = "nvidia")]
+            GpuSource::Nvml(ref nvml, index) => {
+                let device = nvml.device_by_index(index)?;
+                let memory = device.memory_info()?;
+
+                Ok(GpuStats {
+                    load: device.utilization_rates()?.gpu,
+                    vram_used: memory.used,
+                    vram_total: memory.total,
+                    temperature: device.temperature(TemperatureSensor::Gpu)? as f32,
+                })
+            }
+            #[cfg(target_os = "linux")]
+            GpuSource::Amdgpu(ref device) => amdgpu::read(device),
+            #[cfg(all(target_os = "windows", feature = "lhm"))]
+            GpuSource::Lhm(ref sensors, ref hardware) => lhm::read(&sensors.all(), hardware),
+        }
+    }
+}
+
+/// Reads the stats of GPUs driven by amdgpu from sysfs
+#[cfg(target_os = "linux")]
+mod amdgpu {
+    use super::GpuStats;
+    use anyhow::{anyhow, Result};
+    use std::{
+        error::Error,
+        fs,
+        path::{Path, PathBuf},
+        str::FromStr,
+    };
+
+    /// The `device` directories of the cards, only amdgpu reports how busy
+    /// the GPU is
+    pub(super) fn devices() -> Vec<PathBuf> {
+        let mut devices = fs::read_dir("/sys/class/drm")
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            // The outputs of the cards are listed as `card0-DP-1` and so on
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("card") && !name.contains('-')
+            })
+            .map(|entry| entry.path().join("device"))
+            .filter(|device| device.join("gpu_busy_percent").exists())
+            .collect::<Vec<_>>();
+        devices.sort();
+
+        devices
+    }
+
+    fn read_value<T>(path: &Path) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Error + Send + Sync + 'static,
+    {
+        Ok(fs::read_to_string(path)?.trim().parse()?)
+    }
+
+    /// `temp1` is the edge of the chip, it's reported in millidegrees
+    fn temperature(device: &Path) -> Result<f32> {
+        let hwmon = fs::read_dir(device.join("hwmon"))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .next()
+            .ok_or_else(|| anyhow!("{} doesn't have any sensors", device.display()))?;
+        let temperature: f32 = read_value(&hwmon.join("temp1_input"))?;
+
+        Ok(temperature / 1000.0)
+    }
+
+    pub(super) fn read(device: &Path) -> Result<GpuStats> {
+        Ok(GpuStats {
+            load: read_value(&device.join("gpu_busy_percent"))?,
+            vram_used: read_value(&device.join("mem_info_vram_used"))?,
+            vram_total: read_value(&device.join("mem_info_vram_total"))?,
+            temperature: temperature(device)?,
+        })
+    }
+}
+
+/// Reads the stats of any GPU LibreHardwareMonitor knows about, this covers
+/// the AMD and Intel ones on Windows
+#[cfg(all(target_os = "windows", feature = "lhm"))]
+mod lhm {
+    use super::GpuStats;
+    use anyhow::{anyhow, Result};
+    use apex_windows::Sensor;
+
+    /// The hardware a sensor belongs to, e.g. `/gpu-nvidia/0` for
+    /// `/gpu-nvidia/0/load/0`
+    fn hardware(sensor: &Sensor) -> &str {
+        let end = sensor
+            .identifier
+            .match_indices('/')
+            .nth(2)
+            .map_or(sensor.identifier.len(), |(index, _)| index);
+        &sensor.identifier[..end]
+    }
+
+    /// The GPUs in the order LibreHardwareMonitor lists them
+    pub(super) fn gpus(sensors: &[Sensor]) -> Vec<String> {
+        let mut gpus: Vec<String> = Vec::new();
+        for sensor in sensors {
+            let hardware = hardware(sensor);
+            if hardware.starts_with("/gpu-") && !gpus.iter().any(|gpu| gpu == hardware) {
+                gpus.push(hardware.to_string());
+            }
+        }
+        gpus
+    }
+
+    fn value(sensors: &[Sensor], gpu: &str, kind: &str, name: &str) -> Result<f32> {
+        sensors
+            .iter()
+            .find(|sensor| {
+                hardware(sensor) == gpu && sensor.sensor_type == kind && sensor.name == name
+            })
+            .map(|sensor| sensor.value)
+            .ok_or_else(|| anyhow!("{} doesn't have a `{}` {} sensor", gpu, name, kind))
+    }
+
+    pub(super) fn read(sensors: &[Sensor], gpu: &str) -> Result<GpuStats> {
+        // The memory is reported in MiB
+        let mib = |name: &str| value(sensors, gpu, "SmallData", name).map(|mib| (mib as u64) << 20);
+
+        Ok(GpuStats {
+            load: value(sensors, gpu, "Load", "GPU Core")? as u32,
+            vram_used: mib("GPU Memory Used")?,
+            vram_total: mib("GPU Memory Total")?,
+            temperature: value(sensors, gpu, "Temperature", "GPU Core")?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SysinfoSettings {
+    /// In milliseconds
+    polling_interval: u64,
+    /// In MiB
+    net_load_max: f64,
+    /// In GHz
+    cpu_frequency_max: f64,
+    /// In degrees Celsius
+    temperature_max: f64,
+    /// In MiB per second
+    disk_io_max: f64,
+    net_interface_name: String,
+    sensor_name: String,
+    /// The mount points shown by the `disk` slots
+    disks: Vec<String>,
+    mode: Mode,
+    /// The stats from top to bottom
+    slots: Vec<Slot>,
+    /// NVIDIA GPUs are counted like `nvidia-smi` does, AMD ones in the order
+    /// of their card number
+    gpu_index: u32,
+}
+
+/// Windows names the interfaces after their kind and LibreHardwareMonitor
+/// names the sensors after the part of the hardware they're on
+#[cfg(target_os = "windows")]
+pub(crate) const DEFAULT_NAMES: (&str, &str) = ("Ethernet", "CPU Package");
+#[cfg(not(target_os = "windows"))]
+pub(crate) const DEFAULT_NAMES: (&str, &str) = ("eth0", "hwmon0 CPU Temperature");
+#[cfg(target_os = "windows")]
+const DEFAULT_DISK: &str = "C:\\";
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_DISK: &str = "/";
+
+impl Default for SysinfoSettings {
+    fn default() -> Self {
+        let (net_interface_name, sensor_name) = DEFAULT_NAMES;
+
+        Self {
+            polling_interval: 2000,
+            net_load_max: 100.0,
+            cpu_frequency_max: 7.0,
+            temperature_max: 100.0,
+            disk_io_max: 500.0,
+            net_interface_name: String::from(net_interface_name),
+            sensor_name: String::from(sensor_name),
+            disks: vec![String::from(DEFAULT_DISK)],
+            mode: Mode::Slots,
+            slots: vec![Slot::Cpu, Slot::Freq, Slot::Mem, Slot::Net, Slot::Temp],
+            gpu_index: 0,
+        }
+    }
+}
+
 #[doc(hidden)]
-#[allow(clippy::unnecessary_wraps)]
 fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
     info!("Registering Sysinfo display source.");
 
-    let refreshes = RefreshKind::new()
+    let mut settings: SysinfoSettings = settings::section(config, "sysinfo")?;
+    if settings.slots.is_empty() {
+        return Err(anyhow!("There are no sysinfo slots configured"));
+    }
+    if settings.slots.len() > FONTS.len() {
+        return Err(anyhow!(
+            "Only {} of the sysinfo slots fit on the screen, {} are configured",
+            FONTS.len(),
+            settings.slots.len()
+        ));
+    }
+    // Large text only has room for the first few slots, the others aren't loaded
+    if accessibility::large_text() {
+        settings.slots.truncate(LARGE_TEXT_ROWS);
+    }
+
+    let mut refreshes = RefreshKind::new()
         .with_cpu(CpuRefreshKind::everything())
         .with_components_list()
         .with_components()
         .with_networks_list()
         .with_networks()
         .with_memory();
-    let sys = System::new_with_specifics(refreshes);
-
-    let tick = tick();
-    let last_tick = 0;
-
-    let net_interface_name = config
-        .get_str("sysinfo.net_interface_name")
-        .unwrap_or("eth0".to_string());
-
-    if sys
-        .networks()
-        .iter()
-        .find(|(name, _)| **name == net_interface_name)
-        .is_none()
-    {
-        warn!("Couldn't find network interface `{}`", net_interface_name);
-        info!("Instead, found those interfaces:");
-        for (interface_name, _) in sys.networks() {
-            info!("\t{}", interface_name);
-        }
+    // The disks and especially the processes are only loaded if they're shown
+    if settings.slots.contains(&Slot::Disk) {
+        refreshes = refreshes.with_disks_list().with_disks();
     }
-
-    let sensor_name = config
-        .get_str("sysinfo.sensor_name")
-        .unwrap_or("hwmon0 CPU Temperature".to_string());
-
-    if sys
-        .components()
-        .iter()
-        .find(|component| component.label() == sensor_name)
-        .is_none()
-    {
-        warn!("Couldn't find sensor `{}`", sensor_name);
-        info!("Instead, found those sensors:");
-        for component in sys.components() {
-            info!("\t{:?}", component);
+    let disk_io = settings.slots.contains(&Slot::DiskIo);
+    let top = settings.mode == Mode::Processes;
+    if disk_io || top {
+        let mut processes = ProcessRefreshKind::new();
+        if disk_io {
+            processes = processes.with_disk_usage();
+        }
+        if top {
+            processes = processes.with_cpu();
         }
+        refreshes = refreshes.with_processes(processes);
     }
+    // Nothing is loaded yet, that only happens once the provider is shown
+    let sys = System::new();
+
+    let tick = tick();
+    let last_tick = 0;
 
     Ok(Box::new(Sysinfo {
         sys,
         tick,
         last_tick,
         refreshes,
-        polling_interval: config.get_int("sysinfo.polling_interval").unwrap_or(2000) as u64,
-        net_load_max: config.get_float("sysinfo.net_load_max").unwrap_or(100.0),
-        cpu_frequency_max: config.get_float("sysinfo.cpu_frequency_max").unwrap_or(7.0),
-        temperature_max: config.get_float("sysinfo.temperature_max").unwrap_or(100.0),
-        net_interface_name,
-        sensor_name,
+        polling_interval: settings.polling_interval,
+        net_load_max: settings.net_load_max,
+        cpu_frequency_max: settings.cpu_frequency_max,
+        temperature_max: settings.temperature_max,
+        disk_io_max: settings.disk_io_max,
+        net_interface_name: settings.net_interface_name,
+        sensor_name: settings.sensor_name,
+        disks: settings.disks,
+        mode: settings.mode,
+        slots: settings.slots,
+        processes: if accessibility::large_text() {
+            LARGE_TEXT_ROWS
+        } else {
+            TOP_PROCESSES
+        },
+        gpu_index: settings.gpu_index,
+        gpu: None,
+        gpu_stats: None,
+        #[cfg(all(target_os = "windows", feature = "lhm"))]
+        sensors: None,
     }))
 }
 
@@ -114,84 +468,412 @@ struct Sysinfo {
     net_load_max: f64,
     cpu_frequency_max: f64,
     temperature_max: f64,
+    disk_io_max: f64,
 
     net_interface_name: String,
     sensor_name: String,
+    disks: Vec<String>,
+
+    mode: Mode,
+    slots: Vec<Slot>,
+    /// How many processes are listed
+    processes: usize,
+
+    gpu_index: u32,
+    /// Only looked for if one of the slots shows the GPU
+    gpu: Option<GpuSource>,
+    gpu_stats: Option<GpuStats>,
+
+    /// The sensors of LibreHardwareMonitor or the thermal zones as sysinfo
+    /// doesn't find many of them on Windows
+    #[cfg(all(target_os = "windows", feature = "lhm"))]
+    sensors: Option<apex_windows::Sensors>,
 }
 
 impl Sysinfo {
-    pub fn render(&mut self) -> Result<FrameBuffer> {
-        self.poll();
-
-        let load = self.sys.global_cpu_info().cpu_usage() as f64;
-        let freq = self.sys.global_cpu_info().frequency() as f64 / 1000.0;
-        let mem_used = self.sys.used_memory() as f64 / pow(1024, 3) as f64;
-
-        let mut buffer = FrameBuffer::new();
+    /// Loads the system information for the first time, this takes a moment
+    /// so it's only done once the provider is shown
+    fn setup(&mut self) {
+        self.sys.refresh_specifics(self.refreshes);
 
-        self.render_stat(0, &mut buffer, format!("C: {:>4.0}%", load), load / 100.0)?;
-        self.render_stat(
-            1,
-            &mut buffer,
-            format!("F: {:>4.2}G", freq),
-            freq / self.cpu_frequency_max,
-        )?;
-        self.render_stat(
-            2,
-            &mut buffer,
-            format!("M: {:>4.1}G", mem_used),
-            self.sys.used_memory() as f64 / self.sys.total_memory() as f64,
-        )?;
-
-        if let Some(n) = self
+        if self
             .sys
             .networks()
             .iter()
             .find(|(name, _)| **name == self.net_interface_name)
-            .map(|t| t.1)
+            .is_none()
         {
-            let net_direction = if n.received() > n.transmitted() {
-                "I"
-            } else {
-                "O"
-            };
-
-            let (net_load, net_load_power, net_load_unit) = self.calculate_max_net_rate(n);
-            let mut adjusted_net_load = format!(
-                "{:.4}",
-                (net_load / 1024_f64.pow(net_load_power)).to_string()
+            warn!(
+                "Couldn't find network interface `{}`",
+                self.net_interface_name
             );
-
-            if adjusted_net_load.ends_with(".") {
-                adjusted_net_load = adjusted_net_load.replace(".", "");
+            info!("Instead, found those interfaces:");
+            for (interface_name, _) in self.sys.networks() {
+                info!("\t{}", interface_name);
             }
+        }
 
-            let _ = self.render_stat(
-                3,
-                &mut buffer,
-                format!(
-                    "{}: {:>4}{}",
-                    net_direction, adjusted_net_load, net_load_unit
+        #[cfg(all(target_os = "windows", feature = "lhm"))]
+        if self.sensors.is_none() {
+            let interval = Duration::from_millis(self.polling_interval);
+            match apex_windows::Sensors::spawn(interval) {
+                Ok(sensors) => self.sensors = Some(sensors),
+                Err(e) => warn!(
+                    "Couldn't read the sensors of LibreHardwareMonitor or the thermal zones, is \
+                     LibreHardwareMonitor running? {}",
+                    e
                 ),
-                net_load / (self.net_load_max * 1024_f64.pow(2)),
-            );
-        };
+            }
+        }
 
-        if let Some(c) = self
-            .sys
-            .components()
+        if self.slots.contains(&Slot::Disk) {
+            for mount_point in &self.disks {
+                if self.disk(mount_point).is_none() {
+                    warn!("Couldn't find a disk mounted at `{}`", mount_point);
+                    info!("Instead, found those mount points:");
+                    for disk in self.sys.disks() {
+                        info!("\t{}", disk.mount_point().display());
+                    }
+                }
+            }
+        }
+
+        if self.temperature().is_none() {
+            warn!("Couldn't find sensor `{}`", self.sensor_name);
+            info!("Instead, found those sensors:");
+            for component in self.sys.components() {
+                info!("\t{:?}", component);
+            }
+            #[cfg(all(target_os = "windows", feature = "lhm"))]
+            for sensor in self.sensors.iter().flat_map(apex_windows::Sensors::all) {
+                if sensor.sensor_type == "Temperature" {
+                    info!(
+                        "\t{} ({}): {}°C",
+                        sensor.name, sensor.identifier, sensor.value
+                    );
+                }
+            }
+        }
+
+        if self.gpu.is_none() && self.slots.iter().any(Slot::is_gpu) {
+            self.gpu = GpuSource::find(self.gpu_index);
+            // LibreHardwareMonitor also knows about the GPUs NVML doesn't
+            #[cfg(all(target_os = "windows", feature = "lhm"))]
+            if self.gpu.is_none() {
+                if let Some(sensors) = &self.sensors {
+                    self.gpu = lhm::gpus(&sensors.all())
+                        .into_iter()
+                        .nth(self.gpu_index as usize)
+                        .map(|gpu| GpuSource::Lhm(sensors.clone(), gpu));
+                }
+            }
+            if self.gpu.is_none() {
+                warn!(
+                    "Couldn't find GPU {}, the GPU stats stay empty",
+                    self.gpu_index
+                );
+            }
+        }
+    }
+
+    pub fn render(&mut self) -> Result<FrameBuffer> {
+        self.poll();
+
+        let mut buffer = FrameBuffer::new();
+        match self.mode {
+            Mode::Slots => {
+                for (row, slot) in self.slots.iter().enumerate() {
+                    self.render_slot(row as i32, *slot, &mut buffer)?;
+                }
+            }
+            Mode::Cores => self.render_cores(&mut buffer)?,
+            Mode::Processes => self.render_processes(&mut buffer)?,
+        }
+
+        Ok(buffer)
+    }
+
+    /// Lists the processes using the most CPU with their usage on the right,
+    /// like `top` a process using several cores goes above 100%
+    fn render_processes(&self, buffer: &mut FrameBuffer) -> Result<()> {
+        let mut processes = self.sys.processes().values().collect::<Vec<_>>();
+        processes.sort_by(|a, b| b.cpu_usage().total_cmp(&a.cpu_usage()));
+
+        let font = FONTS[self.processes - 1];
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let right = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+        let row_height = 40 / self.processes as i32;
+        // The name is cut off where the usage starts
+        let name_width = 128 / font.character_size.width as usize - 6;
+
+        for (row, process) in processes.iter().take(self.processes).enumerate() {
+            let y = row as i32 * row_height + (row_height - font.character_size.height as i32) / 2;
+            let name = process.name().chars().take(name_width).collect::<String>();
+
+            Text::with_baseline(&name, Point::new(0, y), style, Baseline::Top).draw(buffer)?;
+            Text::with_text_style(
+                &format!("{:.0}%", process.cpu_usage()),
+                Point::new(127, y),
+                style,
+                right,
+            )
+            .draw(buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws a thin bar for every core from top to bottom, many cores wrap
+    /// into a second column
+    fn render_cores(&self, buffer: &mut FrameBuffer) -> Result<()> {
+        let cpus = self.sys.cpus();
+        let shown = cpus.len().min(MAX_CORES);
+        if shown == 0 {
+            return Ok(());
+        }
+
+        let columns = if shown > CORES_PER_COLUMN { 2 } else { 1 };
+        let rows = ((shown + columns - 1) / columns) as i32;
+        // There's a gap between the bars unless they're only a pixel tall
+        let pitch = 40 / rows;
+        let height = (pitch - 1).max(1);
+        let width = (128 - (columns as i32 - 1) * 2) / columns as i32;
+        let border_style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        let fill_style = PrimitiveStyle::with_fill(BinaryColor::On);
+
+        for (index, cpu) in cpus.iter().take(shown).enumerate() {
+            let (column, row) = (index as i32 / rows, index as i32 % rows);
+            let top_left = Point::new(column * (width + 2), row * pitch);
+            let load = (cpu.cpu_usage() / 100.0).clamp(0.0, 1.0);
+
+            // Bars that are too thin for a border are only filled
+            if height > 2 {
+                Rectangle::new(top_left, Size::new(width as u32, height as u32))
+                    .into_styled(border_style)
+                    .draw(buffer)?;
+            }
+            Rectangle::new(
+                top_left,
+                Size::new((load * width as f32).round() as u32, height as u32),
+            )
+            .into_styled(fill_style)
+            .draw(buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Stats that aren't available, e.g. because the sensor doesn't exist,
+    /// leave their row empty
+    fn render_slot(&self, row: i32, slot: Slot, buffer: &mut FrameBuffer) -> Result<()> {
+        match slot {
+            Slot::Cpu => {
+                let load = self.sys.global_cpu_info().cpu_usage() as f64;
+                self.render_stat(row, buffer, format!("C: {:>4.0}%", load), load / 100.0)?;
+            }
+            Slot::Freq => {
+                let freq = self.frequency() / 1000.0;
+                self.render_stat(
+                    row,
+                    buffer,
+                    format!("F: {:>4.2}G", freq),
+                    freq / self.cpu_frequency_max,
+                )?;
+            }
+            Slot::Mem => {
+                let mem_used = self.sys.used_memory() as f64 / pow(1024, 3) as f64;
+                self.render_stat(
+                    row,
+                    buffer,
+                    format!("M: {:>4.1}G", mem_used),
+                    self.sys.used_memory() as f64 / self.sys.total_memory() as f64,
+                )?;
+            }
+            Slot::Swap => {
+                // There's nothing to show if swap is turned off
+                if self.sys.total_swap() > 0 {
+                    let swap_used = self.sys.used_swap() as f64 / pow(1024, 3) as f64;
+                    self.render_stat(
+                        row,
+                        buffer,
+                        format!("S: {:>4.1}G", swap_used),
+                        self.sys.used_swap() as f64 / self.sys.total_swap() as f64,
+                    )?;
+                }
+            }
+            Slot::Net => {
+                if let Some(n) = self
+                    .sys
+                    .networks()
+                    .iter()
+                    .find(|(name, _)| **name == self.net_interface_name)
+                    .map(|t| t.1)
+                {
+                    let net_direction = if n.received() > n.transmitted() {
+                        "I"
+                    } else {
+                        "O"
+                    };
+
+                    let (net_load, net_load_power, net_load_unit) = self.calculate_max_net_rate(n);
+                    let mut adjusted_net_load = format!(
+                        "{:.4}",
+                        (net_load / 1024_f64.pow(net_load_power)).to_string()
+                    );
+
+                    if adjusted_net_load.ends_with(".") {
+                        adjusted_net_load = adjusted_net_load.replace(".", "");
+                    }
+
+                    let _ = self.render_stat(
+                        row,
+                        buffer,
+                        format!(
+                            "{}: {:>4}{}",
+                            net_direction, adjusted_net_load, net_load_unit
+                        ),
+                        net_load / (self.net_load_max * 1024_f64.pow(2)),
+                    );
+                };
+            }
+            Slot::Temp => {
+                if let Some(temperature) = self.temperature() {
+                    let _ = self.render_stat(
+                        row,
+                        buffer,
+                        format!("T: {:>4.1}C", temperature),
+                        temperature as f64 / self.temperature_max,
+                    );
+                }
+            }
+            Slot::Gpu => {
+                if let Some(gpu) = &self.gpu_stats {
+                    let _ = self.render_stat(
+                        row,
+                        buffer,
+                        format!("G: {:>4}%", gpu.load),
+                        gpu.load as f64 / 100.0,
+                    );
+                }
+            }
+            Slot::Vram => {
+                if let Some(gpu) = &self.gpu_stats {
+                    let _ = self.render_stat(
+                        row,
+                        buffer,
+                        format!("V: {:>4.1}G", gpu.vram_used as f64 / pow(1024, 3) as f64),
+                        gpu.vram_used as f64 / gpu.vram_total as f64,
+                    );
+                }
+            }
+            Slot::GpuTemp => {
+                if let Some(gpu) = &self.gpu_stats {
+                    let _ = self.render_stat(
+                        row,
+                        buffer,
+                        format!("GT:{:>4.1}C", gpu.temperature),
+                        gpu.temperature as f64 / self.temperature_max,
+                    );
+                }
+            }
+            Slot::Load => {
+                // The bar is full once every core has something to do
+                let load = self.sys.load_average();
+                let _ = self.render_stat(
+                    row,
+                    buffer,
+                    format!("L:{:.1} {:.1} {:.1}", load.one, load.five, load.fifteen),
+                    load.one / self.sys.cpus().len().max(1) as f64,
+                );
+            }
+            Slot::Disk => {
+                // The first `disk` slot shows the first mount point and so on
+                let index = self.slots[..row as usize]
+                    .iter()
+                    .filter(|slot| **slot == Slot::Disk)
+                    .count();
+                if let Some(disk) = self
+                    .disks
+                    .get(index)
+                    .and_then(|mount_point| self.disk(mount_point))
+                {
+                    let used = disk.total_space().saturating_sub(disk.available_space());
+                    let _ = self.render_stat(
+                        row,
+                        buffer,
+                        format!("D: {:>4.0}G", used as f64 / pow(1024, 3) as f64),
+                        used as f64 / disk.total_space() as f64,
+                    );
+                }
+            }
+            Slot::DiskIo => {
+                let (read, written) = self
+                    .sys
+                    .processes()
+                    .values()
+                    .map(|process| process.disk_usage())
+                    .fold((0, 0), |(read, written), usage| {
+                        (read + usage.read_bytes, written + usage.written_bytes)
+                    });
+                let (direction, bytes) = if read > written {
+                    ("R", read)
+                } else {
+                    ("W", written)
+                };
+
+                let seconds = (self.tick - self.last_tick).max(1) as f64 / 1000.0;
+                let rate = bytes as f64 / seconds / pow(1024, 2) as f64;
+                let _ = self.render_stat(
+                    row,
+                    buffer,
+                    format!("{}: {:>4.0}M", direction, rate),
+                    rate / self.disk_io_max,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disk(&self, mount_point: &str) -> Option<&Disk> {
+        self.sys
+            .disks()
             .iter()
-            .find(|component| component.label() == self.sensor_name)
+            .find(|disk| disk.mount_point() == Path::new(mount_point))
+    }
+
+    /// The frequency of the CPU in MHz
+    fn frequency(&self) -> f64 {
+        let frequency = self.sys.global_cpu_info().frequency() as f64;
+
+        #[cfg(target_os = "linux")]
+        if frequency == 0.0 {
+            return cpufreq().unwrap_or_default();
+        }
+
+        frequency
+    }
+
+    /// Reads the configured sensor, LibreHardwareMonitor is asked first if
+    /// it's available
+    fn temperature(&self) -> Option<f32> {
+        #[cfg(all(target_os = "windows", feature = "lhm"))]
+        if let Some(sensor) = self
+            .sensors
+            .as_ref()
+            .and_then(|sensors| sensors.find(&self.sensor_name))
         {
-            let _ = self.render_stat(
-                4,
-                &mut buffer,
-                format!("T: {:>4.1}C", c.temperature()),
-                c.temperature() as f64 / self.temperature_max,
-            );
+            return Some(sensor.value);
         }
 
-        Ok(buffer)
+        self.sys
+            .components()
+            .iter()
+            .find(|component| component.label() == self.sensor_name)
+            .map(|component| component.temperature())
     }
 
     fn calculate_max_net_rate(&self, net: &NetworkData) -> (f64, i32, &str) {
@@ -209,6 +891,13 @@ impl Sysinfo {
     fn poll(&mut self) {
         self.sys.refresh_specifics(self.refreshes);
 
+        if let Some(gpu) = &self.gpu {
+            self.gpu_stats = gpu
+                .read()
+                .map_err(|e| debug!("Couldn't read the GPU stats: {}", e))
+                .ok();
+        }
+
         self.last_tick = self.tick;
         self.tick = tick();
     }
@@ -220,10 +909,14 @@ impl Sysinfo {
         text: String,
         fill: f64,
     ) -> Result<()> {
-        let style = MonoTextStyle::new(&iso_8859_15::FONT_4X6, BinaryColor::On);
+        let font = FONTS[self.slots.len() - 1];
+        let style = MonoTextStyle::new(font, BinaryColor::On);
         let metrics = style.measure_string(&text, Point::zero(), Baseline::Top);
 
-        let slot_y = slot * 8 + 1;
+        // The bar is a pixel taller than the text, it's centered in the row
+        let height = font.character_size.height as i32;
+        let row_height = 40 / self.slots.len() as i32;
+        let slot_y = slot * row_height + (row_height - height) / 2;
 
         Text::with_baseline(&text, Point::new(0, slot_y), style, Baseline::Top).draw(buffer)?;
 
@@ -236,13 +929,16 @@ impl Sysinfo {
             (fill * (127 - bar_start) as f64).floor() as i32
         };
 
-        Rectangle::with_corners(Point::new(bar_start, slot_y), Point::new(127, slot_y + 6))
-            .into_styled(border_style)
-            .draw(buffer)?;
+        Rectangle::with_corners(
+            Point::new(bar_start, slot_y),
+            Point::new(127, slot_y + height),
+        )
+        .into_styled(border_style)
+        .draw(buffer)?;
 
         Rectangle::with_corners(
             Point::new(bar_start + 1, slot_y + 1),
-            Point::new(bar_start + fill_width, slot_y + 5),
+            Point::new(bar_start + fill_width, slot_y + height - 1),
         )
         .into_styled(fill_style)
         .draw(buffer)?;
@@ -259,6 +955,7 @@ impl ContentProvider for Sysinfo {
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
         Ok(try_stream! {
+            self.setup();
             loop {
                 if let Ok(image) = self.render() {
                     yield image;
@@ -0,0 +1,364 @@
+use crate::{
+    render::{
+        accessibility::{self, LARGE_TEXT_ROWS},
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider},
+        poller::{Refresh, SharedPoller},
+        scheduler::{ContentWrapper, NotificationWrapper},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Circle, Primitive, PrimitiveStyle},
+    text::{renderer::TextRenderer, Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{future::BoxFuture, Stream};
+use log::{debug, info};
+use reqwest::{Client, ClientBuilder, StatusCode};
+use serde::Deserialize;
+use std::collections::HashSet;
+use tokio::{sync::watch, time::Duration};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+const TOKEN_URL: &str = "https://id.twitch.tv/oauth2/token";
+const STREAMS_URL: &str = "https://api.twitch.tv/helix/streams";
+
+/// The maximum amount of rows that fit on the display with the font we're
+/// using
+const MAX_ENTRIES: usize = 4;
+
+/// The dot in front of live channels, the names start right after it
+const NAME_LEFT: i32 = 9;
+
+/// The channels that are live, `None` until Twitch answered for the first time
+type Live = Option<Vec<LiveStream>>;
+
+/// The screen and the notifications ask Twitch together
+static POLLER: SharedPoller<TwitchSettings, Live> = SharedPoller::new();
+
+/// The settings of the `[twitch]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TwitchSettings {
+    /// The login names of the channels, as they appear in the URL
+    channels: Vec<String>,
+    /// The credentials of an application registered on dev.twitch.tv
+    client_id: String,
+    client_secret: String,
+    /// In seconds
+    polling_interval: u64,
+}
+
+impl Default for TwitchSettings {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            polling_interval: 60,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Twitch display source.");
+
+    let settings: TwitchSettings = settings::section(config, "twitch")?;
+    let max_entries = if accessibility::large_text() {
+        LARGE_TEXT_ROWS
+    } else {
+        MAX_ENTRIES
+    };
+
+    Ok(Box::new(Twitch {
+        live: subscribe(&settings)?,
+        entries: max_entries,
+    }))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering Twitch notification source.");
+
+    let live = subscribe(&settings::section(config, "twitch")?)?;
+
+    Ok(Box::new(TwitchNotifier { live }))
+}
+
+fn subscribe(settings: &TwitchSettings) -> Result<watch::Receiver<Live>> {
+    let interval = Duration::from_secs(settings.polling_interval.max(1));
+    POLLER.subscribe(settings, None, interval, || Helix::new(settings.clone()))
+}
+
+#[derive(Debug, Deserialize)]
+struct Token {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Streams {
+    data: Vec<LiveStream>,
+}
+
+/// A channel that's live right now, offline channels aren't part of the
+/// answer
+#[derive(Debug, Clone, Deserialize)]
+struct LiveStream {
+    user_login: String,
+    user_name: String,
+    game_name: String,
+    title: String,
+    viewer_count: u64,
+}
+
+/// Talks to the Helix API of Twitch with an app access token
+struct Helix {
+    client: Client,
+    settings: TwitchSettings,
+    /// Fetched on the first request and again once Twitch doesn't accept it
+    /// anymore
+    token: Option<String>,
+}
+
+impl Helix {
+    fn new(settings: TwitchSettings) -> Result<Self> {
+        Ok(Self {
+            client: ClientBuilder::new().user_agent(APP_USER_AGENT).build()?,
+            settings,
+            token: None,
+        })
+    }
+
+    async fn token(&mut self) -> Result<String> {
+        if let Some(token) = &self.token {
+            return Ok(token.clone());
+        }
+
+        let token = self
+            .client
+            .post(TOKEN_URL)
+            .query(&[
+                ("client_id", self.settings.client_id.as_str()),
+                ("client_secret", self.settings.client_secret.as_str()),
+                ("grant_type", "client_credentials"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Token>()
+            .await?
+            .access_token;
+
+        self.token = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Returns the channels of the settings that are live, the most watched
+    /// come first
+    async fn live(&mut self) -> Result<Vec<LiveStream>> {
+        if self.settings.channels.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let token = self.token().await?;
+        let logins = self
+            .settings
+            .channels
+            .iter()
+            .map(|channel| ("user_login", channel.to_lowercase()))
+            .collect::<Vec<_>>();
+
+        let response = self
+            .client
+            .get(STREAMS_URL)
+            .header("Client-Id", &self.settings.client_id)
+            .bearer_auth(token)
+            .query(&logins)
+            .send()
+            .await?;
+
+        // App access tokens expire after a while, the next poll gets a new one
+        if response.status() == StatusCode::UNAUTHORIZED {
+            self.token = None;
+            return Err(anyhow!("Twitch didn't accept the access token anymore"));
+        }
+
+        let mut live = response.error_for_status()?.json::<Streams>().await?.data;
+        live.sort_by(|a, b| b.viewer_count.cmp(&a.viewer_count));
+        Ok(live)
+    }
+}
+
+impl Refresh for Helix {
+    type Output = Live;
+
+    // The last answer stays if Twitch can't be reached
+    fn refresh(&mut self) -> BoxFuture<'_, Option<Live>> {
+        Box::pin(async move {
+            match self.live().await {
+                Ok(streams) => Some(Some(streams)),
+                Err(e) => {
+                    debug!("Couldn't check who's live on Twitch: {}", e);
+                    None
+                }
+            }
+        })
+    }
+}
+
+/// Shortens large numbers of viewers, e.g. `12.3k`
+fn format_viewers(viewers: u64) -> String {
+    match viewers {
+        0..=9_999 => viewers.to_string(),
+        10_000..=999_999 => format!("{:.1}k", viewers as f64 / 1_000.0),
+        _ => format!("{:.1}M", viewers as f64 / 1_000_000.0),
+    }
+}
+
+struct Twitch {
+    live: watch::Receiver<Live>,
+    entries: usize,
+}
+
+impl Twitch {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let font = if accessibility::large_text() {
+            &iso_8859_15::FONT_9X15
+        } else {
+            &iso_8859_15::FONT_6X10
+        };
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let char_width = style.font.character_size.width as i32;
+        let char_height = style.font.character_size.height as i32;
+
+        let live = self.live.borrow();
+        let message = match &*live {
+            None => Some("Connecting"),
+            Some(live) if live.is_empty() => Some("Nobody is live"),
+            Some(_) => None,
+        };
+        if let Some(message) = message {
+            let centered = TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Middle)
+                .build();
+            Text::with_text_style(message, Point::new(64, 20), style, centered)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        let row_height = 40 / self.entries as i32;
+        let live = live.as_deref().unwrap_or_default();
+
+        for (row, stream) in live.iter().take(self.entries).enumerate() {
+            let y = row as i32 * row_height + (row_height - char_height) / 2;
+
+            Circle::new(Point::new(0, y + (char_height - 6) / 2), 6)
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(&mut buffer)?;
+
+            let viewers = format_viewers(stream.viewer_count);
+            let metrics = style.measure_string(&viewers, Point::zero(), Baseline::Top);
+            let viewers_x = 128 - metrics.bounding_box.size.width as i32;
+            Text::with_baseline(&viewers, Point::new(viewers_x, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            // Cut off the name so it doesn't run into the viewers
+            let max_chars = ((viewers_x - NAME_LEFT - char_width) / char_width).max(0) as usize;
+            let name = stream.user_name.chars().take(max_chars).collect::<String>();
+            Text::with_baseline(&name, Point::new(NAME_LEFT, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Twitch {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        Ok(try_stream! {
+            loop {
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                if self.live.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "twitch"
+    }
+}
+
+/// Tells when one of the channels goes live
+struct TwitchNotifier {
+    live: watch::Receiver<Live>,
+}
+
+impl NotificationProvider for TwitchNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        Ok(try_stream! {
+            // Channels that were already live when we started aren't announced
+            let logins = |live: &[LiveStream]| -> HashSet<String> {
+                live.iter().map(|stream| stream.user_login.clone()).collect()
+            };
+            let mut previous = self.live.borrow().as_deref().map(logins);
+
+            while self.live.changed().await.is_ok() {
+                let live = match self.live.borrow().clone() {
+                    Some(live) => live,
+                    None => continue,
+                };
+
+                if let Some(previous) = &previous {
+                    for stream in live.iter().filter(|stream| !previous.contains(&stream.user_login)) {
+                        let title = format!("{} is live", stream.user_name);
+                        let content = if stream.game_name.is_empty() {
+                            stream.title.clone()
+                        } else {
+                            format!("{}: {}", stream.game_name, stream.title)
+                        };
+                        if let Ok(notification) = NotificationBuilder::new()
+                            .with_title(&title)
+                            .with_content(content)
+                            .build()
+                        {
+                            yield notification;
+                        }
+                    }
+                }
+
+                previous = Some(logins(&live));
+            }
+        })
+    }
+}
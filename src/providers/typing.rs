@@ -0,0 +1,259 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper},
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use chrono::{Local, NaiveDate, Timelike};
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use tokio::{
+    sync::{mpsc, watch},
+    time,
+    time::{Duration, Instant, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// A word is five keystrokes, spaces included
+const WORD_LENGTH: usize = 5;
+/// Where the bars of the hours start and how wide each of them is, 24 of them
+/// fill the width of the screen
+const HISTOGRAM_LEFT: i32 = 4;
+const BAR_WIDTH: i32 = 5;
+/// The histogram takes the rest of the screen below the text
+const HISTOGRAM_TOP: i32 = 12;
+const HISTOGRAM_HEIGHT: u32 = 28;
+
+/// The settings of the `[typing]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TypingSettings {
+    /// The words per minute are estimated from the keystrokes of this many
+    /// seconds
+    window: u64,
+}
+
+impl Default for TypingSettings {
+    fn default() -> Self {
+        Self { window: 60 }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering Typing display source.");
+
+    let settings: TypingSettings = settings::section(config, "typing")?;
+    if settings.window == 0 {
+        return Err(anyhow!(
+            "Invalid settings in [typing]: The `window` has to be at least a second"
+        ));
+    }
+
+    // Nothing is read from the keyboards unless the screen is turned on
+    let stats = if settings::common(config, "typing")?.enabled {
+        count(Duration::from_secs(settings.window))?
+    } else {
+        watch::channel(Stats::default()).1
+    };
+
+    Ok(Box::new(Typing { stats }))
+}
+
+/// What's shown on the screen
+#[derive(Debug, Clone, Default)]
+struct Stats {
+    words_per_minute: usize,
+    /// The keystrokes of today
+    total: u32,
+    /// The keystrokes of today per hour
+    hours: [u32; 24],
+}
+
+/// The keystrokes are counted all the time, not just while the screen is
+/// shown, and the numbers are published to the screen once a second
+fn count(window: Duration) -> Result<watch::Receiver<Stats>> {
+    let mut keystrokes = keystrokes()?;
+    let (tx, rx) = watch::channel(Stats::default());
+
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let mut recent = VecDeque::new();
+        let mut hours = [0; 24];
+        let mut today = Local::now().naive_local().date();
+
+        loop {
+            tokio::select! {
+                // The provider is gone, e.g. because the settings were reloaded
+                _ = tx.closed() => break,
+                keystroke = keystrokes.recv() => {
+                    if keystroke.is_none() {
+                        warn!("Lost the keyboards, the keystrokes aren't counted anymore");
+                        break;
+                    }
+                    recent.push_back(Instant::now());
+                    hours[Local::now().hour() as usize] += 1;
+                },
+                _ = interval.tick() => {
+                    while recent.front().map_or(false, |t| t.elapsed() > window) {
+                        recent.pop_front();
+                    }
+                    if roll_over(&mut today) {
+                        hours = [0; 24];
+                    }
+
+                    // Scaled to a minute for windows of any length
+                    let per_minute = recent.len() as u64 * 60 / window.as_secs();
+                    tx.send_replace(Stats {
+                        words_per_minute: per_minute as usize / WORD_LENGTH,
+                        total: hours.iter().sum(),
+                        hours,
+                    });
+                },
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+/// Moves `today` to the current date, `true` if it wasn't already
+fn roll_over(today: &mut NaiveDate) -> bool {
+    let now = Local::now().naive_local().date();
+    let changed = now != *today;
+    *today = now;
+    changed
+}
+
+/// Opens every device that has letter keys, that needs read access to
+/// `/dev/input`, usually by being in the `input` group
+#[cfg(target_os = "linux")]
+fn keystrokes() -> Result<mpsc::UnboundedReceiver<()>> {
+    use evdev::{EventType, Key};
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut keyboards = 0;
+
+    // Devices that can't be opened are left out by `enumerate`
+    for (path, device) in evdev::enumerate() {
+        if !device
+            .supported_keys()
+            .map_or(false, |keys| keys.contains(Key::KEY_A))
+        {
+            continue;
+        }
+
+        let mut events = match device.into_event_stream() {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("Couldn't read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = events.next_event().await {
+                // 1 is a press, 0 a release and 2 the key repeating while it's held
+                if event.event_type() == EventType::KEY
+                    && event.value() == 1
+                    && tx.send(()).is_err()
+                {
+                    break;
+                }
+            }
+        });
+        keyboards += 1;
+    }
+
+    if keyboards == 0 {
+        return Err(anyhow!(
+            "Couldn't read any keyboard, the user has to be in the `input` group"
+        ));
+    }
+
+    Ok(rx)
+}
+
+#[cfg(target_os = "windows")]
+fn keystrokes() -> Result<mpsc::UnboundedReceiver<()>> {
+    apex_windows::keystrokes()
+}
+
+/// Shows how fast and how much was typed, with a histogram of the hours of
+/// today below
+struct Typing {
+    stats: watch::Receiver<Stats>,
+}
+
+impl Typing {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let stats = self.stats.borrow().clone();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        let wpm = format!("{} WPM", stats.words_per_minute);
+        Text::with_baseline(&wpm, Point::zero(), style, Baseline::Top).draw(&mut buffer)?;
+
+        let right = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+        let total = format!("{} keys", stats.total);
+        Text::with_text_style(&total, Point::new(127, 0), style, right).draw(&mut buffer)?;
+
+        // Scaled to the busiest hour, there's no bar for hours without a keystroke
+        let busiest = stats.hours.iter().copied().max().unwrap_or_default().max(1);
+        let bar = PrimitiveStyle::with_fill(BinaryColor::On);
+        for (hour, keystrokes) in stats.hours.iter().enumerate() {
+            let height = (*keystrokes as u64 * u64::from(HISTOGRAM_HEIGHT) / u64::from(busiest))
+                .max(u64::from(*keystrokes > 0)) as u32;
+            let x = HISTOGRAM_LEFT + hour as i32 * BAR_WIDTH;
+            let y = HISTOGRAM_TOP + (HISTOGRAM_HEIGHT - height) as i32;
+            Rectangle::new(Point::new(x, y), Size::new(BAR_WIDTH as u32 - 1, height))
+                .into_styled(bar)
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Typing {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "typing"
+    }
+}
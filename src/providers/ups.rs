@@ -0,0 +1,320 @@
+use crate::{
+    render::{
+        display::ContentProvider,
+        notifications::{Notification, NotificationBuilder, NotificationProvider, Urgency},
+        scheduler::{ContentWrapper, NotificationWrapper},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{debug, info};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+#[doc(hidden)]
+pub static NOTIFICATION_INIT: fn(&Config) -> Result<Box<dyn NotificationWrapper>> =
+    register_notification_callback;
+
+/// The bars start in the middle of the screen
+const BAR_LEFT: i32 = 64;
+
+/// The settings of the `[ups]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UpsSettings {
+    /// Where upsd is listening
+    host: String,
+    port: u16,
+    /// The name of the UPS in the configuration of NUT, `upsc -l` lists them
+    name: String,
+    /// In milliseconds
+    polling_interval: u64,
+}
+
+impl Default for UpsSettings {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 3493,
+            name: String::from("ups"),
+            polling_interval: 5000,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering UPS display source.");
+
+    let settings: UpsSettings = settings::section(config, "ups")?;
+
+    Ok(Box::new(Ups {
+        settings,
+        reading: None,
+    }))
+}
+
+#[doc(hidden)]
+fn register_notification_callback(config: &Config) -> Result<Box<dyn NotificationWrapper>> {
+    info!("Registering UPS notification source.");
+
+    let settings = settings::section(config, "ups")?;
+
+    Ok(Box::new(UpsNotifier { settings }))
+}
+
+/// What upsd knows about the UPS, the charge and the load aren't reported by
+/// every model
+#[derive(Debug, Clone, Default)]
+struct Reading {
+    /// In percent
+    charge: Option<f32>,
+    /// In percent
+    load: Option<f32>,
+    on_battery: bool,
+    low_battery: bool,
+}
+
+impl Reading {
+    fn state(&self) -> &'static str {
+        match (self.on_battery, self.low_battery) {
+            (_, true) => "Low battery",
+            (true, false) => "On battery",
+            (false, false) => "Online",
+        }
+    }
+}
+
+/// Reads the value out of an answer like `VAR ups battery.charge "100"`
+fn parse_var(line: &str, ups: &str, var: &str) -> Result<String> {
+    if let Some(error) = line.strip_prefix("ERR ") {
+        return Err(anyhow!("upsd couldn't read `{}`: {}", var, error));
+    }
+
+    let value = line
+        .strip_prefix(&format!("VAR {} {} ", ups, var))
+        .ok_or_else(|| anyhow!("Unexpected answer from upsd: {}", line))?;
+
+    Ok(value
+        .trim_matches('"')
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\"))
+}
+
+async fn get_var(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+    writer: &mut OwnedWriteHalf,
+    ups: &str,
+    var: &str,
+) -> Result<String> {
+    writer
+        .write_all(format!("GET VAR {} {}\n", ups, var).as_bytes())
+        .await?;
+    let line = lines
+        .next_line()
+        .await?
+        .ok_or_else(|| anyhow!("upsd closed the connection"))?;
+    parse_var(&line, ups, var)
+}
+
+/// Asks upsd for the state of the UPS using the network protocol of NUT.
+/// Reading variables doesn't need a login so every poll gets a connection of
+/// its own, that way a restart of upsd doesn't need any special handling.
+async fn query(settings: &UpsSettings) -> Result<Reading> {
+    let stream = TcpStream::connect((settings.host.as_str(), settings.port)).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let ups = &settings.name;
+
+    // The status is a list of flags, e.g. `OB DISCHRG` or `OL CHRG`
+    let status = get_var(&mut lines, &mut writer, ups, "ups.status").await?;
+    let flags = status.split_whitespace().collect::<Vec<_>>();
+    let charge = get_var(&mut lines, &mut writer, ups, "battery.charge").await;
+    let load = get_var(&mut lines, &mut writer, ups, "ups.load").await;
+
+    let _ = writer.write_all(b"LOGOUT\n").await;
+
+    Ok(Reading {
+        charge: charge.ok().and_then(|charge| charge.parse().ok()),
+        load: load.ok().and_then(|load| load.parse().ok()),
+        on_battery: flags.contains(&"OB"),
+        low_battery: flags.contains(&"LB"),
+    })
+}
+
+struct Ups {
+    settings: UpsSettings,
+    /// `None` if upsd couldn't be reached
+    reading: Option<Reading>,
+}
+
+impl Ups {
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_5X8, BinaryColor::On);
+
+        let reading = match &self.reading {
+            Some(reading) => reading,
+            None => {
+                let centered = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                Text::with_text_style("upsd unreachable", Point::new(64, 20), style, centered)
+                    .draw(&mut buffer)?;
+                return Ok(buffer);
+            }
+        };
+
+        let name = self.settings.name.chars().take(10).collect::<String>();
+        Text::with_baseline(&name, Point::new(1, 1), style, Baseline::Top).draw(&mut buffer)?;
+        let right = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(reading.state(), Point::new(127, 1), style, right)
+            .draw(&mut buffer)?;
+
+        let rows = [("Charge", reading.charge, 15), ("Load", reading.load, 28)];
+        for (label, value, top) in rows {
+            let text = match value {
+                Some(value) => format!("{} {:.0}%", label, value),
+                None => format!("{} ?", label),
+            };
+            Text::with_baseline(&text, Point::new(1, top), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            let outline = Rectangle::new(Point::new(BAR_LEFT, top), Size::new(64, 8));
+            outline
+                .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                .draw(&mut buffer)?;
+            let filled = (value.unwrap_or(0.0) / 100.0).clamp(0.0, 1.0) * 62.0;
+            Rectangle::new(
+                Point::new(BAR_LEFT + 1, top + 1),
+                Size::new(filled.round() as u32, 6),
+            )
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for Ups {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval =
+            time::interval(Duration::from_millis(self.settings.polling_interval.max(1)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+                self.reading = match query(&self.settings).await {
+                    Ok(reading) => Some(reading),
+                    Err(e) => {
+                        debug!("Couldn't read the state of the UPS: {}", e);
+                        None
+                    }
+                };
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "ups"
+    }
+}
+
+/// Tells about power outages no matter which screen is shown
+struct UpsNotifier {
+    settings: UpsSettings,
+}
+
+/// Describes how the state changed between two readings, `None` if it's not
+/// worth a notification
+fn describe(previous: &Reading, current: &Reading) -> Option<(&'static str, Urgency)> {
+    if current.low_battery && !previous.low_battery {
+        return Some(("Battery low", Urgency::Critical));
+    }
+
+    match (previous.on_battery, current.on_battery) {
+        (false, true) => Some(("On battery", Urgency::Critical)),
+        (true, false) => Some(("Power is back", Urgency::Normal)),
+        _ => None,
+    }
+}
+
+impl NotificationProvider for UpsNotifier {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        let mut interval =
+            time::interval(Duration::from_millis(self.settings.polling_interval.max(1)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        Ok(try_stream! {
+            // Starting up while the UPS is already on battery is worth a notification as well
+            let mut previous = Reading::default();
+
+            loop {
+                interval.tick().await;
+                // upsd being gone is already shown by the screen, there's nothing to announce
+                let current = match query(&self.settings).await {
+                    Ok(reading) => reading,
+                    Err(_) => continue,
+                };
+
+                if let Some((title, urgency)) = describe(&previous, &current) {
+                    let content = match current.charge {
+                        Some(charge) => format!("Charge {:.0}%", charge),
+                        None => String::from("UPS"),
+                    };
+                    if let Ok(notification) = NotificationBuilder::new()
+                        .with_title(title)
+                        .with_content(content)
+                        .with_urgency(urgency)
+                        .build()
+                    {
+                        yield notification;
+                    }
+                }
+
+                previous = current;
+            }
+        })
+    }
+}
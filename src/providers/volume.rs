@@ -0,0 +1,206 @@
+use crate::{
+    pulse::{self, Event},
+    render::{
+        overlay::{Overlay, OverlayProvider},
+        scheduler::OverlayWrapper,
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::{BlendMode, FrameBuffer};
+use async_stream::{stream, try_stream};
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{Stream, StreamExt};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use tokio::{time, time::Duration};
+
+#[doc(hidden)]
+pub static OVERLAY_INIT: fn(&Config) -> Result<Box<dyn OverlayWrapper>> = register_callback;
+
+/// How long to wait before asking PulseAudio again after it went away
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The box the bar is drawn in, the page stays visible around it
+const BOX: Rectangle = Rectangle::new(Point::new(4, 9), Size::new(120, 22));
+
+/// The bar inside of the box, the percentage is drawn to the right of it
+const BAR: Rectangle = Rectangle::new(Point::new(8, 15), Size::new(80, 10));
+
+/// The settings of the `[volume]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct VolumeSettings {
+    /// How long the bar is shown after the last change, in milliseconds
+    duration: u64,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self { duration: 1500 }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn OverlayWrapper>> {
+    info!("Registering Volume overlay source.");
+
+    // Overlay sources aren't filtered by the scheduler
+    let settings = if settings::common(config, "volume")?.enabled {
+        Some(settings::section(config, "volume")?)
+    } else {
+        None
+    };
+
+    Ok(Box::new(Volume { settings }))
+}
+
+/// The volume of the default sink in percent and whether it's muted
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Level {
+    volume: u32,
+    muted: bool,
+}
+
+async fn level() -> Result<Level> {
+    Ok(Level {
+        volume: pulse::sink_volume().await?,
+        muted: pulse::sink_muted().await?,
+    })
+}
+
+/// Asks PulseAudio again after something changed, `None` if the volume
+/// stayed the same, e.g. because another sink changed
+async fn changed(last: Level) -> Option<Level> {
+    match level().await {
+        Ok(now) => (now != last).then_some(now),
+        Err(e) => {
+            debug!("Couldn't get the volume: {}", e);
+            None
+        }
+    }
+}
+
+fn render(level: Level) -> Result<Overlay> {
+    let mut buffer = FrameBuffer::new();
+    let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+    BOX.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(&mut buffer)?;
+    BAR.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(&mut buffer)?;
+
+    // Muted sinks keep their volume but the bar stays empty, amplified ones
+    // fill it up
+    if !level.muted {
+        let width = (BAR.size.width - 4) * level.volume.min(100) / 100;
+        Rectangle::new(
+            BAR.top_left + Point::new(2, 2),
+            Size::new(width, BAR.size.height - 4),
+        )
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(&mut buffer)?;
+    }
+
+    let text = if level.muted {
+        String::from("muted")
+    } else {
+        format!("{}%", level.volume)
+    };
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let bar_right = BAR.top_left.x + BAR.size.width as i32;
+    let box_right = BOX.top_left.x + BOX.size.width as i32;
+    let center = Point::new((bar_right + box_right) / 2, BAR.center().y);
+    Text::with_text_style(&text, center, style, centered).draw(&mut buffer)?;
+
+    Ok(Overlay::new(buffer, BlendMode::Mask).with_background(BOX))
+}
+
+/// Shows the bar for a while whenever the volume changes, PulseAudio is
+/// asked again after a while if it goes away
+fn watch(settings: &VolumeSettings) -> impl Stream<Item = Option<Overlay>> + '_ {
+    let duration = Duration::from_millis(settings.duration);
+
+    stream! {
+        loop {
+            // Subscribing first so no change gets lost in between
+            let mut events = Box::pin(pulse::events());
+            // The volume at startup isn't shown
+            let mut last = match level().await {
+                Ok(level) => level,
+                Err(e) => {
+                    warn!("Couldn't get the volume: {}", e);
+                    time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            let mut hide_at: Option<time::Instant> = None;
+            loop {
+                tokio::select! {
+                    event = events.next() => match event {
+                        // Switching to other speakers is a change of the server
+                        Some(Ok(Event::Sink | Event::Server)) => {
+                            if let Some(now) = changed(last).await {
+                                last = now;
+                                hide_at = Some(time::Instant::now() + duration);
+                                if let Ok(overlay) = render(last) {
+                                    yield Some(overlay);
+                                }
+                            }
+                        },
+                        Some(Ok(_)) => {},
+                        Some(Err(e)) => {
+                            warn!("Lost track of PulseAudio: {}", e);
+                            break;
+                        },
+                        None => break,
+                    },
+                    _ = time::sleep_until(hide_at.unwrap_or_else(time::Instant::now)),
+                        if hide_at.is_some() => {
+                        hide_at = None;
+                        yield None;
+                    }
+                }
+            }
+
+            yield None;
+            time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Shows a volume bar on top of the screens whenever the volume of the
+/// default sink of PulseAudio or PipeWire changes, like the OSD of a desktop
+struct Volume {
+    /// `None` if the overlay is turned off
+    settings: Option<VolumeSettings>,
+}
+
+impl OverlayProvider for Volume {
+    type OverlayStream<'a> = impl Stream<Item = Result<Option<Overlay>>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::OverlayStream<'this>> {
+        Ok(try_stream! {
+            if let Some(settings) = &self.settings {
+                let mut overlays = Box::pin(watch(settings));
+                while let Some(overlay) = overlays.next().await {
+                    yield overlay;
+                }
+            }
+        })
+    }
+}
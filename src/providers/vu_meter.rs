@@ -0,0 +1,221 @@
+use crate::{
+    pulse::{self, CAPTURE_RATE},
+    render::{
+        display::ContentProvider,
+        scheduler::{ContentWrapper, TICK_LENGTH},
+    },
+    settings,
+};
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Line, Primitive, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::{Stream, StreamExt};
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::{time, time::Duration};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// How long to wait before recording again after PulseAudio went away
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// How many ticks the peak stays where it is before it falls
+const PEAK_HOLD_TICKS: usize = 20;
+
+/// How far the peak falls every tick once it's held long enough, as a part of
+/// the bar
+const PEAK_DECAY: f32 = 0.02;
+
+/// The bars start right after the channel names
+const BAR_LEFT: i32 = 8;
+
+/// The settings of the `[vu_meter]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct VuMeterSettings {
+    /// The source to record, the monitor of the default sink if it's not set
+    device: Option<String>,
+    /// The quietest level in dB that still shows up, the bars are empty below
+    /// it
+    min_db: f32,
+}
+
+impl Default for VuMeterSettings {
+    fn default() -> Self {
+        Self {
+            device: None,
+            min_db: -60.0,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering VU meter display source.");
+
+    let settings: VuMeterSettings = settings::section(config, "vu_meter")?;
+
+    Ok(Box::new(VuMeter {
+        settings,
+        channels: [Channel::default(); 2],
+        recording: false,
+    }))
+}
+
+/// The levels of one channel as parts of the bar between 0 and 1
+#[derive(Debug, Copy, Clone, Default)]
+struct Channel {
+    rms: f32,
+    peak: f32,
+    /// The highest peak of the last moments
+    hold: f32,
+    /// How long the held peak has been around
+    held_for: usize,
+}
+
+impl Channel {
+    fn update(&mut self, rms: f32, peak: f32) {
+        self.rms = rms;
+        self.peak = peak;
+
+        if peak >= self.hold {
+            self.hold = peak;
+            self.held_for = 0;
+        } else if self.held_for < PEAK_HOLD_TICKS {
+            self.held_for += 1;
+        } else {
+            self.hold = (self.hold - PEAK_DECAY).max(peak);
+        }
+    }
+}
+
+/// Turns a level between 0 and 1 into a part of the bar, the bars follow the
+/// dB scale just like the ones of a mixer
+fn scale(level: f32, min_db: f32) -> f32 {
+    let min_db = min_db.min(-1.0);
+    let db = 20.0 * level.max(f32::EPSILON).log10();
+    ((db - min_db) / -min_db).clamp(0.0, 1.0)
+}
+
+/// Shows how loud both channels of the output are with the peaks held for a
+/// moment, a lot lighter than a full spectrum
+struct VuMeter {
+    settings: VuMeterSettings,
+    channels: [Channel; 2],
+    /// Whether the output is being recorded right now
+    recording: bool,
+}
+
+impl VuMeter {
+    fn update(&mut self, block: &[[f32; 2]]) {
+        let min_db = self.settings.min_db;
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let peak = block
+                .iter()
+                .fold(0.0_f32, |peak, frame| peak.max(frame[index].abs()));
+            let squares = block
+                .iter()
+                .map(|frame| frame[index] * frame[index])
+                .sum::<f32>();
+            let rms = (squares / block.len().max(1) as f32).sqrt();
+
+            channel.update(scale(rms, min_db), scale(peak, min_db));
+        }
+    }
+
+    fn render(&self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        if !self.recording {
+            let centered = TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Middle)
+                .build();
+            Text::with_text_style("No audio", Point::new(64, 20), style, centered)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+
+        // The held peak is two pixels wide and has to stay on the screen
+        let width = (126 - BAR_LEFT) as f32;
+        for ((name, channel), top) in ["L", "R"].iter().zip(&self.channels).zip([3, 23]) {
+            Text::with_baseline(name, Point::new(0, top + 2), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            // The RMS is the solid part of the bar, the outline goes up to the peak
+            let rms = (channel.rms * width) as u32;
+            Rectangle::new(Point::new(BAR_LEFT, top), Size::new(rms, 14))
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(&mut buffer)?;
+            let peak = (channel.peak * width) as u32;
+            if peak > rms {
+                Rectangle::new(Point::new(BAR_LEFT, top), Size::new(peak, 14))
+                    .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                    .draw(&mut buffer)?;
+            }
+
+            let hold = BAR_LEFT + (channel.hold * width) as i32;
+            Line::new(Point::new(hold, top), Point::new(hold, top + 13))
+                .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 2))
+                .draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for VuMeter {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        // Every block of samples is one frame
+        let frames = CAPTURE_RATE * TICK_LENGTH / 1000;
+
+        Ok(try_stream! {
+            loop {
+                let device = self.settings.device.clone();
+                let mut blocks = Box::pin(pulse::capture(device.as_deref(), frames));
+
+                while let Some(block) = blocks.next().await {
+                    match block {
+                        Ok(block) => {
+                            self.recording = true;
+                            self.update(&block);
+                        },
+                        Err(e) => {
+                            warn!("Couldn't record the output: {}", e);
+                            break;
+                        }
+                    }
+                    if let Ok(image) = self.render() {
+                        yield image;
+                    }
+                }
+
+                self.recording = false;
+                self.channels = [Channel::default(); 2];
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                time::sleep(RETRY_DELAY).await;
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "vu_meter"
+    }
+}
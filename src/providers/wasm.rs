@@ -0,0 +1,427 @@
+use crate::{
+    render::{
+        display::{ContentProvider, ProviderInput},
+        scheduler::ContentWrapper,
+        util,
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use apex_input::Command;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Baseline, Text},
+    Drawable, Pixel,
+};
+use futures::{future, Stream};
+use log::{info, warn};
+use reqwest::{redirect, Client, ClientBuilder, Url};
+use serde::Deserialize;
+use std::{future::Future, path::PathBuf};
+use tokio::{
+    sync::mpsc,
+    time,
+    time::{Duration, Interval, MissedTickBehavior},
+};
+use wasmtime::{Caller, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::tokio::{add_to_linker, WasiCtx, WasiCtxBuilder};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The module the host functions are imported from
+const HOST_MODULE: &str = "apex";
+/// A plugin can't grow its memory past this
+const MEMORY_LIMIT: usize = 16 << 20;
+/// `fetch` gives up on larger responses
+const FETCH_LIMIT: usize = 1 << 20;
+/// How often a plugin is interrupted so it can't block the other screens, even
+/// if it never calls `frame`
+const TIME_SLICE: Duration = Duration::from_millis(10);
+
+/// The settings of the `[wasm]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WasmSettings {
+    /// The plugins compiled to `wasm32-wasi`, `Select` switches between them
+    plugins: Vec<PluginSettings>,
+    /// The shortest time between two frames, in milliseconds
+    frame_interval: u64,
+}
+
+impl Default for WasmSettings {
+    fn default() -> Self {
+        Self {
+            plugins: Vec::new(),
+            frame_interval: 100,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PluginSettings {
+    path: PathBuf,
+    /// The hosts the plugin may `fetch` from, it can't reach anything else
+    #[serde(default)]
+    hosts: Vec<String>,
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering WASM display source.");
+
+    let settings: WasmSettings = settings::section(config, "wasm")?;
+    if settings.plugins.is_empty() {
+        warn!("There are no `plugins` in [wasm], the screen stays empty");
+    }
+
+    let mut engine_config = wasmtime::Config::new();
+    engine_config.async_support(true).epoch_interruption(true);
+    let engine = Engine::new(&engine_config)?;
+
+    // Compiling takes a while, broken plugins are reported right away this way
+    let plugins = settings
+        .plugins
+        .into_iter()
+        .map(|plugin| {
+            let module = Module::from_file(&engine, &plugin.path).map_err(|e| {
+                anyhow!(
+                    "Invalid settings in [wasm]: Couldn't load `{}`: {}",
+                    plugin.path.display(),
+                    e
+                )
+            })?;
+            Ok(Plugin {
+                module,
+                client: client(plugin.hosts.clone())?,
+                hosts: plugin.hosts,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (input, commands) = mpsc::unbounded_channel();
+
+    Ok(Box::new(Wasm {
+        engine,
+        plugins,
+        current: 0,
+        frame_interval: Duration::from_millis(settings.frame_interval.max(1)),
+        input,
+        commands: Some(commands),
+    }))
+}
+
+struct Plugin {
+    module: Module,
+    /// Only follows redirects to the `hosts`
+    client: Client,
+    hosts: Vec<String>,
+}
+
+/// An allowed host could redirect the plugin anywhere else otherwise, e.g.
+/// into the local network
+fn client(hosts: Vec<String>) -> Result<Client> {
+    let policy = redirect::Policy::custom(move |attempt| {
+        let host = attempt.url().host_str().unwrap_or_default();
+        if !hosts.iter().any(|allowed| allowed == host) {
+            let error = anyhow!(
+                "The redirect to `{}` leaves the `hosts` of the plugin",
+                host
+            );
+            attempt.error(error)
+        } else if attempt.previous().len() > 5 {
+            attempt.error(anyhow!("Too many redirects"))
+        } else {
+            attempt.follow()
+        }
+    });
+
+    Ok(ClientBuilder::new()
+        .user_agent(APP_USER_AGENT)
+        .redirect(policy)
+        .build()?)
+}
+
+/// Everything the host functions of a running plugin can reach
+struct State {
+    wasi: WasiCtx,
+    limits: StoreLimits,
+    /// What the plugin drew since its last frame
+    frame: FrameBuffer,
+    frames: mpsc::Sender<FrameBuffer>,
+    interval: Interval,
+    client: Client,
+    hosts: Vec<String>,
+}
+
+impl State {
+    /// Copies `len` bytes at `ptr` out of the memory of the plugin
+    fn read(caller: &mut Caller<'_, Self>, ptr: u32, len: u32) -> Result<Vec<u8>> {
+        let memory = caller
+            .get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| anyhow!("The plugin doesn't export its memory"))?;
+        let (ptr, len) = (ptr as usize, len as usize);
+
+        memory
+            .data(&caller)
+            .get(ptr..ptr + len)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| anyhow!("The plugin passed memory it doesn't have"))
+    }
+
+    fn read_str(caller: &mut Caller<'_, Self>, ptr: u32, len: u32) -> Result<String> {
+        Ok(String::from_utf8(Self::read(caller, ptr, len)?)?)
+    }
+
+    /// Copies as much of `data` as fits into the `len` bytes at `ptr`
+    fn write(caller: &mut Caller<'_, Self>, ptr: u32, len: u32, data: &[u8]) -> Result<()> {
+        let memory = caller
+            .get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| anyhow!("The plugin doesn't export its memory"))?;
+        let data = &data[..data.len().min(len as usize)];
+
+        memory.write(caller, ptr as usize, data)?;
+        Ok(())
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let url = Url::parse(url)?;
+        let host = url.host_str().unwrap_or_default();
+        if !self.hosts.iter().any(|allowed| allowed == host) {
+            return Err(anyhow!("`{}` isn't one of the `hosts` of the plugin", host));
+        }
+
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        if response.content_length().unwrap_or(0) > FETCH_LIMIT as u64 {
+            return Err(anyhow!("The response is too large"));
+        }
+
+        // Not every response has a length, the body is cut off as it arrives
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > FETCH_LIMIT {
+                return Err(anyhow!("The response is too large"));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body)
+    }
+}
+
+/// The functions a plugin can import from the `apex` module. Coordinates are
+/// pixels from the top left corner, strings are UTF-8 without a terminator.
+fn host_functions(linker: &mut Linker<State>) -> Result<()> {
+    // Starts a new frame
+    linker.func_wrap(HOST_MODULE, "clear", |mut caller: Caller<'_, State>| {
+        caller.data_mut().frame = FrameBuffer::new();
+    })?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "draw_pixel",
+        |mut caller: Caller<'_, State>, x: i32, y: i32, on: i32| -> Result<()> {
+            let color = if on == 0 {
+                BinaryColor::Off
+            } else {
+                BinaryColor::On
+            };
+            Pixel(Point::new(x, y), color).draw(&mut caller.data_mut().frame)?;
+            Ok(())
+        },
+    )?;
+
+    // Draws with the 6x10 font, `y` is the top of the text
+    linker.func_wrap(
+        HOST_MODULE,
+        "draw_text",
+        |mut caller: Caller<'_, State>, x: i32, y: i32, ptr: u32, len: u32| -> Result<()> {
+            let text = State::read_str(&mut caller, ptr, len)?;
+            let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+            Text::with_baseline(&text, Point::new(x, y), style, Baseline::Top)
+                .draw(&mut caller.data_mut().frame)?;
+            Ok(())
+        },
+    )?;
+
+    // Shows what was drawn and waits until it's time for the next frame
+    linker.func_wrap_async(
+        HOST_MODULE,
+        "frame",
+        |mut caller: Caller<'_, State>, (): ()| {
+            Box::new(async move {
+                let state = caller.data_mut();
+                state.interval.tick().await;
+                state
+                    .frames
+                    .send(state.frame.clone())
+                    .await
+                    .map_err(|_| anyhow!("The screen isn't shown anymore"))
+            })
+        },
+    )?;
+
+    // Fetches `url` with a GET request and copies the body to `buf`. Returns the
+    // length of the whole body so the plugin can try again with a larger
+    // buffer, -1 if the request failed.
+    linker.func_wrap_async(
+        HOST_MODULE,
+        "fetch",
+        |mut caller: Caller<'_, State>, (url, url_len, buf, buf_len): (u32, u32, u32, u32)| {
+            Box::new(async move {
+                let url = State::read_str(&mut caller, url, url_len)?;
+                match caller.data().fetch(&url).await {
+                    Ok(body) => {
+                        State::write(&mut caller, buf, buf_len, &body)?;
+                        Ok(body.len() as i32)
+                    }
+                    Err(e) => {
+                        warn!("A WASM plugin couldn't fetch {}: {}", url, e);
+                        Ok(-1)
+                    }
+                }
+            })
+        },
+    )?;
+
+    Ok(())
+}
+
+struct Wasm {
+    engine: Engine,
+    plugins: Vec<Plugin>,
+    /// The index of the plugin that's shown
+    current: usize,
+    frame_interval: Duration,
+    input: mpsc::UnboundedSender<Command>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+/// What happened while a plugin was shown
+enum Event {
+    Frame(FrameBuffer),
+    Exited(Result<()>),
+    Select,
+}
+
+impl Wasm {
+    /// Prepares the current plugin, it runs while the returned future is polled
+    /// and its frames arrive through the channel
+    fn run(
+        &self,
+    ) -> (
+        mpsc::Receiver<FrameBuffer>,
+        impl Future<Output = Result<()>>,
+    ) {
+        let plugin = &self.plugins[self.current];
+        let (frames, rx) = mpsc::channel(1);
+
+        let mut interval = time::interval(self.frame_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        // Plugins can't see the files, the environment or the network
+        let state = State {
+            wasi: WasiCtxBuilder::new().inherit_stderr().build(),
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+            frame: FrameBuffer::new(),
+            frames,
+            interval,
+            client: plugin.client.clone(),
+            hosts: plugin.hosts.clone(),
+        };
+
+        let engine = self.engine.clone();
+        let module = plugin.module.clone();
+        let run = async move {
+            let mut linker = Linker::new(&engine);
+            add_to_linker(&mut linker, |state: &mut State| &mut state.wasi)?;
+            host_functions(&mut linker)?;
+
+            let mut store = Store::new(&engine, state);
+            store.limiter(|state| &mut state.limits);
+            store.epoch_deadline_async_yield_and_update(1);
+
+            let instance = linker.instantiate_async(&mut store, &module).await?;
+            let main = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+
+            let mut slices = time::interval(TIME_SLICE);
+            tokio::select! {
+                result = main.call_async(&mut store, ()) => result,
+                () = async {
+                    loop {
+                        slices.tick().await;
+                        engine.increment_epoch();
+                    }
+                } => unreachable!(),
+            }
+        };
+
+        (rx, run)
+    }
+}
+
+impl ContentProvider for Wasm {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut commands = self
+            .commands
+            .take()
+            .ok_or_else(|| anyhow!("The wasm stream can only be started once!"))?;
+
+        Ok(try_stream! {
+            if self.plugins.is_empty() {
+                yield util::placeholder("No plugins configured")?;
+                future::pending::<()>().await;
+            }
+
+            loop {
+                // Dropping the plugin stops it, e.g. when switching to the next one
+                let (mut frames, plugin) = self.run();
+                tokio::pin!(plugin);
+                let mut exited = false;
+
+                loop {
+                    let event = tokio::select! {
+                        result = &mut plugin, if !exited => Event::Exited(result),
+                        Some(frame) = frames.recv() => Event::Frame(frame),
+                        Some(Command::Select) = commands.recv() => Event::Select,
+                    };
+
+                    match event {
+                        Event::Frame(frame) => yield frame,
+                        // The last frame stays until the next plugin is selected
+                        Event::Exited(result) => {
+                            result?;
+                            exited = true;
+                        }
+                        Event::Select => {
+                            self.current = (self.current + 1) % self.plugins.len();
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "wasm"
+    }
+
+    fn input(&self) -> Option<Box<dyn ProviderInput>> {
+        Some(Box::new(self.input.clone()))
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper},
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use serde::Deserialize;
+use std::{fs, process};
+use tokio::{
+    task, time,
+    time::{Duration, MissedTickBehavior},
+};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// The signal strength in dBm a bar needs, from the first to the fourth one
+const BARS: [i32; 4] = [-85, -75, -67, -55];
+/// The width of every bar
+const BAR_WIDTH: i32 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WifiSettings {
+    /// In milliseconds
+    polling_interval: u64,
+    /// The first wireless interface is used if this isn't set
+    interface: Option<String>,
+}
+
+impl Default for WifiSettings {
+    fn default() -> Self {
+        Self {
+            polling_interval: 3000,
+            interface: None,
+        }
+    }
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering WiFi display source.");
+
+    let settings: WifiSettings = settings::section(config, "wifi")?;
+
+    Ok(Box::new(Wifi {
+        polling_interval: settings.polling_interval,
+        interface: settings.interface,
+    }))
+}
+
+/// What `iw` knows about the connection
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Link {
+    ssid: String,
+    /// In dBm
+    signal: Option<i32>,
+    /// In MBit/s
+    rx_bitrate: Option<f32>,
+    tx_bitrate: Option<f32>,
+}
+
+/// Wireless interfaces have a `wireless` directory in sysfs
+fn find_interface() -> Option<String> {
+    fs::read_dir("/sys/class/net")
+        .ok()?
+        .flatten()
+        .find(|entry| entry.path().join("wireless").exists())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+}
+
+/// Parses the output of `iw dev <interface> link`, that's `None` if there's no
+/// connection
+fn parse_link(output: &str) -> Option<Link> {
+    if output.trim_start().starts_with("Not connected") {
+        return None;
+    }
+
+    let mut link = Link::default();
+    for line in output.lines() {
+        let (key, value) = match line.trim().split_once(": ") {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        // Bitrates are followed by the modulation, e.g. `866.7 MBit/s VHT-MCS 9`
+        let number = value.split_whitespace().next();
+        match key {
+            "SSID" => link.ssid = value.to_string(),
+            "signal" => link.signal = number.and_then(|n| n.parse().ok()),
+            "rx bitrate" => link.rx_bitrate = number.and_then(|n| n.parse().ok()),
+            "tx bitrate" => link.tx_bitrate = number.and_then(|n| n.parse().ok()),
+            _ => {}
+        }
+    }
+
+    Some(link)
+}
+
+fn read_link(interface: &str) -> Result<Option<Link>> {
+    let output = process::Command::new("iw")
+        .args(["dev", interface, "link"])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`iw` failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(parse_link(&String::from_utf8_lossy(&output.stdout)))
+}
+
+struct Wifi {
+    polling_interval: u64,
+    interface: Option<String>,
+}
+
+fn render(link: Option<&Link>) -> Result<FrameBuffer> {
+    let mut buffer = FrameBuffer::new();
+    let large = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+    let small = MonoTextStyle::new(&iso_8859_15::FONT_5X8, BinaryColor::On);
+
+    let link = match link {
+        Some(link) => link,
+        None => {
+            Text::with_baseline("Not connected", Point::new(0, 15), large, Baseline::Top)
+                .draw(&mut buffer)?;
+            return Ok(buffer);
+        }
+    };
+
+    // The SSID is cut off before the bars
+    let ssid = link.ssid.chars().take(16).collect::<String>();
+    Text::with_baseline(&ssid, Point::new(0, 2), large, Baseline::Top).draw(&mut buffer)?;
+
+    if let Some(signal) = link.signal {
+        Text::with_baseline(
+            &format!("{} dBm", signal),
+            Point::new(0, 16),
+            small,
+            Baseline::Top,
+        )
+        .draw(&mut buffer)?;
+    }
+
+    let bitrate =
+        |rate: Option<f32>| rate.map_or_else(|| String::from("?"), |r| format!("{:.0}", r));
+    Text::with_baseline(
+        &format!(
+            "{}/{} MBit/s",
+            bitrate(link.rx_bitrate),
+            bitrate(link.tx_bitrate)
+        ),
+        Point::new(0, 28),
+        small,
+        Baseline::Top,
+    )
+    .draw(&mut buffer)?;
+
+    // The bars get taller from left to right, the ones above the signal are
+    // only outlined
+    let bars = link.signal.map_or(0, |signal| {
+        BARS.iter()
+            .filter(|threshold| signal >= **threshold)
+            .count()
+    });
+    for index in 0..BARS.len() {
+        let height = 10 + index as i32 * 8;
+        let style = if index < bars {
+            PrimitiveStyle::with_fill(BinaryColor::On)
+        } else {
+            PrimitiveStyle::with_stroke(BinaryColor::On, 1)
+        };
+
+        Rectangle::new(
+            Point::new(
+                128 - (BARS.len() - index) as i32 * (BAR_WIDTH + 2),
+                40 - height,
+            ),
+            Size::new(BAR_WIDTH as u32, height as u32),
+        )
+        .into_styled(style)
+        .draw(&mut buffer)?;
+    }
+
+    Ok(buffer)
+}
+
+impl ContentProvider for Wifi {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        let mut interval = time::interval(Duration::from_millis(self.polling_interval.max(1)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let interface = match self.interface.clone().or_else(find_interface) {
+            Some(interface) => interface,
+            None => return Err(anyhow!("Couldn't find a wireless interface")),
+        };
+
+        Ok(try_stream! {
+            loop {
+                interval.tick().await;
+
+                // `iw` is quick but it's still a process, it shouldn't hold up the runtime
+                let name = interface.clone();
+                match task::spawn_blocking(move || read_link(&name)).await? {
+                    Ok(link) => yield render(link.as_ref())?,
+                    Err(e) => warn!("Couldn't read the WiFi link of `{}`: {}", interface, e),
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "wifi"
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{
+    render::{display::ContentProvider, scheduler::ContentWrapper, util},
+    settings,
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::FrameBuffer;
+use async_stream::try_stream;
+use chrono::{Local, Timelike, Utc};
+use chrono_tz::Tz;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+use futures::Stream;
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::{time, time::Duration};
+
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+
+/// More columns don't fit next to each other
+const MAX_ZONES: usize = 4;
+
+/// The settings of the `[world_clock]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WorldClockSettings {
+    zones: Vec<ZoneSettings>,
+    twelve_hour: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ZoneSettings {
+    /// A short name shown above the time, e.g. `NYC`
+    label: String,
+    /// A name of the tz database, e.g. `America/New_York`
+    zone: String,
+}
+
+#[doc(hidden)]
+fn register_callback(config: &Config) -> Result<Box<dyn ContentWrapper>> {
+    info!("Registering World Clock display source.");
+
+    let settings: WorldClockSettings = settings::section(config, "world_clock")?;
+    if settings.zones.is_empty() {
+        warn!("There are no `zones` in [world_clock], the screen stays empty");
+    } else if !(2..=MAX_ZONES).contains(&settings.zones.len()) {
+        return Err(anyhow!(
+            "Invalid settings in [world_clock]: There have to be 2 to {} `zones`",
+            MAX_ZONES
+        ));
+    }
+
+    let zones = settings
+        .zones
+        .into_iter()
+        .map(|zone| {
+            let tz = zone.zone.parse::<Tz>().map_err(|e| {
+                anyhow!(
+                    "Invalid settings in [world_clock]: `{}` isn't a time zone: {}",
+                    zone.zone,
+                    e
+                )
+            })?;
+            Ok((zone.label, tz))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Box::new(WorldClock {
+        zones,
+        twelve_hour: settings.twelve_hour,
+    }))
+}
+
+/// Shows the time of a few places side by side
+struct WorldClock {
+    zones: Vec<(String, Tz)>,
+    twelve_hour: bool,
+}
+
+impl WorldClock {
+    fn render(&self) -> Result<FrameBuffer> {
+        if self.zones.is_empty() {
+            return util::placeholder("No zones configured");
+        }
+
+        let mut buffer = FrameBuffer::new();
+        let small = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        let now = Utc::now();
+        let today = Local::now().naive_local().date();
+
+        let width = 128 / self.zones.len() as i32;
+        // Four columns are too narrow for the bold font
+        let font = if self.zones.len() < MAX_ZONES {
+            &iso_8859_15::FONT_8X13_BOLD
+        } else {
+            &iso_8859_15::FONT_6X10
+        };
+        let large = MonoTextStyle::new(font, BinaryColor::On);
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build();
+
+        for (column, (label, tz)) in self.zones.iter().enumerate() {
+            let x = column as i32 * width + width / 2;
+            let time = now.with_timezone(tz);
+
+            let text = if self.twelve_hour {
+                time.format("%-I:%M").to_string()
+            } else {
+                time.format("%H:%M").to_string()
+            };
+            Text::with_text_style(label, Point::new(x, 0), small, centered).draw(&mut buffer)?;
+            Text::with_text_style(&text, Point::new(x, 13), large, centered).draw(&mut buffer)?;
+
+            // Places that are a day ahead or behind are marked, the line shows AM or PM
+            // otherwise
+            let days = (time.naive_local().date() - today).num_days();
+            let note = match days {
+                0 if self.twelve_hour => time.format("%p").to_string(),
+                0 => String::new(),
+                _ => format!("{:+}d", days),
+            };
+            Text::with_text_style(&note, Point::new(x, 29), small, centered).draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl ContentProvider for WorldClock {
+    type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::ContentStream<'this>> {
+        Ok(try_stream! {
+            loop {
+                if let Ok(image) = self.render() {
+                    yield image;
+                }
+                // Only the minutes are shown
+                let second = u64::from(Local::now().second());
+                time::sleep(Duration::from_secs(60 - second.min(59))).await;
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "world_clock"
+    }
+}
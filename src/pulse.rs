@@ -0,0 +1,153 @@
+//! Follows the default devices of PulseAudio through `pactl` and records
+//! what they play with `parec`, PipeWire ships both with `pipewire-pulse`
+
+use anyhow::{anyhow, Result};
+use async_stream::try_stream;
+use futures::Stream;
+use std::process::Stdio;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    process::Command,
+};
+
+/// How many frames per second `capture` records
+pub(crate) const CAPTURE_RATE: usize = 44100;
+
+/// What changed according to `pactl subscribe`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// A sink like the speakers or its volume changed
+    Sink,
+    /// A source like a microphone or its volume changed
+    Source,
+    /// The server changed, this includes switching to other default devices
+    Server,
+}
+
+/// Parses a line of `pactl subscribe`, e.g. `Event 'change' on source #52`.
+/// Events about e.g. clients or modules are `None`.
+fn parse_event(line: &str) -> Option<Event> {
+    let facility = line.split(" on ").nth(1)?.split_whitespace().next()?;
+    match facility {
+        "sink" => Some(Event::Sink),
+        "source" => Some(Event::Source),
+        "server" => Some(Event::Server),
+        _ => None,
+    }
+}
+
+/// Runs `pactl` with `args` and returns what it printed
+async fn pactl(args: &[&str]) -> Result<String> {
+    let output = Command::new("pactl").args(args).output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`pactl {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the answer of `get-sink-mute` or `get-source-mute`, e.g. `Mute: yes`
+fn parse_mute(output: &str) -> Result<bool> {
+    match output.trim().strip_prefix("Mute: ") {
+        Some("yes") => Ok(true),
+        Some("no") => Ok(false),
+        _ => Err(anyhow!("Unexpected answer of pactl: {}", output.trim())),
+    }
+}
+
+/// Reads the volume of the first channel in percent from the answer of
+/// `get-sink-volume`, e.g. `Volume: front-left: 32768 /  50% / -18.06 dB, ...`
+fn parse_volume(output: &str) -> Result<u32> {
+    output
+        .split('/')
+        .nth(1)
+        .and_then(|percent| percent.trim().strip_suffix('%'))
+        .and_then(|percent| percent.parse().ok())
+        .ok_or_else(|| anyhow!("Unexpected answer of pactl: {}", output.trim()))
+}
+
+/// Whether the default source, usually the microphone, is muted
+pub(crate) async fn source_muted() -> Result<bool> {
+    parse_mute(&pactl(&["get-source-mute", "@DEFAULT_SOURCE@"]).await?)
+}
+
+/// Whether the default sink is muted
+pub(crate) async fn sink_muted() -> Result<bool> {
+    parse_mute(&pactl(&["get-sink-mute", "@DEFAULT_SINK@"]).await?)
+}
+
+/// The volume of the default sink in percent, this goes above 100 if it's
+/// amplified
+pub(crate) async fn sink_volume() -> Result<u32> {
+    parse_volume(&pactl(&["get-sink-volume", "@DEFAULT_SINK@"]).await?)
+}
+
+/// Tells what changed for as long as `pactl subscribe` runs, the stream ends
+/// if the server goes away
+pub(crate) fn events() -> impl Stream<Item = Result<Event>> {
+    try_stream! {
+        let mut child = Command::new("pactl")
+            .arg("subscribe")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Couldn't read the output of `pactl subscribe`"))?;
+
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(event) = parse_event(&line) {
+                yield event;
+            }
+        }
+    }
+}
+
+/// Records what's played on `device`, or on the default sink if it's `None`,
+/// in blocks of `frames` stereo frames between -1 and 1. The stream ends if
+/// the server goes away.
+pub(crate) fn capture(
+    device: Option<&str>,
+    frames: usize,
+) -> impl Stream<Item = Result<Vec<[f32; 2]>>> + '_ {
+    try_stream! {
+        let mut child = Command::new("parec")
+            .args(["--format=s16le", "--channels=2"])
+            .arg(format!("--rate={}", CAPTURE_RATE))
+            .arg(format!("--device={}", device.unwrap_or("@DEFAULT_MONITOR@")))
+            // Keeps the blocks coming steadily instead of in large bursts
+            .arg(format!("--latency={}", frames * 4))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Couldn't read the output of `parec`"))?;
+
+        // Two channels with two bytes per sample
+        let mut bytes = vec![0; frames * 4];
+        loop {
+            stdout.read_exact(&mut bytes).await?;
+            yield bytes
+                .chunks_exact(4)
+                .map(|frame| {
+                    let left = i16::from_le_bytes([frame[0], frame[1]]);
+                    let right = i16::from_le_bytes([frame[2], frame[3]]);
+                    [
+                        f32::from(left) / f32::from(i16::MAX),
+                        f32::from(right) / f32::from(i16::MAX),
+                    ]
+                })
+                .collect::<Vec<_>>();
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use embedded_graphics::mono_font::{iso_8859_15, MonoFont};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Screens that list several things show at most this many of them in large
+/// text mode
+pub const LARGE_TEXT_ROWS: usize = 2;
+
+/// The settings of the `[accessibility]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessibilitySettings {
+    /// Whether large text mode is on from the start
+    large_text: bool,
+}
+
+static LARGE_TEXT: AtomicBool = AtomicBool::new(false);
+
+/// Turns large text mode on or off, `toggled` is what was picked with the
+/// hotkey and wins over the settings. The providers pick their layout when
+/// they're created so this has to happen before that.
+pub fn configure(settings: &AccessibilitySettings, toggled: Option<bool>) {
+    LARGE_TEXT.store(toggled.unwrap_or(settings.large_text), Ordering::SeqCst);
+}
+
+/// Whether the providers should use larger fonts and fewer rows
+pub fn large_text() -> bool {
+    LARGE_TEXT.load(Ordering::SeqCst)
+}
+
+/// Swaps `font` for one that's readable from a distance in large text mode,
+/// fonts that are large enough already stay as they are
+pub fn font(font: &'static MonoFont<'static>) -> &'static MonoFont<'static> {
+    if large_text() && font.character_size.height < 10 {
+        &iso_8859_15::FONT_6X10
+    } else {
+        font
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use apex_hardware::{BlendMode, FrameBuffer};
+use embedded_graphics::{
+    geometry::{Point, Size},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use lazy_static::lazy_static;
+use log::debug;
+use serde::Deserialize;
+use std::{
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// How long the area stays inverted and then normal again, two flashes a
+/// second stay well below the rates that are known to trigger seizures
+const FLASH_PERIOD: Duration = Duration::from_millis(250);
+
+/// The settings of the `[alerts]` section
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertSettings {
+    /// Turns off flashing altogether, the alerts are still shown otherwise
+    enabled: bool,
+    /// Seconds that have to pass after an alert before the next one may flash
+    min_interval: u64,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_interval: 10,
+        }
+    }
+}
+
+lazy_static! {
+    static ref SETTINGS: RwLock<AlertSettings> = RwLock::new(AlertSettings::default());
+    /// When the last alert that was allowed to flash started
+    static ref LAST: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+pub fn configure(settings: AlertSettings) {
+    if let Ok(mut current) = SETTINGS.write() {
+        *current = settings;
+    }
+}
+
+/// Every provider goes through this so several of them alerting at once
+/// can't turn the display into a strobe
+fn may_flash() -> bool {
+    let settings = SETTINGS
+        .read()
+        .map(|settings| *settings)
+        .unwrap_or_default();
+    if !settings.enabled {
+        return false;
+    }
+
+    let mut last = match LAST.lock() {
+        Ok(last) => last,
+        Err(_) => return false,
+    };
+    let min_interval = Duration::from_secs(settings.min_interval);
+    if last.map_or(false, |last| last.elapsed() < min_interval) {
+        debug!(
+            "Skipping an alert, the last one was less than {:?} ago",
+            min_interval
+        );
+        return false;
+    }
+
+    *last = Some(Instant::now());
+    true
+}
+
+/// Inverts an area of the screen a few times to draw attention to it. The
+/// alert starts as soon as it's created, alerts that aren't allowed to flash
+/// don't do anything.
+#[derive(Debug, Copy, Clone)]
+pub struct Alert {
+    area: Rectangle,
+    flashes: u32,
+    started: Instant,
+}
+
+impl Alert {
+    /// Flashes the whole screen
+    pub fn screen(flashes: u32) -> Self {
+        Self::region(Rectangle::new(Point::zero(), Size::new(128, 40)), flashes)
+    }
+
+    /// Flashes only `area`, e.g. the value that went over its threshold
+    pub fn region(area: Rectangle, flashes: u32) -> Self {
+        Self {
+            area,
+            flashes: if may_flash() { flashes } else { 0 },
+            started: Instant::now(),
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.started.elapsed() >= FLASH_PERIOD * 2 * self.flashes
+    }
+
+    /// Inverts the area of `frame` if the alert is in the first half of a
+    /// flash, the frames have to come in more often than `FLASH_PERIOD` for
+    /// this to show
+    pub fn apply(&self, frame: &mut FrameBuffer) -> Result<()> {
+        if self.is_over() {
+            return Ok(());
+        }
+
+        let phase = self.started.elapsed().as_millis() / FLASH_PERIOD.as_millis();
+        if phase % 2 == 0 {
+            self.area
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(&mut frame.blend(BlendMode::Xor))?;
+        }
+
+        Ok(())
+    }
+}
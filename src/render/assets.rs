@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use embedded_graphics::{geometry::OriginDimensions, pixelcolor::BinaryColor};
+use std::{fs, path::Path};
+use tinybmp::Bmp;
+
+/// The size of the file header and the smallest DIB header
+const MIN_HEADER_SIZE: usize = 14 + 40;
+
+const fn u16_at(data: &[u8], index: usize) -> u16 {
+    u16::from_le_bytes([data[index], data[index + 1]])
+}
+
+const fn u32_at(data: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes([
+        data[index],
+        data[index + 1],
+        data[index + 2],
+        data[index + 3],
+    ])
+}
+
+/// Checks that `data` is an uncompressed monochrome BMP and that none of its
+/// pixels are missing, this is a `const fn` so the bundled icons can be
+/// checked while compiling
+const fn validate(data: &[u8]) -> Result<(), &'static str> {
+    if data.len() < MIN_HEADER_SIZE {
+        return Err("The file is too short to be a BMP");
+    }
+    if data[0] != b'B' || data[1] != b'M' {
+        return Err("The file isn't a BMP");
+    }
+    if u16_at(data, 28) != 1 {
+        return Err("Only monochrome BMPs are supported");
+    }
+    if u32_at(data, 30) != 0 {
+        return Err("Compressed BMPs aren't supported");
+    }
+
+    let width = u32_at(data, 18) as i32;
+    let height = u32_at(data, 22) as i32;
+    if width <= 0 || height == 0 {
+        return Err("The BMP doesn't have any pixels");
+    }
+
+    // Every row is padded to a multiple of 4 bytes
+    let stride = (width as usize + 31) / 32 * 4;
+    let end = u32_at(data, 10) as usize + stride * height.unsigned_abs() as usize;
+    if end > data.len() {
+        return Err("The pixel data of the BMP is incomplete");
+    }
+
+    Ok(())
+}
+
+/// Checks an icon that's embedded with `include_bytes!`. This has to be used
+/// in a `static` so a broken icon fails the build instead of panicking when
+/// it's first drawn.
+pub(crate) const fn checked(data: &'static [u8]) -> &'static [u8] {
+    match validate(data) {
+        Ok(()) => data,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Parses an icon that was embedded with [`checked`]
+pub(crate) fn bundled(data: &'static [u8]) -> Bmp<'static, BinaryColor> {
+    Bmp::from_slice(data).expect("The bundled icons are checked while compiling")
+}
+
+/// An icon that's loaded from a path in the settings. Unlike the bundled icons
+/// this can be anything so every problem with it is reported as an error.
+pub(crate) struct UserIcon {
+    data: Vec<u8>,
+}
+
+impl UserIcon {
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |e: &str| anyhow!("'{}' can't be used as an icon: {}", path.display(), e);
+
+        let data = fs::read(path)
+            .map_err(|e| anyhow!("Couldn't read the icon '{}': {}", path.display(), e))?;
+        validate(&data).map_err(invalid)?;
+
+        // tinybmp is a bit stricter than the checks above
+        let bmp = Bmp::<BinaryColor>::from_slice(&data)
+            .map_err(|e| invalid(format!("{:?}", e).as_str()))?;
+        let size = bmp.size();
+        if size.width > 128 || size.height > 40 {
+            return Err(invalid("It's larger than the screen"));
+        }
+
+        Ok(Self { data })
+    }
+
+    pub fn bmp(&self) -> Bmp<'_, BinaryColor> {
+        Bmp::from_slice(&self.data).expect("The icon was parsed when it was loaded")
+    }
+}
@@ -0,0 +1,75 @@
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{borrow::Cow, sync::RwLock};
+use unicode_bidi::{BidiInfo, Level};
+
+/// Which way text is read
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Picked by the first strongly directional character of the text
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+/// The settings of the `[bidi]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BidiSettings {
+    direction: Direction,
+}
+
+impl Default for BidiSettings {
+    fn default() -> Self {
+        Self {
+            direction: Direction::Auto,
+        }
+    }
+}
+
+lazy_static! {
+    static ref DIRECTION: RwLock<Direction> = RwLock::new(Direction::Auto);
+}
+
+/// Applies the settings, like the emoji this has to happen before the
+/// providers lay out their text
+pub fn configure(settings: BidiSettings) {
+    if let Ok(mut direction) = DIRECTION.write() {
+        *direction = settings.direction;
+    }
+}
+
+/// Puts the characters of `text` in the order they're drawn in from left to
+/// right. Returns whether the text is read from right to left, it has to
+/// scroll the other way then.
+pub fn reorder(text: &str) -> (Cow<'_, str>, bool) {
+    let direction = DIRECTION
+        .read()
+        .map_or(Direction::Auto, |direction| *direction);
+    let level = match direction {
+        Direction::Auto => None,
+        Direction::LeftToRight => Some(Level::ltr()),
+        Direction::RightToLeft => Some(Level::rtl()),
+    };
+
+    let info = BidiInfo::new(text, level);
+    // Text that's entirely left to right, the usual case, stays as it is
+    if !info.has_rtl() {
+        return (Cow::Borrowed(text), false);
+    }
+
+    let rtl = info
+        .paragraphs
+        .first()
+        .map_or(false, |paragraph| paragraph.level.is_rtl());
+    // Everything ends up on a single line, the paragraphs keep their order
+    let reordered = info
+        .paragraphs
+        .iter()
+        .map(|paragraph| info.reorder_line(paragraph, paragraph.range.clone()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (Cow::Owned(reordered), rtl)
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use embedded_graphics::{draw_target::DrawTarget, geometry::Point, pixelcolor::BinaryColor, Pixel};
+
+/// How bright the display is. The OLED can't be dimmed by itself so every
+/// other pixel is turned off instead, which also slows down the burn-in.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Brightness {
+    #[default]
+    Full,
+    /// Keeps half of the pixels in a checkerboard pattern
+    Half,
+    /// Keeps every other pixel of every other row
+    Quarter,
+}
+
+impl Brightness {
+    /// The next lower brightness, wrapping around to full brightness
+    pub fn next(self) -> Self {
+        match self {
+            Self::Full => Self::Half,
+            Self::Half => Self::Quarter,
+            Self::Quarter => Self::Full,
+        }
+    }
+
+    pub fn percent(self) -> u8 {
+        match self {
+            Self::Full => 100,
+            Self::Half => 50,
+            Self::Quarter => 25,
+        }
+    }
+
+    fn keeps(self, x: i32, y: i32) -> bool {
+        match self {
+            Self::Full => true,
+            Self::Half => (x + y) % 2 == 0,
+            Self::Quarter => x % 2 == 0 && y % 2 == 0,
+        }
+    }
+
+    /// Turns off the pixels of `frame` that don't fit the pattern
+    pub fn apply(self, frame: &FrameBuffer) -> Result<FrameBuffer> {
+        let mut frame = *frame;
+        if self == Self::Full {
+            return Ok(frame);
+        }
+
+        let pixels = (0..40)
+            .flat_map(|y| (0..128).map(move |x| (x, y)))
+            .filter(|(x, y)| !self.keeps(*x, *y))
+            .map(|(x, y)| Pixel(Point::new(x, y), BinaryColor::Off));
+        frame.draw_iter(pixels)?;
+
+        Ok(frame)
+    }
+}
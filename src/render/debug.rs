@@ -1,6 +1,6 @@
 use crate::render::{
     display::{ContentProvider, FrameBuffer},
-    scheduler::{ContentWrapper, CONTENT_PROVIDERS},
+    scheduler::ContentWrapper,
 };
 use anyhow::Result;
 use async_stream::try_stream;
@@ -12,15 +12,14 @@ use embedded_graphics::{
     Drawable,
 };
 use futures::Stream;
-use linkme::distributed_slice;
 use log::info;
 use tokio::{
     time,
     time::{Duration, MissedTickBehavior},
 };
 
-#[distributed_slice(CONTENT_PROVIDERS)]
-static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
+#[doc(hidden)]
+pub static PROVIDER_INIT: fn(&Config) -> Result<Box<dyn ContentWrapper>> = register_callback;
 
 #[allow(clippy::unnecessary_wraps)]
 fn register_callback(_config: &Config) -> Result<Box<dyn ContentWrapper>> {
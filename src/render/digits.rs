@@ -0,0 +1,131 @@
+use embedded_graphics::{
+    geometry::{Point, Size},
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, Primitive},
+    primitives::{PrimitiveStyle, Rectangle},
+    Drawable,
+};
+
+/// The segments that are lit for the digits 0 to 9, in the order
+/// top, top right, bottom right, bottom, bottom left, top left, middle
+const SEGMENTS: [[bool; 7]; 10] = [
+    [true, true, true, true, true, true, false],
+    [false, true, true, false, false, false, false],
+    [true, true, false, true, true, false, true],
+    [true, true, true, true, false, false, true],
+    [false, true, true, false, false, true, true],
+    [true, false, true, true, false, true, true],
+    [true, false, true, true, true, true, true],
+    [true, true, true, false, false, false, false],
+    [true, true, true, true, true, true, true],
+    [true, true, true, true, false, true, true],
+];
+
+/// Renders numbers as seven-segment digits that are a lot larger than any of
+/// the fonts we have available. Supports the digits 0-9, `:`, `-` and spaces.
+#[derive(Debug, Copy, Clone)]
+pub struct BigDigits {
+    size: Size,
+    thickness: u32,
+    spacing: u32,
+}
+
+impl BigDigits {
+    /// Creates a new renderer where every digit is `size` large and the
+    /// segments are `thickness` pixels wide
+    pub fn new(size: Size, thickness: u32) -> Self {
+        Self {
+            size,
+            thickness,
+            spacing: thickness,
+        }
+    }
+
+    fn char_width(&self, c: char) -> u32 {
+        match c {
+            ':' => self.thickness,
+            _ => self.size.width,
+        }
+    }
+
+    /// Calculates how wide `text` will be once drawn
+    pub fn width(&self, text: &str) -> u32 {
+        let chars = text.chars().count() as u32;
+        text.chars().map(|c| self.char_width(c)).sum::<u32>()
+            + chars.saturating_sub(1) * self.spacing
+    }
+
+    /// The rectangles making up every segment of a digit at `origin`
+    fn segments(&self, origin: Point) -> [Rectangle; 7] {
+        let Size { width, height } = self.size;
+        let t = self.thickness;
+        // Where the middle segment starts
+        let middle = (height - t) / 2;
+
+        let rect = |x: u32, y: u32, width: u32, height: u32| {
+            Rectangle::new(
+                origin + Point::new(x as i32, y as i32),
+                Size::new(width, height),
+            )
+        };
+
+        [
+            rect(t, 0, width - 2 * t, t),
+            rect(width - t, t, t, middle - t),
+            rect(width - t, middle + t, t, height - middle - 2 * t),
+            rect(t, height - t, width - 2 * t, t),
+            rect(0, middle + t, t, height - middle - 2 * t),
+            rect(0, t, t, middle - t),
+            rect(t, middle, width - 2 * t, t),
+        ]
+    }
+
+    /// Draws `text` with its top left corner at `origin`
+    pub fn draw<D: DrawTarget<Color = BinaryColor>>(
+        &self,
+        text: &str,
+        origin: Point,
+        target: &mut D,
+    ) -> Result<(), D::Error> {
+        let style = PrimitiveStyle::with_fill(BinaryColor::On);
+        let thickness = self.thickness;
+        let mut x = origin.x;
+
+        for c in text.chars() {
+            let position = Point::new(x, origin.y);
+
+            match c {
+                '0'..='9' => {
+                    let lit = SEGMENTS[c as usize - '0' as usize];
+                    for (segment, _) in self
+                        .segments(position)
+                        .iter()
+                        .zip(lit)
+                        .filter(|(_, lit)| *lit)
+                    {
+                        segment.into_styled(style).draw(target)?;
+                    }
+                }
+                '-' => {
+                    self.segments(position)[6].into_styled(style).draw(target)?;
+                }
+                ':' => {
+                    let height = self.size.height as i32;
+                    for dot in [height / 3, height * 2 / 3] {
+                        Rectangle::new(
+                            position + Point::new(0, dot - thickness as i32 / 2),
+                            Size::new(thickness, thickness),
+                        )
+                        .into_styled(style)
+                        .draw(target)?;
+                    }
+                }
+                _ => {}
+            }
+
+            x += (self.char_width(c) + self.spacing) as i32;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{borrow::Cow, collections::HashMap, sync::RwLock};
+
+/// The fonts only cover ISO 8859-15 so emoji would end up as blanks, these
+/// are the most common ones written out instead
+const DEFAULTS: [(char, &str); 24] = [
+    ('😀', ":D"),
+    ('😃', ":D"),
+    ('😄', ":D"),
+    ('😁', ":D"),
+    ('😂', "xD"),
+    ('🤣', "xD"),
+    ('🙂', ":)"),
+    ('😊', ":)"),
+    ('😉', ";)"),
+    ('😛', ":P"),
+    ('😜', ";P"),
+    ('😢', ":'("),
+    ('😭', ":'("),
+    ('🙁', ":("),
+    ('😞', ":("),
+    ('😮', ":O"),
+    ('❤', "<3"),
+    ('👍', "(y)"),
+    ('👎', "(n)"),
+    ('🔥', "(fire)"),
+    ('🎵', "(note)"),
+    ('🎶', "(notes)"),
+    ('✅', "[x]"),
+    ('❌', "[ ]"),
+];
+
+/// The settings of the `[emoji]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmojiSettings {
+    /// Replaces or adds to the defaults, an empty replacement removes the
+    /// emoji. Sequences of several characters, e.g. flags, work as well.
+    substitutions: HashMap<String, String>,
+}
+
+lazy_static! {
+    static ref SUBSTITUTIONS: RwLock<HashMap<String, String>> = RwLock::new(defaults());
+}
+
+fn defaults() -> HashMap<String, String> {
+    DEFAULTS
+        .iter()
+        .map(|(emoji, text)| (emoji.to_string(), text.to_string()))
+        .collect()
+}
+
+/// Applies the substitutions of the settings, this has to happen before the
+/// providers are created as some of them lay out their text right away
+pub fn configure(settings: EmojiSettings) {
+    let mut substitutions = defaults();
+    substitutions.extend(
+        settings
+            .substitutions
+            .into_iter()
+            .filter(|(emoji, _)| !emoji.is_empty()),
+    );
+
+    if let Ok(mut current) = SUBSTITUTIONS.write() {
+        *current = substitutions;
+    }
+}
+
+/// Zero width joiners and variation selectors turn several characters into a
+/// single emoji, they don't mean anything on their own
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200d}' | '\u{fe0e}' | '\u{fe0f}')
+}
+
+/// Replaces the emoji in `text`, text without any of them is returned as is
+pub fn substitute(text: &str) -> Cow<'_, str> {
+    let substitutions = match SUBSTITUTIONS.read() {
+        Ok(substitutions) => substitutions,
+        Err(_) => return Cow::Borrowed(text),
+    };
+
+    if !text.chars().any(is_invisible)
+        && !substitutions
+            .keys()
+            .any(|emoji| text.contains(emoji.as_str()))
+    {
+        return Cow::Borrowed(text);
+    }
+
+    // Sequences are replaced before their joiners are dropped so they can be
+    // substituted as a whole
+    let mut substituted = text.to_string();
+    for (emoji, replacement) in substitutions.iter() {
+        if substituted.contains(emoji.as_str()) {
+            substituted = substituted.replace(emoji.as_str(), replacement);
+        }
+    }
+    substituted.retain(|c| !is_invisible(c));
+
+    Cow::Owned(substituted)
+}
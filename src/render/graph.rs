@@ -0,0 +1,99 @@
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::Point,
+    pixelcolor::BinaryColor,
+    primitives::{Line, Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use std::collections::VecDeque;
+
+/// How the values of a [`Graph`] are drawn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GraphStyle {
+    /// The area below the values is filled
+    Filled,
+    /// The values are connected by a line, this way several graphs can share
+    /// the same area
+    Line,
+}
+
+/// A history of values that scrolls from right to left. Every value takes a
+/// column of pixels with the newest one on the right.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    values: VecDeque<f32>,
+    capacity: usize,
+    style: GraphStyle,
+}
+
+impl Graph {
+    /// `capacity` should be the width of the area the graph is drawn in
+    pub fn new(capacity: usize, style: GraphStyle) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+            style,
+        }
+    }
+
+    /// Adds a value, the oldest one is dropped once the graph is full
+    pub fn push(&mut self, value: f32) {
+        if self.values.len() >= self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// The largest value, this scales graphs that don't have a fixed maximum
+    pub fn max(&self) -> f32 {
+        self.values.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Draws the values into `area`, `max` is at the top of it and larger
+    /// values are cut off
+    pub fn draw<D>(&self, target: &mut D, area: Rectangle, max: f32) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let height = area.size.height as i32;
+        if height == 0 || max <= 0.0 {
+            return Ok(());
+        }
+
+        let bottom = area.top_left.y + height - 1;
+        let right = area.top_left.x + area.size.width as i32 - 1;
+        let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+        let points = self
+            .values
+            .iter()
+            .rev()
+            .take(area.size.width as usize)
+            .enumerate()
+            .map(|(column, value)| {
+                let y = ((value / max).clamp(0.0, 1.0) * (height - 1) as f32).round() as i32;
+                Point::new(right - column as i32, bottom - y)
+            });
+
+        match self.style {
+            GraphStyle::Filled => {
+                for point in points {
+                    Line::new(point, Point::new(point.x, bottom))
+                        .into_styled(style)
+                        .draw(target)?;
+                }
+            }
+            GraphStyle::Line => {
+                let mut previous = None;
+                for point in points {
+                    Line::new(previous.unwrap_or(point), point)
+                        .into_styled(style)
+                        .draw(target)?;
+                    previous = Some(point);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
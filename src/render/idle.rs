@@ -0,0 +1,61 @@
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, Drawable, Pixel};
+use serde::Deserialize;
+
+/// What's shown on the display once nothing happened for a while
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleMode {
+    /// Turns off every pixel
+    #[default]
+    Blank,
+    /// A single pixel that bounces off the edges so it's obvious that the
+    /// display is still on
+    Dot,
+}
+
+/// Keeps the OLED from burning in by replacing the content after the
+/// configured timeout
+#[derive(Debug, Copy, Clone)]
+pub struct ScreenSaver {
+    mode: IdleMode,
+    position: Point,
+    velocity: Point,
+}
+
+impl ScreenSaver {
+    pub fn new(mode: IdleMode) -> Self {
+        Self {
+            mode,
+            position: Point::zero(),
+            velocity: Point::new(1, 1),
+        }
+    }
+
+    /// Moves the dot one step further, flipping its direction at the edges
+    fn advance(&mut self) {
+        let next = self.position + self.velocity;
+
+        if !(0..128).contains(&next.x) {
+            self.velocity.x = -self.velocity.x;
+        }
+        if !(0..40).contains(&next.y) {
+            self.velocity.y = -self.velocity.y;
+        }
+
+        self.position += self.velocity;
+    }
+
+    /// Advances the animation and returns the frame that should be shown
+    pub fn next_frame(&mut self) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+
+        if self.mode == IdleMode::Dot {
+            self.advance();
+            Pixel(self.position, BinaryColor::On).draw(&mut buffer)?;
+        }
+
+        Ok(buffer)
+    }
+}
@@ -1,13 +1,17 @@
 use std::{
-    cell::RefCell,
-    fs::File,
+    cell::Cell,
+    collections::hash_map::DefaultHasher,
+    fs::{self, File},
+    hash::{Hash, Hasher},
     io::Read,
-    rc::Rc,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicUsize, Ordering},
     time::{Duration, Instant},
 };
 
-use apex_hardware::FrameBuffer;
+use crate::state;
+use anyhow::{anyhow, Result};
+use apex_hardware::{BlendMode, FrameBuffer};
 use embedded_graphics::{
     image::{Image, ImageRaw},
     pixelcolor::BinaryColor,
@@ -19,14 +23,17 @@ use image::{AnimationDecoder, DynamicImage};
 static GIF_MISSING: &[u8] = include_bytes!("./../../assets/gif_missing.gif");
 static DISPLAY_HEIGHT: i32 = 40;
 static DISPLAY_WIDTH: i32 = 128;
+/// Change this whenever the conversion of the images changes so the old
+/// cache entries aren't used anymore
+static CACHE_VERSION: u8 = 1;
 
 pub struct ImageRenderer {
-    stop: Point,
-    origin: Point,
-    decoded_frames: Vec<Vec<u8>>,
+    /// Every frame is only rendered once when the image is loaded, showing it
+    /// is just a copy after that
+    frames: Vec<FrameBuffer>,
     current_frame: AtomicUsize,
     delays: Vec<u16>,
-    time_frame_last_update: Rc<RefCell<Instant>>,
+    time_frame_last_update: Cell<Instant>,
 }
 
 impl ImageRenderer {
@@ -225,21 +232,101 @@ impl ImageRenderer {
                               // rendering
         }
 
+        //render the frames once instead of drawing them pixel by pixel every tick
+        let frames = decoded_frames
+            .iter()
+            .map(|frame_data| {
+                let mut frame = FrameBuffer::new();
+                let raw_image_frame = ImageRaw::<BinaryColor>::new(frame_data, image_width as u32);
+                let _ = Image::new(&raw_image_frame, origin).draw(&mut frame);
+                frame
+            })
+            .collect();
+
+        Self::from_frames(frames, delays)
+    }
+
+    fn from_frames(frames: Vec<FrameBuffer>, delays: Vec<u16>) -> Self {
         Self {
-            stop,
-            origin,
-            decoded_frames,
+            frames,
             current_frame: AtomicUsize::new(0),
             delays,
-            time_frame_last_update: Rc::new(RefCell::new(Instant::now())),
+            time_frame_last_update: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Where the converted frames of the image in `buffer` are cached. The
+    /// name depends on the content of the image and the area it's drawn in so
+    /// a changed file is converted again.
+    fn cache_path(buffer: &[u8], origin: Point, stop: Point) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        CACHE_VERSION.hash(&mut hasher);
+        buffer.hash(&mut hasher);
+        (origin.x, origin.y, stop.x, stop.y).hash(&mut hasher);
+
+        state::cache_dir()
+            .join("images")
+            .join(format!("{:016x}.bin", hasher.finish()))
+    }
+
+    /// Loads the frames that were converted by a previous run. Every frame is
+    /// stored as its delay followed by the raw frame buffer.
+    fn read_cache(path: &Path) -> Result<Self> {
+        let data = fs::read(path)?;
+        let frame_size = FrameBuffer::new().framebuffer.as_raw_slice().len();
+        let entry_size = 2 + frame_size;
+        if data.is_empty() || data.len() % entry_size != 0 {
+            return Err(anyhow!("{} is corrupted", path.display()));
         }
+
+        let mut frames = Vec::new();
+        let mut delays = Vec::new();
+        for entry in data.chunks(entry_size) {
+            delays.push(u16::from_le_bytes([entry[0], entry[1]]));
+            let mut frame = FrameBuffer::new();
+            frame
+                .framebuffer
+                .as_raw_mut_slice()
+                .copy_from_slice(&entry[2..]);
+            frames.push(frame);
+        }
+
+        Ok(Self::from_frames(frames, delays))
+    }
+
+    fn write_cache(&self, path: &Path) -> Result<()> {
+        let mut data = Vec::new();
+        for (frame, delay) in self.frames.iter().zip(&self.delays) {
+            data.extend_from_slice(&delay.to_le_bytes());
+            data.extend_from_slice(frame.framebuffer.as_raw_slice());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)?;
+        Ok(())
     }
 
     pub fn new(origin: Point, stop: Point, mut file: File) -> Self {
         let mut buffer = Vec::new();
         if let Ok(_) = file.read_to_end(&mut buffer) {
+            //decoding large gifs takes a while so the result is only computed once
+            let cache = Self::cache_path(&buffer, origin, stop);
+            match Self::read_cache(&cache) {
+                Ok(renderer) => {
+                    log::debug!("Loaded the image from {}", cache.display());
+                    return renderer;
+                }
+                Err(e) => log::debug!("The image isn't cached yet: {}", e),
+            }
+
             if let Ok(image) = image::load_from_memory(&buffer) {
-                Self::read_dynamic_image(origin, stop, image, &buffer)
+                let renderer = Self::read_dynamic_image(origin, stop, image, &buffer);
+                if let Err(e) = renderer.write_cache(&cache) {
+                    log::warn!("Failed to cache the image: {}", e);
+                }
+                renderer
             } else {
                 log::error!("Failed to decode the image.");
                 Self::new_error(origin, stop)
@@ -263,21 +350,25 @@ impl ImageRenderer {
         }
     }
 
-    pub fn draw(&self, target: &mut FrameBuffer) -> bool {
-        let frame = self.current_frame.load(Ordering::Relaxed);
-
-        //get the data for the specified frame
-        let frame_data = &self.decoded_frames[frame];
+    /// The frame that's shown right now
+    pub fn frame(&self) -> &FrameBuffer {
+        &self.frames[self.current_frame.load(Ordering::Relaxed)]
+    }
 
-        //convert the data to an ImageRaw
-        let raw_image_frame =
-            ImageRaw::<BinaryColor>::new(&frame_data, (self.stop.x - self.origin.x) as u32);
+    /// Draws the current frame over `target` and moves on to the next frame if
+    /// it's due. Returns `true` once the image starts over.
+    pub fn draw(&self, target: &mut FrameBuffer) -> bool {
+        target.blit(self.frame(), BlendMode::Replace);
+        self.advance()
+    }
 
-        //draw the ImageRaw on the buffer
-        let _ = Image::new(&raw_image_frame, self.origin).draw(target);
+    /// Moves on to the next frame once the current one was shown for long
+    /// enough. Returns `true` once the image starts over.
+    pub fn advance(&self) -> bool {
+        let frame = self.current_frame.load(Ordering::Relaxed);
 
         //detect if we should change the frame
-        let last_display_time = self.time_frame_last_update.borrow().clone();
+        let last_display_time = self.time_frame_last_update.get();
         let current_time = Instant::now();
         let elapsed_time = current_time - last_display_time;
 
@@ -286,12 +377,12 @@ impl ImageRenderer {
             // crate! before we had a *10 because of it
 
             //update the variable only if we update the frame
-            *self.time_frame_last_update.borrow_mut() = current_time;
+            self.time_frame_last_update.set(current_time);
 
             //increment the current_frame using atomic operations
             let next_frame = frame + 1;
 
-            let has_gif_ended = next_frame >= self.decoded_frames.len();
+            let has_gif_ended = next_frame >= self.frames.len();
             if has_gif_ended {
                 //reset to frame 0
                 self.current_frame.store(0, Ordering::Relaxed);
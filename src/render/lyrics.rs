@@ -0,0 +1,193 @@
+//! Synced lyrics in the LRC format, e.g. `[01:23.45]Some line`, for the
+//! music screen
+
+use apex_music::Metadata;
+use log::debug;
+#[cfg(feature = "http")]
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "http")]
+const LRCLIB_URL: &str = "https://lrclib.net/api/search";
+
+#[cfg(feature = "http")]
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Where the music screen looks for lyrics
+#[derive(Debug, Clone, Default)]
+pub struct LyricsSettings {
+    /// A folder with files like `Artist - Title.lrc`, files right next to the
+    /// track are found either way
+    pub directory: Option<PathBuf>,
+    /// Whether to ask lrclib.net for the lyrics that aren't found locally, this
+    /// sends the title and the artists of every track there
+    pub online: bool,
+}
+
+/// The lines of a song along with when they're sung
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lyrics {
+    /// The start of every line in microseconds, ordered by it
+    lines: Vec<(u64, String)>,
+}
+
+/// Reads a timestamp like `01:23.45` or `01:23` in microseconds
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    let seconds = seconds.trim().parse::<f64>().ok()?;
+    if seconds < 0.0 {
+        return None;
+    }
+
+    Some(minutes * 60_000_000 + (seconds * 1_000_000.0) as u64)
+}
+
+impl Lyrics {
+    /// Parses the contents of an `.lrc` file. Lines may have several
+    /// timestamps if they're sung more than once, tags like `[ar:Artist]` are
+    /// skipped apart from `[offset:...]`.
+    pub fn parse(lrc: &str) -> Self {
+        let mut lines = Vec::new();
+        // In milliseconds, positive offsets make the lines show up earlier
+        let mut offset = 0_i64;
+
+        for line in lrc.lines() {
+            let mut rest = line.trim();
+            let mut starts = Vec::new();
+
+            while let Some(tag) = rest.strip_prefix('[') {
+                let (tag, after) = match tag.split_once(']') {
+                    Some(pair) => pair,
+                    None => break,
+                };
+                rest = after;
+
+                if let Some(value) = tag.strip_prefix("offset:") {
+                    offset = value.trim().parse().unwrap_or(0);
+                } else if let Some(start) = parse_timestamp(tag) {
+                    starts.push(start);
+                }
+            }
+
+            for start in starts {
+                lines.push((start, rest.trim().to_string()));
+            }
+        }
+
+        let offset = offset * 1000;
+        let mut lines = lines
+            .into_iter()
+            .map(|(start, line)| ((start as i64 - offset).max(0) as u64, line))
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|(start, _)| *start);
+        Self { lines }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The line that's sung at `position`, nothing before the first one
+    pub fn line(&self, position: u64) -> Option<&str> {
+        let index = self.lines.partition_point(|(start, _)| *start <= position);
+        index
+            .checked_sub(1)
+            .map(|index| self.lines[index].1.as_str())
+    }
+}
+
+/// Turns a `file://` URL as the players report it into a path, e.g.
+/// `file:///music/Some%20song.flac`
+fn file_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+
+    while let Some((&byte, after)) = rest.split_first() {
+        rest = after;
+        if byte == b'%' && rest.len() >= 2 {
+            if let Ok(decoded) = u8::from_str_radix(std::str::from_utf8(&rest[..2]).ok()?, 16) {
+                bytes.push(decoded);
+                rest = &rest[2..];
+                continue;
+            }
+        }
+        bytes.push(byte);
+    }
+
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// Reads `path` if it's there and has any synced lines
+async fn read(path: &Path) -> Option<Lyrics> {
+    let lyrics = Lyrics::parse(&tokio::fs::read_to_string(path).await.ok()?);
+    if lyrics.is_empty() {
+        return None;
+    }
+
+    debug!("Found lyrics in {}", path.display());
+    Some(lyrics)
+}
+
+#[cfg(feature = "http")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LrclibTrack {
+    synced_lyrics: Option<String>,
+}
+
+/// Asks lrclib.net for the lyrics of `title` by `artists`
+#[cfg(feature = "http")]
+async fn fetch(title: &str, artists: &str) -> anyhow::Result<Option<Lyrics>> {
+    let client = reqwest::ClientBuilder::new()
+        .user_agent(APP_USER_AGENT)
+        .timeout(std::time::Duration::from_secs(5))
+        .build()?;
+
+    let tracks = client
+        .get(LRCLIB_URL)
+        .query(&[("track_name", title), ("artist_name", artists)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<LrclibTrack>>()
+        .await?;
+
+    Ok(tracks
+        .into_iter()
+        .filter_map(|track| track.synced_lyrics)
+        .map(|lrc| Lyrics::parse(&lrc))
+        .find(|lyrics| !lyrics.is_empty()))
+}
+
+/// Looks for the lyrics of a track, first right next to its file, then in
+/// the folder of the settings and at last online if that's turned on
+pub async fn find<T: Metadata>(metadata: &T, settings: &LyricsSettings) -> Option<Lyrics> {
+    if let Some(path) = metadata.url().as_deref().and_then(file_path) {
+        if let Some(lyrics) = read(&path.with_extension("lrc")).await {
+            return Some(lyrics);
+        }
+    }
+
+    let title = metadata.title().ok()?;
+    let artists = metadata.artists().unwrap_or_default();
+
+    if let Some(directory) = &settings.directory {
+        // Slashes would end up as folders
+        let name = format!("{} - {}.lrc", artists, title).replace('/', "_");
+        if let Some(lyrics) = read(&directory.join(name)).await {
+            return Some(lyrics);
+        }
+    }
+
+    #[cfg(feature = "http")]
+    if settings.online {
+        match fetch(&title, &artists).await {
+            Ok(lyrics) => return lyrics,
+            Err(e) => debug!("Couldn't get the lyrics from lrclib.net: {}", e),
+        }
+    }
+
+    None
+}
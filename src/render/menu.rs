@@ -0,0 +1,202 @@
+use crate::render::{accessibility, brightness::Brightness, pages::Pages};
+use anyhow::Result;
+use apex_hardware::{BlendMode, FrameBuffer};
+use apex_input::Command;
+use embedded_graphics::{
+    geometry::{Point, Size},
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+
+/// The font is 10 pixels high so four rows fit on the display
+const ROW_HEIGHT: i32 = 10;
+const VISIBLE_ROWS: usize = 4;
+/// The amount of characters that fit in a row next to the margin
+const MAX_CHARS: usize = 21;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Entry {
+    /// Opens the list of pages
+    Screens,
+    /// Jumps to the page at this index
+    Screen(usize),
+    Focus,
+    Dnd,
+    LargeText,
+    Rotation,
+    Brightness,
+    Shutdown,
+    /// Goes back to the main menu from the list of pages
+    Back,
+    Close,
+}
+
+/// A menu on the display itself that's navigated with the same hotkeys that
+/// switch between the screens otherwise
+#[derive(Debug, Default)]
+pub struct Menu {
+    open: bool,
+    /// Whether the list of pages is shown instead of the main menu
+    screens: bool,
+    selected: usize,
+    rotation_paused: bool,
+    brightness: Brightness,
+}
+
+impl Menu {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Whether the automatic rotation was paused through the menu
+    pub fn is_rotation_paused(&self) -> bool {
+        self.rotation_paused
+    }
+
+    pub fn brightness(&self) -> Brightness {
+        self.brightness
+    }
+
+    /// Whether `command` is meant for the menu instead of the screens
+    pub fn handles(&self, command: &Command) -> bool {
+        match command {
+            Command::ToggleMenu => true,
+            Command::NextSource | Command::PreviousSource | Command::Select => self.open,
+            _ => false,
+        }
+    }
+
+    fn entries(&self, pages: &Pages) -> Vec<Entry> {
+        if self.screens {
+            std::iter::once(Entry::Back)
+                .chain((0..pages.len()).map(Entry::Screen))
+                .collect()
+        } else {
+            vec![
+                Entry::Screens,
+                Entry::Focus,
+                Entry::Dnd,
+                Entry::LargeText,
+                Entry::Rotation,
+                Entry::Brightness,
+                Entry::Shutdown,
+                Entry::Close,
+            ]
+        }
+    }
+
+    fn label(&self, entry: Entry, pages: &Pages, focus: bool, dnd: bool) -> String {
+        match entry {
+            Entry::Screens => String::from("Go to screen"),
+            Entry::Screen(index) => pages.title(index).unwrap_or_default(),
+            Entry::Focus if focus => String::from("Stop focus mode"),
+            Entry::Focus => String::from("Start focus mode"),
+            Entry::Dnd if dnd => String::from("Do not disturb: on"),
+            Entry::Dnd => String::from("Do not disturb: off"),
+            Entry::LargeText if accessibility::large_text() => String::from("Large text: on"),
+            Entry::LargeText => String::from("Large text: off"),
+            Entry::Rotation if self.rotation_paused => String::from("Resume rotation"),
+            Entry::Rotation => String::from("Pause rotation"),
+            Entry::Brightness => format!("Brightness: {}%", self.brightness.percent()),
+            Entry::Shutdown => String::from("Shut down"),
+            Entry::Back => String::from("Back"),
+            Entry::Close => String::from("Close"),
+        }
+    }
+
+    /// Moves through the menu according to `command`. Returns the command the
+    /// scheduler should run if an entry stands for one.
+    pub fn input(&mut self, command: Command, pages: &Pages) -> Option<Command> {
+        let entries = self.entries(pages);
+
+        match command {
+            Command::ToggleMenu if self.open => self.close(),
+            Command::ToggleMenu => {
+                self.open = true;
+                self.screens = false;
+                self.selected = 0;
+            }
+            Command::NextSource => self.selected = (self.selected + 1) % entries.len(),
+            Command::PreviousSource => {
+                self.selected = (self.selected + entries.len() - 1) % entries.len();
+            }
+            Command::Select => match entries.get(self.selected).copied()? {
+                Entry::Screens => {
+                    self.screens = true;
+                    // Start out at the page that's currently shown, `Back` comes first
+                    self.selected = pages.index() + 1;
+                }
+                Entry::Screen(index) => {
+                    self.close();
+                    return pages
+                        .name_at(index)
+                        .map(|name| Command::SetSource(name.to_string()));
+                }
+                Entry::Focus => {
+                    self.close();
+                    return Some(Command::ToggleFocus);
+                }
+                // This one stays open to show the new state
+                Entry::Dnd => return Some(Command::ToggleDnd),
+                // The screens start over with the new layout
+                Entry::LargeText => {
+                    self.close();
+                    return Some(Command::ToggleLargeText);
+                }
+                Entry::Rotation => self.rotation_paused = !self.rotation_paused,
+                Entry::Brightness => self.brightness = self.brightness.next(),
+                Entry::Shutdown => {
+                    self.close();
+                    return Some(Command::Shutdown);
+                }
+                Entry::Back => {
+                    self.screens = false;
+                    self.selected = 0;
+                }
+                Entry::Close => self.close(),
+            },
+            command => return Some(command),
+        }
+
+        None
+    }
+
+    /// Draws the visible part of the menu, the selected entry is inverted
+    pub fn render(&self, pages: &Pages, focus: bool, dnd: bool) -> Result<FrameBuffer> {
+        let mut buffer = FrameBuffer::new();
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+
+        // Scroll just far enough to keep the selected entry on the screen
+        let first = self.selected.saturating_sub(VISIBLE_ROWS - 1);
+        let entries = self.entries(pages);
+
+        for (row, (index, entry)) in entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_ROWS)
+            .enumerate()
+        {
+            let y = row as i32 * ROW_HEIGHT;
+            let label = self.label(*entry, pages, focus, dnd);
+            let label = label.chars().take(MAX_CHARS).collect::<String>();
+            Text::with_baseline(&label, Point::new(2, y), style, Baseline::Top)
+                .draw(&mut buffer)?;
+
+            if index == self.selected {
+                Rectangle::new(Point::new(0, y), Size::new(128, ROW_HEIGHT as u32))
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                    .draw(&mut buffer.blend(BlendMode::Xor))?;
+            }
+        }
+
+        Ok(buffer)
+    }
+}
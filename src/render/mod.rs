@@ -1,12 +1,47 @@
+pub(crate) mod accessibility;
+pub(crate) mod alert;
+// Only some of the providers load icons from the settings
+#[allow(dead_code)]
+pub(crate) mod assets;
+pub(crate) mod bidi;
+pub(crate) mod brightness;
 #[cfg(feature = "debug")]
 pub(crate) mod debug;
+// Only some of the providers need large digits
+#[allow(dead_code)]
+pub(crate) mod digits;
 pub(crate) mod display;
+pub(crate) mod emoji;
+// Only some of the providers draw graphs
+#[allow(dead_code)]
+pub(crate) mod graph;
+pub(crate) mod idle;
+// Only the music screen shows lyrics
+#[allow(dead_code)]
+pub(crate) mod lyrics;
 // This technically doesn't need DBus but nothing else implements it atm
 #[cfg(feature = "image")]
 pub(crate) mod image;
+pub(crate) mod menu;
 #[allow(dead_code)]
 pub(crate) mod notifications;
+pub(crate) mod overlay;
+pub(crate) mod pages;
+// Only the music providers draw players
+#[allow(dead_code)]
+pub(crate) mod player;
+// Only the providers that poll web services share a poller
+#[allow(dead_code)]
+pub(crate) mod poller;
+pub(crate) mod preview;
+pub mod registry;
 pub mod scheduler;
+pub(crate) mod shift;
 pub(crate) mod stream;
+// Only some of the providers use templates
+#[allow(dead_code)]
+pub(crate) mod template;
 pub(crate) mod text;
+pub(crate) mod timers;
 pub(crate) mod util;
+pub(crate) mod watchdog;
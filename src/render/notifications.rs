@@ -1,6 +1,7 @@
 use crate::render::display::ContentProvider;
 use anyhow::{anyhow, Result};
 use async_stream::try_stream;
+use chrono::NaiveTime;
 use embedded_graphics::{
     geometry::{OriginDimensions, Point, Size},
     image::Image,
@@ -8,8 +9,12 @@ use embedded_graphics::{
     Drawable,
 };
 use num_traits::AsPrimitive;
+use serde::Deserialize;
+use std::{collections::VecDeque, convert::TryFrom};
 
 use crate::render::{
+    alert::Alert,
+    emoji,
     scheduler::{TICKS_PER_SECOND, TICK_LENGTH},
     text::{Scrollable, ScrollableBuilder},
     util::ProgressBar,
@@ -18,21 +23,124 @@ use embedded_graphics::{
     mono_font::{iso_8859_15, MonoFont, MonoTextStyle},
     text::Text,
 };
+use futures::StreamExt;
 use futures_core::stream::Stream;
 
 use apex_hardware::FrameBuffer;
+pub use apex_provider::Urgency;
 use tinybmp::Bmp;
 use tokio::{
     time,
     time::{Duration, MissedTickBehavior},
 };
 
+/// How often the screen flashes when a critical notification comes up
+const CRITICAL_FLASHES: u32 = 3;
+
+/// The settings of the `[notifications]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationSettings {
+    /// Notifications are hidden between these two times of the day
+    quiet_hours: Option<QuietHours>,
+    /// The hotkey that takes a notification off the screen
+    #[cfg_attr(not(feature = "hotkeys"), allow(dead_code))]
+    pub dismiss: String,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            quiet_hours: None,
+            dismiss: String::from("ALT+SHIFT+X"),
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// Whether notifications are hidden at `time`
+    pub fn is_quiet(&self, time: NaiveTime) -> bool {
+        self.quiet_hours.map_or(false, |QuietHours { start, end }| {
+            if start <= end {
+                start <= time && time < end
+            } else {
+                // The quiet hours go past midnight
+                time >= start || time < end
+            }
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(try_from = "[String; 2]")]
+struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl TryFrom<[String; 2]> for QuietHours {
+    type Error = anyhow::Error;
+
+    fn try_from([start, end]: [String; 2]) -> Result<Self, Self::Error> {
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|e| anyhow!("`{}` isn't a time of day like `22:00`: {}", time, e))
+        };
+
+        Ok(Self {
+            start: parse(&start)?,
+            end: parse(&end)?,
+        })
+    }
+}
+
 pub struct Notification {
     frame: FrameBuffer,
     ticks: u32,
     title: Scrollable,
+    /// The text of the title, it's published while the notification is shown
+    summary: String,
     scroll: bool,
     content: String,
+    urgency: Urgency,
+}
+
+/// How long a notification stays on the screen before and after its title
+/// scrolled by
+fn hold_ticks(urgency: Urgency) -> usize {
+    match urgency {
+        Urgency::Low => TICKS_PER_SECOND / 2,
+        Urgency::Normal => TICKS_PER_SECOND,
+        Urgency::Critical => TICKS_PER_SECOND * 3,
+    }
+}
+
+/// The notifications that wait for their turn, the more urgent ones go first
+/// and the others keep the order they arrived in
+#[derive(Default)]
+pub struct NotificationQueue {
+    waiting: VecDeque<Notification>,
+}
+
+impl NotificationQueue {
+    pub fn push(&mut self, notification: Notification) {
+        // A burst of unimportant notifications only shows the latest one
+        if notification.urgency == Urgency::Low {
+            self.waiting
+                .retain(|waiting| waiting.urgency != Urgency::Low);
+        }
+
+        let position = self
+            .waiting
+            .iter()
+            .position(|waiting| waiting.urgency < notification.urgency)
+            .unwrap_or(self.waiting.len());
+        self.waiting.insert(position, notification);
+    }
+
+    pub fn pop(&mut self) -> Option<Notification> {
+        self.waiting.pop_front()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +158,7 @@ pub struct NotificationBuilder<'a> {
     content: Option<String>,
     icon: Option<Icon<'a>>,
     font: Option<&'a MonoFont<'a>>,
+    urgency: Urgency,
 }
 
 pub trait NotificationProvider {
@@ -61,6 +170,27 @@ pub trait NotificationProvider {
     fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>>;
 }
 
+impl Notification {
+    pub fn urgency(&self) -> Urgency {
+        self.urgency
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+
+    /// Plays the notification to the end. Unlike `ContentProvider::stream` the
+    /// returned stream owns the notification so it can be kept around.
+    pub fn play(mut self) -> impl Stream<Item = Result<FrameBuffer>> {
+        try_stream! {
+            let mut frames = Box::pin(self.stream()?);
+            while let Some(frame) = frames.next().await {
+                yield frame?;
+            }
+        }
+    }
+}
+
 impl ContentProvider for Notification {
     type ContentStream<'a> = impl Stream<Item = Result<FrameBuffer>> + 'a;
 
@@ -71,6 +201,8 @@ impl ContentProvider for Notification {
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
         let origin = Point::new(117, 29);
         let progress = ProgressBar::new(origin, self.ticks as f32);
+        // Critical notifications flash the screen a few times when they come up
+        let alert = (self.urgency == Urgency::Critical).then(|| Alert::screen(CRITICAL_FLASHES));
 
         // TODO: Remove hardcoded font
         let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
@@ -85,6 +217,9 @@ impl ContentProvider for Notification {
                 })?;
                 Text::new(&self.content, Point::new(3 + 24, 10 + 10), style).draw(&mut image)?;
                 progress.draw_at(i as f32, &mut image)?;
+                if let Some(alert) = &alert {
+                    alert.apply(&mut image)?;
+                }
                 yield image;
                 interval.tick().await;
             }
@@ -116,6 +251,11 @@ impl<'a> NotificationBuilder<'a> {
         self
     }
 
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = urgency;
+        self
+    }
+
     fn title(&self) -> &'a str {
         self.title.unwrap_or("Notification")
     }
@@ -162,7 +302,8 @@ impl<'a> NotificationBuilder<'a> {
             0
         };
 
-        (TICKS_PER_SECOND + scroll_time + TICKS_PER_SECOND).as_()
+        let hold = hold_ticks(self.urgency);
+        (hold + scroll_time + hold).as_()
     }
 
     pub fn build(self) -> Result<Notification> {
@@ -195,8 +336,12 @@ impl<'a> NotificationBuilder<'a> {
             frame: base_image,
             ticks: self.required_ticks(),
             title,
+            summary: self.title().to_string(),
             scroll: self.needs_scroll(),
-            content: self.content.unwrap_or_default(),
+            content: self.content.map_or_else(String::new, |content| {
+                emoji::substitute(&content).into_owned()
+            }),
+            urgency: self.urgency,
         })
     }
 }
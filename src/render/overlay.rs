@@ -0,0 +1,59 @@
+use anyhow::Result;
+use apex_hardware::{BlendMode, FrameBuffer};
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use futures::Stream;
+
+/// Something that's drawn on top of the pages, e.g. a status icon or a
+/// banner. Overlays aren't drawn over notifications, the menu or the screen
+/// saver.
+#[derive(Debug, Copy, Clone)]
+pub struct Overlay {
+    image: FrameBuffer,
+    mode: BlendMode,
+    /// The part of the page that's cleared before the overlay is drawn
+    background: Option<Rectangle>,
+}
+
+impl Overlay {
+    /// `mode` decides how `image` is combined with the page, `BlendMode::Xor`
+    /// keeps small icons visible on top of anything while
+    /// `BlendMode::Replace` hides the page altogether
+    pub fn new(image: FrameBuffer, mode: BlendMode) -> Self {
+        Self {
+            image,
+            mode,
+            background: None,
+        }
+    }
+
+    /// Clears `area` of the page first so the overlay sits on a box of its
+    /// own, e.g. a volume bar that shouldn't mix with the page below
+    pub fn with_background(mut self, area: Rectangle) -> Self {
+        self.background = Some(area);
+        self
+    }
+
+    pub fn apply(&self, frame: &mut FrameBuffer) -> Result<()> {
+        if let Some(area) = self.background {
+            area.into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+                .draw(frame)?;
+        }
+        frame.blit(&self.image, self.mode);
+        Ok(())
+    }
+}
+
+/// Overlays are shown until their provider sends the next one, `None` takes
+/// it off the screen
+pub trait OverlayProvider {
+    type OverlayStream<'a>: Stream<Item = Result<Option<Overlay>>> + 'a
+    where
+        Self: 'a;
+
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::OverlayStream<'this>>;
+}
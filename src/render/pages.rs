@@ -0,0 +1,306 @@
+use crate::render::display::ProviderInput;
+use anyhow::Result;
+use apex_hardware::{BlendMode, FrameBuffer};
+use apex_input::Command;
+use embedded_graphics::{draw_target::DrawTarget, geometry::Point, pixelcolor::BinaryColor, Pixel};
+use futures::{future, stream, Stream, StreamExt};
+use log::warn;
+use serde::Deserialize;
+use std::{
+    cell::{Cell, RefCell},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::watch;
+
+/// The distance between two dots of the page indicator
+const INDICATOR_SPACING: i32 = 4;
+
+pub type FrameStream<'a> = Pin<Box<dyn Stream<Item = Result<FrameBuffer>> + 'a>>;
+
+/// The settings of the `[pages]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageSettings {
+    /// Draws a dot for every page along the bottom row
+    pub indicator: bool,
+    /// The providers that share a page, they're drawn on top of each other.
+    /// Every other provider gets a page of its own.
+    pub layout: Vec<Vec<String>>,
+}
+
+/// A running provider that still needs a page
+pub struct Layer<'a> {
+    pub name: &'static str,
+    pub input: Option<Box<dyn ProviderInput>>,
+    pub activity: Option<watch::Receiver<bool>>,
+    /// Whether the provider wants its content moved around to prevent burn-in
+    pub shifted: bool,
+    pub stream: FrameStream<'a>,
+}
+
+struct Page {
+    /// The providers on this page, the first one is drawn at the bottom
+    names: Vec<&'static str>,
+    /// Input goes to the first provider on the page that accepts any
+    input: Option<RefCell<Box<dyn ProviderInput>>>,
+    /// Tells whether the providers have anything to show, `None` stands for a
+    /// provider that always does
+    activity: Vec<Option<watch::Receiver<bool>>>,
+    shifted: bool,
+    /// The latest frame of the page, the providers only send a new one once
+    /// their content changes so this is shown when coming back to the page
+    last_frame: Cell<Option<FrameBuffer>>,
+}
+
+impl Page {
+    fn is_active(&self) -> bool {
+        self.activity
+            .iter()
+            .any(|activity| activity.as_ref().map_or(true, |active| *active.borrow()))
+    }
+}
+
+/// Keeps track of the pages and which one of them is shown
+pub struct Pages {
+    pages: Vec<Page>,
+    current: Arc<AtomicUsize>,
+    indicator: bool,
+}
+
+/// Combines the frames of all `streams` into one, the later streams are drawn
+/// on top of the earlier ones
+fn compose(streams: Vec<FrameStream<'_>>) -> FrameStream<'_> {
+    if streams.len() == 1 {
+        return streams
+            .into_iter()
+            .next()
+            .expect("There's exactly one stream");
+    }
+
+    let count = streams.len();
+    let merged = stream::select_all(
+        streams
+            .into_iter()
+            .enumerate()
+            .map(|(index, stream)| stream.map(move |frame| (index, frame))),
+    )
+    // Every layer only sends a new frame when its own content changes so the
+    // last frame of every other layer is kept around
+    .scan(vec![None; count], |latest, (index, frame)| {
+        let frame = frame.map(|frame| {
+            latest[index] = Some(frame);
+            let mut buffer = FrameBuffer::new();
+            for layer in latest.iter().flatten() {
+                buffer.blit(layer, BlendMode::Mask);
+            }
+            buffer
+        });
+        future::ready(Some(frame))
+    });
+
+    Box::pin(merged)
+}
+
+impl Pages {
+    /// Puts `layers`, which are sorted by priority, on pages according to
+    /// `settings`. Pages are sorted by their provider with the highest
+    /// priority. The returned streams share their indices with the pages.
+    pub fn new<'a>(
+        layers: Vec<Layer<'a>>,
+        settings: &PageSettings,
+    ) -> (Self, Vec<FrameStream<'a>>) {
+        for name in settings.layout.iter().flatten() {
+            if !layers.iter().any(|layer| layer.name == name) {
+                warn!("There's no enabled provider called `{}`", name);
+            }
+        }
+
+        let mut layers = layers.into_iter().map(Some).collect::<Vec<_>>();
+        let mut pages = Vec::new();
+        let mut streams = Vec::new();
+
+        for index in 0..layers.len() {
+            let name = match &layers[index] {
+                Some(layer) => layer.name,
+                // This one already is on the page of a provider with a higher priority
+                None => continue,
+            };
+
+            let group = settings
+                .layout
+                .iter()
+                .find(|group| group.iter().any(|n| n == name));
+            let members = match group {
+                Some(group) => group
+                    .iter()
+                    .filter_map(|n| {
+                        layers
+                            .iter()
+                            .position(|l| matches!(l, Some(l) if l.name == n))
+                    })
+                    .collect::<Vec<_>>(),
+                None => vec![index],
+            };
+
+            let mut page = Page {
+                names: Vec::new(),
+                input: None,
+                activity: Vec::new(),
+                shifted: false,
+                last_frame: Cell::new(None),
+            };
+            let mut parts = Vec::new();
+            for member in members {
+                let layer = layers[member]
+                    .take()
+                    .expect("Every provider is only used once");
+                page.names.push(layer.name);
+                page.input = page.input.or_else(|| layer.input.map(RefCell::new));
+                page.activity.push(layer.activity);
+                page.shifted |= layer.shifted;
+                parts.push(layer.stream);
+            }
+
+            pages.push(page);
+            streams.push(compose(parts));
+        }
+
+        let pages = Self {
+            pages,
+            current: Arc::new(AtomicUsize::new(0)),
+            indicator: settings.indicator,
+        };
+
+        (pages, streams)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The index of the page that's currently shown, this is shared with the
+    /// stream that picks the content to show
+    pub fn handle(&self) -> Arc<AtomicUsize> {
+        self.current.clone()
+    }
+
+    /// The index of the page that's currently shown
+    pub fn index(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    fn current(&self) -> Option<&Page> {
+        self.pages.get(self.index())
+    }
+
+    /// Keeps `frame` as the latest frame of the current page
+    pub fn remember(&self, frame: FrameBuffer) {
+        if let Some(page) = self.current() {
+            page.last_frame.set(Some(frame));
+        }
+    }
+
+    /// The latest frame of the current page, `None` if it never sent one
+    pub fn last_frame(&self) -> Option<FrameBuffer> {
+        self.current().and_then(|page| page.last_frame.get())
+    }
+
+    pub fn show(&self, index: usize) {
+        self.current.store(index, Ordering::SeqCst);
+    }
+
+    pub fn next(&self) {
+        let next = self.current.load(Ordering::SeqCst).wrapping_add(1) % self.len();
+        self.show(next);
+    }
+
+    /// Moves on to the next page that has anything to show. Returns whether
+    /// another page is shown now.
+    pub fn next_active(&self) -> bool {
+        let current = self.index();
+        let next = (1..=self.len())
+            .map(|step| (current + step) % self.len())
+            .find(|index| self.pages[*index].is_active());
+
+        match next {
+            Some(next) if next != current => {
+                self.show(next);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn previous(&self) {
+        let previous = match self.current.load(Ordering::SeqCst) {
+            0 => self.len() - 1,
+            n => (n - 1) % self.len(),
+        };
+        self.show(previous);
+    }
+
+    /// The index of the page that shows the provider called `name`
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.pages
+            .iter()
+            .position(|page| page.names.iter().any(|n| *n == name))
+    }
+
+    /// The name of the bottom provider of the current page
+    pub fn name(&self) -> Option<&'static str> {
+        self.current().and_then(|page| page.names.first().copied())
+    }
+
+    /// The name of the bottom provider of the page at `index`
+    pub fn name_at(&self, index: usize) -> Option<&'static str> {
+        self.pages
+            .get(index)
+            .and_then(|page| page.names.first().copied())
+    }
+
+    /// Names the page at `index` after all of its providers
+    pub fn title(&self, index: usize) -> Option<String> {
+        self.pages.get(index).map(|page| page.names.join(" + "))
+    }
+
+    /// Hands `command` to the current page. Returns `true` if the page used
+    /// it.
+    pub fn handle_input(&self, command: Command) -> bool {
+        self.current()
+            .and_then(|page| page.input.as_ref())
+            .map_or(false, |input| input.borrow_mut().handle_input(command))
+    }
+
+    pub fn is_shifted(&self) -> bool {
+        self.current().map_or(false, |page| page.shifted)
+    }
+
+    pub fn any_shifted(&self) -> bool {
+        self.pages.iter().any(|page| page.shifted)
+    }
+
+    /// Draws a dot for every page along the bottom row, the one of the current
+    /// page is wider than the others. This is inverted on top of the content
+    /// so it's always visible.
+    pub fn draw_indicator(&self, buffer: &mut FrameBuffer) -> Result<()> {
+        if !self.indicator || self.len() < 2 {
+            return Ok(());
+        }
+
+        let current = self.current.load(Ordering::SeqCst) as i32;
+        let width = (self.len() as i32 - 1) * INDICATOR_SPACING + 1;
+        let start = 64 - width / 2;
+
+        let pixels = (0..self.len() as i32).flat_map(|page| {
+            let x = start + page * INDICATOR_SPACING;
+            let half = i32::from(page == current);
+            (x - half..=x + half).map(|x| Pixel(Point::new(x, 39), BinaryColor::On))
+        });
+
+        buffer.blend(BlendMode::Xor).draw_iter(pixels)
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::Result;
+use embedded_graphics::{
+    geometry::Size,
+    image::Image,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, Point, Primitive},
+    primitives::{Line, PrimitiveStyle, Rectangle, Triangle},
+    text::{Baseline, Text},
+    Drawable,
+};
+use tinybmp::Bmp;
+
+use crate::render::{
+    assets,
+    text::{ScrollableBuilder, StatefulScrollable},
+};
+use apex_hardware::FrameBuffer;
+use apex_music::{Metadata, PlaybackStatus, Progress};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::convert::TryInto;
+
+static NOTE_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/note.bmp"));
+static PAUSE_ICON: &[u8] = assets::checked(include_bytes!("./../../assets/pause.bmp"));
+
+lazy_static! {
+    static ref PAUSE_BMP: Bmp<'static, BinaryColor> = assets::bundled(PAUSE_ICON);
+}
+
+lazy_static! {
+    static ref NOTE_BMP: Bmp<'static, BinaryColor> = assets::bundled(NOTE_ICON);
+}
+lazy_static! {
+static ref PLAYER_TEMPLATE: FrameBuffer = {
+    let mut base = FrameBuffer::new();
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+    let points = vec![
+        (Point::new(0, 39), Point::new(127, 39)),
+        (Point::new(0, 39), Point::new(0, 39 - 5)),
+        (Point::new(127, 39), Point::new(127, 39 - 5)),
+    ];
+
+    // Draw a border for the progress bar
+    points
+        .iter()
+        .try_for_each(|(first, second)| {
+            Line::new(*first, *second)
+                .into_styled(style)
+                .draw(&mut base)
+        })
+        .expect("Failed to prepare template image for music player!");
+
+    base
+};
+}
+lazy_static! {
+    static ref PLAY_TEMPLATE: FrameBuffer = {
+        let mut base = *PLAYER_TEMPLATE;
+        Image::new(&*NOTE_BMP, Point::new(5, 5))
+            .draw(&mut base)
+            .expect("Failed to prepare 'play' template for music player");
+        base
+    };
+}
+lazy_static! {
+    static ref PAUSE_TEMPLATE: FrameBuffer = {
+        let mut base = *PLAYER_TEMPLATE;
+        Image::new(&*PAUSE_BMP, Point::new(5, 5))
+            .draw(&mut base)
+            .expect("Failed to prepare 'pause' template for music player");
+        base
+    };
+}
+lazy_static! {
+    /// Shown while there's no player to show
+    pub static ref IDLE_TEMPLATE: FrameBuffer = {
+        let mut base = *PAUSE_TEMPLATE;
+        let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+        Text::with_baseline(
+            "No player found",
+            Point::new(5 + 3 + 24, 3),
+            style,
+            Baseline::Top,
+        )
+        .draw(&mut base)
+        .expect("Failed to prepare 'idle' template for music player");
+        base
+    };
+}
+
+static UNKNOWN_TITLE: &str = "Unknown title";
+static UNKNOWN_ARTIST: &str = "Unknown artist";
+static UNKNOWN_ALBUM: &str = "Unknown album";
+
+/// The glyphs go into the row between the artists and the progress bar
+const CONTROLS_TOP: i32 = 25;
+
+/// How the player repeats, see `LoopStatus` of MPRIS
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Repeat {
+    #[default]
+    Off,
+    Track,
+    Playlist,
+}
+
+/// What the player does apart from playing the track, not every player
+/// tells
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Controls {
+    pub shuffle: bool,
+    pub repeat: Repeat,
+    /// Between 0 and 1
+    pub volume: Option<f64>,
+}
+
+impl Controls {
+    /// Draws two crossing arrows for shuffle, a loop for repeat with a bar in
+    /// it if only the track repeats and a speaker with a bar for the volume
+    fn draw<D: DrawTarget<Color = BinaryColor>>(&self, target: &mut D) -> Result<(), D::Error> {
+        let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+        let top = CONTROLS_TOP;
+
+        if self.shuffle {
+            let (left, right) = (32, 40);
+            Line::new(Point::new(left, top), Point::new(right, top + 6))
+                .into_styled(stroke)
+                .draw(target)?;
+            Line::new(Point::new(left, top + 6), Point::new(right, top))
+                .into_styled(stroke)
+                .draw(target)?;
+            // The arrow heads on the right
+            for (tip, back) in [(top, top + 2), (top + 6, top + 4)] {
+                Line::new(Point::new(right - 2, tip), Point::new(right, tip))
+                    .into_styled(stroke)
+                    .draw(target)?;
+                Line::new(Point::new(right, tip), Point::new(right, back))
+                    .into_styled(stroke)
+                    .draw(target)?;
+            }
+        }
+
+        if self.repeat != Repeat::Off {
+            let left = 44;
+            Rectangle::new(Point::new(left, top + 1), Size::new(9, 6))
+                .into_styled(stroke)
+                .draw(target)?;
+            // The arrow head on top of the loop
+            Triangle::new(
+                Point::new(left + 4, top - 1),
+                Point::new(left + 6, top + 1),
+                Point::new(left + 4, top + 3),
+            )
+            .into_styled(fill)
+            .draw(target)?;
+            if self.repeat == Repeat::Track {
+                Line::new(Point::new(left + 4, top + 3), Point::new(left + 4, top + 5))
+                    .into_styled(stroke)
+                    .draw(target)?;
+            }
+        }
+
+        if let Some(volume) = self.volume {
+            let left = 60;
+            Rectangle::new(Point::new(left, top + 2), Size::new(2, 3))
+                .into_styled(fill)
+                .draw(target)?;
+            // The cone of the speaker
+            Triangle::new(
+                Point::new(left + 2, top + 3),
+                Point::new(left + 5, top),
+                Point::new(left + 5, top + 6),
+            )
+            .into_styled(fill)
+            .draw(target)?;
+
+            let bar = Rectangle::new(
+                Point::new(left + 9, top + 1),
+                Size::new(127 - left as u32 - 9, 5),
+            );
+            bar.into_styled(stroke).draw(target)?;
+            let inner = bar.size.width.saturating_sub(2);
+            let width = (f64::from(inner) * volume.clamp(0.0, 1.0)).round() as u32;
+            Rectangle::new(bar.top_left + Point::new(1, 1), Size::new(width, 3))
+                .into_styled(fill)
+                .draw(target)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How much of the track the music screen shows
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// The title and the artists with the controls below them
+    #[default]
+    Compact,
+    /// The album and the track number on a third line instead of the
+    /// controls
+    Detailed,
+}
+
+/// Moves `scrollable` along if `text` didn't change and is too long to fit
+fn scroll(scrollable: &mut StatefulScrollable, text: &str) {
+    if let Ok(false) = scrollable.update(text) {
+        if text.len() > 16 {
+            scrollable.text.scroll();
+        }
+    }
+}
+
+/// The third line of the detailed layout, e.g. `3. Some album`
+fn album_line<T: Metadata>(metadata: &T) -> String {
+    match (metadata.track_number(), metadata.album()) {
+        (Some(number), Some(album)) => format!("{}. {}", number, album),
+        (None, Some(album)) => album,
+        (Some(number), None) => format!("Track {}", number),
+        (None, None) => String::from(UNKNOWN_ALBUM),
+    }
+}
+
+/// Draws the title, the artists and the progress of whatever is playing
+#[derive(Debug, Clone)]
+pub struct MediaPlayerRenderer {
+    artist: StatefulScrollable,
+    title: StatefulScrollable,
+    /// Only there in the detailed layout
+    album: Option<StatefulScrollable>,
+    /// Only drawn if the player tells
+    controls: Option<Controls>,
+}
+
+impl MediaPlayerRenderer {
+    pub fn new() -> Result<Self> {
+        let artist = ScrollableBuilder::new()
+            .with_text(UNKNOWN_ARTIST)
+            .with_custom_spacing(10)
+            .with_position(Point::new(5 + 3 + 24, 3 + 10))
+            .with_projection(Size::new(16 * 6, 10));
+        let title = ScrollableBuilder::new()
+            .with_text(UNKNOWN_TITLE)
+            .with_custom_spacing(10)
+            .with_position(Point::new(5 + 3 + 24, 3))
+            .with_projection(Size::new(16 * 6, 10));
+
+        Ok(Self {
+            artist: artist.try_into()?,
+            title: title.try_into()?,
+            album: None,
+            controls: None,
+        })
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Result<Self> {
+        self.album = match layout {
+            Layout::Compact => None,
+            Layout::Detailed => Some(
+                ScrollableBuilder::new()
+                    .with_text(UNKNOWN_ALBUM)
+                    .with_custom_spacing(10)
+                    .with_position(Point::new(5 + 3 + 24, 3 + 20))
+                    .with_projection(Size::new(16 * 6, 10))
+                    .try_into()?,
+            ),
+        };
+        Ok(self)
+    }
+
+    /// Shows `controls` along with the following updates
+    pub fn set_controls(&mut self, controls: Option<Controls>) {
+        self.controls = controls;
+    }
+
+    pub fn update<T: Metadata>(&mut self, progress: &Progress<T>) -> Result<FrameBuffer> {
+        self.update_with_lyric(progress, None)
+    }
+
+    /// Same as `update` but with `lyric` in place of the artists, e.g. the line
+    /// of the lyrics that's sung right now
+    pub fn update_with_lyric<T: Metadata>(
+        &mut self,
+        progress: &Progress<T>,
+        lyric: Option<&str>,
+    ) -> Result<FrameBuffer> {
+        let mut display = match progress.status {
+            PlaybackStatus::Playing => *PLAY_TEMPLATE,
+            PlaybackStatus::Paused | PlaybackStatus::Stopped => *PAUSE_TEMPLATE,
+        };
+
+        let metadata = &progress.metadata;
+
+        // Podcasts and audio books show the chapter instead of the title along with the
+        // progress within the chapter
+        let chapters = metadata.chapters();
+        let position = progress.position.max(0) as u64;
+        let chapter = chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= position);
+
+        let (start, end) = match chapter {
+            Some(index) => (
+                chapters[index].start,
+                chapters
+                    .get(index + 1)
+                    .map_or_else(|| metadata.length().unwrap_or(0), |next| next.start),
+            ),
+            None => (0, metadata.length().unwrap_or(0)),
+        };
+        let length = end.saturating_sub(start) as f64;
+
+        let current = position.saturating_sub(start) as f64;
+
+        let completion = (current / length).clamp(0_f64, 1_f64);
+
+        let pixels = (128_f64 - 2_f64 * 3_f64) * completion;
+        let style = PrimitiveStyle::with_stroke(BinaryColor::On, 3);
+        Line::new(Point::new(3, 35), Point::new(pixels as i32 + 3, 35))
+            .into_styled(style)
+            .draw(&mut display)?;
+
+        let artists = match lyric {
+            Some(lyric) => lyric.to_string(),
+            None => metadata.artists()?,
+        };
+        let title = match chapter {
+            Some(index) => chapters[index].title.clone(),
+            None => metadata.title()?,
+        };
+
+        scroll(&mut self.artist, &artists);
+        scroll(&mut self.title, &title);
+
+        self.title.text.draw(&mut display)?;
+        self.artist.text.draw(&mut display)?;
+        // The album takes the place of the controls
+        if let Some(album) = &mut self.album {
+            scroll(album, &album_line(metadata));
+            album.text.draw(&mut display)?;
+        } else if let Some(controls) = &self.controls {
+            controls.draw(&mut display)?;
+        }
+
+        Ok(display)
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex};
+use tokio::{
+    sync::watch,
+    time::{self, Duration, MissedTickBehavior},
+};
+
+/// Asks a service for the latest state, `None` keeps the last answer, e.g.
+/// because the service couldn't be reached
+pub trait Refresh: Send + 'static {
+    type Output: Send + Sync + 'static;
+
+    fn refresh(&mut self) -> BoxFuture<'_, Option<Self::Output>>;
+}
+
+/// A poller that a screen and its notifications share so the service is only
+/// asked once per interval, along with the settings it polls with. It's kept
+/// in a `static` of the provider.
+pub struct SharedPoller<S, T> {
+    current: Mutex<Option<(S, Arc<watch::Sender<T>>)>>,
+}
+
+impl<S: Clone + PartialEq, T: Send + Sync + 'static> SharedPoller<S, T> {
+    pub const fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Starts polling with `settings` unless that's already going on, `start`
+    /// creates what's polled. The poller stops once neither the screen nor the
+    /// notifications are left, e.g. because the settings were reloaded.
+    pub fn subscribe<R: Refresh<Output = T>>(
+        &self,
+        settings: &S,
+        initial: T,
+        interval: Duration,
+        start: impl FnOnce() -> Result<R>,
+    ) -> Result<watch::Receiver<T>> {
+        let mut current = self
+            .current
+            .lock()
+            .map_err(|_| anyhow!("The poller isn't available anymore"))?;
+        if let Some((polled, tx)) = &*current {
+            if polled == settings && !tx.is_closed() {
+                return Ok(tx.subscribe());
+            }
+        }
+
+        let source = start()?;
+        let (tx, rx) = watch::channel(initial);
+        let tx = Arc::new(tx);
+        tokio::spawn(poll(source, interval, Arc::clone(&tx)));
+        *current = Some((settings.clone(), tx));
+
+        Ok(rx)
+    }
+}
+
+impl<S: Clone + PartialEq, T: Send + Sync + 'static> Default for SharedPoller<S, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn poll<R: Refresh>(mut source: R, interval: Duration, tx: Arc<watch::Sender<R::Output>>) {
+    let mut interval = time::interval(interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            // Nobody is left if the screen was only created to find out its name
+            biased;
+            _ = tx.closed() => break,
+            _ = interval.tick() => {
+                if let Some(latest) = source.refresh().await {
+                    tx.send_replace(latest);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    render::{
+        accessibility, alert, bidi, emoji,
+        registry::ProviderRegistry,
+        scheduler::{ContentWrapper, Exit},
+    },
+    settings::{self, Settings},
+};
+use anyhow::{anyhow, Result};
+use apex_hardware::{AsyncDevice, BlendMode, FrameBuffer};
+use apex_input::Command;
+use config::Config;
+use embedded_graphics::{
+    geometry::Point,
+    mono_font::{iso_8859_15, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Line, Primitive, PrimitiveStyle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable, Pixel,
+};
+use futures::StreamExt;
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use tokio::sync::broadcast;
+
+/// The ticks of the rulers are this many pixels apart, every fourth one is
+/// longer
+const RULER_STEP: i32 = 8;
+
+/// Reads one of the global sections, problems only end up in the log so a
+/// typo doesn't end the preview
+fn section<T>(config: &Config, name: &str) -> T
+where
+    T: DeserializeOwned + Default,
+{
+    settings::section(config, name).unwrap_or_else(|e| {
+        warn!("{}", e);
+        T::default()
+    })
+}
+
+/// Applies the settings the scheduler usually takes care of before it
+/// creates the providers
+fn configure(config: &Config, large_text: Option<bool>) {
+    emoji::configure(section(config, "emoji"));
+    bidi::configure(section(config, "bidi"));
+    accessibility::configure(&section(config, "accessibility"), large_text);
+    alert::configure(section(config, "alerts"));
+}
+
+/// Creates the provider called `name`. The other providers have to be
+/// created as well to find it, they're dropped right away.
+fn create(
+    registry: &ProviderRegistry,
+    config: &Config,
+    name: &str,
+) -> Result<Box<dyn ContentWrapper>> {
+    for provider in registry.content(config) {
+        match provider {
+            Ok(provider) if provider.provider_name() == name => return Ok(provider),
+            Ok(_) => {}
+            // There's no telling which provider an error belongs to
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    Err(anyhow!(
+        "There's no screen called `{}`, see `--list-providers`",
+        name
+    ))
+}
+
+/// Draws rulers along the top and the left edge and a dot wherever the lines
+/// of the grid cross. Everything is inverted so it shows up on top of the
+/// content as well.
+fn draw_overlay(frame: &mut FrameBuffer) -> Result<()> {
+    let mut target = frame.blend(BlendMode::Xor);
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let length = |position: i32| {
+        if position % (RULER_STEP * 4) == 0 {
+            4
+        } else {
+            2
+        }
+    };
+
+    for x in (RULER_STEP..128).step_by(RULER_STEP as usize) {
+        Line::new(Point::new(x, 0), Point::new(x, length(x) - 1))
+            .into_styled(style)
+            .draw(&mut target)?;
+    }
+    for y in (RULER_STEP..40).step_by(RULER_STEP as usize) {
+        Line::new(Point::new(0, y), Point::new(length(y) - 1, y))
+            .into_styled(style)
+            .draw(&mut target)?;
+    }
+
+    for x in (RULER_STEP..128).step_by(RULER_STEP as usize) {
+        for y in (RULER_STEP..40).step_by(RULER_STEP as usize) {
+            Pixel(Point::new(x, y), BinaryColor::On).draw(&mut target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Tells that the screen couldn't be created, the details are in the log
+fn render_error(name: &str) -> Result<FrameBuffer> {
+    let mut buffer = FrameBuffer::new();
+    let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+
+    Text::with_text_style(
+        &format!("Couldn't load {}", name),
+        Point::new(64, 14),
+        style,
+        centered,
+    )
+    .draw(&mut buffer)?;
+    Text::with_text_style("See the log", Point::new(64, 26), style, centered).draw(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Shows only the provider called `name`, the rotation, the notifications and
+/// everything else the scheduler does are left out. The provider is created
+/// again whenever the settings change so its layout can be tweaked without
+/// restarting. M toggles the grid, the other keys go to the provider.
+pub struct Preview<T: AsyncDevice> {
+    device: T,
+    registry: ProviderRegistry,
+    name: String,
+    overlay: bool,
+    /// What was picked with the hotkey, this wins over the settings
+    large_text: Option<bool>,
+}
+
+impl<T: AsyncDevice> Preview<T> {
+    pub fn new(device: T, registry: ProviderRegistry, name: impl Into<String>) -> Self {
+        Self {
+            device,
+            registry,
+            name: name.into(),
+            overlay: true,
+            large_text: None,
+        }
+    }
+
+    pub async fn start(
+        &mut self,
+        mut rx: broadcast::Receiver<Command>,
+        settings: &Settings,
+    ) -> Result<()> {
+        self.device.clear().await?;
+
+        loop {
+            let provider = settings.load().and_then(|config| {
+                configure(&config, self.large_text);
+                create(&self.registry, &config, &self.name)
+            });
+
+            let exit = match provider {
+                Ok(mut provider) => self.show(&mut rx, provider.as_mut()).await?,
+                Err(e) => {
+                    warn!("{}", e);
+                    self.device.draw(&render_error(&self.name)?).await?;
+                    self.wait(&mut rx).await
+                }
+            };
+
+            if exit == Exit::Shutdown {
+                break;
+            }
+            info!("Reloading {}", self.name);
+        }
+
+        self.device.shutdown().await
+    }
+
+    /// Handles the commands that aren't meant for the provider, returns
+    /// `Some` if the preview has to stop
+    fn handle(&mut self, command: Result<Command, broadcast::error::RecvError>) -> Option<Exit> {
+        match command {
+            Ok(Command::ReloadConfig) => Some(Exit::Reload),
+            Ok(Command::ToggleLargeText) => {
+                self.large_text = Some(!accessibility::large_text());
+                Some(Exit::Reload)
+            }
+            Ok(Command::ToggleMenu) => {
+                self.overlay = !self.overlay;
+                None
+            }
+            Ok(Command::Shutdown) | Err(broadcast::error::RecvError::Closed) => {
+                Some(Exit::Shutdown)
+            }
+            _ => None,
+        }
+    }
+
+    /// Waits for the settings to change while there's nothing to show
+    async fn wait(&mut self, rx: &mut broadcast::Receiver<Command>) -> Exit {
+        loop {
+            if let Some(exit) = self.handle(rx.recv().await) {
+                return exit;
+            }
+        }
+    }
+
+    async fn show(
+        &mut self,
+        rx: &mut broadcast::Receiver<Command>,
+        provider: &mut dyn ContentWrapper,
+    ) -> Result<Exit> {
+        let mut input = provider.input_handler();
+        let mut stream = Box::into_pin(provider.proxy_stream()?);
+        let mut ended = false;
+        let mut last = FrameBuffer::new();
+
+        loop {
+            tokio::select! {
+                frame = stream.next(), if !ended => match frame {
+                    Some(Ok(frame)) => last = frame,
+                    Some(Err(e)) => {
+                        warn!("{} couldn't draw a frame: {}", self.name, e);
+                        continue;
+                    }
+                    None => {
+                        info!("{} doesn't have anything else to show", self.name);
+                        ended = true;
+                        continue;
+                    }
+                },
+                command = rx.recv() => {
+                    let overlay = self.overlay;
+                    if let Some(exit) = self.handle(command.clone()) {
+                        return Ok(exit);
+                    }
+                    if overlay == self.overlay {
+                        // The provider draws its next frame if it used the command
+                        if let (Ok(command), Some(input)) = (command, input.as_mut()) {
+                            input.handle_input(command);
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            let mut frame = last;
+            if self.overlay {
+                draw_overlay(&mut frame)?;
+            }
+            self.device.draw(&frame).await?;
+        }
+    }
+}
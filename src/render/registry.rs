@@ -0,0 +1,200 @@
+use crate::{
+    render::{
+        notifications::{Icon, Notification, NotificationBuilder, NotificationProvider},
+        scheduler::{ContentWrapper, NotificationWrapper, OverlayWrapper},
+    },
+    settings,
+};
+use anyhow::{anyhow, Result};
+pub use apex_provider::ContentInit;
+use async_stream::try_stream;
+use config::Config;
+use embedded_graphics::pixelcolor::BinaryColor;
+use futures::{Stream, StreamExt};
+use itertools::Itertools;
+#[cfg(feature = "linkme")]
+use linkme::distributed_slice;
+use tinybmp::Bmp;
+
+/// Creates a source of notifications from the settings
+pub type NotificationInit = fn(&Config) -> Result<Box<dyn NotificationWrapper>>;
+/// Creates something that's drawn on top of every screen from the settings
+pub type OverlayInit = fn(&Config) -> Result<Box<dyn OverlayWrapper>>;
+
+// Providers used to register themselves here, code that still does can turn on
+// the `linkme` feature. linkme doesn't find them on every platform.
+#[cfg(feature = "linkme")]
+#[distributed_slice]
+pub static CONTENT_PROVIDERS: [ContentInit] = [..];
+
+#[cfg(feature = "linkme")]
+#[distributed_slice]
+pub static NOTIFICATION_PROVIDERS: [NotificationInit] = [..];
+
+#[cfg(feature = "linkme")]
+#[distributed_slice]
+pub static OVERLAY_PROVIDERS: [OverlayInit] = [..];
+
+/// Everything the scheduler can show. `main.rs` registers the providers of
+/// this crate, others can add their own before the scheduler starts.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderRegistry {
+    content: Vec<ContentInit>,
+    /// Along with the section whose `enabled` turns them off
+    notifications: Vec<(&'static str, NotificationInit)>,
+    /// Sources of notifications that registered themselves, there's no telling
+    /// which section they belong to
+    #[cfg(feature = "linkme")]
+    distributed: Vec<NotificationInit>,
+    /// Sources of notifications of provider crates, they're turned into the
+    /// notifications of this crate as they arrive
+    external: Vec<apex_provider::NotificationInit>,
+    overlays: Vec<OverlayInit>,
+    /// What the shared libraries in the plugin directory registered
+    #[cfg(feature = "plugins")]
+    plugins: Vec<apex_provider::Plugin>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a screen, they're shown in the order they're registered in unless
+    /// the settings say otherwise
+    pub fn register(&mut self, init: ContentInit) -> &mut Self {
+        self.content.push(init);
+        self
+    }
+
+    /// Adds a source of notifications, it isn't created if `[name]` turns it
+    /// off. That's the section of the screen it belongs to if there is one.
+    pub fn register_notifications(
+        &mut self,
+        name: &'static str,
+        init: NotificationInit,
+    ) -> &mut Self {
+        self.notifications.push((name, init));
+        self
+    }
+
+    pub fn register_overlay(&mut self, init: OverlayInit) -> &mut Self {
+        self.overlays.push(init);
+        self
+    }
+
+    #[cfg(feature = "plugins")]
+    pub fn register_plugin(&mut self, plugin: apex_provider::Plugin) -> &mut Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// Adds whatever registered itself in the distributed slices
+    #[cfg(feature = "linkme")]
+    pub fn register_distributed(&mut self) -> &mut Self {
+        self.content.extend(CONTENT_PROVIDERS.iter());
+        self.distributed.extend(NOTIFICATION_PROVIDERS.iter());
+        self.overlays.extend(OVERLAY_PROVIDERS.iter());
+        self
+    }
+
+    /// Instantiates every screen, the ones whose settings are broken are
+    /// errors
+    pub(crate) fn content(&self, config: &Config) -> Vec<Result<Box<dyn ContentWrapper>>> {
+        let mut content = self.content.iter().map(|f| (f)(config)).collect::<Vec<_>>();
+        #[cfg(feature = "plugins")]
+        content.extend(crate::plugins::screens(&self.plugins, config));
+        content
+    }
+
+    pub(crate) fn notifications(
+        &self,
+        config: &Config,
+    ) -> Vec<Result<Box<dyn NotificationWrapper>>> {
+        let external = self.external.iter().map(|f| {
+            let source = (f)(config)?;
+            Ok(Box::new(External(source)) as Box<dyn NotificationWrapper>)
+        });
+        let mut notifications = self
+            .notifications
+            .iter()
+            .filter_map(|(name, f)| match settings::common(config, name) {
+                Ok(common) if !common.enabled => None,
+                Ok(_) => Some((f)(config)),
+                Err(e) => Some(Err(e)),
+            })
+            .chain(external)
+            .collect::<Vec<_>>();
+        #[cfg(feature = "linkme")]
+        notifications.extend(self.distributed.iter().map(|f| (f)(config)));
+        #[cfg(feature = "plugins")]
+        notifications.extend(crate::plugins::notifiers(&self.plugins, config));
+        notifications
+    }
+
+    pub(crate) fn overlays(&self, config: &Config) -> Vec<Result<Box<dyn OverlayWrapper>>> {
+        self.overlays.iter().map(|f| (f)(config)).collect()
+    }
+
+    /// Instantiates every screen to find out their names
+    pub fn names(&self, config: &Config) -> Vec<&'static str> {
+        self.content(config)
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(|provider| provider.provider_name())
+            .sorted()
+            .collect()
+    }
+}
+
+/// Lets provider crates register themselves, see the `apex-provider` crate
+impl apex_provider::Registry for ProviderRegistry {
+    fn register(&mut self, init: ContentInit) -> &mut Self {
+        ProviderRegistry::register(self, init)
+    }
+
+    fn register_notifications(&mut self, init: apex_provider::NotificationInit) -> &mut Self {
+        self.external.push(init);
+        self
+    }
+}
+
+/// A source of notifications of a provider crate
+pub(crate) struct External(pub(crate) Box<dyn apex_provider::NotificationWrapper>);
+
+impl External {
+    fn build(notification: apex_provider::Notification) -> Result<Notification> {
+        let icon = notification
+            .icon
+            .as_deref()
+            .map(Bmp::<BinaryColor>::from_slice)
+            .transpose()
+            .map_err(|e| anyhow!("The icon of a notification is broken: {:?}", e))?;
+
+        let mut builder = NotificationBuilder::new()
+            .with_title(&notification.title)
+            .with_content(notification.content)
+            .with_urgency(notification.urgency);
+        if let Some(icon) = icon {
+            builder = builder.with_icon(Icon::new(icon));
+        }
+
+        builder.build()
+    }
+}
+
+impl NotificationProvider for External {
+    type NotificationStream<'a> = impl Stream<Item = Result<Notification>> + 'a;
+
+    // This needs to be enabled until full GAT support is here
+    #[allow(clippy::needless_lifetimes)]
+    fn stream<'this>(&'this mut self) -> Result<Self::NotificationStream<'this>> {
+        let mut notifications = Box::into_pin(self.0.proxy_stream()?);
+
+        Ok(try_stream! {
+            while let Some(notification) = notifications.next().await {
+                yield Self::build(notification?)?;
+            }
+        })
+    }
+}
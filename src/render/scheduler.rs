@@ -1,40 +1,55 @@
 use anyhow::{anyhow, Result};
 use std::{
     cell::RefCell,
+    collections::HashMap,
     marker::PhantomData,
     rc::Rc,
     time::{Duration, Instant},
 };
 
-use crate::render::{
-    display::ContentProvider,
-    notifications::{Notification, NotificationProvider},
-    stream::multiplex,
+use crate::{
+    crash,
+    focus::{Focus, FocusSettings},
+    render::{
+        accessibility::{self, AccessibilitySettings},
+        alert, bidi,
+        emoji,
+        idle::{IdleMode, ScreenSaver},
+        menu::Menu,
+        notifications::{
+            Notification, NotificationProvider, NotificationQueue, NotificationSettings, Urgency,
+        },
+        overlay::{Overlay, OverlayProvider},
+        pages::{FrameStream, Layer, PageSettings, Pages},
+        registry::ProviderRegistry,
+        shift::PixelShift,
+        stream::multiplex,
+        util,
+        watchdog::Watchdog,
+    },
+    settings::{self, Settings},
 };
-use apex_hardware::{AsyncDevice, FrameBuffer};
+use apex_hardware::{AsyncDevice, BlendMode, FrameBuffer};
 use apex_input::Command;
+use chrono::Local;
 use config::Config;
-use futures::{pin_mut, stream, stream::Stream, StreamExt};
+use futures::{future, stream, stream::Stream, StreamExt};
 use itertools::Itertools;
-use linkme::distributed_slice;
-use log::{error, info};
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
-};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
 use tokio::{
-    sync::broadcast,
+    sync::{broadcast, watch},
     time::{self, MissedTickBehavior},
 };
 
 pub const TICK_LENGTH: usize = 50;
 pub const TICKS_PER_SECOND: usize = 1000 / TICK_LENGTH;
+/// How long a page may take to send its first frame before a loading screen
+/// is shown, most providers are much faster than this
+const LOADING_DELAY: Duration = Duration::from_millis(250);
 
-#[distributed_slice]
-pub static CONTENT_PROVIDERS: [fn(&Config) -> Result<Box<dyn ContentWrapper>>] = [..];
-
-#[distributed_slice]
-pub static NOTIFICATION_PROVIDERS: [fn() -> Result<Box<dyn NotificationWrapper>>] = [..];
+pub use apex_provider::ContentWrapper;
 
 pub trait NotificationWrapper {
     fn proxy_stream<'a>(&'a mut self) -> Result<Box<dyn Stream<Item = Result<Notification>> + 'a>>;
@@ -49,26 +64,160 @@ impl<T: NotificationProvider> NotificationWrapper for T {
     }
 }
 
-pub trait ContentWrapper {
-    fn proxy_stream<'a>(&'a mut self) -> Result<Box<dyn Stream<Item = Result<FrameBuffer>> + 'a>>;
-    fn provider_name(&self) -> &'static str;
+pub trait OverlayWrapper {
+    fn proxy_stream<'a>(
+        &'a mut self,
+    ) -> Result<Box<dyn Stream<Item = Result<Option<Overlay>>> + 'a>>;
 }
 
-impl<T: ContentProvider> ContentWrapper for T {
+impl<T: OverlayProvider> OverlayWrapper for T {
     fn proxy_stream<'this>(
         &'this mut self,
-    ) -> Result<Box<dyn Stream<Item = Result<FrameBuffer>> + 'this>> {
-        let x = <T as ContentProvider>::stream(self)?;
+    ) -> Result<Box<dyn Stream<Item = Result<Option<Overlay>>> + 'this>> {
+        let x = <T as OverlayProvider>::stream(self)?;
         Ok(Box::new(x.fuse()))
     }
+}
+
+/// Why the scheduler stopped running the providers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Exit {
+    Shutdown,
+    /// The settings changed so the providers need to be restarted
+    Reload,
+}
+
+/// What the scheduler is showing
+enum State {
+    /// The current page, or the menu if it's open
+    Content,
+    /// A notification that's played to the end. The current page doesn't get
+    /// to produce any frames until then so it continues where it left off.
+    Notification(FrameStream<'static>, Urgency),
+}
+
+impl State {
+    fn is_notification(&self) -> bool {
+        matches!(self, Self::Notification(..))
+    }
+
+    /// Whether a notification with `urgency` should take over the screen right
+    /// away instead of waiting for the current one
+    fn is_interrupted_by(&self, urgency: Urgency) -> bool {
+        match self {
+            Self::Notification(_, current) => urgency == Urgency::Critical && *current < urgency,
+            Self::Content => false,
+        }
+    }
+
+    /// The next frame of the notification, this never resolves while the
+    /// content is shown
+    async fn next_frame(&mut self) -> Option<Result<FrameBuffer>> {
+        match self {
+            Self::Notification(frames, _) => frames.next().await,
+            Self::Content => future::pending().await,
+        }
+    }
+}
+
+/// What's on the keyboard, this is published for other programs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Status {
+    /// The name of the page or e.g. `menu` or `notification` if something
+    /// else is drawn over it
+    pub screen: String,
+    /// The title of the notification that's shown, empty otherwise
+    pub notification: String,
+    pub dnd: bool,
+    pub focus: bool,
+}
+
+/// The settings of the `[interval]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IntervalSettings {
+    /// Seconds until the next screen is shown automatically, 0 turns this off
+    refresh: u64,
+}
+
+impl Default for IntervalSettings {
+    fn default() -> Self {
+        Self { refresh: 30 }
+    }
+}
+
+/// The settings of the `[idle]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IdleSettings {
+    /// Seconds without any activity until the screen saver kicks in, 0 turns
+    /// it off
+    timeout: u64,
+    mode: IdleMode,
+}
 
-    fn provider_name(&self) -> &'static str {
-        self.name()
+/// The settings of the `[burn_in]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BurnInSettings {
+    /// Seconds between two steps of the pixel shift
+    interval: u64,
+    /// How many pixels every step moves the content
+    distance: i32,
+}
+
+impl Default for BurnInSettings {
+    fn default() -> Self {
+        Self {
+            interval: 120,
+            distance: 1,
+        }
     }
 }
 
+/// The settings of the `[debug]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DebugSettings {
+    /// Logs every frame that's sent to the device along with what drew it
+    log_frames: bool,
+    /// Writes every frame that's sent to the log as well
+    dump_frames: bool,
+}
+
+/// Returns the value of `result` or the default value after remembering the
+/// error, so all problems can be reported at once instead of one per start
+fn collect_error<T: Default>(result: Result<T>, errors: &mut Vec<anyhow::Error>) -> T {
+    result.unwrap_or_else(|e| {
+        errors.push(e);
+        T::default()
+    })
+}
+
 pub struct Scheduler<'a, T: AsyncDevice + 'a> {
     device: T,
+    registry: ProviderRegistry,
+    /// The name of the provider to show first
+    initial: Option<String>,
+    /// This lives here so focus mode stays on when the settings are reloaded
+    focus: Focus,
+    /// The same goes for do not disturb
+    dnd: bool,
+    /// And for the brightness and the paused rotation
+    menu: Menu,
+    /// And for large text mode once it was toggled, until then the settings
+    /// decide
+    large_text: Option<bool>,
+    /// What the device shows right now, frames that wouldn't change it aren't
+    /// sent at all
+    screen: FrameBuffer,
+    debug: DebugSettings,
+    status: watch::Sender<Status>,
+    /// The title of the last notification that was shown, it's only
+    /// published while it's on the screen
+    notification: String,
+    /// Lives here so the number of reconnects survives a reload
+    watchdog: Watchdog,
     _marker: PhantomData<&'a T>,
 }
 
@@ -76,32 +225,271 @@ impl<'a, T: 'a + AsyncDevice> Scheduler<'a, T> {
     pub fn new(device: T) -> Self {
         Self {
             device,
+            registry: ProviderRegistry::new(),
+            initial: None,
+            focus: Focus::default(),
+            dnd: false,
+            menu: Menu::default(),
+            large_text: None,
+            screen: FrameBuffer::new(),
+            debug: DebugSettings::default(),
+            status: watch::channel(Status::default()).0,
+            notification: String::new(),
+            watchdog: Watchdog::default(),
             _marker: PhantomData::default(),
         }
     }
 
+    pub fn with_registry(mut self, registry: ProviderRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub fn with_initial_provider(mut self, name: impl Into<String>) -> Self {
+        self.initial = Some(name.into());
+        self
+    }
+
+    /// Follows what the scheduler shows along with do not disturb and focus
+    /// mode
+    #[cfg_attr(
+        not(all(feature = "dbus-support", target_os = "linux")),
+        allow(dead_code)
+    )]
+    pub fn status(&self) -> watch::Receiver<Status> {
+        self.status.subscribe()
+    }
+
+    /// Updates the status, `screen` stays the same if it's `None`
+    fn publish(&self, screen: Option<&str>) {
+        let (dnd, focus) = (self.dnd, self.focus.is_active());
+        self.status.send_if_modified(|status| {
+            let screen = screen.map_or_else(|| status.screen.clone(), String::from);
+            let notification = if screen == "notification" {
+                self.notification.clone()
+            } else {
+                String::new()
+            };
+            let next = Status {
+                screen,
+                notification,
+                dnd,
+                focus,
+            };
+            let changed = *status != next;
+            *status = next;
+            changed
+        });
+    }
+
     pub async fn start(
         &mut self,
-        tx: broadcast::Sender<Command>,
-        rx: broadcast::Receiver<Command>,
-        mut config: Config,
+        mut rx: broadcast::Receiver<Command>,
+        settings: &Settings,
     ) -> Result<()> {
-        #[cfg(not(target_os = "macos"))]
-        let mut providers = CONTENT_PROVIDERS
-            .iter()
-            .map(|f| (f)(&mut config))
-            .collect::<Result<Vec<_>>>()?;
-
-        #[cfg(target_os = "macos")]
-        let mut providers = [
-            crate::providers::clock::PROVIDER_INIT(&mut config)?,
-            crate::providers::coindesk::PROVIDER_INIT(&mut config)?,
-        ];
-
-        let mut notifications = NOTIFICATION_PROVIDERS
-            .iter()
-            .map(|f| (f)())
-            .collect::<Result<Vec<_>>>()?;
+        let mut config = settings.load()?;
+        // The settings that worked before the last reload
+        let mut previous: Option<Config> = None;
+
+        // Every provider gets restarted on a reload so they all pick up their new
+        // settings
+        loop {
+            match self.run(&mut rx, &config).await {
+                Ok(Exit::Shutdown) => break,
+                Ok(Exit::Reload) => match settings.load() {
+                    Ok(settings) => previous = Some(std::mem::replace(&mut config, settings)),
+                    Err(e) => error!("Failed to reload the settings, keeping the old ones: {}", e),
+                },
+                // A typo in the settings shouldn't take down a daemon that's already running
+                Err(e) => match previous.take() {
+                    Some(old) => {
+                        error!("{}", e);
+                        warn!("Going back to the previous settings");
+                        config = old;
+                    }
+                    None => return Err(e),
+                },
+            }
+        }
+
+        // Don't leave the desktop notifications turned off
+        self.focus.disable().await;
+
+        self.clear().await?;
+        self.device.shutdown().await?;
+        Ok(())
+    }
+
+    /// Sends `frame` to the device at the brightness that was picked in the
+    /// menu, `source` names what drew it for the debug log
+    async fn draw(&mut self, frame: &FrameBuffer, source: &str) -> Result<()> {
+        self.publish(Some(source));
+        let frame = self.menu.brightness().apply(frame)?;
+        self.screen.blit(&frame, BlendMode::Replace);
+        self.watchdog.stamp(&mut self.screen)?;
+        self.send(source).await
+    }
+
+    /// Sends the changes of `self.screen` to the device, a few failures in a
+    /// row are fine but then the device is connected again
+    async fn send(&mut self, source: &str) -> Result<()> {
+        let dirty = match self.screen.dirty() {
+            Some(dirty) => dirty,
+            None => return Ok(()),
+        };
+
+        // Logged before sending so it's clear whether drawing or sending broke
+        if self.debug.log_frames || self.debug.dump_frames {
+            info!("Sending a frame of {}, {:?} changed", source, dirty);
+        }
+        if self.debug.dump_frames {
+            info!("\n{}", util::ascii(&self.screen));
+        }
+        crash::record_frame(source, &self.screen);
+
+        // The changes are kept around if sending fails so they're sent with the
+        // next frame
+        if let Err(e) = self.device.draw(&self.screen).await {
+            if !self.watchdog.failed(&e) {
+                return Ok(());
+            }
+            self.device.reconnect().await?;
+            self.device.draw(&self.screen).await?;
+        }
+        self.watchdog.succeeded();
+        self.screen.clear_dirty();
+        Ok(())
+    }
+
+    /// Changes the counter of the watchdog so a frame is sent even if nothing
+    /// else changed
+    async fn heartbeat(&mut self) -> Result<()> {
+        self.watchdog.advance();
+        self.watchdog.stamp(&mut self.screen)?;
+        self.send("watchdog").await
+    }
+
+    async fn clear(&mut self) -> Result<()> {
+        if self.debug.log_frames || self.debug.dump_frames {
+            info!("Clearing the screen");
+        }
+        self.screen = FrameBuffer::new();
+        self.device.clear().await
+    }
+
+    /// Shows the page that was just switched to with its latest frame
+    /// `content`, providers only send a frame once something changed so
+    /// waiting for the next one could take a minute. Returns when to show
+    /// the loading screen if the page never sent anything.
+    async fn switch_page(
+        &mut self,
+        pages: &Pages,
+        content: Option<FrameBuffer>,
+    ) -> Result<Option<time::Instant>> {
+        let loading = content
+            .is_none()
+            .then(|| time::Instant::now() + LOADING_DELAY);
+        self.restore(pages, content).await?;
+        Ok(loading)
+    }
+
+    /// Puts the menu or `content` back on the screen after something else was
+    /// drawn over it
+    async fn restore(&mut self, pages: &Pages, content: Option<FrameBuffer>) -> Result<()> {
+        if self.menu.is_open() {
+            let frame = self.menu.render(pages, self.focus.is_active(), self.dnd)?;
+            self.draw(&frame, "menu").await
+        } else {
+            let frame = content.unwrap_or_default();
+            self.draw(&frame, pages.name().unwrap_or_default()).await
+        }
+    }
+
+    async fn run(
+        &mut self,
+        rx: &mut broadcast::Receiver<Command>,
+        config: &Config,
+    ) -> Result<Exit> {
+        // The entries of the menu belong to the pages from before the reload
+        self.menu.close();
+        crash::record_settings(config);
+
+        // Everything that's wrong with the settings is collected first so it can
+        // be fixed in one go
+        let mut errors = Vec::new();
+
+        // Some providers lay out their text as soon as they're created
+        emoji::configure(collect_error(
+            settings::section(config, "emoji"),
+            &mut errors,
+        ));
+        bidi::configure(collect_error(
+            settings::section(config, "bidi"),
+            &mut errors,
+        ));
+        let accessibility: AccessibilitySettings =
+            collect_error(settings::section(config, "accessibility"), &mut errors);
+        accessibility::configure(&accessibility, self.large_text);
+        alert::configure(collect_error(
+            settings::section(config, "alerts"),
+            &mut errors,
+        ));
+
+        let mut providers = self
+            .registry
+            .content(config)
+            .into_iter()
+            .filter_map(|provider| provider.map_err(|e| errors.push(e)).ok())
+            .collect::<Vec<_>>();
+
+        let mut notifications = self
+            .registry
+            .notifications(config)
+            .into_iter()
+            .filter_map(|provider| provider.map_err(|e| errors.push(e)).ok())
+            .collect::<Vec<_>>();
+
+        let mut overlays = self
+            .registry
+            .overlays(config)
+            .into_iter()
+            .filter_map(|provider| provider.map_err(|e| errors.push(e)).ok())
+            .collect::<Vec<_>>();
+
+        let mut common = HashMap::new();
+        for provider in &providers {
+            let name = provider.provider_name();
+            common.insert(
+                name,
+                collect_error(settings::common(config, name), &mut errors),
+            );
+        }
+
+        let interval: IntervalSettings =
+            collect_error(settings::section(config, "interval"), &mut errors);
+        let idle: IdleSettings = collect_error(settings::section(config, "idle"), &mut errors);
+        let burn_in: BurnInSettings =
+            collect_error(settings::section(config, "burn_in"), &mut errors);
+        let focus: FocusSettings = collect_error(settings::section(config, "focus"), &mut errors);
+        let page_settings: PageSettings =
+            collect_error(settings::section(config, "pages"), &mut errors);
+        let notification_settings: NotificationSettings =
+            collect_error(settings::section(config, "notifications"), &mut errors);
+        self.debug = collect_error(settings::section(config, "debug"), &mut errors);
+        self.watchdog.configure(collect_error(
+            settings::section(config, "watchdog"),
+            &mut errors,
+        ));
+
+        if !errors.is_empty() {
+            for e in &errors {
+                error!("{}", e);
+            }
+            return Err(anyhow!(
+                "Couldn't start the providers because of {} error(s), see above",
+                errors.len()
+            ));
+        }
 
         let (notifications, errors): (Vec<_>, Vec<_>) = notifications
             .iter_mut()
@@ -114,26 +502,43 @@ impl<'a, T: 'a + AsyncDevice> Scheduler<'a, T> {
 
         let mut notifications = stream::select_all(notifications.into_iter());
 
-        let current = Arc::new(AtomicUsize::new(0));
-        info!("Found {} registered providers", providers.len());
+        let (overlays, errors): (Vec<_>, Vec<_>) = overlays
+            .iter_mut()
+            .map(|s| s.proxy_stream().map(Box::into_pin))
+            .partition_result();
+
+        for e in errors {
+            error!("{}", e);
+        }
 
-        pin_mut!(rx);
+        // Every overlay stays on the screen until its provider sends the next one,
+        // they're drawn in the order they were registered in
+        let active_overlays = RefCell::new(vec![None::<Overlay>; overlays.len()]);
+        let mut overlays = stream::select_all(
+            overlays
+                .into_iter()
+                .enumerate()
+                .map(|(index, overlay)| overlay.map(move |overlay| (index, overlay))),
+        );
+
+        info!("Found {} registered providers", providers.len());
 
         let (providers, errors): (Vec<_>, Vec<_>) = providers
             .iter_mut()
-            .map(|i| (i.provider_name(), i.proxy_stream()))
-            .filter(|(name, _)| {
-                let key = format!("{}.enabled", name);
-                config.get_bool(&key).unwrap_or(true)
+            .map(|i| {
+                let name = i.provider_name();
+                (
+                    name,
+                    i.input_handler(),
+                    i.activity_receiver(),
+                    i.proxy_stream(),
+                )
             })
-            .map(|(name, i)| {
-                let key = format!("{}.priority", name);
-                let prio = config.get_int(&key).unwrap_or(99i64);
-                (name, i, prio)
-            })
-            .sorted_by_key(|(_, _, prio)| *prio)
-            .map(|(name, i, _)| {
-                i.map_err(|e| anyhow!("Failed to initialize provider: {}. Error: {}", name, e))
+            .filter(|(name, ..)| common[name].enabled)
+            .sorted_by_key(|(name, ..)| common[name].priority)
+            .map(|(name, input, activity, i)| {
+                i.map(|i| (name, input, activity, i))
+                    .map_err(|e| anyhow!("Failed to initialize provider: {}. Error: {}", name, e))
             })
             .partition_result();
 
@@ -141,19 +546,32 @@ impl<'a, T: 'a + AsyncDevice> Scheduler<'a, T> {
             error!("{}", e);
         }
 
-        let providers = providers
-            .into_iter()
+        let layers = providers
             .into_iter()
-            .map(Box::into_pin)
-            .map(StreamExt::fuse)
-            .collect::<Vec<_>>();
-        let size = providers.len();
-        let z = current.clone();
+            .map(|(name, input, activity, provider)| Layer {
+                name,
+                input,
+                activity,
+                shifted: common[name].pixel_shift,
+                stream: Box::into_pin(provider),
+            })
+            .collect();
+        let (pages, streams) = Pages::new(layers, &page_settings);
+        let current = pages.handle();
+
+        if let Some(initial) = self.initial.take() {
+            match pages.find(&initial) {
+                Some(index) => pages.show(index),
+                None => warn!("There's no enabled provider called `{}`", initial),
+            }
+        }
 
-        let mut y = multiplex(providers, move || z.load(Ordering::SeqCst));
+        let mut y = multiplex(streams.into_iter().map(StreamExt::fuse), move || {
+            current.load(Ordering::SeqCst)
+        });
 
         //get the interval
-        let interval_between_change = config.get_int("interval.refresh").unwrap_or(30);
+        let interval_between_change = interval.refresh;
         //flag to know if auto changer is enabled
         let is_auto_change_enabled = interval_between_change != 0;
         //the interval to check wether to change the screen or not
@@ -166,59 +584,341 @@ impl<'a, T: 'a + AsyncDevice> Scheduler<'a, T> {
         change.set_missed_tick_behavior(MissedTickBehavior::Skip);
         //the last time the screen was changed
         let time_last_change = Rc::new(RefCell::new(Instant::now()));
+
+        // The screen saver kicks in once there was neither input nor a notification
+        // for a while
+        let idle_timeout = match idle.timeout {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        };
+        let mut saver = ScreenSaver::new(idle.mode);
+        let mut idle_tick = time::interval(Duration::from_millis(100));
+        idle_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut last_activity = Instant::now();
+        let is_idle = move |last_activity: Instant| {
+            idle_timeout.map_or(false, |timeout| last_activity.elapsed() > timeout)
+        };
+        // The content keeps coming in while the screen saver is shown so we can put it
+        // back right away when waking up
+        let mut last_content: Option<FrameBuffer> = None;
+        // When to show the loading screen if the current page didn't send anything
+        // until then. Providers only start once they're shown for the first time so
+        // this may take a while.
+        let mut loading = Some(time::Instant::now() + LOADING_DELAY);
+        let mut state = State::Content;
+        let mut queue = NotificationQueue::default();
+
+        // Burn-in protection for the providers that opted into it with
+        // `pixel_shift = true`
+        let shift = PixelShift::new(
+            Duration::from_secs(burn_in.interval.max(1)),
+            burn_in.distance,
+        );
+        let mut shift_tick = time::interval(Duration::from_secs(1));
+        shift_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut last_offset = shift.offset();
+        let any_shifted = pages.any_shifted();
+        let mut heartbeat = time::interval(self.watchdog.heartbeat());
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let has_heartbeat = self.watchdog.has_heartbeat();
+        // Moves the content of the current page into place if it asked for it and
+        // adds the overlays and the page indicator on top, which always stay where
+        // they are
+        let place = |content: &FrameBuffer| -> Result<FrameBuffer> {
+            let mut content = if pages.is_shifted() {
+                shift.apply(content)?
+            } else {
+                *content
+            };
+            for overlay in active_overlays.borrow().iter().flatten() {
+                overlay.apply(&mut content)?;
+            }
+            pages.draw_indicator(&mut content)?;
+            Ok(content)
+        };
+
         loop {
+            // The next notification starts as soon as the previous one is over
+            if !state.is_notification() {
+                if let Some(notification) = queue.pop() {
+                    let urgency = notification.urgency();
+                    self.notification = notification.summary().to_string();
+                    state = State::Notification(Box::pin(notification.play()), urgency);
+                    // Notifications wake the display up as well
+                    last_activity = Instant::now();
+                }
+            }
+
             tokio::select! {
                 cmd = rx.recv() => {
+                    if let Ok(command) = &cmd {
+                        crash::record_command(command);
+                    }
+                    // Reloading isn't user input so it neither wakes the display up nor does it
+                    // reset the auto changer
+                    if let Ok(Command::ReloadConfig) = cmd {
+                        // Come back to the same screen after the reload
+                        self.initial = pages.name().map(ToString::to_string);
+                        return Ok(Exit::Reload);
+                    }
+
                     //update the last time the screen was updated to now
                     *time_last_change.borrow_mut() = Instant::now();
+                    // Any key press takes a notification off the screen before it's over, the
+                    // dismiss key doesn't do anything else
+                    if state.is_notification() && !matches!(cmd, Ok(Command::Shutdown)) {
+                        state = State::Content;
+                        last_activity = Instant::now();
+                        let content = last_content.as_ref().map(place).transpose()?;
+                        self.restore(&pages, content).await?;
+                    }
+                    if let Ok(Command::DismissNotification) = cmd {
+                        continue;
+                    }
+                    // The first key press after the screen saver kicked in only wakes the
+                    // display up
+                    let woke_up = is_idle(last_activity);
+                    last_activity = Instant::now();
+                    if woke_up {
+                        info!("Waking up from idle");
+                        self.menu.close();
+                        if let Some(content) = &last_content {
+                            let content = place(content)?;
+                            self.draw(&content, pages.name().unwrap_or_default()).await?;
+                        }
+                    }
+
+                    // The menu takes over the navigation while it's open, some of its entries
+                    // turn into commands that are handled below
+                    let menu_was_open = self.menu.is_open();
+                    let cmd = match cmd {
+                        Ok(command) if !woke_up && self.menu.handles(&command) => {
+                            self.menu.input(command, &pages).map(Ok)
+                        },
+                        cmd => Some(cmd),
+                    };
+                    if self.menu.is_open() {
+                        let frame = self.menu.render(&pages, self.focus.is_active(), self.dnd)?;
+                        self.draw(&frame, "menu").await?;
+                    } else if menu_was_open {
+                        let content = last_content.as_ref().map(place).transpose()?;
+                        self.restore(&pages, content).await?;
+                    }
+                    let cmd = match cmd {
+                        Some(cmd) => cmd,
+                        None => continue,
+                    };
+
+                    // Input only goes to the page that's currently on screen and it gets the
+                    // first go at the navigation, e.g. a game may want to use the arrow keys
+                    let navigation = matches!(
+                        cmd,
+                        Ok(
+                            Command::NextSource
+                                | Command::PreviousSource
+                                | Command::Select
+                                | Command::NextPlayer
+                        )
+                    );
+                    if navigation && !woke_up {
+                        if let Ok(command) = &cmd {
+                            if pages.handle_input(command.clone()) {
+                                continue;
+                            }
+                        }
+                    }
+
                     match cmd {
-                        Ok(Command::Shutdown) => break,
+                        Ok(Command::Shutdown) => return Ok(Exit::Shutdown),
+                        _ if woke_up => {},
                         Ok(Command::NextSource) => {
-                            let new = current.load(Ordering::SeqCst).wrapping_add(1) % size;
-                            current.store(new, Ordering::SeqCst);
-                            self.device.clear().await?;
+                            pages.next();
+                            last_content = pages.last_frame();
+                            let content = last_content.as_ref().map(place).transpose()?;
+                            loading = self.switch_page(&pages, content).await?;
                         },
                         Ok(Command::PreviousSource) => {
-                            let new = match current.load(Ordering::SeqCst) {
-                                0 => size - 1,
-                                n => (n - 1) % size
+                            pages.previous();
+                            last_content = pages.last_frame();
+                            let content = last_content.as_ref().map(place).transpose()?;
+                            loading = self.switch_page(&pages, content).await?;
+                        },
+                        Ok(Command::SetSource(name)) => {
+                            match pages.find(&name) {
+                                Some(index) => {
+                                    pages.show(index);
+                                    last_content = pages.last_frame();
+                                    let content = last_content.as_ref().map(place).transpose()?;
+                                    loading = self.switch_page(&pages, content).await?;
+                                },
+                                None => warn!("There's no enabled provider called `{}`", name),
+                            }
+                        },
+                        Ok(Command::ToggleDnd) => {
+                            self.dnd = !self.dnd;
+                            info!("Turning {} do not disturb", if self.dnd { "on" } else { "off" });
+                            self.publish(None);
+                            if self.dnd {
+                                // The notifications that are still waiting are hidden as well
+                                queue = NotificationQueue::default();
+                            }
+                            // The menu shows whether it's on
+                            if self.menu.is_open() {
+                                let frame =
+                                    self.menu.render(&pages, self.focus.is_active(), self.dnd)?;
+                                self.draw(&frame, "menu").await?;
+                            }
+                        },
+                        Ok(Command::ToggleLargeText) => {
+                            let enabled = !accessibility::large_text();
+                            self.large_text = Some(enabled);
+                            info!("Turning {} large text", if enabled { "on" } else { "off" });
+                            // The providers pick their layout when they're created so they're
+                            // restarted just like on a reload
+                            self.initial = pages.name().map(ToString::to_string);
+                            return Ok(Exit::Reload);
+                        },
+                        Ok(Command::ToggleFocus) => {
+                            let target = if self.focus.is_active() {
+                                self.focus.disable().await
+                            } else {
+                                self.focus.enable(&focus, pages.name()).await
                             };
-                            current.store(new, Ordering::SeqCst);
-                            self.device.clear().await?;
+                            self.publish(None);
+
+                            if let Some(index) = target.and_then(|target| pages.find(&target)) {
+                                pages.show(index);
+                                last_content = pages.last_frame();
+                                let content = last_content.as_ref().map(place).transpose()?;
+                                loading = self.switch_page(&pages, content).await?;
+                            }
                         },
                         _ => {}
                     }
                 },
                 notification = notifications.next(), if !notifications.is_empty() => {
-                    if let Some(Ok(mut notification)) = notification {
-                        let mut stream = Box::pin(notification.stream()?);
-                        while let Some(display) = stream.next().await {
-                            self.device.draw(&display?).await?;
+                    if let Some(Ok(notification)) = notification {
+                        if self.focus.is_active() {
+                            debug!("Hiding a notification because of focus mode");
+                            continue;
+                        }
+                        if self.dnd || notification_settings.is_quiet(Local::now().time()) {
+                            debug!("Hiding a notification because of do not disturb");
+                            continue;
                         }
+
+                        // Critical notifications cut the current one short, it's the most
+                        // urgent one in the queue so it's shown right away
+                        if state.is_interrupted_by(notification.urgency()) {
+                            state = State::Content;
+                        }
+                        queue.push(notification);
                     }
                 }
-                content = y.next() => {
+                frame = state.next_frame(), if state.is_notification() => {
+                    match frame {
+                        Some(frame) => self.draw(&frame?, "notification").await?,
+                        None => {
+                            state = State::Content;
+                            // The display stays awake for as long as the notification is shown
+                            last_activity = Instant::now();
+                            let content = last_content.as_ref().map(place).transpose()?;
+                            self.restore(&pages, content).await?;
+                        }
+                    }
+                }
+                // The current page is paused while a notification is shown
+                content = y.next(), if !state.is_notification() => {
                     if let Some(Ok(content)) = &content {
-                        self.device.draw(content).await?;
+                        last_content = Some(*content);
+                        pages.remember(*content);
+                        loading = None;
+                        let content = place(content)?;
+                        let visible = !is_idle(last_activity) && !self.menu.is_open();
+                        if visible {
+                            self.draw(&content, pages.name().unwrap_or_default()).await?;
+                        }
+                    }
+                }
+                overlay = overlays.next(), if !overlays.is_empty() => {
+                    match overlay {
+                        Some((index, Ok(overlay))) => active_overlays.borrow_mut()[index] = overlay,
+                        Some((_, Err(e))) => {
+                            warn!("Couldn't draw an overlay: {}", e);
+                            continue;
+                        },
+                        None => continue,
+                    }
+                    let visible = !is_idle(last_activity)
+                        && !self.menu.is_open()
+                        && !state.is_notification();
+                    if visible {
+                        if let Some(content) = &last_content {
+                            let content = place(content)?;
+                            self.draw(&content, pages.name().unwrap_or_default()).await?;
+                        }
+                    }
+                }
+                _ = shift_tick.tick(), if any_shifted => {
+                    // Static content doesn't produce new frames so it's moved here instead
+                    let offset = shift.offset();
+                    let visible = !is_idle(last_activity)
+                        && !self.menu.is_open()
+                        && !state.is_notification();
+                    if offset != last_offset && visible {
+                        last_offset = offset;
+                        if let Some(content) = &last_content {
+                            let content = place(content)?;
+                            self.draw(&content, pages.name().unwrap_or_default()).await?;
+                        }
+                    }
+                }
+                _ = time::sleep_until(loading.unwrap_or_else(time::Instant::now)),
+                    if loading.is_some() => {
+                    loading = None;
+                    let visible = !is_idle(last_activity)
+                        && !self.menu.is_open()
+                        && !state.is_notification();
+                    if last_content.is_none() && visible {
+                        self.draw(&util::loading()?, "loading").await?;
+                    }
+                }
+                _ = heartbeat.tick(), if has_heartbeat => {
+                    self.heartbeat().await?;
+                }
+                _ = idle_tick.tick(), if idle_timeout.is_some() => {
+                    if is_idle(last_activity) && !state.is_notification() {
+                        let frame = saver.next_frame()?;
+                        self.draw(&frame, "screen saver").await?;
                     }
                 }
                 _ = change.tick() => {
-                    if is_auto_change_enabled {
+                    // There's no point in cycling through the screens while nobody is looking,
+                    // focus mode stays on its own screen and the menu may have paused it.
+                    // Notifications finish on the page they interrupted.
+                    let paused = self.focus.is_active()
+                        || self.menu.is_open()
+                        || state.is_notification()
+                        || self.menu.is_rotation_paused();
+                    if is_auto_change_enabled && !is_idle(last_activity) && !paused {
                         //get the time since the last update
                         let current_time = Instant::now();
                         let elapsed_time = current_time - time_last_change.borrow().clone();
                         //if the last update is over the choosen interval
-                        if elapsed_time > Duration::from_secs(interval_between_change as u64) {
-                            //change the screen
-                            let _ = tx.send(Command::NextSource);
+                        if elapsed_time > Duration::from_secs(interval_between_change) {
+                            //change the screen, this doesn't go through the command channel
+                            //so it doesn't count as input that keeps the display awake. Screens
+                            //that have nothing to show are skipped.
+                            if pages.next_active() {
+                                last_content = pages.last_frame();
+                                let content = last_content.as_ref().map(place).transpose()?;
+                                loading = self.switch_page(&pages, content).await?;
+                            }
+                            *time_last_change.borrow_mut() = Instant::now();
                         }
                     }
                 }
             };
         }
-
-        self.device.clear().await?;
-        self.device.shutdown().await?;
-        Ok(())
     }
 }
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use embedded_graphics::{draw_target::DrawTargetExt, geometry::Point, Drawable};
+use std::time::{Duration, Instant};
+
+/// The path the content takes, every step is only a single pixel away from the
+/// previous one so the movement is barely noticeable
+const OFFSETS: [(i32, i32); 8] = [
+    (0, 0),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+];
+
+/// Moves the whole frame around by a pixel or two on a slow schedule so static
+/// elements like borders or the frame of a progress bar don't burn into the
+/// OLED
+#[derive(Debug, Copy, Clone)]
+pub struct PixelShift {
+    interval: Duration,
+    /// How many pixels every step moves the content
+    distance: i32,
+    started: Instant,
+}
+
+impl PixelShift {
+    pub fn new(interval: Duration, distance: i32) -> Self {
+        Self {
+            // Guard against a division by zero in `offset`
+            interval: interval.max(Duration::from_secs(1)),
+            distance: distance.clamp(1, 2),
+            started: Instant::now(),
+        }
+    }
+
+    /// The offset the content should currently be drawn at
+    pub fn offset(&self) -> Point {
+        let step = self.started.elapsed().as_secs() / self.interval.as_secs();
+        let (x, y) = OFFSETS[step as usize % OFFSETS.len()];
+        Point::new(x, y) * self.distance
+    }
+
+    /// Returns a copy of `frame` that's moved by the current offset. Whatever
+    /// is moved past the edges gets cut off.
+    pub fn apply(&self, frame: &FrameBuffer) -> Result<FrameBuffer> {
+        let offset = self.offset();
+        if offset == Point::zero() {
+            return Ok(*frame);
+        }
+
+        let mut shifted = FrameBuffer::new();
+        frame.draw(&mut shifted.translated(offset))?;
+        Ok(shifted)
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::Local;
+use std::{env, fmt::Write, fs};
+
+/// The name of the machine, it doesn't change while the program is running
+fn hostname() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Looks up the value of a single variable, `None` if the variable is unknown
+fn variable(name: &str, argument: Option<&str>, hostname: &str) -> Option<String> {
+    let now = Local::now();
+    let mut value = String::new();
+
+    // `chrono` reports invalid format strings as a formatting error so those are
+    // left untouched as well
+    let formatted = match name {
+        "time" => write!(value, "{}", now.format(argument.unwrap_or("%H:%M"))),
+        "date" => write!(value, "{}", now.format(argument.unwrap_or("%Y-%m-%d"))),
+        "weekday" => write!(value, "{}", now.format("%A")),
+        "user" => {
+            value = env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_default();
+            Ok(())
+        }
+        "hostname" => {
+            value = hostname.to_string();
+            Ok(())
+        }
+        "env" => {
+            value = env::var(argument?).unwrap_or_default();
+            Ok(())
+        }
+        _ => return None,
+    };
+
+    formatted.ok().map(|_| value)
+}
+
+/// Text from the config with `{variable}` placeholders. Some variables take an
+/// argument after a colon, e.g. `{time:%I:%M %p}` or `{env:EDITOR}`. Unknown
+/// variables are kept as they are and `{{` or `}}` produce literal braces.
+///
+/// # Supported variables
+///
+/// * `time` and `date`, optionally with a `strftime` format
+/// * `weekday`
+/// * `user` and `hostname`
+/// * `env:NAME` for any environment variable
+#[derive(Debug, Clone)]
+pub struct Template {
+    text: String,
+    /// Looked up once, the template is rendered on every frame
+    hostname: String,
+}
+
+impl Template {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            hostname: hostname(),
+        }
+    }
+
+    /// Fills in the placeholders with their current values
+    pub fn render(&self) -> String {
+        render(&self.text, &self.hostname)
+    }
+}
+
+fn render(template: &str, hostname: &str) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(|c| c == '{' || c == '}') {
+        output.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            output.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+
+        // A stray closing brace or a placeholder that never ends
+        let end = match tail.find('}').filter(|_| tail.starts_with('{')) {
+            Some(end) => end,
+            None => {
+                output.push_str(&tail[..1]);
+                rest = &tail[1..];
+                continue;
+            }
+        };
+
+        let placeholder = &tail[1..end];
+        let (name, argument) = match placeholder.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (placeholder, None),
+        };
+
+        match variable(name.trim(), argument, hostname) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&tail[..=end]),
+        }
+        rest = &tail[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(text: &str) -> String {
+        render(text, "workstation")
+    }
+
+    #[test]
+    fn plain_text_is_kept() {
+        assert_eq!(fill(""), "");
+        assert_eq!(fill("Hello, world!"), "Hello, world!");
+        assert_eq!(fill("äöü €"), "äöü €");
+    }
+
+    #[test]
+    fn variables_are_filled_in() {
+        assert_eq!(fill("Hi from {hostname}"), "Hi from workstation");
+        assert_eq!(fill("{ hostname }"), "workstation");
+        assert_eq!(fill("{date:%Y}"), Local::now().format("%Y").to_string());
+    }
+
+    #[test]
+    fn environment_variables_are_filled_in() {
+        env::set_var("APEX_TUX_TEMPLATE_TEST", "value");
+        assert_eq!(fill("{env:APEX_TUX_TEMPLATE_TEST}"), "value");
+        assert_eq!(fill("[{env:APEX_TUX_TEMPLATE_UNSET}]"), "[]");
+    }
+
+    #[test]
+    fn unknown_variables_are_kept() {
+        assert_eq!(fill("{unknown}"), "{unknown}");
+        assert_eq!(fill("{unknown:argument}"), "{unknown:argument}");
+        assert_eq!(fill("{env}"), "{env}");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(fill("{{hostname}}"), "{hostname}");
+        assert_eq!(fill("{{{hostname}}}"), "{workstation}");
+        assert_eq!(fill("}}"), "}");
+    }
+
+    #[test]
+    fn stray_braces_are_kept() {
+        assert_eq!(fill("a } b"), "a } b");
+        assert_eq!(fill("{hostname"), "{hostname");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::render::{bidi, emoji};
 use anyhow::Result;
 use apex_hardware::BitVec;
 use embedded_graphics::{
@@ -9,7 +10,14 @@ use embedded_graphics::{
     Drawable, Pixel,
 };
 use num_traits::AsPrimitive;
-use std::convert::TryFrom;
+use std::{borrow::Cow, convert::TryFrom};
+
+/// How wide the rendered text may get by default, that's about 340
+/// characters in the default font which should be plenty for anything that's
+/// supposed to scroll by
+const DEFAULT_MAX_WIDTH: u32 = 2048;
+/// Appended to text that had to be cut off
+const ELLIPSIS: &str = "...";
 
 #[derive(Debug, Clone)]
 pub struct ScrollableCanvas {
@@ -67,6 +75,7 @@ pub struct ScrollableBuilder {
     position: Option<Point>,
     projection: Option<Size>,
     font: Option<&'static MonoFont<'static>>,
+    max_width: Option<u32>,
     text: String,
 }
 
@@ -153,15 +162,48 @@ impl ScrollableBuilder {
         self
     }
 
+    /// Caps the width of the rendered text in pixels, longer text is cut off
+    /// and ends in an ellipsis. This keeps absurdly long strings from
+    /// allocating a huge canvas that takes forever to scroll through.
+    pub fn with_max_width(mut self, max_width: u32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
     fn calculate_spacing(&self) -> u32 {
         self.spacing.unwrap_or(5)
     }
 
-    fn calculate_size(&self, renderer: &MonoTextStyle<BinaryColor>) -> Size {
-        let metrics = renderer.measure_string(&self.text, Point::new(0, 0), Baseline::Top);
+    fn calculate_size(&self, text: &str, renderer: &MonoTextStyle<BinaryColor>) -> Size {
+        let metrics = renderer.measure_string(text, Point::new(0, 0), Baseline::Top);
         metrics.bounding_box.size + Size::new(self.calculate_spacing(), 0)
     }
 
+    /// Substitutes emoji and cuts the text off so it fits into the maximum
+    /// width. The original text is kept around so `update` can still tell
+    /// whether it changed.
+    fn clamped_text(&self) -> Cow<'_, str> {
+        let text = emoji::substitute(&self.text);
+        let font = self.font.unwrap_or_else(Self::default_font);
+        let advance = (font.character_size.width + font.character_spacing).max(1);
+        let max_width = self.max_width.unwrap_or(DEFAULT_MAX_WIDTH);
+        let max_chars = (max_width / advance) as usize;
+
+        // The fonts are monospaced so the number of characters is all that matters
+        if text.chars().count() <= max_chars {
+            return text;
+        }
+
+        // There's no room for the ellipsis, the text is just cut off then
+        if max_chars <= ELLIPSIS.len() {
+            return Cow::Owned(text.chars().take(max_chars).collect());
+        }
+
+        let mut clamped: String = text.chars().take(max_chars - ELLIPSIS.len()).collect();
+        clamped.push_str(ELLIPSIS);
+        Cow::Owned(clamped)
+    }
+
     fn default_font() -> &'static MonoFont<'static> {
         &FONT_6X10
     }
@@ -171,11 +213,12 @@ impl ScrollableBuilder {
             .font(self.font.unwrap_or_else(Self::default_font))
             .text_color(BinaryColor::On)
             .build();
-        let size = self.calculate_size(&renderer);
+        let text = self.clamped_text();
+        let (text, reverse) = bidi::reorder(&text);
+        let size = self.calculate_size(&text, &renderer);
         let mut canvas = ScrollableCanvas::new(size.width, size.height);
 
-        Text::with_baseline(&self.text, Point::new(0, 0), renderer, Baseline::Top)
-            .draw(&mut canvas)?;
+        Text::with_baseline(&text, Point::new(0, 0), renderer, Baseline::Top).draw(&mut canvas)?;
 
         Ok(Scrollable {
             canvas,
@@ -183,6 +226,7 @@ impl ScrollableBuilder {
             position: self.position.unwrap_or_default(),
             spacing: self.calculate_spacing(),
             scroll: 0,
+            reverse,
         })
     }
 }
@@ -194,6 +238,8 @@ pub struct Scrollable {
     pub position: Point,
     pub spacing: u32,
     pub scroll: u32,
+    /// Right to left text scrolls the other way
+    pub reverse: bool,
 }
 
 impl Drawable for Scrollable {
@@ -215,7 +261,11 @@ impl Scrollable {
         D: DrawTarget<Color = <Scrollable as Drawable>::Color>,
     {
         // TODO: There's probably some really cool bitwise hacks to do here...
-        let scroll = tick % self.canvas.width;
+        let scroll = if self.reverse {
+            (self.canvas.width - tick % self.canvas.width) % self.canvas.width
+        } else {
+            tick % self.canvas.width
+        };
         let pixels = self.projection.height * self.projection.width;
         // We know exactly how many pixels we can push so we can pre-allocate exactly.
         let mut pixels = Vec::with_capacity(pixels as usize);
@@ -261,3 +311,55 @@ impl Scrollable {
         self.scroll += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default font is 6 pixels wide
+    fn clamp(text: &str, max_width: u32) -> String {
+        ScrollableBuilder::new()
+            .with_text(text)
+            .with_max_width(max_width)
+            .clamped_text()
+            .into_owned()
+    }
+
+    #[test]
+    fn short_text_is_kept() {
+        assert_eq!(clamp("Hello", 60), "Hello");
+        assert_eq!(clamp("0123456789", 60), "0123456789");
+    }
+
+    #[test]
+    fn long_text_ends_in_an_ellipsis() {
+        let text = "a".repeat(10_000);
+        let clamped = clamp(&text, DEFAULT_MAX_WIDTH);
+
+        assert_eq!(clamped.chars().count(), (DEFAULT_MAX_WIDTH / 6) as usize);
+        assert!(clamped.ends_with(ELLIPSIS));
+    }
+
+    #[test]
+    fn multi_byte_text_is_cut_between_characters() {
+        let clamped = clamp(&"äöü€".repeat(10), 60);
+        assert_eq!(clamped, format!("{}{}", "äöü€äöü", ELLIPSIS));
+
+        let clamped = clamp(&"🎵".repeat(100), 60);
+        assert!(clamped.chars().count() <= 10);
+        assert!(clamped.ends_with(ELLIPSIS));
+    }
+
+    #[test]
+    fn narrow_widths_leave_the_ellipsis_out() {
+        assert_eq!(clamp("Hello", 12), "He");
+        assert_eq!(clamp("Hello", 18), "Hel");
+        assert_eq!(clamp("Hello", 24), "H...");
+    }
+
+    #[test]
+    fn zero_width_leaves_nothing() {
+        assert_eq!(clamp("Hello", 0), "");
+        assert_eq!(clamp("Hello", 5), "");
+    }
+}
@@ -0,0 +1,87 @@
+use lazy_static::lazy_static;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+/// The timers of every screen, keyed by the name of the screen
+pub type Timers = BTreeMap<&'static str, Vec<Timer>>;
+
+lazy_static! {
+    /// The screens publish their timers here so they can be followed on D-Bus
+    static ref TIMERS: watch::Sender<Timers> = watch::channel(Timers::new()).0;
+}
+
+/// A timer that's shown on a screen, e.g. an event of the countdown or a side
+/// of the chess clock
+#[cfg_attr(
+    not(all(feature = "dbus-support", target_os = "linux")),
+    allow(dead_code)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timer {
+    pub title: String,
+    /// When a running timer is up, it isn't published again while it counts
+    /// down
+    pub end: Option<SystemTime>,
+    /// The time left on a timer that's paused, zero while it's running
+    pub remaining: Duration,
+}
+
+impl Timer {
+    pub fn running(title: impl Into<String>, end: SystemTime) -> Self {
+        Self {
+            title: title.into(),
+            end: Some(end),
+            remaining: Duration::ZERO,
+        }
+    }
+
+    pub fn paused(title: impl Into<String>, remaining: Duration) -> Self {
+        Self {
+            title: title.into(),
+            end: None,
+            remaining,
+        }
+    }
+}
+
+/// Follows the timers of all screens
+#[cfg_attr(
+    not(all(feature = "dbus-support", target_os = "linux")),
+    allow(dead_code)
+)]
+pub fn subscribe() -> watch::Receiver<Timers> {
+    TIMERS.subscribe()
+}
+
+/// Publishes the timers of one screen, they're taken down again once it's
+/// dropped, e.g. when the settings are reloaded
+#[derive(Debug)]
+pub struct Publisher {
+    screen: &'static str,
+}
+
+impl Publisher {
+    pub fn new(screen: &'static str) -> Self {
+        Self { screen }
+    }
+
+    /// Replaces the timers of the screen, nothing is sent if they didn't change
+    pub fn publish(&self, timers: Vec<Timer>) {
+        TIMERS.send_if_modified(|all| {
+            if all.get(self.screen) == Some(&timers) {
+                return false;
+            }
+            all.insert(self.screen, timers);
+            true
+        });
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        TIMERS.send_if_modified(|all| all.remove(self.screen).is_some());
+    }
+}
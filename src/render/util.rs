@@ -1,10 +1,66 @@
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
 use embedded_graphics::{
+    mono_font::{iso_8859_15, MonoTextStyle},
     pixelcolor::BinaryColor,
     prelude::{Angle, AngleUnit, DrawTarget, Point, Primitive},
     primitives::{Arc, PrimitiveStyle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
     Drawable,
 };
 
+/// Shown while a provider is still starting up
+pub fn loading() -> Result<FrameBuffer> {
+    let mut buffer = FrameBuffer::new();
+    let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+    Text::with_alignment("Loading...", Point::new(64, 23), style, Alignment::Center)
+        .draw(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// A line of text in the middle of the screen, e.g. for screens that have
+/// nothing to show because a setting is missing
+#[cfg_attr(
+    not(any(
+        feature = "json",
+        feature = "mqtt",
+        feature = "sports",
+        feature = "wasm",
+        feature = "world-clock"
+    )),
+    allow(dead_code)
+)]
+pub fn placeholder(text: &str) -> Result<FrameBuffer> {
+    let mut buffer = FrameBuffer::new();
+    let style = MonoTextStyle::new(&iso_8859_15::FONT_6X10, BinaryColor::On);
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    Text::with_text_style(text, Point::new(64, 20), style, centered).draw(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Draws `frame` as text with one line per row of pixels
+pub fn ascii(frame: &FrameBuffer) -> String {
+    (0..40)
+        .map(|y| {
+            (0..128)
+                .map(|x| {
+                    if frame.framebuffer[x + y * 128 + 8] {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub struct ProgressBar {
     maximum_value: f32,
     origin: Point,
@@ -0,0 +1,110 @@
+use anyhow::Result;
+use apex_hardware::FrameBuffer;
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, Drawable, Pixel};
+use log::{info, warn};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// How many bits of the counter are shown, they take the last pixels of the
+/// bottom row
+const BITS: u32 = 4;
+
+/// The settings of the `[watchdog]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchdogSettings {
+    /// Seconds between two heartbeats, 0 turns them off. Failed frames still
+    /// lead to a reconnect without them.
+    heartbeat: u64,
+    /// How many frames in a row may fail before the device is reconnected
+    max_failures: u32,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            heartbeat: 10,
+            max_failures: 3,
+        }
+    }
+}
+
+/// Makes sure the device actually keeps up with the frames. A counter in the
+/// bottom right corner changes with every heartbeat so a frame goes out even if
+/// the content stays the same, a frozen counter means the display hangs.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    settings: WatchdogSettings,
+    counter: u8,
+    /// Failed frames since the last one that went through
+    failures: u32,
+    /// How often the device was reconnected since the start
+    reconnects: u32,
+}
+
+impl Watchdog {
+    /// The number of reconnects is kept when the settings are reloaded
+    pub fn configure(&mut self, settings: WatchdogSettings) {
+        self.settings = settings;
+        self.failures = 0;
+    }
+
+    pub fn has_heartbeat(&self) -> bool {
+        self.settings.heartbeat != 0
+    }
+
+    pub fn heartbeat(&self) -> Duration {
+        Duration::from_secs(self.settings.heartbeat.max(1))
+    }
+
+    /// Moves the counter on to the next heartbeat
+    pub fn advance(&mut self) {
+        self.counter = self.counter.wrapping_add(1);
+    }
+
+    /// Draws the counter into the bottom right corner of `frame`
+    pub fn stamp(&self, frame: &mut FrameBuffer) -> Result<()> {
+        if !self.has_heartbeat() {
+            return Ok(());
+        }
+
+        for bit in 0..BITS {
+            let on = self.counter & (1 << bit) != 0;
+            let point = Point::new(127 - bit as i32, 39);
+            Pixel(point, BinaryColor::from(on)).draw(frame)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn succeeded(&mut self) {
+        if self.failures != 0 {
+            info!(
+                "The device is responding again after {} failed frame(s)",
+                self.failures
+            );
+        }
+        self.failures = 0;
+    }
+
+    /// Counts a failed frame, returns whether it's time to reconnect
+    pub fn failed(&mut self, error: &anyhow::Error) -> bool {
+        self.failures += 1;
+        warn!(
+            "Sending a frame failed, {} in a row: {}",
+            self.failures, error
+        );
+
+        if self.failures < self.settings.max_failures.max(1) {
+            return false;
+        }
+
+        self.failures = 0;
+        self.reconnects += 1;
+        warn!(
+            "Reconnecting to the device, this happened {} time(s) so far",
+            self.reconnects
+        );
+        true
+    }
+}
@@ -0,0 +1,171 @@
+use crate::Opts;
+use anyhow::{anyhow, Result};
+use apex_input::Command;
+use clap::Parser;
+use log::{error, info};
+use std::{ffi::OsString, time::Duration};
+use tokio::{runtime::Runtime, sync::broadcast};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+/// The name of the service and of the source of its messages in the event log
+const NAME: &str = "apex-tux";
+const DISPLAY_NAME: &str = "apex-tux";
+const DESCRIPTION: &str =
+    "Shows useful information on the OLED screen of SteelSeries Apex keyboards";
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Registers the service, it's started along with Windows from then on. The
+/// service runs the same executable with `service` and the options given
+/// here.
+///
+/// It runs as LocalSystem in session 0, away from the desktop of the user.
+/// That's why the settings have to be passed with `--config`, the default
+/// location would be in the profile of SYSTEM. The hotkeys and the music of
+/// the user don't reach the service either.
+pub(crate) fn install(opts: &Opts) -> Result<()> {
+    let config = opts.config.as_ref().ok_or_else(|| {
+        anyhow!(
+            "The service doesn't run as your user and wouldn't find your settings, pass them with \
+             --config"
+        )
+    })?;
+
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+
+    // Services start in the system folder so relative paths would end up there
+    let mut arguments = vec![
+        OsString::from("--config"),
+        std::fs::canonicalize(config)?.into_os_string(),
+    ];
+    arguments.push(OsString::from("--log-level"));
+    arguments.push(OsString::from(opts.log_level.to_string()));
+    if let Some(provider) = &opts.provider {
+        arguments.push(OsString::from("--provider"));
+        arguments.push(OsString::from(provider));
+    }
+    arguments.push(OsString::from("service"));
+
+    let info = ServiceInfo {
+        name: OsString::from(NAME),
+        display_name: OsString::from(DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments: arguments,
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+    service.set_description(DESCRIPTION)?;
+
+    eventlog::register(NAME)?;
+
+    println!(
+        "Installed the {} service, it starts along with Windows",
+        NAME
+    );
+    Ok(())
+}
+
+/// Stops the service if it's running and removes it again
+pub(crate) fn uninstall() -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        NAME,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )?;
+
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+    // Windows only removes it once the last handle to it is closed
+    service.delete()?;
+
+    eventlog::deregister(NAME)?;
+
+    println!("Uninstalled the {} service", NAME);
+    Ok(())
+}
+
+/// Hands the process over to Windows, this only returns once the service
+/// stopped
+pub(crate) fn dispatch() -> Result<()> {
+    service_dispatcher::start(NAME, ffi_service_main)?;
+    Ok(())
+}
+
+fn status(state: ServiceState, controls: ServiceControlAccept, exit_code: u32) -> ServiceStatus {
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: controls,
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
+
+// The arguments are the ones passed when the service is started by hand, the
+// ones of the installation are read like always
+fn service_main(_arguments: Vec<OsString>) {
+    let opts = Opts::parse_from(std::env::args_os());
+
+    // There's no console to print to
+    if let Some(level) = opts.log_level.to_level() {
+        let _ = eventlog::init(NAME, level);
+    }
+    crate::crash::install();
+
+    if let Err(e) = run(opts) {
+        error!("The service stopped: {}", e);
+    }
+}
+
+fn run(opts: Opts) -> Result<()> {
+    let (tx, rx) = broadcast::channel::<Command>(100);
+
+    // Stopping the service and shutting down Windows shut down apex-tux like
+    // the menu does
+    let shutdown = tx.clone();
+    let handle = service_control_handler::register(NAME, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            info!("Windows asked the service to stop, shutting down!");
+            let _ = shutdown.send(Command::Shutdown);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+
+    handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        0,
+    ))?;
+
+    let result = Runtime::new()?.block_on(crate::start(opts, tx, rx));
+
+    handle.set_service_status(status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        u32::from(result.is_err()),
+    ))?;
+
+    result
+}
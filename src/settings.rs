@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use apex_input::Command;
+use config::{Config, ConfigError, Value};
+use log::{info, warn};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+/// Editors tend to save files in multiple steps, the changes within this
+/// window only cause a single reload
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// The keys every provider section may contain, they're read by the scheduler
+/// instead of the provider itself
+const COMMON_KEYS: [&str; 3] = ["enabled", "priority", "pixel_shift"];
+
+/// The settings the scheduler reads from the section of every provider
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommonSettings {
+    pub enabled: bool,
+    /// Providers with a lower priority come first
+    pub priority: i64,
+    /// Moves the content around a little to prevent burn-in
+    pub pixel_shift: bool,
+}
+
+impl Default for CommonSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            priority: 99,
+            pixel_shift: false,
+        }
+    }
+}
+
+/// Deserializes the keys of `[name]` that `keep` returns `true` for
+fn deserialize<T>(config: &Config, name: &str, keep: impl Fn(&str) -> bool) -> Result<T>
+where
+    T: DeserializeOwned + Default,
+{
+    let mut table: HashMap<String, Value> = match config.get_table(name) {
+        Ok(table) => table,
+        Err(ConfigError::NotFound(_)) => return Ok(T::default()),
+        Err(e) => return Err(anyhow!("Invalid settings in [{}]: {}", name, e)),
+    };
+    table.retain(|key, _| keep(key));
+
+    Value::new(None, table)
+        .try_into()
+        .map_err(|e| anyhow!("Invalid settings in [{}]: {}", name, e))
+}
+
+/// Reads the `[name]` section of the settings. Missing keys fall back to the
+/// defaults of `T` but unknown keys and values of the wrong type are reported
+/// so typos don't go unnoticed.
+pub fn section<T>(config: &Config, name: &str) -> Result<T>
+where
+    T: DeserializeOwned + Default,
+{
+    deserialize(config, name, |key| !COMMON_KEYS.contains(&key))
+}
+
+/// Reads the keys every provider supports from the `[name]` section
+pub fn common(config: &Config, name: &str) -> Result<CommonSettings> {
+    deserialize(config, name, |key| COMMON_KEYS.contains(&key))
+}
+
+/// Knows where the settings are read from
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// The file passed with `--config`, this replaces the default locations
+    file: Option<PathBuf>,
+}
+
+impl Settings {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        Self { file }
+    }
+
+    /// The directories that may contain a `settings.toml`, in the order
+    /// they're merged in
+    fn directories(&self) -> Vec<PathBuf> {
+        if let Some(file) = &self.file {
+            let parent = file.parent().unwrap_or_else(|| Path::new("."));
+            // `Path::parent` returns an empty path for relative file names
+            return vec![if parent.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                parent.to_path_buf()
+            }];
+        }
+
+        let mut directories = Vec::new();
+        // `$USER_CONFIG_DIR/apex-tux/settings.toml`
+        if let Some(user_config_dir) = dirs::config_dir() {
+            directories.push(user_config_dir.join("apex-tux"));
+        }
+        // `./settings.toml`
+        directories.push(PathBuf::from("."));
+        directories
+    }
+
+    /// Checks whether a change to `path` affects the settings
+    fn is_settings_file(&self, path: &Path) -> bool {
+        match &self.file {
+            Some(file) => path.file_name() == file.file_name(),
+            None => path.file_stem().map_or(false, |stem| stem == "settings"),
+        }
+    }
+
+    /// Reads the settings from all the config files and the environment
+    pub fn load(&self) -> Result<Config> {
+        let mut settings = Config::default();
+
+        match &self.file {
+            Some(file) => {
+                settings.merge(config::File::from(file.as_path()))?;
+            }
+            None => {
+                for directory in self.directories() {
+                    settings.merge(
+                        config::File::with_name(&directory.join("settings").to_string_lossy())
+                            .required(false),
+                    )?;
+                }
+            }
+        }
+
+        // Add in settings from the environment (with a prefix of APEX)
+        // Eg.. `APEX_DEBUG=1 ./target/app` would set the `debug` key
+        settings.merge(config::Environment::with_prefix("APEX_"))?;
+
+        Ok(settings)
+    }
+
+    /// Watches the config files and asks the scheduler to reload the settings
+    /// whenever one of them changes. The files are only watched as long as
+    /// the returned watcher is alive.
+    pub fn watch(&self, tx: broadcast::Sender<Command>) -> Result<RecommendedWatcher> {
+        let (events_tx, events_rx) = mpsc::channel();
+        let settings = self.clone();
+
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) => {
+                    let is_settings = event
+                        .paths
+                        .iter()
+                        .any(|path| settings.is_settings_file(path));
+                    let is_change = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    );
+                    if is_settings && is_change {
+                        let _ = events_tx.send(());
+                    }
+                }
+                Err(e) => warn!("Failed to watch the settings: {}", e),
+            })?;
+
+        // The files themselves may be replaced on save so their directories are
+        // watched instead
+        for directory in self.directories().into_iter().filter(|d| d.is_dir()) {
+            watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+        }
+
+        thread::spawn(move || {
+            while events_rx.recv().is_ok() {
+                thread::sleep(DEBOUNCE);
+                while events_rx.try_recv().is_ok() {}
+
+                info!("Settings changed, reloading");
+                if tx.send(Command::ReloadConfig).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(watcher)
+    }
+}
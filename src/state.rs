@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+/// Returns the directory apex-tux keeps its persistent state in, e.g.
+/// `~/.local/state/apex-tux` on Linux. Platforms without a dedicated state
+/// directory fall back to the local data directory instead.
+pub(crate) fn state_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_default()
+        .join("apex-tux")
+}
+
+/// Returns the directory apex-tux keeps data in that can be recreated at any
+/// time, e.g. `~/.cache/apex-tux` on Linux
+pub(crate) fn cache_dir() -> PathBuf {
+    dirs::cache_dir().unwrap_or_default().join("apex-tux")
+}
+
+/// Returns the directory the settings are read from, e.g.
+/// `~/.config/apex-tux` on Linux. This is meant for credentials that are
+/// created at runtime, they belong next to the settings but not into them.
+pub(crate) fn config_dir() -> PathBuf {
+    dirs::config_dir().unwrap_or_default().join("apex-tux")
+}